@@ -234,10 +234,47 @@ export function CredentialCard({
               <span className="text-muted-foreground">Successes: </span>
               <span className="font-medium">{credential.successCount}</span>
             </div>
+            <div>
+              <span className="text-muted-foreground">Cancelled: </span>
+              <span className="font-medium">{credential.cancelledCount}</span>
+            </div>
+            <div>
+              <span className="text-muted-foreground">Credits used: </span>
+              <span className="font-medium">{credential.creditsUsed.toFixed(2)}</span>
+            </div>
+            <div>
+              <span className="text-muted-foreground">Sessions: </span>
+              <span className="font-medium">{credential.affinitySessions}</span>
+            </div>
+            {credential.source !== 'file' && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">Source: </span>
+                <span className="font-medium">
+                  {credential.source}
+                  {credential.readOnly && ' (read-only)'}
+                </span>
+              </div>
+            )}
             <div className="col-span-2">
               <span className="text-muted-foreground">Last Used: </span>
               <span className="font-medium">{formatLastUsed(credential.lastUsedAt)}</span>
             </div>
+            {credential.quotaResetAt && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">Quota Resets: </span>
+                <span className="font-medium">{new Date(credential.quotaResetAt).toLocaleString()}</span>
+              </div>
+            )}
+            {credential.refreshHealth.consecutiveFailures > 0 && (
+              <div className="col-span-2" title={credential.refreshHealth.lastError ?? undefined}>
+                <span className="text-muted-foreground">Token Refresh: </span>
+                <span className="font-medium text-red-500">
+                  {credential.refreshHealth.consecutiveFailures} failure(s)
+                  {credential.refreshHealth.nextRetryAt &&
+                    `, retry ${new Date(credential.refreshHealth.nextRetryAt).toLocaleTimeString()}`}
+                </span>
+              </div>
+            )}
             <div className="col-span-2">
               <span className="text-muted-foreground">Remaining: </span>
               {loadingBalance ? (
@@ -14,7 +14,19 @@ import { BatchVerifyDialog, type VerifyResult } from '@/components/batch-verify-
 import { useCredentials, useDeleteCredential, useResetFailure, useLoadBalancingMode, useSetLoadBalancingMode } from '@/hooks/use-credentials'
 import { getCredentialBalance } from '@/api/credentials'
 import { extractErrorMessage } from '@/lib/utils'
-import type { BalanceResponse } from '@/types/api'
+import type { BalanceResponse, LoadBalancingMode } from '@/types/api'
+
+const LOAD_BALANCING_MODE_NAMES: Record<LoadBalancingMode, string> = {
+  priority: 'Priority Mode',
+  balanced: 'Balanced Mode',
+  quota: 'Quota Mode',
+}
+
+const NEXT_LOAD_BALANCING_MODE: Record<LoadBalancingMode, LoadBalancingMode> = {
+  priority: 'balanced',
+  balanced: 'quota',
+  quota: 'priority',
+}
 
 interface DashboardProps {
   onLogout: () => void
@@ -524,12 +536,11 @@ export function Dashboard({ onLogout }: DashboardProps) {
   // Toggle load balancing mode
   const handleToggleLoadBalancing = () => {
     const currentMode = loadBalancingData?.mode || 'priority'
-    const newMode = currentMode === 'priority' ? 'balanced' : 'priority'
+    const newMode = NEXT_LOAD_BALANCING_MODE[currentMode]
 
     setLoadBalancingMode(newMode, {
       onSuccess: () => {
-        const modeName = newMode === 'priority' ? 'Priority Mode' : 'Balanced Mode'
-        toast.success(`Switched to ${modeName}`)
+        toast.success(`Switched to ${LOAD_BALANCING_MODE_NAMES[newMode]}`)
       },
       onError: (error) => {
         toast.error(`Switch failed: ${extractErrorMessage(error)}`)
@@ -582,7 +593,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
               disabled={isLoadingMode || isSettingMode}
               title="Toggle load balancing mode"
             >
-              {isLoadingMode ? 'Loading...' : (loadBalancingData?.mode === 'priority' ? 'Priority Mode' : 'Balanced')}
+              {isLoadingMode ? 'Loading...' : LOAD_BALANCING_MODE_NAMES[loadBalancingData?.mode || 'priority']}
             </Button>
             <Button variant="ghost" size="icon" onClick={toggleDarkMode}>
               {darkMode ? <Sun className="h-5 w-5" /> : <Moon className="h-5 w-5" />}
@@ -19,9 +19,27 @@ export interface CredentialStatusItem {
   email?: string
   refreshTokenHash?: string
   successCount: number
+  cancelledCount: number
+  creditsUsed: number
   lastUsedAt: string | null
+  affinitySessions: number
+  quotaResetAt: string | null
+  refreshHealth: RefreshHealth
+  source: string
+  readOnly: boolean
 }
 
+// Token refresh health
+export interface RefreshHealth {
+  lastRefreshAt: string | null
+  consecutiveFailures: number
+  lastError: string | null
+  nextRetryAt: string | null
+}
+
+// Load balancing mode
+export type LoadBalancingMode = 'priority' | 'balanced' | 'quota'
+
 // Balance response
 export interface BalanceResponse {
   id: number
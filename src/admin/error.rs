@@ -26,6 +26,12 @@ pub enum AdminServiceError {
 
     /// Token refresh failed
     TokenRefreshFailed(String),
+
+    /// API key not found (by ID)
+    ApiKeyNotFound(u64),
+
+    /// Invalid request parameters
+    InvalidRequest(String),
 }
 
 impl fmt::Display for AdminServiceError {
@@ -41,6 +47,8 @@ impl fmt::Display for AdminServiceError {
             AdminServiceError::InternalError(msg) => write!(f, "Internal error: {}", msg),
             AdminServiceError::InvalidCredential(msg) => write!(f, "Invalid credential: {}", msg),
             AdminServiceError::TokenRefreshFailed(msg) => write!(f, "Token refresh failed: {}", msg),
+            AdminServiceError::ApiKeyNotFound(id) => write!(f, "API key not found: {}", id),
+            AdminServiceError::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
         }
     }
 }
@@ -57,6 +65,8 @@ impl AdminServiceError {
             AdminServiceError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AdminServiceError::InvalidCredential(_) => StatusCode::BAD_REQUEST,
             AdminServiceError::TokenRefreshFailed(_) => StatusCode::BAD_GATEWAY,
+            AdminServiceError::ApiKeyNotFound(_) => StatusCode::NOT_FOUND,
+            AdminServiceError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -75,6 +85,10 @@ impl AdminServiceError {
             AdminServiceError::TokenRefreshFailed(_) => {
                 AdminErrorResponse::api_error(self.to_string())
             }
+            AdminServiceError::ApiKeyNotFound(_) => AdminErrorResponse::not_found(self.to_string()),
+            AdminServiceError::InvalidRequest(_) => {
+                AdminErrorResponse::invalid_request(self.to_string())
+            }
         }
     }
 }
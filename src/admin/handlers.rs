@@ -9,10 +9,11 @@ use axum::{
 use super::{
     middleware::AdminState,
     types::{
-        AddCredentialRequest, SetDisabledRequest, SetLoadBalancingModeRequest, SetPriorityRequest,
-        SuccessResponse,
+        AddCredentialRequest, CreateApiKeyRequest, SetDisabledRequest,
+        SetLoadBalancingModeRequest, SetPriorityRequest, SuccessResponse,
     },
 };
+use crate::anthropic::keyring::ApiKeySettings;
 
 /// GET /api/admin/credentials
 /// Get all credential statuses
@@ -82,6 +83,18 @@ pub async fn get_credential_balance(
     }
 }
 
+/// GET /api/admin/credentials/:id/history
+/// Get disable/enable history for specified credential
+pub async fn get_credential_history(
+    State(state): State<AdminState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match state.service.get_history(id) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
 /// POST /api/admin/credentials
 /// Add new credential
 pub async fn add_credential(
@@ -125,6 +138,15 @@ pub async fn set_load_balancing_mode(
     }
 }
 
+/// POST /api/admin/config/reload
+/// Reload config.json and credentials.json without restarting
+pub async fn reload_config(State(state): State<AdminState>) -> impl IntoResponse {
+    match state.service.reload_config().await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
 /// POST /api/admin/credentials/:id/refresh
 /// Force refresh token for credential
 pub async fn refresh_credential_token(
@@ -140,3 +162,52 @@ pub async fn refresh_credential_token(
         Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
     }
 }
+
+/// GET /api/admin/stats
+/// Get proxy statistics
+pub async fn get_stats(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_stats())
+}
+
+/// GET /api/admin/keys
+/// Get all client API keys
+pub async fn get_api_keys(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_api_keys())
+}
+
+/// POST /api/admin/keys
+/// Create client API key
+pub async fn create_api_key(
+    State(state): State<AdminState>,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> impl IntoResponse {
+    match state.service.create_api_key(payload) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// PUT /api/admin/keys/:id
+/// Update client API key settings
+pub async fn update_api_key(
+    State(state): State<AdminState>,
+    Path(id): Path<u64>,
+    Json(payload): Json<ApiKeySettings>,
+) -> impl IntoResponse {
+    match state.service.update_api_key(id, payload) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// DELETE /api/admin/keys/:id
+/// Delete client API key
+pub async fn delete_api_key(
+    State(state): State<AdminState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match state.service.delete_api_key(id) {
+        Ok(_) => Json(SuccessResponse::new(format!("API key #{} has been deleted", id))).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
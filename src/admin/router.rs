@@ -2,14 +2,16 @@
 
 use axum::{
     Router, middleware,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
 };
 
 use super::{
     handlers::{
-        add_credential, delete_credential, get_all_credentials, get_credential_balance,
-        get_load_balancing_mode, refresh_credential_token, reset_failure_count,
-        set_credential_disabled, set_credential_priority, set_load_balancing_mode,
+        add_credential, create_api_key, delete_api_key, delete_credential, get_all_credentials,
+        get_api_keys, get_credential_balance, get_credential_history, get_load_balancing_mode,
+        get_stats,
+        refresh_credential_token, reload_config, reset_failure_count, set_credential_disabled,
+        set_credential_priority, set_load_balancing_mode, update_api_key,
     },
     middleware::{AdminState, admin_auth_middleware},
 };
@@ -25,8 +27,15 @@ use super::{
 /// - `POST /credentials/:id/reset` - Reset failure count
 /// - `POST /credentials/:id/refresh` - Force refresh token
 /// - `GET /credentials/:id/balance` - Get credential balance
+/// - `GET /credentials/:id/history` - Get credential disable/enable history
 /// - `GET /config/load-balancing` - Get load balancing mode
 /// - `PUT /config/load-balancing` - Set load balancing mode
+/// - `POST /config/reload` - Reload config.json and credentials.json
+/// - `GET /stats` - Get proxy statistics (tool compression)
+/// - `GET /keys` - Get all client API keys
+/// - `POST /keys` - Create client API key
+/// - `PUT /keys/:id` - Update client API key settings
+/// - `DELETE /keys/:id` - Delete client API key
 ///
 /// # Authentication
 /// Requires Admin API Key authentication, supports:
@@ -44,10 +53,15 @@ pub fn create_admin_router(state: AdminState) -> Router {
         .route("/credentials/{id}/reset", post(reset_failure_count))
         .route("/credentials/{id}/refresh", post(refresh_credential_token))
         .route("/credentials/{id}/balance", get(get_credential_balance))
+        .route("/credentials/{id}/history", get(get_credential_history))
         .route(
             "/config/load-balancing",
             get(get_load_balancing_mode).put(set_load_balancing_mode),
         )
+        .route("/config/reload", post(reload_config))
+        .route("/stats", get(get_stats))
+        .route("/keys", get(get_api_keys).post(create_api_key))
+        .route("/keys/{id}", put(update_api_key).delete(delete_api_key))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             admin_auth_middleware,
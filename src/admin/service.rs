@@ -8,15 +8,24 @@ use chrono::Utc;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 
-use crate::kiro::model::credentials::KiroCredentials;
-use crate::kiro::token_manager::MultiTokenManager;
+use crate::anthropic::keyring::{ApiKeyEntry, ApiKeyring, ApiKeySettings};
+use crate::anthropic::tool_compression;
+use crate::common::encryption;
+use crate::kiro::model::credentials::{CredentialSource, KiroCredentials};
+use crate::kiro::token_manager::{LOAD_BALANCING_MODES, MultiTokenManager};
+use crate::reload::{ReloadSummary, Reloader};
 
 use super::error::AdminServiceError;
 use super::types::{
-    AddCredentialRequest, AddCredentialResponse, BalanceResponse, CredentialStatusItem,
-    CredentialsStatusResponse, LoadBalancingModeResponse, SetLoadBalancingModeRequest,
+    AddCredentialRequest, AddCredentialResponse, ApiKeyItem, ApiKeysResponse, BalanceResponse,
+    CreateApiKeyRequest, CreateApiKeyResponse, CredentialHistoryResponse, CredentialStatusItem,
+    CredentialsStatusResponse,
+    LoadBalancingModeResponse, SetLoadBalancingModeRequest, StatsResponse,
 };
 
+/// Balance cache file name (in the credentials directory)
+pub const BALANCE_CACHE_FILE_NAME: &str = "kiro_balance_cache.json";
+
 /// Balance cache expiration time (seconds), 5 minutes
 const BALANCE_CACHE_TTL_SECS: i64 = 300;
 
@@ -34,25 +43,35 @@ struct CachedBalance {
 /// Encapsulates all Admin API business logic
 pub struct AdminService {
     token_manager: Arc<MultiTokenManager>,
+    keyring: Arc<ApiKeyring>,
     balance_cache: Mutex<HashMap<u64, CachedBalance>>,
     cache_path: Option<PathBuf>,
+    reloader: Option<Arc<Reloader>>,
 }
 
 impl AdminService {
-    pub fn new(token_manager: Arc<MultiTokenManager>) -> Self {
+    pub fn new(token_manager: Arc<MultiTokenManager>, keyring: Arc<ApiKeyring>) -> Self {
         let cache_path = token_manager
             .cache_dir()
-            .map(|d| d.join("kiro_balance_cache.json"));
+            .map(|d| d.join(BALANCE_CACHE_FILE_NAME));
 
         let balance_cache = Self::load_balance_cache_from(&cache_path);
 
         Self {
             token_manager,
+            keyring,
             balance_cache: Mutex::new(balance_cache),
             cache_path,
+            reloader: None,
         }
     }
 
+    /// Set config/credentials reloader (enables the reload endpoint)
+    pub fn with_reloader(mut self, reloader: Arc<Reloader>) -> Self {
+        self.reloader = Some(reloader);
+        self
+    }
+
     /// Get all credential statuses
     pub fn get_all_credentials(&self) -> CredentialsStatusResponse {
         let snapshot = self.token_manager.snapshot();
@@ -72,7 +91,14 @@ impl AdminService {
                 refresh_token_hash: entry.refresh_token_hash,
                 email: entry.email,
                 success_count: entry.success_count,
+                cancelled_count: entry.cancelled_count,
+                credits_used: entry.credits_used,
                 last_used_at: entry.last_used_at.clone(),
+                affinity_sessions: entry.affinity_sessions,
+                quota_reset_at: entry.quota_reset_at,
+                refresh_health: entry.refresh_health,
+                source: entry.source,
+                read_only: entry.read_only,
             })
             .collect();
 
@@ -118,6 +144,15 @@ impl AdminService {
             .map_err(|e| self.classify_error(e, id))
     }
 
+    /// Get credential disable/enable history
+    pub fn get_history(&self, id: u64) -> Result<CredentialHistoryResponse, AdminServiceError> {
+        let events = self
+            .token_manager
+            .credential_history(id)
+            .map_err(|e| self.classify_error(e, id))?;
+        Ok(CredentialHistoryResponse { id, events })
+    }
+
     /// Get credential balance (with cache)
     pub async fn get_balance(&self, id: u64) -> Result<BalanceResponse, AdminServiceError> {
         // Check cache first
@@ -161,7 +196,7 @@ impl AdminService {
 
         let current_usage = usage.current_usage();
         let usage_limit = usage.usage_limit();
-        let remaining = (usage_limit - current_usage).max(0.0);
+        let remaining = usage.remaining();
         let usage_percentage = if usage_limit > 0.0 {
             (current_usage / usage_limit * 100.0).min(100.0)
         } else {
@@ -176,7 +211,7 @@ impl AdminService {
             usage_limit,
             remaining,
             usage_percentage,
-            next_reset_at: usage.next_date_reset,
+            next_reset_at: usage.next_reset_at(),
         })
     }
 
@@ -203,6 +238,7 @@ impl AdminService {
             machine_id: req.machine_id,
             email: req.email,
             subscription_title: None,
+            source: CredentialSource::File,
         };
 
         // Call token_manager to add credential
@@ -243,16 +279,24 @@ impl AdminService {
         }
     }
 
+    /// Get proxy statistics
+    pub fn get_stats(&self) -> StatsResponse {
+        StatsResponse {
+            tool_compression: tool_compression::stats(),
+        }
+    }
+
     /// Set load balancing mode
     pub fn set_load_balancing_mode(
         &self,
         req: SetLoadBalancingModeRequest,
     ) -> Result<LoadBalancingModeResponse, AdminServiceError> {
         // Validate mode value
-        if req.mode != "priority" && req.mode != "balanced" {
-            return Err(AdminServiceError::InvalidCredential(
-                "mode must be 'priority' or 'balanced'".to_string(),
-            ));
+        if !LOAD_BALANCING_MODES.contains(&req.mode.as_str()) {
+            return Err(AdminServiceError::InvalidCredential(format!(
+                "mode must be one of: {}",
+                LOAD_BALANCING_MODES.join(", ")
+            )));
         }
 
         self.token_manager
@@ -262,6 +306,17 @@ impl AdminService {
         Ok(LoadBalancingModeResponse { mode: req.mode })
     }
 
+    /// Reload config.json and credentials.json
+    pub async fn reload_config(&self) -> Result<ReloadSummary, AdminServiceError> {
+        let reloader = self.reloader.as_ref().ok_or_else(|| {
+            AdminServiceError::InternalError("Config reload is not available".to_string())
+        })?;
+        reloader
+            .reload()
+            .await
+            .map_err(|e| AdminServiceError::InternalError(format!("Reload failed: {}", e)))
+    }
+
     /// Force refresh token for a credential
     pub async fn refresh_token(&self, id: u64) -> Result<(), AdminServiceError> {
         self.token_manager
@@ -288,6 +343,64 @@ impl AdminService {
         AdminServiceError::InternalError(format!("Refresh failed: {}", msg))
     }
 
+    // ============ API keys ============
+
+    /// Get all API keys (secret values masked)
+    pub fn get_api_keys(&self) -> ApiKeysResponse {
+        let keys: Vec<ApiKeyItem> = self.keyring.list().into_iter().map(api_key_item).collect();
+        ApiKeysResponse {
+            total: keys.len(),
+            keys,
+        }
+    }
+
+    /// Create API key
+    pub fn create_api_key(
+        &self,
+        req: CreateApiKeyRequest,
+    ) -> Result<CreateApiKeyResponse, AdminServiceError> {
+        let entry = self
+            .keyring
+            .create(req.settings, req.key)
+            .map_err(|e| self.classify_api_key_error(e, None))?;
+
+        Ok(CreateApiKeyResponse {
+            success: true,
+            message: format!("API key '{}' created, ID: {}", entry.settings.name, entry.id),
+            id: entry.id,
+            key: entry.key,
+        })
+    }
+
+    /// Update API key settings
+    pub fn update_api_key(
+        &self,
+        id: u64,
+        settings: ApiKeySettings,
+    ) -> Result<ApiKeyItem, AdminServiceError> {
+        self.keyring
+            .update(id, settings)
+            .map(api_key_item)
+            .map_err(|e| self.classify_api_key_error(e, Some(id)))
+    }
+
+    /// Delete API key
+    pub fn delete_api_key(&self, id: u64) -> Result<(), AdminServiceError> {
+        self.keyring
+            .delete(id)
+            .map_err(|e| self.classify_api_key_error(e, Some(id)))
+    }
+
+    /// Classify keyring errors
+    fn classify_api_key_error(&self, e: anyhow::Error, id: Option<u64>) -> AdminServiceError {
+        let msg = e.to_string();
+        match id {
+            Some(id) if msg.contains("does not exist") => AdminServiceError::ApiKeyNotFound(id),
+            _ if msg.contains("Failed to") => AdminServiceError::InternalError(msg),
+            _ => AdminServiceError::InvalidRequest(msg),
+        }
+    }
+
     // ============ Balance cache persistence ============
 
     fn load_balance_cache_from(cache_path: &Option<PathBuf>) -> HashMap<u64, CachedBalance> {
@@ -296,9 +409,15 @@ impl AdminService {
             None => return HashMap::new(),
         };
 
-        let content = match std::fs::read_to_string(path) {
+        if !path.exists() {
+            return HashMap::new();
+        }
+        let content = match encryption::read_to_string(path) {
             Ok(c) => c,
-            Err(_) => return HashMap::new(),
+            Err(e) => {
+                tracing::warn!("Failed to read balance cache, ignoring: {:#}", e);
+                return HashMap::new();
+            }
         };
 
         // File uses string keys for JSON format compatibility
@@ -337,8 +456,8 @@ impl AdminService {
 
         match serde_json::to_string_pretty(&map) {
             Ok(json) => {
-                if let Err(e) = std::fs::write(path, json) {
-                    tracing::warn!("Failed to save balance cache: {}", e);
+                if let Err(e) = encryption::write(path, &json) {
+                    tracing::warn!("Failed to save balance cache: {:#}", e);
                 }
             }
             Err(e) => tracing::warn!("Failed to serialize balance cache: {}", e),
@@ -421,10 +540,25 @@ impl AdminService {
         let msg = e.to_string();
         if msg.contains("not found") || msg.contains("does not exist") {
             AdminServiceError::NotFound { id }
-        } else if msg.contains("can only delete disabled credentials") || msg.contains("please disable the credential first") {
+        } else if msg.contains("can only delete disabled credentials")
+            || msg.contains("please disable the credential first")
+            || msg.contains("read-only source")
+        {
             AdminServiceError::InvalidCredential(msg)
         } else {
             AdminServiceError::InternalError(msg)
         }
     }
 }
+
+/// Convert a keyring entry to its admin view (secret masked)
+fn api_key_item(entry: ApiKeyEntry) -> ApiKeyItem {
+    let prefix: String = entry.key.chars().take(12).collect();
+    ApiKeyItem {
+        id: entry.id,
+        key_preview: format!("{}***", prefix),
+        settings: entry.settings,
+        created_at: entry.created_at,
+        usage: entry.usage,
+    }
+}
@@ -2,6 +2,10 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::anthropic::keyring::{ApiKeySettings, ApiKeyUsage};
+use crate::anthropic::tool_compression::ToolCompressionStats;
+use crate::kiro::token_manager::{CredentialEvent, RefreshHealth};
+
 // ============ Credential Status ============
 
 /// All credentials status response
@@ -44,8 +48,22 @@ pub struct CredentialStatusItem {
     pub email: Option<String>,
     /// API call success count
     pub success_count: u64,
+    /// Streaming responses abandoned because the client disconnected
+    pub cancelled_count: u64,
+    /// Credits consumed, as reported by Kiro meteringEvents
+    pub credits_used: f64,
     /// Last API call time (RFC3339 format)
     pub last_used_at: Option<String>,
+    /// Conversations currently pinned to this credential (session affinity)
+    pub affinity_sessions: usize,
+    /// When a quota-disabled credential is next probed for recovery (RFC3339 format)
+    pub quota_reset_at: Option<String>,
+    /// Token refresh health
+    pub refresh_health: RefreshHealth,
+    /// Source the credential was loaded from ("file", "dir:<path>" or "env:<index>")
+    pub source: String,
+    /// Whether the source is read-only (changes are not written back)
+    pub read_only: bool,
 }
 
 // ============ Operation Requests ============
@@ -122,6 +140,18 @@ pub struct AddCredentialResponse {
     pub email: Option<String>,
 }
 
+// ============ Credential History ============
+
+/// Credential state transition history response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialHistoryResponse {
+    /// Credential ID
+    pub id: u64,
+    /// Disable/enable transitions, oldest first
+    pub events: Vec<CredentialEvent>,
+}
+
 // ============ Balance Query ============
 
 /// Balance query response
@@ -152,7 +182,7 @@ pub struct BalanceResponse {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LoadBalancingModeResponse {
-    /// Current mode ("priority" or "balanced")
+    /// Current mode ("priority", "balanced" or "quota")
     pub mode: String,
 }
 
@@ -160,10 +190,70 @@ pub struct LoadBalancingModeResponse {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SetLoadBalancingModeRequest {
-    /// Mode ("priority" or "balanced")
+    /// Mode ("priority", "balanced" or "quota")
     pub mode: String,
 }
 
+// ============ Statistics ============
+
+/// Proxy statistics response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsResponse {
+    /// Tool definition compression statistics
+    pub tool_compression: ToolCompressionStats,
+}
+
+// ============ API Keys ============
+
+/// API key list response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeysResponse {
+    /// Total number of keys
+    pub total: usize,
+    /// Key list (secret values masked)
+    pub keys: Vec<ApiKeyItem>,
+}
+
+/// Single API key information
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyItem {
+    /// Key unique ID
+    pub id: u64,
+    /// Masked key value (prefix only)
+    pub key_preview: String,
+    #[serde(flatten)]
+    pub settings: ApiKeySettings,
+    /// Creation time (RFC3339 format)
+    pub created_at: String,
+    /// Usage counters
+    pub usage: ApiKeyUsage,
+}
+
+/// Create API key request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    /// Key value (optional, generated if not provided)
+    pub key: Option<String>,
+    #[serde(flatten)]
+    pub settings: ApiKeySettings,
+}
+
+/// Create API key success response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyResponse {
+    pub success: bool,
+    pub message: String,
+    /// Newly created key ID
+    pub id: u64,
+    /// Full key value (only returned on creation)
+    pub key: String,
+}
+
 // ============ Common Responses ============
 
 /// Operation success response
@@ -0,0 +1,840 @@
+//! Request capture and offline replay
+//!
+//! With `captureDir` set, every `/v1/messages` and `/cc/v1/messages` request is written to a
+//! JSONL bundle in that directory (one file per request). A bundle holds the Anthropic request as
+//! passed to the converter, the converted Kiro request, the raw upstream event-stream chunks and
+//! the response: the emitted SSE events (pings excluded) for streaming requests, the message body
+//! otherwise. Credential-like fields are redacted before anything is written, and bundles are
+//! encrypted like the credential files when encryption at rest is enabled.
+//!
+//! `kiro-rs replay <bundle>` feeds a bundle back through `convert_request` and the stream context
+//! (or the non-streaming message collector) without any network access and reports every
+//! difference to the captured output.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::converter::convert_request;
+use super::handlers::{
+    MessageCollector, UNDECODABLE_STREAM_MESSAGE, collected_response_body, process_stream_chunk,
+};
+use super::stream::{BufferedStreamContext, SseEvent, StreamContext};
+use super::thinking::ThinkingFormat;
+use super::types::MessagesRequest;
+use crate::common::encryption;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::model::config::Config;
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Fields whose values are replaced by `REDACTED` wherever they appear
+const REDACTED_FIELDS: &[&str] = &[
+    "user_id",
+    "profileArn",
+    "accessToken",
+    "refreshToken",
+    "clientSecret",
+    "apiKey",
+    "api_key",
+    "authorization",
+];
+
+/// Kiro request fields generated randomly per conversion (ignored by replay)
+const VOLATILE_KIRO_FIELDS: &[&str] = &["conversationId", "agentContinuationId"];
+
+/// Endpoint a bundle was captured on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureEndpoint {
+    #[serde(rename = "/v1/messages")]
+    Messages,
+    /// Buffered streaming (`BufferedStreamContext`)
+    #[serde(rename = "/cc/v1/messages")]
+    ClaudeCode,
+}
+
+/// Bundle metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureMeta {
+    pub id: String,
+    /// Capture time (RFC3339)
+    pub timestamp: String,
+    pub endpoint: CaptureEndpoint,
+    pub model: String,
+    pub stream: bool,
+    /// Estimated input tokens the stream context was created with
+    pub input_tokens: i32,
+    /// `thinkingFormat` in effect (non-streaming requests only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_format: Option<String>,
+}
+
+/// One line of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CaptureRecord {
+    Meta(CaptureMeta),
+    AnthropicRequest {
+        data: Value,
+    },
+    KiroRequest {
+        data: Value,
+    },
+    /// One upstream response chunk (base64, chunk boundaries are preserved)
+    Upstream {
+        data: String,
+    },
+    Sse {
+        event: String,
+        data: Value,
+    },
+    /// Non-streaming response body (the error body when a forced tool_choice was not honored)
+    Response {
+        data: Value,
+    },
+    /// Upstream call or stream read failure
+    Error {
+        message: String,
+    },
+}
+
+/// Capture of one request, written to its bundle file when dropped
+pub struct Capture {
+    path: PathBuf,
+    meta: CaptureMeta,
+    request: Value,
+    kiro_request: Value,
+    records: Vec<CaptureRecord>,
+}
+
+impl Capture {
+    /// Start capturing a converted request (None when `captureDir` is not set)
+    pub fn start(
+        config: &Config,
+        endpoint: CaptureEndpoint,
+        payload: &MessagesRequest,
+        request_body: &str,
+    ) -> Option<Self> {
+        let dir = config.capture_dir.as_ref()?;
+        let values = serde_json::to_value(payload)
+            .and_then(|request| Ok((request, serde_json::from_str::<Value>(request_body)?)));
+        let (mut request, mut kiro_request) = match values {
+            Ok(values) => values,
+            Err(e) => {
+                tracing::warn!("Failed to capture request: {}", e);
+                return None;
+            }
+        };
+        redact(&mut request);
+        redact(&mut kiro_request);
+
+        let now = chrono::Utc::now();
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let file_name = format!("{}-{}.jsonl", now.format("%Y%m%dT%H%M%S%.3fZ"), &id[..8]);
+        Some(Self {
+            path: Path::new(dir).join(file_name),
+            meta: CaptureMeta {
+                id,
+                timestamp: now.to_rfc3339(),
+                endpoint,
+                model: payload.model.clone(),
+                stream: payload.stream,
+                input_tokens: 0,
+                thinking_format: (!payload.stream).then(|| config.thinking_format().to_string()),
+            },
+            request,
+            kiro_request,
+            records: Vec::new(),
+        })
+    }
+
+    pub fn set_input_tokens(&mut self, input_tokens: i32) {
+        self.meta.input_tokens = input_tokens;
+    }
+
+    /// Record a raw upstream chunk
+    pub fn record_upstream(&mut self, chunk: &[u8]) {
+        self.records.push(CaptureRecord::Upstream {
+            data: STANDARD.encode(chunk),
+        });
+    }
+
+    /// Record emitted SSE events (pings depend on timing and are skipped)
+    pub fn record_events(&mut self, events: &[SseEvent]) {
+        self.records
+            .extend(
+                events
+                    .iter()
+                    .filter(|e| e.event != "ping")
+                    .map(|e| CaptureRecord::Sse {
+                        event: e.event.clone(),
+                        data: e.data.clone(),
+                    }),
+            );
+    }
+
+    /// Record the non-streaming response body
+    pub fn record_response(&mut self, body: &Value) {
+        self.records
+            .push(CaptureRecord::Response { data: body.clone() });
+    }
+
+    pub fn record_error(&mut self, message: &str) {
+        self.records.push(CaptureRecord::Error {
+            message: message.to_string(),
+        });
+    }
+
+    /// Move the records out into a bundle
+    fn bundle(&mut self) -> Bundle {
+        let mut lines = vec![
+            CaptureRecord::Meta(self.meta.clone()),
+            CaptureRecord::AnthropicRequest {
+                data: self.request.take(),
+            },
+            CaptureRecord::KiroRequest {
+                data: self.kiro_request.take(),
+            },
+        ];
+        lines.append(&mut self.records);
+        Bundle {
+            path: self.path.clone(),
+            lines,
+        }
+    }
+}
+
+impl Drop for Capture {
+    /// The capture is dropped with the response stream, so in a Tokio runtime the bundle is
+    /// written on the blocking pool
+    fn drop(&mut self) {
+        let bundle = self.bundle();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || bundle.write_logged());
+            }
+            Err(_) => bundle.write_logged(),
+        }
+    }
+}
+
+/// Records of a finished capture
+struct Bundle {
+    path: PathBuf,
+    lines: Vec<CaptureRecord>,
+}
+
+impl Bundle {
+    /// Write the bundle (encrypted if enabled)
+    ///
+    /// Written to a temporary file first, so a bundle in the directory is always complete.
+    fn write(&self) -> anyhow::Result<()> {
+        let mut contents = String::new();
+        for line in &self.lines {
+            contents.push_str(&serde_json::to_string(line)?);
+            contents.push('\n');
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        encryption::write(&tmp, &contents)?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to rename {}", tmp.display()))
+    }
+
+    fn write_logged(self) {
+        match self.write() {
+            Ok(()) => tracing::debug!("Captured request to {}", self.path.display()),
+            Err(e) => tracing::warn!("Failed to write capture {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// Replace the values of credential-like fields
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    if !value.is_null() {
+                        *value = Value::from(REDACTED);
+                    }
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Bundle files (`*.jsonl`) in `dir`, sorted by name (i.e. capture time)
+pub fn bundle_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read capture directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Load a bundle file (decrypting it if encrypted)
+pub fn load_bundle(path: &Path) -> anyhow::Result<Vec<CaptureRecord>> {
+    let content = encryption::read_to_string(path)?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("{}:{}: invalid capture record", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Raw upstream chunks of a bundle
+pub fn upstream_chunks(records: &[CaptureRecord]) -> anyhow::Result<Vec<Vec<u8>>> {
+    records
+        .iter()
+        .filter_map(|record| match record {
+            CaptureRecord::Upstream { data } => Some(
+                STANDARD
+                    .decode(data)
+                    .context("Upstream chunk is not valid base64"),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replay a bundle offline and return the differences to the captured output
+///
+/// The Kiro request is compared whenever the bundle has one; the SSE events or the message body
+/// are compared for requests that received a response.
+pub fn replay(records: &[CaptureRecord]) -> anyhow::Result<Vec<String>> {
+    let mut meta = None;
+    let mut request = None;
+    let mut kiro_request = None;
+    let mut captured_events = Vec::new();
+    let mut captured_response = None;
+    let mut stream_error = None;
+    for record in records {
+        match record {
+            CaptureRecord::Meta(m) => meta = Some(m),
+            CaptureRecord::AnthropicRequest { data } => request = Some(data),
+            CaptureRecord::KiroRequest { data } => kiro_request = Some(data),
+            CaptureRecord::Sse { event, data } => {
+                captured_events.push((event.clone(), data.clone()))
+            }
+            CaptureRecord::Response { data } => captured_response = Some(data),
+            CaptureRecord::Error { message } => stream_error = Some(message.as_str()),
+            CaptureRecord::Upstream { .. } => {}
+        }
+    }
+    let meta = meta.context("Bundle has no meta record")?;
+    let chunks = upstream_chunks(records)?;
+    let request = request.context("Bundle has no anthropicRequest record")?;
+    let payload: MessagesRequest =
+        serde_json::from_value(request.clone()).context("Captured Anthropic request is invalid")?;
+
+    let mut differences = Vec::new();
+
+    if let Some(expected) = kiro_request {
+        match convert_request(&payload) {
+            Ok(result) => {
+                let mut actual = serde_json::to_value(KiroRequest {
+                    conversation_state: result.conversation_state,
+                    profile_arn: None,
+                })?;
+                redact(&mut actual);
+                let mut expected = expected.clone();
+                strip_volatile_kiro_fields(&mut expected);
+                strip_volatile_kiro_fields(&mut actual);
+                json_diff("kiroRequest", &expected, &actual, &mut differences);
+            }
+            Err(e) => differences.push(format!("kiroRequest: conversion failed: {}", e)),
+        }
+    }
+
+    let thinking_enabled = payload
+        .thinking
+        .as_ref()
+        .map(|t| t.is_enabled())
+        .unwrap_or(false);
+    if meta.stream && !captured_events.is_empty() {
+        let events = replay_stream(meta, &payload, thinking_enabled, &chunks, stream_error);
+        diff_events(&captured_events, &events, &mut differences);
+    }
+
+    if let Some(expected) = captured_response {
+        let actual = replay_message(meta, &payload, thinking_enabled, &chunks);
+        json_diff(
+            "response",
+            &without_message_id(expected),
+            &without_message_id(&actual),
+            &mut differences,
+        );
+    }
+
+    Ok(differences)
+}
+
+/// Run upstream chunks through the endpoint's stream context
+///
+/// `stream_error` is the recorded upstream read error that ended the stream, if any.
+fn replay_stream(
+    meta: &CaptureMeta,
+    payload: &MessagesRequest,
+    thinking_enabled: bool,
+    chunks: &[Vec<u8>],
+    stream_error: Option<&str>,
+) -> Vec<SseEvent> {
+    let stream_error = stream_error.map(|e| format!("Upstream stream interrupted: {}", e));
+    let mut decoder = EventStreamDecoder::new();
+    match meta.endpoint {
+        CaptureEndpoint::Messages => {
+            let mut ctx = StreamContext::new_with_thinking(
+                &payload.model,
+                meta.input_tokens,
+                thinking_enabled,
+            )
+            .with_tool_choice(payload.tool_choice_mode())
+            .with_stop_sequences(payload.stop_sequences.clone().unwrap_or_default());
+            let mut events = ctx.generate_initial_events();
+            for chunk in chunks {
+                process_stream_chunk(&mut decoder, chunk, |event| {
+                    events.extend(ctx.process_kiro_event(event));
+                });
+                if decoder.is_stopped() {
+                    events
+                        .extend(ctx.generate_error_events("api_error", UNDECODABLE_STREAM_MESSAGE));
+                }
+            }
+            if let Some(message) = &stream_error {
+                events.extend(ctx.generate_error_events("api_error", message));
+            }
+            events.extend(ctx.generate_final_events());
+            events
+        }
+        CaptureEndpoint::ClaudeCode => {
+            let mut ctx =
+                BufferedStreamContext::new(&payload.model, meta.input_tokens, thinking_enabled)
+                    .with_tool_choice(payload.tool_choice_mode())
+                    .with_stop_sequences(payload.stop_sequences.clone().unwrap_or_default());
+            for chunk in chunks {
+                process_stream_chunk(&mut decoder, chunk, |event| ctx.process_and_buffer(event));
+                if decoder.is_stopped() {
+                    ctx.fail("api_error", UNDECODABLE_STREAM_MESSAGE);
+                }
+            }
+            if let Some(message) = &stream_error {
+                ctx.fail("api_error", message);
+            }
+            ctx.finish_and_get_all_events()
+        }
+    }
+}
+
+/// Run upstream chunks through the non-streaming message collector, returning the response body
+fn replay_message(
+    meta: &CaptureMeta,
+    payload: &MessagesRequest,
+    thinking_enabled: bool,
+    chunks: &[Vec<u8>],
+) -> Value {
+    let thinking = thinking_enabled.then(|| {
+        ThinkingFormat::from_config(meta.thinking_format.as_deref().unwrap_or("thinking"))
+    });
+    let mut collector = MessageCollector::new(
+        &payload.model,
+        meta.input_tokens,
+        thinking,
+        payload.tool_choice_mode(),
+        payload.stop_sequences.clone().unwrap_or_default(),
+    );
+    for chunk in chunks {
+        if collector.stopped() {
+            break;
+        }
+        collector.feed(chunk);
+    }
+    collected_response_body(&collector.finish())
+}
+
+/// Message body without the random message id
+fn without_message_id(body: &Value) -> Value {
+    let mut body = body.clone();
+    if let Some(message) = body.as_object_mut() {
+        message.remove("id");
+    }
+    body
+}
+
+fn strip_volatile_kiro_fields(kiro_request: &mut Value) {
+    if let Some(state) = kiro_request
+        .get_mut("conversationState")
+        .and_then(Value::as_object_mut)
+    {
+        for field in VOLATILE_KIRO_FIELDS {
+            state.remove(*field);
+        }
+    }
+}
+
+/// Compare replayed SSE events with the captured ones (the random message id is ignored)
+fn diff_events(expected: &[(String, Value)], actual: &[SseEvent], differences: &mut Vec<String>) {
+    let without_message_id = |event: &str, data: &Value| {
+        let mut data = data.clone();
+        if event == "message_start"
+            && let Some(message) = data.get_mut("message").and_then(Value::as_object_mut)
+        {
+            message.remove("id");
+        }
+        data
+    };
+
+    if expected.len() != actual.len() {
+        differences.push(format!(
+            "sse: expected {} events, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    for (i, ((expected_event, expected_data), actual)) in expected.iter().zip(actual).enumerate() {
+        let path = format!("sse[{}]", i);
+        if *expected_event != actual.event {
+            differences.push(format!(
+                "{}: expected event {}, got {}",
+                path, expected_event, actual.event
+            ));
+            continue;
+        }
+        json_diff(
+            &path,
+            &without_message_id(expected_event, expected_data),
+            &without_message_id(&actual.event, &actual.data),
+            differences,
+        );
+    }
+}
+
+/// Describe every difference between two JSON values, one line per differing path
+fn json_diff(path: &str, expected: &Value, actual: &Value, differences: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected_value) in expected {
+                let child = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual_value) => {
+                        json_diff(&child, expected_value, actual_value, differences)
+                    }
+                    None => differences.push(format!("{}: missing", child)),
+                }
+            }
+            for key in actual.keys().filter(|key| !expected.contains_key(*key)) {
+                differences.push(format!("{}.{}: unexpected field", path, key));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if expected.len() != actual.len() {
+                differences.push(format!(
+                    "{}: expected {} items, got {}",
+                    path,
+                    expected.len(),
+                    actual.len()
+                ));
+            }
+            for (i, (e, a)) in expected.iter().zip(actual).enumerate() {
+                json_diff(&format!("{}[{}]", path, i), e, a, differences);
+            }
+        }
+        _ if expected != actual => differences.push(format!(
+            "{}: expected {}, got {}",
+            path,
+            truncate(expected),
+            truncate(actual)
+        )),
+        _ => {}
+    }
+}
+
+/// Compact JSON, shortened for diff output
+fn truncate(value: &Value) -> String {
+    const MAX_CHARS: usize = 120;
+    let text = value.to_string();
+    match text.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use futures::StreamExt;
+
+    use super::super::handlers::{
+        DisconnectGuard, UpstreamBody, collect_non_stream_message, create_buffered_sse_stream,
+        create_event_stream,
+    };
+    use crate::kiro::mock::{self, MockEndpoint, MockReply, MockUpstream};
+    use crate::kiro::parser::encoder::FrameBuilder;
+    use crate::kiro::provider::KiroProvider;
+    use crate::kiro::token_manager::MultiTokenManager;
+
+    fn payload(stream: bool) -> MessagesRequest {
+        serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "stream": stream,
+            "system": "Be brief",
+            "metadata": {"user_id": "user_abc_account__session_0b4445e1-f5be-49e1-87ce-62bbc28ad705"},
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .unwrap()
+    }
+
+    fn capture_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("kiro-capture-{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Wait for the bundle written on the blocking pool and load it
+    async fn wait_for_bundle(dir: &Path) -> Vec<CaptureRecord> {
+        for _ in 0..500 {
+            if let Some(path) = bundle_files(dir)
+                .ok()
+                .and_then(|paths| paths.into_iter().next())
+            {
+                let records = load_bundle(&path).unwrap();
+                std::fs::remove_dir_all(dir).unwrap();
+                return records;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("bundle was not written to {}", dir.display());
+    }
+
+    /// Capture a request through the handlers' response functions and return the written bundle
+    async fn capture(
+        endpoint: CaptureEndpoint,
+        stream: bool,
+        chunks: &[Vec<u8>],
+    ) -> Vec<CaptureRecord> {
+        let dir = capture_dir();
+        let mut config = Config::default();
+        config.capture_dir = Some(dir.to_string_lossy().into_owned());
+        let payload = payload(stream);
+        let result = convert_request(&payload).unwrap();
+        let body = serde_json::to_string(&KiroRequest {
+            conversation_state: result.conversation_state,
+            profile_arn: None,
+        })
+        .unwrap();
+
+        let mut capture = Capture::start(&config, endpoint, &payload, &body).unwrap();
+        capture.set_input_tokens(12);
+        if stream {
+            let manager =
+                MultiTokenManager::new(Config::default(), Vec::new(), None, None, false).unwrap();
+            let guard = DisconnectGuard::new(Arc::new(KiroProvider::new(Arc::new(manager))), 0);
+            let body: UpstreamBody = futures::stream::iter(
+                chunks
+                    .iter()
+                    .map(|chunk| Ok(bytes::Bytes::from(chunk.clone())))
+                    .collect::<Vec<_>>(),
+            )
+            .boxed();
+            let stop_sequences = payload.stop_sequences.clone().unwrap_or_default();
+            match endpoint {
+                CaptureEndpoint::Messages => {
+                    let mut ctx = StreamContext::new_with_thinking(&payload.model, 12, false)
+                        .with_tool_choice(payload.tool_choice_mode())
+                        .with_stop_sequences(stop_sequences);
+                    let initial_events = ctx.generate_initial_events();
+                    create_event_stream(body, guard, ctx, initial_events, Some(capture))
+                        .collect::<Vec<_>>()
+                        .await;
+                }
+                CaptureEndpoint::ClaudeCode => {
+                    let ctx = BufferedStreamContext::new(&payload.model, 12, false)
+                        .with_tool_choice(payload.tool_choice_mode())
+                        .with_stop_sequences(stop_sequences);
+                    create_buffered_sse_stream(body, guard, ctx, Some(capture))
+                        .collect::<Vec<_>>()
+                        .await;
+                }
+            }
+        } else {
+            let mock = MockUpstream::start().await;
+            mock.enqueue(
+                MockEndpoint::GenerateAssistantResponse,
+                MockReply::EventStream(chunks.concat()),
+            );
+            let manager = MultiTokenManager::new(
+                mock.config(),
+                vec![mock::credentials(1, "token-1")],
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+            let provider = Arc::new(KiroProvider::new(Arc::new(manager)));
+            collect_non_stream_message(
+                provider,
+                &body,
+                &payload.model,
+                12,
+                None,
+                &payload.tool_choice_mode(),
+                None,
+                Vec::new(),
+                Some(&mut capture),
+            )
+            .await
+            .unwrap();
+            drop(capture);
+        }
+
+        wait_for_bundle(&dir).await
+    }
+
+    fn upstream_chunks() -> Vec<Vec<u8>> {
+        let frame = [
+            FrameBuilder::event("assistantResponseEvent")
+                .payload(br#"{"content":"Hi there"}"#.to_vec())
+                .encode()
+                .unwrap(),
+            FrameBuilder::event("contextUsageEvent")
+                .payload(br#"{"contextUsagePercentage":1.5}"#.to_vec())
+                .encode()
+                .unwrap(),
+        ]
+        .concat();
+        // Split mid-frame so replay exercises partial frame decoding
+        let (a, b) = frame.split_at(20);
+        vec![a.to_vec(), b.to_vec()]
+    }
+
+    #[tokio::test]
+    async fn test_capture_redacts_and_skips_pings() {
+        let records = capture(CaptureEndpoint::Messages, true, &upstream_chunks()).await;
+
+        let CaptureRecord::Meta(meta) = &records[0] else {
+            panic!("first record must be meta");
+        };
+        assert_eq!(meta.input_tokens, 12);
+        let CaptureRecord::AnthropicRequest { data } = &records[1] else {
+            panic!("second record must be the Anthropic request");
+        };
+        assert_eq!(data["metadata"]["user_id"], REDACTED);
+        let CaptureRecord::KiroRequest { data } = &records[2] else {
+            panic!("third record must be the Kiro request");
+        };
+        assert!(data["conversationState"]["conversationId"].is_string());
+
+        let upstream = records
+            .iter()
+            .filter(|r| matches!(r, CaptureRecord::Upstream { .. }))
+            .count();
+        assert_eq!(upstream, 2);
+        assert!(
+            records
+                .iter()
+                .any(|r| matches!(r, CaptureRecord::Sse { .. }))
+        );
+        assert!(
+            !records
+                .iter()
+                .any(|r| matches!(r, CaptureRecord::Sse { event, .. } if event == "ping"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_capture() {
+        for endpoint in [CaptureEndpoint::Messages, CaptureEndpoint::ClaudeCode] {
+            let records = capture(endpoint, true, &upstream_chunks()).await;
+            assert_eq!(replay(&records).unwrap(), Vec::<String>::new());
+        }
+        let records = capture(CaptureEndpoint::Messages, false, &upstream_chunks()).await;
+        assert!(
+            records
+                .iter()
+                .any(|r| matches!(r, CaptureRecord::Upstream { .. }))
+        );
+        let response = records.iter().find_map(|r| match r {
+            CaptureRecord::Response { data } => Some(data),
+            _ => None,
+        });
+        assert_eq!(response.unwrap()["content"][0]["text"], "Hi there");
+        assert_eq!(replay(&records).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_non_stream_replay_reports_differences() {
+        let chunks = upstream_chunks();
+        let mut request = serde_json::to_value(payload(false)).unwrap();
+        redact(&mut request);
+        let meta = CaptureMeta {
+            id: "test".to_string(),
+            timestamp: String::new(),
+            endpoint: CaptureEndpoint::Messages,
+            model: "claude-sonnet-4-5".to_string(),
+            stream: false,
+            input_tokens: 12,
+            thinking_format: Some("thinking".to_string()),
+        };
+        let mut response = replay_message(&meta, &payload(false), false, &chunks);
+        response["stop_reason"] = Value::from("max_tokens");
+
+        let mut records = vec![
+            CaptureRecord::Meta(meta),
+            CaptureRecord::AnthropicRequest { data: request },
+        ];
+        records.extend(chunks.iter().map(|chunk| CaptureRecord::Upstream {
+            data: STANDARD.encode(chunk),
+        }));
+        records.push(CaptureRecord::Response { data: response });
+
+        assert_eq!(
+            replay(&records).unwrap(),
+            vec![r#"response.stop_reason: expected "max_tokens", got "end_turn""#]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_differences() {
+        let mut records = capture(CaptureEndpoint::Messages, true, &upstream_chunks()).await;
+        for record in &mut records {
+            match record {
+                CaptureRecord::KiroRequest { data } => {
+                    data["conversationState"]["chatTriggerType"] = Value::from("AUTO");
+                }
+                CaptureRecord::Sse { event, data } if event == "content_block_delta" => {
+                    data["delta"]["text"] = Value::from("Bye");
+                }
+                _ => {}
+            }
+        }
+
+        let differences = replay(&records).unwrap();
+        assert_eq!(
+            differences,
+            vec![
+                r#"kiroRequest.conversationState.chatTriggerType: expected "AUTO", got "MANUAL""#,
+                r#"sse[2].delta.text: expected "Bye", got "Hi there""#,
+            ]
+        );
+    }
+}
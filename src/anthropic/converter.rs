@@ -11,8 +11,10 @@ use crate::kiro::model::requests::conversation::{
 use crate::kiro::model::requests::tool::{
     InputSchema, Tool, ToolResult, ToolSpecification, ToolUseEntry,
 };
+use crate::model::catalog;
 
-use super::types::{ContentBlock, MessagesRequest};
+use super::tool_compression;
+use super::types::{ContentBlock, MessagesRequest, ToolChoice};
 
 /// Content appended to the end of Write tool description
 const WRITE_TOOL_DESCRIPTION_SUFFIX: &str = "- IMPORTANT: If the content to write exceeds 150 lines, you MUST only write the first 50 lines using this tool, then use `Edit` tool to append the remaining content in chunks of no more than 50 lines each. If needed, leave a unique placeholder to help append content. Do NOT attempt to write all content at once.";
@@ -73,38 +75,11 @@ pub fn inject_agentic_prompt(system_prompt: &str) -> String {
 
 /// Model mapping: Map Anthropic model names to Kiro model IDs
 ///
-/// Model mapping with version-specific internal IDs:
-/// - Sonnet 4.5 → CLAUDE_SONNET_4_5_20250929_V1_0
-/// - Sonnet 4 → CLAUDE_SONNET_4_20250514_V1_0
-/// - Sonnet 3.7 → CLAUDE_3_7_SONNET_20250219_V1_0
-/// - Other sonnet → claude-sonnet-4.5
-/// - Opus 4.5 → claude-opus-4.5
-/// - Opus (other) → claude-opus-4.6
-/// - Haiku → claude-haiku-4.5
+/// Looked up in the model catalog (see `models.example.json` for the built-in mapping)
 pub fn map_model(model: &str) -> Option<String> {
-    let model_lower = model.to_lowercase();
-
-    if model_lower.contains("sonnet") {
-        if model_lower.contains("4-5") || model_lower.contains("4.5") {
-            Some("CLAUDE_SONNET_4_5_20250929_V1_0".to_string())
-        } else if model_lower.contains("sonnet-4") || model_lower.contains("sonnet_4") {
-            Some("CLAUDE_SONNET_4_20250514_V1_0".to_string())
-        } else if model_lower.contains("3-7") || model_lower.contains("3.7") {
-            Some("CLAUDE_3_7_SONNET_20250219_V1_0".to_string())
-        } else {
-            Some("claude-sonnet-4.5".to_string())
-        }
-    } else if model_lower.contains("opus") {
-        if model_lower.contains("4-5") || model_lower.contains("4.5") {
-            Some("claude-opus-4.5".to_string())
-        } else {
-            Some("claude-opus-4.6".to_string())
-        }
-    } else if model_lower.contains("haiku") {
-        Some("claude-haiku-4.5".to_string())
-    } else {
-        None
-    }
+    catalog::current()
+        .resolve(model)
+        .map(|entry| entry.kiro_id.clone())
 }
 
 /// Conversion result
@@ -119,6 +94,7 @@ pub struct ConversionResult {
 pub enum ConversionError {
     UnsupportedModel(String),
     EmptyMessages,
+    InvalidToolChoice(String),
 }
 
 impl std::fmt::Display for ConversionError {
@@ -126,6 +102,7 @@ impl std::fmt::Display for ConversionError {
         match self {
             ConversionError::UnsupportedModel(model) => write!(f, "Model not supported: {}", model),
             ConversionError::EmptyMessages => write!(f, "Message list is empty"),
+            ConversionError::InvalidToolChoice(msg) => write!(f, "Invalid tool_choice: {}", msg),
         }
     }
 }
@@ -218,8 +195,9 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
     let last_message = req.messages.last().unwrap();
     let (text_content, images, tool_results) = process_message_content(&last_message.content)?;
 
-    // 6. Convert tool definitions
-    let mut tools = convert_tools(&req.tools);
+    // 6. Convert tool definitions, narrowed according to tool_choice
+    let tool_choice = req.tool_choice_mode();
+    let mut tools = apply_tool_choice(convert_tools(&req.tools), &tool_choice)?;
 
     // 7. Build history messages (need to build first to collect tools used in history)
     let mut history = build_history(req, &model_id)?;
@@ -248,7 +226,10 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
         }
     }
 
-    // 11. Build UserInputMessageContext
+    // 11. Compress tool definitions if they exceed the configured size (avoids Kiro 500 errors)
+    let tools = tool_compression::compress_tools_if_needed(tools);
+
+    // 12. Build UserInputMessageContext
     let mut context = UserInputMessageContext::new();
     if !tools.is_empty() {
         context = context.with_tools(tools);
@@ -257,9 +238,13 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
         context = context.with_tool_results(validated_tool_results);
     }
 
-    // 12. Build current message
+    // 13. Build current message
     // Preserve text content, don't discard user text even if there are tool results
-    let content = text_content;
+    let content = match tool_choice_instruction(&tool_choice) {
+        Some(instruction) if text_content.is_empty() => instruction,
+        Some(instruction) => format!("{}\n\n{}", text_content, instruction),
+        None => text_content,
+    };
 
     let mut user_input = UserInputMessage::new(content, &model_id)
         .with_context(context)
@@ -271,7 +256,7 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
 
     let current_message = CurrentMessage::new(user_input);
 
-    // 13. Build ConversationState
+    // 14. Build ConversationState
     let conversation_state = ConversationState::new(conversation_id)
         .with_agent_continuation_id(agent_continuation_id)
         .with_agent_task_type("vibe")
@@ -282,6 +267,62 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
     Ok(ConversionResult { conversation_state })
 }
 
+/// Narrow tool definitions according to tool_choice
+///
+/// - `none`: drop all tools (placeholders for history tools are still added later)
+/// - `tool`: keep only the named tool, error if it is not defined
+/// - `any`: keep all tools, error if there are none
+fn apply_tool_choice(
+    tools: Vec<Tool>,
+    tool_choice: &ToolChoice,
+) -> Result<Vec<Tool>, ConversionError> {
+    match tool_choice {
+        ToolChoice::Auto => Ok(tools),
+        ToolChoice::None => Ok(Vec::new()),
+        ToolChoice::Any => {
+            if tools.is_empty() {
+                return Err(ConversionError::InvalidToolChoice(
+                    "tool_choice \"any\" requires at least one tool".to_string(),
+                ));
+            }
+            Ok(tools)
+        }
+        ToolChoice::Tool(name) => {
+            let narrowed: Vec<Tool> = tools
+                .into_iter()
+                .filter(|t| t.tool_specification.name == *name)
+                .collect();
+            if narrowed.is_empty() {
+                return Err(ConversionError::InvalidToolChoice(format!(
+                    "tool '{}' is not defined in tools",
+                    name
+                )));
+            }
+            Ok(narrowed)
+        }
+    }
+}
+
+/// Instruction appended to the current message to enforce tool_choice
+///
+/// Kiro API has no native tool_choice parameter, so the mode is conveyed as an instruction.
+fn tool_choice_instruction(tool_choice: &ToolChoice) -> Option<String> {
+    match tool_choice {
+        ToolChoice::Auto => None,
+        ToolChoice::Any => Some(
+            "You must respond by calling at least one of the available tools. Do not reply with text only."
+                .to_string(),
+        ),
+        ToolChoice::Tool(name) => Some(format!(
+            "You must respond by calling the `{}` tool. Do not call any other tool and do not reply with text only.",
+            name
+        )),
+        ToolChoice::None => Some(
+            "Do not call any tools. Respond with text only.".to_string(),
+        ),
+    }
+}
+
 /// Determine chat trigger type
 /// "AUTO" mode may cause 400 Bad Request errors
 fn determine_chat_trigger_type(_req: &MessagesRequest) -> String {
@@ -970,6 +1011,7 @@ mod tests {
             system: None,
             tools: None,
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: None,
@@ -1049,6 +1091,7 @@ mod tests {
             system: None,
             tools: None, // No tool definitions provided
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: None,
@@ -1114,6 +1157,7 @@ mod tests {
             system: None,
             tools: None,
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: Some(Metadata {
@@ -1146,6 +1190,7 @@ mod tests {
             system: None,
             tools: None,
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: None,
@@ -1502,4 +1547,83 @@ mod tests {
             panic!("Should be Assistant message");
         }
     }
+
+    fn tool_choice_request(tool_choice: serde_json::Value) -> MessagesRequest {
+        use super::super::types::{Message as AnthropicMessage, Tool as AnthropicTool};
+
+        let tool = |name: &str| AnthropicTool {
+            tool_type: None,
+            name: name.to_string(),
+            description: format!("{} tool", name),
+            input_schema: std::collections::HashMap::new(),
+            max_uses: None,
+        };
+
+        MessagesRequest {
+            model: "claude-sonnet-4".to_string(),
+            max_tokens: 1024,
+            messages: vec![AnthropicMessage {
+                role: "user".to_string(),
+                content: serde_json::json!("Hello"),
+            }],
+            stream: false,
+            system: None,
+            tools: Some(vec![tool("read"), tool("write")]),
+            tool_choice: Some(tool_choice),
+            stop_sequences: None,
+            thinking: None,
+            output_config: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_tool_choice_tool_narrows_tools() {
+        let req = tool_choice_request(serde_json::json!({"type": "tool", "name": "write"}));
+        let result = convert_request(&req).unwrap();
+        let user_input = &result.conversation_state.current_message.user_input_message;
+
+        let tools = &user_input.user_input_message_context.tools;
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].tool_specification.name, "write");
+        assert!(user_input.content.starts_with("Hello\n\n"));
+        assert!(user_input.content.contains("`write` tool"));
+    }
+
+    #[test]
+    fn test_tool_choice_tool_unknown_name() {
+        let req = tool_choice_request(serde_json::json!({"type": "tool", "name": "missing"}));
+        assert!(matches!(
+            convert_request(&req),
+            Err(ConversionError::InvalidToolChoice(_))
+        ));
+    }
+
+    #[test]
+    fn test_tool_choice_none_drops_tools() {
+        let req = tool_choice_request(serde_json::json!({"type": "none"}));
+        let result = convert_request(&req).unwrap();
+        let user_input = &result.conversation_state.current_message.user_input_message;
+
+        assert!(user_input.user_input_message_context.tools.is_empty());
+        assert!(user_input.content.contains("Do not call any tools"));
+    }
+
+    #[test]
+    fn test_tool_choice_any_keeps_tools() {
+        let req = tool_choice_request(serde_json::json!({"type": "any"}));
+        let result = convert_request(&req).unwrap();
+        let user_input = &result.conversation_state.current_message.user_input_message;
+
+        assert_eq!(user_input.user_input_message_context.tools.len(), 2);
+        assert!(user_input.content.contains("at least one of the available tools"));
+
+        // Auto leaves content untouched
+        let req = tool_choice_request(serde_json::json!({"type": "auto"}));
+        let result = convert_request(&req).unwrap();
+        assert_eq!(
+            result.conversation_state.current_message.user_input_message.content,
+            "Hello"
+        );
+    }
 }
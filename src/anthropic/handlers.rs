@@ -2,35 +2,49 @@
 
 use std::convert::Infallible;
 
-use crate::kiro::model::events::Event;
+use crate::kiro::model::events::{Event, MeteringUsage};
 use crate::kiro::model::requests::kiro::KiroRequest;
 use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::provider::{ApiResponse, KiroProvider};
+use crate::metrics::{self, RequestModel};
+use crate::model::catalog::{self, ModelEntry};
 use crate::token;
 use axum::{
     Json as JsonExtractor,
     body::Body,
-    extract::State,
+    extract::{Extension, State},
     http::{StatusCode, header},
     response::{IntoResponse, Json, Response},
 };
 use bytes::Bytes;
-use futures::{Stream, StreamExt, stream};
+use futures::{Stream, StreamExt, stream, stream::BoxStream};
 use serde_json::json;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::time::interval;
 use uuid::Uuid;
 
+use super::capture::{Capture, CaptureEndpoint};
 use super::converter::{ConversionError, convert_request, inject_agentic_prompt};
+use super::keyring::ClientKey;
 use super::middleware::AppState;
-use super::stream::{BufferedStreamContext, SseEvent, StreamContext};
-use super::types::{CountTokensRequest, CountTokensResponse, ErrorResponse, MessagesRequest, Model, ModelsResponse, OutputConfig, Thinking};
+use super::stop_sequence::StopSequenceScanner;
+use super::stream::{
+    BufferedStreamContext, SseEvent, SseStateManager, StreamContext, upstream_error_type,
+};
+use super::thinking::{ThinkingChunk, ThinkingFormat, ThinkingParser};
+use super::truncation;
+use super::types::{
+    CountTokensRequest, CountTokensResponse, ErrorResponse, MessagesRequest, Model, ModelsResponse,
+    OutputConfig, Thinking, ToolChoice, get_context_window_size,
+};
 use super::websearch;
 
 /// Convert Kiro API error to Anthropic-compatible error response
 /// 
 /// Maps Kiro error messages to appropriate Anthropic error types and status codes
 /// to ensure client compatibility (e.g., Claude Code auto-compress triggers)
-fn convert_kiro_error_to_response(error_message: &str) -> Response {
+pub(super) fn convert_kiro_error_to_response(error_message: &str) -> Response {
     let error_lower = error_message.to_lowercase();
     
     // Check for quota exhausted errors (all credentials used up)
@@ -98,195 +112,41 @@ fn convert_kiro_error_to_response(error_message: &str) -> Response {
 
 /// GET /v1/models
 ///
-/// Returns the list of available models
+/// Returns the listed models of the model catalog, each with its `-thinking` variant
+/// (when thinking is supported), followed by the `-agentic` variants
 pub async fn get_models() -> impl IntoResponse {
     tracing::info!("Received GET /v1/models request");
 
-    let models = vec![
-        Model {
-            id: "claude-sonnet-4-5-20250929".to_string(),
-            object: "model".to_string(),
-            created: 1727568000,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Sonnet 4.5".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-sonnet-4-5-20250929-thinking".to_string(),
-            object: "model".to_string(),
-            created: 1727568000,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Sonnet 4.5 (Thinking)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-opus-4-5-20251101".to_string(),
-            object: "model".to_string(),
-            created: 1730419200,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.5".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-opus-4-5-20251101-thinking".to_string(),
-            object: "model".to_string(),
-            created: 1730419200,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.5 (Thinking)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        // Opus 4.6 - 200K context (standard)
-        Model {
-            id: "claude-opus-4-6".to_string(),
-            object: "model".to_string(),
-            created: 1770314400,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.6".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(128_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-opus-4-6-thinking".to_string(),
-            object: "model".to_string(),
-            created: 1770314400,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.6 (Thinking)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(128_000),
-            thinking: Some(true),
-        },
-        // Opus 4.6 - 1M context (large projects)
-        Model {
-            id: "claude-opus-4-6-1m".to_string(),
-            object: "model".to_string(),
-            created: 1770314400,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.6 (1M Context)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(1_000_000),
-            max_completion_tokens: Some(128_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-opus-4-6-1m-thinking".to_string(),
-            object: "model".to_string(),
-            created: 1770314400,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.6 (1M Context, Thinking)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(1_000_000),
-            max_completion_tokens: Some(128_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-haiku-4-5-20251001".to_string(),
-            object: "model".to_string(),
-            created: 1727740800,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Haiku 4.5".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-haiku-4-5-20251001-thinking".to_string(),
-            object: "model".to_string(),
-            created: 1727740800,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Haiku 4.5 (Thinking)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        // Agentic variants - with chunked write system prompt
-        Model {
-            id: "claude-sonnet-4-5-20250929-agentic".to_string(),
-            object: "model".to_string(),
-            created: 1727568000,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Sonnet 4.5 (Agentic)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-opus-4-5-20251101-agentic".to_string(),
-            object: "model".to_string(),
-            created: 1730419200,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.5 (Agentic)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-opus-4-6-agentic".to_string(),
-            object: "model".to_string(),
-            created: 1770314400,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.6 (Agentic)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(128_000),
-            thinking: Some(true),
+    let catalog = catalog::current();
+    let listed: Vec<_> = catalog.models().iter().filter(|m| m.listed).collect();
+    let model = |entry: &ModelEntry, suffix: &str, label: &str| Model {
+        id: format!("{}{}", entry.id, suffix),
+        object: "model".to_string(),
+        created: entry.created,
+        owned_by: "anthropic".to_string(),
+        display_name: if label.is_empty() {
+            entry.display_name.clone()
+        } else {
+            format!("{} ({})", entry.display_name, label)
         },
-        Model {
-            id: "claude-opus-4-6-1m-agentic".to_string(),
-            object: "model".to_string(),
-            created: 1770314400,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Opus 4.6 (1M, Agentic)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(1_000_000),
-            max_completion_tokens: Some(128_000),
-            thinking: Some(true),
-        },
-        Model {
-            id: "claude-haiku-4-5-20251001-agentic".to_string(),
-            object: "model".to_string(),
-            created: 1727740800,
-            owned_by: "anthropic".to_string(),
-            display_name: "Claude Haiku 4.5 (Agentic)".to_string(),
-            model_type: "chat".to_string(),
-            max_tokens: 32000,
-            context_length: Some(200_000),
-            max_completion_tokens: Some(64_000),
-            thinking: Some(true),
-        },
-    ];
+        model_type: "chat".to_string(),
+        max_tokens: entry.max_output,
+        context_length: Some(entry.context_window as i64),
+        max_completion_tokens: Some(entry.max_output as i64),
+        thinking: Some(entry.thinking),
+    };
+
+    let mut models = Vec::new();
+    for entry in &listed {
+        models.push(model(entry, "", ""));
+        if entry.thinking {
+            models.push(model(entry, "-thinking", "Thinking"));
+        }
+    }
+    // Agentic variants - with chunked write system prompt
+    for entry in &listed {
+        models.push(model(entry, "-agentic", "Agentic"));
+    }
 
     Json(ModelsResponse {
         object: "list".to_string(),
@@ -294,13 +154,32 @@ pub async fn get_models() -> impl IntoResponse {
     })
 }
 
+/// GET /metrics
+///
+/// Prometheus metrics in text exposition format, credential gauges are refreshed on each scrape
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    if let Some(provider) = &state.kiro_provider {
+        metrics::update_credentials(&provider.token_manager().snapshot());
+    }
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        metrics::render(),
+    )
+}
+
 /// POST /v1/messages
 ///
 /// Create a message (conversation)
 pub async fn post_messages(
     State(state): State<AppState>,
+    client_key: Option<Extension<ClientKey>>,
+    request_model: Option<Extension<RequestModel>>,
     JsonExtractor(mut payload): JsonExtractor<MessagesRequest>,
 ) -> Response {
+    let client_key = client_key.map(|Extension(key)| key);
+    if let Some(Extension(request_model)) = &request_model {
+        request_model.set(&payload.model);
+    }
     tracing::info!(
         model = %payload.model,
         max_tokens = %payload.max_tokens,
@@ -324,20 +203,13 @@ pub async fn post_messages(
         }
     };
 
-    // Detect if model name contains thinking suffix, if so override thinking config
-    let thinking_suffix = state.config.thinking_suffix();
-    override_thinking_from_model_name(&mut payload, thinking_suffix);
+    // Apply thinking and agentic model name suffixes
+    let config = state.config.load();
+    apply_model_suffixes(&mut payload, config.thinking_suffix());
 
-    // Detect if model name contains agentic suffix, if so inject agentic prompt
-    let is_agentic = detect_and_strip_agentic_suffix(&mut payload);
-    if is_agentic {
-        let current_system = payload
-            .system
-            .as_ref()
-            .map(|msgs| msgs.iter().map(|m| m.text.as_str()).collect::<Vec<_>>().join("\n"))
-            .unwrap_or_default();
-        let new_system = inject_agentic_prompt(&current_system);
-        payload.system = Some(vec![super::types::SystemMessage { text: new_system }]);
+    // Check the client key's model allow-list
+    if let Some(resp) = reject_disallowed_model(client_key.as_ref(), &payload.model) {
+        return resp;
     }
 
     // Check if this is a WebSearch request
@@ -352,11 +224,105 @@ pub async fn post_messages(
             payload.tools.clone(),
         ) as i32;
 
+        metrics::record_tokens(&payload.model, input_tokens, 0);
+        if let Some(key) = &client_key {
+            key.record_usage(input_tokens, 0);
+        }
         return websearch::handle_websearch_request(provider, &payload, input_tokens).await;
     }
 
-    // Convert request
-    let conversion_result = match convert_request(&payload) {
+    // Convert request and build Kiro request body
+    let request_body = match build_kiro_request_body(&state, &payload) {
+        Ok(body) => body,
+        Err(resp) => return resp,
+    };
+
+    // Record the request for offline replay (captureDir)
+    let mut capture = Capture::start(&config, CaptureEndpoint::Messages, &payload, &request_body);
+
+    let tool_choice = payload.tool_choice_mode();
+    let stop_sequences = payload.stop_sequences.take().unwrap_or_default();
+
+    // Estimate input tokens
+    let input_tokens = token::count_all_tokens(
+        payload.model.clone(),
+        payload.system,
+        payload.messages,
+        payload.tools,
+    ) as i32;
+    if let Some(capture) = capture.as_mut() {
+        capture.set_input_tokens(input_tokens);
+    }
+
+    // Check if thinking is enabled
+    let thinking_enabled = payload
+        .thinking
+        .as_ref()
+        .map(|t| t.is_enabled())
+        .unwrap_or(false);
+
+    if payload.stream {
+        // Streaming response
+        handle_stream_request(
+            provider,
+            &request_body,
+            &payload.model,
+            input_tokens,
+            thinking_enabled,
+            tool_choice,
+            client_key,
+            stop_sequences,
+            capture,
+        )
+        .await
+    } else {
+        // Non-streaming response
+        let thinking = thinking_enabled
+            .then(|| ThinkingFormat::from_config(config.thinking_format()));
+        handle_non_stream_request(
+            provider,
+            &request_body,
+            &payload.model,
+            input_tokens,
+            thinking,
+            tool_choice,
+            client_key,
+            stop_sequences,
+            capture,
+        )
+        .await
+    }
+}
+
+/// Reject the request if the client key's model allow-list does not include the model
+pub(super) fn reject_disallowed_model(client_key: Option<&ClientKey>, model: &str) -> Option<Response> {
+    let key = client_key?;
+    if key.allows_model(model) {
+        return None;
+    }
+    tracing::warn!(key = %key.name, model = %model, "Model not allowed for API key");
+    Some(
+        (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse::new(
+                "permission_error",
+                format!("API key '{}' is not allowed to use model: {}", key.name, model),
+            )),
+        )
+            .into_response(),
+    )
+}
+
+/// Convert an Anthropic request into a serialized Kiro request body
+///
+/// Performs request conversion, serialization and the request body size pre-check.
+/// On failure returns a ready-to-send error response.
+#[allow(clippy::result_large_err)]
+pub(super) fn build_kiro_request_body(
+    state: &AppState,
+    payload: &MessagesRequest,
+) -> Result<String, Response> {
+    let conversion_result = match convert_request(payload) {
         Ok(result) => result,
         Err(e) => {
             let (error_type, message) = match &e {
@@ -366,13 +332,14 @@ pub async fn post_messages(
                 ConversionError::EmptyMessages => {
                     ("invalid_request_error", "Message list is empty".to_string())
                 }
+                ConversionError::InvalidToolChoice(_) => ("invalid_request_error", e.to_string()),
             };
             tracing::warn!("Request conversion failed: {}", e);
-            return (
+            return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ErrorResponse::new(error_type, message)),
             )
-                .into_response();
+                .into_response());
         }
     };
 
@@ -384,29 +351,29 @@ pub async fn post_messages(
         "Request conversion completed"
     );
 
-    // Build Kiro request
+    // Build Kiro request (profileArn is injected by KiroProvider for the selected credential)
     let kiro_request = KiroRequest {
         conversation_state: conversion_result.conversation_state,
-        profile_arn: state.profile_arn.clone(),
+        profile_arn: None,
     };
 
     let request_body = match serde_json::to_string(&kiro_request) {
         Ok(body) => body,
         Err(e) => {
             tracing::error!("Failed to serialize request: {}", e);
-            return (
+            return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse::new(
                     "internal_error",
                     format!("Failed to serialize request: {}", e),
                 )),
             )
-                .into_response();
+                .into_response());
         }
     };
 
     // Request body size pre-check
-    let max_body = state.config.max_request_body_bytes;
+    let max_body = state.config.load().max_request_body_bytes;
     if max_body > 0 && request_body.len() > max_body {
         tracing::warn!(
             request_body_bytes = request_body.len(),
@@ -415,74 +382,57 @@ pub async fn post_messages(
             request_body.len(),
             max_body
         );
-        return (
+        return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse::new(
                 "invalid_request_error",
                 "Input is too long for model context window.",
             )),
         )
-            .into_response();
+            .into_response());
     }
 
     tracing::debug!("Kiro request body: {}", request_body);
 
-    // Estimate input tokens
-    let input_tokens = token::count_all_tokens(
-        payload.model.clone(),
-        payload.system,
-        payload.messages,
-        payload.tools,
-    ) as i32;
-
-    // Check if thinking is enabled
-    let thinking_enabled = payload
-        .thinking
-        .as_ref()
-        .map(|t| t.is_enabled())
-        .unwrap_or(false);
-
-    if payload.stream {
-        // Streaming response
-        handle_stream_request(
-            provider,
-            &request_body,
-            &payload.model,
-            input_tokens,
-            thinking_enabled,
-        )
-        .await
-    } else {
-        // Non-streaming response
-        handle_non_stream_request(provider, &request_body, &payload.model, input_tokens).await
-    }
+    Ok(request_body)
 }
 
 /// Handle streaming request
+#[allow(clippy::too_many_arguments)]
 async fn handle_stream_request(
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
     request_body: &str,
     model: &str,
     input_tokens: i32,
     thinking_enabled: bool,
+    tool_choice: ToolChoice,
+    client_key: Option<ClientKey>,
+    stop_sequences: Vec<String>,
+    mut capture: Option<Capture>,
 ) -> Response {
-    // Call Kiro API (supports multi-credential failover)
-    let response = match provider.call_api_stream(request_body).await {
-        Ok(resp) => resp,
+    // Call Kiro API (supports multi-credential failover, also before the first content)
+    let (body, guard) = match call_api_stream_committed(&provider, request_body).await {
+        Ok(committed) => committed,
         Err(e) => {
             tracing::error!("Kiro API call failed: {}", e);
+            if let Some(capture) = capture.as_mut() {
+                capture.record_error(&e.to_string());
+            }
             return convert_kiro_error_to_response(&e.to_string());
         }
     };
 
     // Create stream processing context
-    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled);
+    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled)
+        .with_tool_choice(tool_choice)
+        .with_client_key(client_key)
+        .with_stop_sequences(stop_sequences);
 
     // Generate initial events
     let initial_events = ctx.generate_initial_events();
 
     // Create SSE stream
-    let stream = create_sse_stream(response, ctx, initial_events);
+    let stream = create_sse_stream(body, guard, ctx, initial_events, capture);
 
     // Return SSE response
     Response::builder()
@@ -504,23 +454,284 @@ fn create_ping_sse() -> Bytes {
 
 /// Create SSE event stream
 fn create_sse_stream(
-    response: reqwest::Response,
+    body: UpstreamBody,
+    guard: DisconnectGuard,
     ctx: StreamContext,
     initial_events: Vec<SseEvent>,
+    capture: Option<Capture>,
 ) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    create_event_stream(body, guard, ctx, initial_events, capture)
+        .map(|e| Ok(Bytes::from(e.to_sse_string())))
+}
+
+/// Upstream response body as a stream of chunks
+pub(super) type UpstreamBody = BoxStream<'static, reqwest::Result<Bytes>>;
+
+/// Retries of a streaming request whose response broke before the first content
+const MAX_STREAM_START_RETRIES: usize = 2;
+
+/// Call the Kiro streaming API and wait for the first content (pre-commit phase)
+///
+/// Nothing is sent to the client until the upstream produced assistant text or a tool call.
+/// If it fails with a transient or credential error, breaks, or stalls for `firstContentTimeoutSecs`
+/// before that, the request is sent again on another credential, invisibly to the client. Errors
+/// that would repeat on any credential (e.g. `ContentLengthExceededException`) are passed through,
+/// as is the last attempt. Chunks read while waiting are replayed at the start of the returned body.
+pub(super) async fn call_api_stream_committed(
+    provider: &Arc<KiroProvider>,
+    request_body: &str,
+) -> anyhow::Result<(UpstreamBody, DisconnectGuard)> {
+    let token_manager = provider.token_manager();
+    let timeout = Duration::from_secs(token_manager.config().first_content_timeout_secs);
+    let mut response = provider.call_api_stream(request_body).await?;
+    let mut attempt = 0;
+
+    loop {
+        let credential_id = response.credential_id;
+        let mut body = response.response.bytes_stream().boxed();
+        let (held, failure) = read_until_first_content(&mut body, timeout).await;
+
+        match &failure {
+            None => token_manager.report_success(credential_id),
+            Some(StreamStartFailure::Credential(_)) => {
+                token_manager.report_failure(credential_id);
+            }
+            Some(StreamStartFailure::Transient(_)) => {}
+        }
+
+        match failure {
+            Some(failure) if attempt < MAX_STREAM_START_RETRIES => {
+                tracing::warn!(
+                    credential_id,
+                    "Streaming response failed before first content (attempt {}/{}), retrying: {}",
+                    attempt + 1,
+                    MAX_STREAM_START_RETRIES + 1,
+                    failure
+                );
+                metrics::record_upstream_retry("stream_start");
+                drop(body);
+                response = provider.retry_api_stream(request_body, credential_id).await?;
+                attempt += 1;
+            }
+            _ => {
+                let body = stream::iter(held).chain(body).boxed();
+                return Ok((body, DisconnectGuard::new(provider.clone(), credential_id)));
+            }
+        }
+    }
+}
+
+/// Why a streaming response failed before its first content
+enum StreamStartFailure {
+    /// Timeout, broken connection or an upstream error that may not repeat
+    Transient(String),
+    /// The upstream rejected the credential (counted as a credential failure)
+    Credential(String),
+}
+
+impl StreamStartFailure {
+    /// Classify an upstream error code or exception type
+    ///
+    /// Returns `None` for errors that would repeat on any credential, such as
+    /// `ContentLengthExceededException` (rendered as `max_tokens`) or validation errors.
+    fn classify(code: &str, message: &str) -> Option<Self> {
+        let reason = format!("{}: {}", code, message);
+        let lower = code.to_lowercase();
+        if ["unauthorized", "expiredtoken", "invalidtoken", "accessdenied"]
+            .iter()
+            .any(|kind| lower.contains(kind))
+        {
+            return Some(Self::Credential(reason));
+        }
+        match upstream_error_type(code, message) {
+            "rate_limit_error" | "overloaded_error" => Some(Self::Transient(reason)),
+            "api_error"
+                if ["internal", "server", "timeout", "streamerror"]
+                    .iter()
+                    .any(|kind| lower.contains(kind)) =>
+            {
+                Some(Self::Transient(reason))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for StreamStartFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Transient(reason) => write!(f, "{}", reason),
+            Self::Credential(reason) => write!(f, "{} (credential error)", reason),
+        }
+    }
+}
+
+/// Read the upstream body until the first assistant text or tool call
+///
+/// Returns the chunks read so far and, if the upstream failed before any content in a way
+/// another attempt could fix, the reason. A body that ends without content is not a failure.
+async fn read_until_first_content(
+    body: &mut UpstreamBody,
+    timeout: Duration,
+) -> (Vec<reqwest::Result<Bytes>>, Option<StreamStartFailure>) {
+    let deadline = (!timeout.is_zero()).then(|| tokio::time::Instant::now() + timeout);
+    let mut decoder = EventStreamDecoder::new();
+    let mut held = Vec::new();
+
+    loop {
+        let next = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, body.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    let reason = format!("no content within {}s", timeout.as_secs());
+                    return (held, Some(StreamStartFailure::Transient(reason)));
+                }
+            },
+            None => body.next().await,
+        };
+        let chunk = match next {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(e)) => {
+                let reason = format!("failed to read response stream: {}", e);
+                held.push(Err(e));
+                return (held, Some(StreamStartFailure::Transient(reason)));
+            }
+            None => return (held, None),
+        };
+
+        // Some(None): content arrived or a final error, Some(Some(failure)): retryable error
+        let mut outcome: Option<Option<StreamStartFailure>> = None;
+        process_stream_chunk(&mut decoder, &chunk, |event| {
+            if outcome.is_some() {
+                return;
+            }
+            outcome = match event {
+                Event::AssistantResponse(resp) if !resp.content.is_empty() => Some(None),
+                Event::ToolUse(_) => Some(None),
+                Event::Error {
+                    error_code,
+                    error_message,
+                } => Some(StreamStartFailure::classify(error_code, error_message)),
+                Event::Exception {
+                    exception_type,
+                    message,
+                } => Some(StreamStartFailure::classify(exception_type, message)),
+                _ => None,
+            };
+        });
+        held.push(Ok(chunk));
+        if let Some(failure) = outcome {
+            return (held, failure);
+        }
+    }
+}
+
+/// Upstream cancellation on client disconnect
+///
+/// Kept in the stream state next to the upstream response. When the client goes away, axum drops
+/// the response body, and with it the upstream response, which aborts the Kiro request.
+/// Dropped before `finish`, the guard logs the cancellation and counts it in stats.
+pub(super) struct DisconnectGuard {
+    provider: Arc<KiroProvider>,
+    credential_id: u64,
+    started_at: Instant,
+    finished: bool,
+}
+
+impl DisconnectGuard {
+    pub(super) fn new(provider: Arc<KiroProvider>, credential_id: u64) -> Self {
+        Self {
+            provider,
+            credential_id,
+            started_at: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// The upstream response was read to the end (or deliberately dropped)
+    ///
+    /// Credits reported by Kiro are added to the credential's stats.
+    fn finish(&mut self, metering: Option<&MeteringUsage>) {
+        self.finished = true;
+        if let Some(metering) = metering {
+            self.provider
+                .token_manager()
+                .report_credits(self.credential_id, metering.credits());
+        }
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        tracing::info!(
+            credential_id = self.credential_id,
+            elapsed_ms = self.started_at.elapsed().as_millis() as u64,
+            "Client disconnected, upstream request cancelled"
+        );
+        self.provider
+            .token_manager()
+            .report_cancelled(self.credential_id);
+        metrics::record_client_disconnect();
+    }
+}
+
+/// Error message sent when the decoder stops after too many consecutive frame errors
+pub(super) const UNDECODABLE_STREAM_MESSAGE: &str =
+    "Upstream event stream could not be decoded";
+
+/// Decode a chunk of the Kiro event stream and pass every complete event to `handle`
+///
+/// Shared by the live streams and capture replay.
+pub(super) fn process_stream_chunk(
+    decoder: &mut EventStreamDecoder,
+    chunk: &[u8],
+    mut handle: impl FnMut(&Event),
+) {
+    if let Err(e) = decoder.feed(chunk) {
+        tracing::warn!("Buffer overflow: {}", e);
+    }
+
+    for result in decoder.decode_iter() {
+        match result {
+            Ok(frame) => {
+                if let Ok(event) = Event::from_frame(frame) {
+                    handle(&event);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to decode event: {}", e);
+            }
+        }
+    }
+}
+
+/// Create Anthropic event stream (before SSE encoding)
+///
+/// Decodes the Kiro response through `StreamContext` and yields Anthropic SSE events,
+/// including a `ping` event every 25 seconds. Shared by endpoints that render events differently.
+/// Upstream chunks and emitted events (except pings) are recorded to `capture` when given.
+/// The upstream request is cancelled when the stream is dropped early (see `DisconnectGuard`).
+pub(super) fn create_event_stream(
+    body_stream: UpstreamBody,
+    guard: DisconnectGuard,
+    ctx: StreamContext,
+    initial_events: Vec<SseEvent>,
+    mut capture: Option<Capture>,
+) -> impl Stream<Item = SseEvent> {
+    if let Some(capture) = capture.as_mut() {
+        capture.record_events(&initial_events);
+    }
+
     // Send initial events first
-    let initial_stream = stream::iter(
-        initial_events
-            .into_iter()
-            .map(|e| Ok(Bytes::from(e.to_sse_string()))),
-    );
+    let initial_stream = stream::iter(initial_events);
 
     // Then process Kiro response stream, sending ping keepalive every 25 seconds
-    let body_stream = response.bytes_stream();
-
     let processing_stream = stream::unfold(
-        (body_stream, ctx, EventStreamDecoder::new(), false, interval(Duration::from_secs(PING_INTERVAL_SECS))),
-        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval)| async move {
+        (body_stream, ctx, EventStreamDecoder::new(), false, interval(Duration::from_secs(PING_INTERVAL_SECS)), capture, guard),
+        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, mut capture, mut guard)| async move {
             if finished {
                 return None;
             }
@@ -531,60 +742,63 @@ fn create_sse_stream(
                 chunk_result = body_stream.next() => {
                     match chunk_result {
                         Some(Ok(chunk)) => {
-                            // Decode events
-                            if let Err(e) = decoder.feed(&chunk) {
-                                tracing::warn!("Buffer overflow: {}", e);
+                            if let Some(capture) = capture.as_mut() {
+                                capture.record_upstream(&chunk);
                             }
 
                             let mut events = Vec::new();
-                            for result in decoder.decode_iter() {
-                                match result {
-                                    Ok(frame) => {
-                                        if let Ok(event) = Event::from_frame(frame) {
-                                            let sse_events = ctx.process_kiro_event(&event);
-                                            events.extend(sse_events);
-                                        }
-                                    }
-                                    Err(e) => {
-                                        tracing::warn!("Failed to decode event: {}", e);
-                                    }
-                                }
+                            process_stream_chunk(&mut decoder, &chunk, |event| {
+                                events.extend(ctx.process_kiro_event(event));
+                            });
+                            if decoder.is_stopped() {
+                                events.extend(ctx.generate_error_events("api_error", UNDECODABLE_STREAM_MESSAGE));
                             }
 
-                            // Convert to SSE byte stream
-                            let bytes: Vec<Result<Bytes, Infallible>> = events
-                                .into_iter()
-                                .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                .collect();
+                            // Stop sequence matched or upstream error: end the message, the upstream connection is dropped with the stream state
+                            let finished = ctx.is_finished();
+                            if finished {
+                                tracing::debug!("Message finished early, dropping upstream response");
+                                guard.finish(ctx.metering());
+                                events.extend(ctx.generate_final_events());
+                            }
+                            if let Some(capture) = capture.as_mut() {
+                                capture.record_events(&events);
+                            }
 
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)))
+                            Some((stream::iter(events), (body_stream, ctx, decoder, finished, ping_interval, capture, guard)))
                         }
                         Some(Err(e)) => {
                             tracing::error!("Failed to read response stream: {}", e);
-                            // Send final events and end
-                            let final_events = ctx.generate_final_events();
-                            let bytes: Vec<Result<Bytes, Infallible>> = final_events
-                                .into_iter()
-                                .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)))
+                            if let Some(capture) = capture.as_mut() {
+                                capture.record_error(&e.to_string());
+                            }
+                            guard.finish(ctx.metering());
+                            // End the message with an error event
+                            let final_events = ctx.generate_error_events(
+                                "api_error",
+                                &format!("Upstream stream interrupted: {}", e),
+                            );
+                            if let Some(capture) = capture.as_mut() {
+                                capture.record_events(&final_events);
+                            }
+                            Some((stream::iter(final_events), (body_stream, ctx, decoder, true, ping_interval, capture, guard)))
                         }
                         None => {
                             // Stream ended, send final events
+                            guard.finish(ctx.metering());
                             let final_events = ctx.generate_final_events();
-                            let bytes: Vec<Result<Bytes, Infallible>> = final_events
-                                .into_iter()
-                                .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)))
+                            if let Some(capture) = capture.as_mut() {
+                                capture.record_events(&final_events);
+                            }
+                            Some((stream::iter(final_events), (body_stream, ctx, decoder, true, ping_interval, capture, guard)))
                         }
                     }
                 }
                 // Send ping keepalive
                 _ = ping_interval.tick() => {
                     tracing::trace!("Sending ping keepalive event");
-                    let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
-                    Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)))
+                    let ping = vec![SseEvent::new("ping", json!({ "type": "ping" }))];
+                    Some((stream::iter(ping), (body_stream, ctx, decoder, false, ping_interval, capture, guard)))
                 }
             }
         },
@@ -594,171 +808,505 @@ fn create_sse_stream(
     initial_stream.chain(processing_stream)
 }
 
-/// Context window size (200k tokens)
-const CONTEXT_WINDOW_SIZE: i32 = 200_000;
-
 /// Handle non-streaming request
+#[allow(clippy::too_many_arguments)]
 async fn handle_non_stream_request(
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
     request_body: &str,
     model: &str,
     input_tokens: i32,
+    thinking: Option<ThinkingFormat>,
+    tool_choice: ToolChoice,
+    client_key: Option<ClientKey>,
+    stop_sequences: Vec<String>,
+    mut capture: Option<Capture>,
 ) -> Response {
+    match collect_non_stream_message(
+        provider,
+        request_body,
+        model,
+        input_tokens,
+        thinking,
+        &tool_choice,
+        client_key.as_ref(),
+        stop_sequences,
+        capture.as_mut(),
+    )
+    .await
+    {
+        Ok(response_body) => (StatusCode::OK, Json(response_body)).into_response(),
+        Err(resp) => resp,
+    }
+}
+
+/// Call Kiro API and collect the full response as an Anthropic message body
+///
+/// `thinking` is set when thinking is enabled: `<thinking>` markup is then extracted with the
+/// same parser as the streaming path and returned in the configured format.
+/// The response is read incrementally and dropped as soon as a stop sequence matches.
+/// Upstream chunks and the response are recorded to `capture` when given.
+/// On failure returns a ready-to-send error response.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn collect_non_stream_message(
+    provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
+    request_body: &str,
+    model: &str,
+    input_tokens: i32,
+    thinking: Option<ThinkingFormat>,
+    tool_choice: &ToolChoice,
+    client_key: Option<&ClientKey>,
+    stop_sequences: Vec<String>,
+    mut capture: Option<&mut Capture>,
+) -> Result<serde_json::Value, Response> {
     // Call Kiro API (supports multi-credential failover)
-    let response = match provider.call_api(request_body).await {
+    let ApiResponse {
+        response,
+        credential_id,
+    } = match provider.call_api(request_body).await {
         Ok(resp) => resp,
         Err(e) => {
             tracing::error!("Kiro API call failed: {}", e);
-            return convert_kiro_error_to_response(&e.to_string());
+            if let Some(capture) = capture.as_mut() {
+                capture.record_error(&e.to_string());
+            }
+            return Err(convert_kiro_error_to_response(&e.to_string()));
         }
     };
 
-    // Read response body
-    let body_bytes = match response.bytes().await {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            tracing::error!("Failed to read response body: {}", e);
-            return (
+    let mut body_stream = response.bytes_stream();
+    let mut collector = MessageCollector::new(
+        model,
+        input_tokens,
+        thinking,
+        tool_choice.clone(),
+        stop_sequences,
+    );
+
+    // Read the event stream chunk by chunk, so the connection can be dropped on a stop sequence
+    while !collector.stopped()
+        && let Some(chunk_result) = body_stream.next().await
+    {
+        let chunk = match chunk_result {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tracing::error!("Failed to read response body: {}", e);
+                if let Some(capture) = capture.as_mut() {
+                    capture.record_error(&e.to_string());
+                }
+                return Err((
+                    StatusCode::BAD_GATEWAY,
+                    Json(ErrorResponse::new(
+                        "api_error",
+                        format!("Failed to read response: {}", e),
+                    )),
+                )
+                    .into_response());
+            }
+        };
+        if let Some(capture) = capture.as_mut() {
+            capture.record_upstream(&chunk);
+        }
+        collector.feed(&chunk);
+    }
+
+    let collected = collector.finish();
+    if let Some(capture) = capture.as_mut() {
+        capture.record_response(&collected_response_body(&collected));
+    }
+    let message = match collected {
+        Ok(message) => message,
+        Err(message) => {
+            tracing::warn!("{}", message);
+            return Err((
                 StatusCode::BAD_GATEWAY,
-                Json(ErrorResponse::new(
-                    "api_error",
-                    format!("Failed to read response: {}", e),
-                )),
+                Json(ErrorResponse::new("api_error", message)),
             )
-                .into_response();
+                .into_response());
         }
     };
 
-    // Parse event stream
-    let mut decoder = EventStreamDecoder::new();
-    if let Err(e) = decoder.feed(&body_bytes) {
-        tracing::warn!("Buffer overflow: {}", e);
+    metrics::record_tokens(model, message.input_tokens, message.output_tokens);
+    if let Some(key) = client_key {
+        key.record_usage(message.input_tokens, message.output_tokens);
+    }
+    if let Some(metering) = &message.metering {
+        provider
+            .token_manager()
+            .report_credits(credential_id, metering.credits());
     }
 
-    let mut text_content = String::new();
-    let mut tool_uses: Vec<serde_json::Value> = Vec::new();
-    let mut has_tool_use = false;
-    let mut stop_reason = "end_turn".to_string();
-    // Actual input tokens calculated from contextUsageEvent
-    let mut context_input_tokens: Option<i32> = None;
+    Ok(message.body)
+}
 
-    // Collect incremental JSON for tool calls
-    let mut tool_json_buffers: std::collections::HashMap<String, String> =
-        std::collections::HashMap::new();
+/// Anthropic message collected from a Kiro event stream
+pub(super) struct CollectedMessage {
+    pub(super) body: serde_json::Value,
+    pub(super) input_tokens: i32,
+    pub(super) output_tokens: i32,
+    pub(super) metering: Option<MeteringUsage>,
+}
 
-    for result in decoder.decode_iter() {
-        match result {
-            Ok(frame) => {
-                if let Ok(event) = Event::from_frame(frame) {
-                    match event {
-                        Event::AssistantResponse(resp) => {
-                            text_content.push_str(&resp.content);
-                        }
-                        Event::ToolUse(tool_use) => {
-                            has_tool_use = true;
-
-                            // Accumulate tool's JSON input
-                            let buffer = tool_json_buffers
-                                .entry(tool_use.tool_use_id.clone())
-                                .or_insert_with(String::new);
-                            buffer.push_str(&tool_use.input);
-
-                            // If this is a complete tool call, add to list
-                            if tool_use.stop {
-                                let input: serde_json::Value = serde_json::from_str(buffer)
-                                    .unwrap_or_else(|e| {
-                                        tracing::warn!(
-                                            "Failed to parse tool input JSON: {}, tool_use_id: {}, raw content: {}",
-                                            e, tool_use.tool_use_id, buffer
-                                        );
-                                        serde_json::json!({})
-                                    });
-
-                                tool_uses.push(json!({
-                                    "type": "tool_use",
-                                    "id": tool_use.tool_use_id,
-                                    "name": tool_use.name,
-                                    "input": input
-                                }));
-                            }
-                        }
-                        Event::ContextUsage(context_usage) => {
-                            // Calculate actual input_tokens from context usage percentage
-                            // Formula: percentage * 200000 / 100 = percentage * 2000
-                            let actual_input_tokens = (context_usage.context_usage_percentage
-                                * (CONTEXT_WINDOW_SIZE as f64)
-                                / 100.0)
-                                as i32;
-                            context_input_tokens = Some(actual_input_tokens);
-                            // When context usage reaches 100%, set stop_reason to model_context_window_exceeded
-                            if context_usage.context_usage_percentage >= 100.0 {
-                                stop_reason = "model_context_window_exceeded".to_string();
-                            }
-                            tracing::debug!(
-                                "Received contextUsageEvent: {}%, calculated input_tokens: {}",
-                                context_usage.context_usage_percentage,
-                                actual_input_tokens
-                            );
-                        }
-                        Event::Exception { exception_type, .. } => {
-                            if exception_type == "ContentLengthExceededException" {
-                                stop_reason = "max_tokens".to_string();
-                            }
-                        }
-                        _ => {}
+/// Body sent for a collected message: the message, or the error when it was rejected
+pub(super) fn collected_response_body(
+    collected: &Result<CollectedMessage, String>,
+) -> serde_json::Value {
+    match collected {
+        Ok(message) => message.body.clone(),
+        Err(message) => json!(ErrorResponse::new("api_error", message.as_str())),
+    }
+}
+
+/// Collects a Kiro event stream into a non-streaming Anthropic message
+///
+/// Shared by the non-streaming handlers and capture replay.
+pub(super) struct MessageCollector {
+    decoder: EventStreamDecoder,
+    stop_scanner: StopSequenceScanner,
+    model: String,
+    input_tokens: i32,
+    thinking: Option<ThinkingFormat>,
+    tool_choice: ToolChoice,
+    text_content: String,
+    /// Thinking content, Some once a thinking block was found
+    thinking_content: Option<String>,
+    thinking_parser: Option<ThinkingParser>,
+    tool_uses: Vec<serde_json::Value>,
+    /// Soft-failure guidance for tool calls truncated by the output limit
+    soft_failures: Vec<String>,
+    /// stop_reason is resolved with the same priority rules as the streaming path
+    stop_state: SseStateManager,
+    /// Actual input tokens calculated from contextUsageEvent
+    context_input_tokens: Option<i32>,
+    /// Usage reported by meteringEvents
+    metering: Option<MeteringUsage>,
+    context_window: i32,
+    /// Incremental JSON for tool calls (tool_use_id, name, buffer)
+    tool_json_buffers: Vec<(String, String, String)>,
+}
+
+impl MessageCollector {
+    pub(super) fn new(
+        model: &str,
+        input_tokens: i32,
+        thinking: Option<ThinkingFormat>,
+        tool_choice: ToolChoice,
+        stop_sequences: Vec<String>,
+    ) -> Self {
+        Self {
+            decoder: EventStreamDecoder::new(),
+            stop_scanner: StopSequenceScanner::new(stop_sequences),
+            model: model.to_string(),
+            input_tokens,
+            thinking,
+            tool_choice,
+            text_content: String::new(),
+            thinking_content: None,
+            thinking_parser: thinking.map(|_| ThinkingParser::new()),
+            tool_uses: Vec::new(),
+            soft_failures: Vec::new(),
+            stop_state: SseStateManager::new(),
+            context_input_tokens: None,
+            metering: None,
+            context_window: get_context_window_size(model),
+            tool_json_buffers: Vec::new(),
+        }
+    }
+
+    /// Whether a stop sequence matched (the rest of the response is not needed)
+    pub(super) fn stopped(&self) -> bool {
+        self.stop_scanner.matched().is_some()
+    }
+
+    /// Decode an upstream chunk
+    pub(super) fn feed(&mut self, chunk: &[u8]) {
+        let mut events = Vec::new();
+        process_stream_chunk(&mut self.decoder, chunk, |event| events.push(event.clone()));
+        for event in events {
+            // Output after a stop sequence is discarded
+            if self.stopped() {
+                break;
+            }
+            self.process_event(event);
+        }
+    }
+
+    fn process_event(&mut self, event: Event) {
+        match event {
+            Event::AssistantResponse(resp) => {
+                let start = self.text_content.len();
+                match self.thinking_parser.as_mut() {
+                    Some(parser) => collect_thinking_chunks(
+                        parser.push(&resp.content),
+                        &mut self.thinking_content,
+                        &mut self.text_content,
+                    ),
+                    None => self.text_content.push_str(&resp.content),
+                }
+                scan_stop_sequences(&mut self.stop_scanner, &mut self.text_content, start);
+            }
+            Event::ToolUse(tool_use) => {
+                let start = self.text_content.len();
+                if let Some(parser) = self.thinking_parser.as_mut() {
+                    collect_thinking_chunks(
+                        parser.flush_before_tool_use(),
+                        &mut self.thinking_content,
+                        &mut self.text_content,
+                    );
+                }
+                scan_stop_sequences(&mut self.stop_scanner, &mut self.text_content, start);
+                if self.stopped() {
+                    return;
+                }
+                self.text_content.push_str(&self.stop_scanner.flush());
+
+                // Accumulate tool's JSON input
+                let pos = match self
+                    .tool_json_buffers
+                    .iter()
+                    .position(|(id, _, _)| *id == tool_use.tool_use_id)
+                {
+                    Some(pos) => pos,
+                    None => {
+                        self.tool_json_buffers.push((
+                            tool_use.tool_use_id.clone(),
+                            tool_use.name.clone(),
+                            String::new(),
+                        ));
+                        self.tool_json_buffers.len() - 1
                     }
+                };
+                self.tool_json_buffers[pos].2.push_str(&tool_use.input);
+
+                // If this is a complete tool call, add to list
+                if tool_use.stop {
+                    let (id, name, buffer) = self.tool_json_buffers.remove(pos);
+                    self.finish_tool_use(id, name, buffer);
                 }
             }
-            Err(e) => {
-                tracing::warn!("Failed to decode event: {}", e);
+            Event::ContextUsage(context_usage) => {
+                // Calculate actual input_tokens from context usage percentage
+                // Formula: percentage * context window / 100
+                let actual_input_tokens = (context_usage.context_usage_percentage
+                    * (self.context_window as f64)
+                    / 100.0) as i32;
+                self.context_input_tokens = Some(actual_input_tokens);
+                // When context usage reaches 100%, set stop_reason to model_context_window_exceeded
+                if context_usage.context_usage_percentage >= 100.0 {
+                    self.stop_state
+                        .set_stop_reason("model_context_window_exceeded");
+                }
+                tracing::debug!(
+                    "Received contextUsageEvent: {}%, calculated input_tokens: {}",
+                    context_usage.context_usage_percentage,
+                    actual_input_tokens
+                );
+            }
+            Event::Metering(event) => {
+                tracing::debug!("Received meteringEvent: {}", event);
+                self.metering.get_or_insert_default().add(&event);
             }
+            Event::Exception { exception_type, .. }
+                if exception_type == "ContentLengthExceededException" =>
+            {
+                self.stop_state.set_stop_reason("max_tokens");
+            }
+            _ => {}
         }
     }
 
-    // Determine stop_reason
-    if has_tool_use && stop_reason == "end_turn" {
-        stop_reason = "tool_use".to_string();
+    /// Validate a complete tool call, truncated calls become soft-failure guidance
+    fn finish_tool_use(&mut self, id: String, name: String, buffer: String) {
+        match truncation::parse_tool_input(&name, &id, &buffer) {
+            Ok(input) => self.tool_uses.push(json!({
+                "type": "tool_use",
+                "id": id,
+                "name": name,
+                "input": input
+            })),
+            Err(info) => self
+                .soft_failures
+                .push(truncation::build_soft_failure_result(&info)),
+        }
     }
 
-    // Build response content
-    let mut content: Vec<serde_json::Value> = Vec::new();
+    /// Build the message, or the error message when a forced tool_choice was not honored
+    pub(super) fn finish(mut self) -> Result<CollectedMessage, String> {
+        // Flush content held back by the thinking parser and the stop sequence scanner
+        if let Some(parser) = self.thinking_parser.as_mut() {
+            let start = self.text_content.len();
+            collect_thinking_chunks(
+                parser.finish(),
+                &mut self.thinking_content,
+                &mut self.text_content,
+            );
+            scan_stop_sequences(&mut self.stop_scanner, &mut self.text_content, start);
+        }
+        self.text_content.push_str(&self.stop_scanner.flush());
+
+        if let Some(sequence) = self.stop_scanner.matched() {
+            tracing::debug!(
+                "Stop sequence matched, upstream response dropped: {:?}",
+                sequence
+            );
+            self.stop_state.set_stop_sequence(sequence);
+        } else {
+            // Tool calls that never received stop=true were cut off by the stream end
+            for (id, name, buffer) in std::mem::take(&mut self.tool_json_buffers) {
+                tracing::warn!(
+                    "Tool call incomplete at stream end: tool={} id={}",
+                    name,
+                    id
+                );
+                self.finish_tool_use(id, name, buffer);
+            }
+        }
+
+        // Determine stop_reason
+        self.stop_state.set_has_tool_use(!self.tool_uses.is_empty());
+        if !self.soft_failures.is_empty() && self.tool_uses.is_empty() {
+            // Only truncated tool calls: output limit was reached
+            self.stop_state.set_stop_reason("max_tokens");
+        }
+
+        // Only a thinking block, no text and no tool_use: the model exhausted the token budget
+        // on thinking. Add a single-space text block so content always has a text block.
+        if self.thinking_content.is_some()
+            && self.text_content.is_empty()
+            && self.tool_uses.is_empty()
+            && self.soft_failures.is_empty()
+        {
+            self.stop_state.set_stop_reason("max_tokens");
+            self.text_content.push(' ');
+        }
+        let stop_reason = self.stop_state.get_stop_reason();
+
+        // Forced tool_choice not honored
+        let called_tools = self
+            .tool_uses
+            .iter()
+            .filter_map(|t| t.get("name").and_then(|n| n.as_str()));
+        if !self.tool_choice.is_satisfied_by(called_tools) {
+            return Err(self.tool_choice.unsatisfied_message());
+        }
+
+        // Build response content
+        let mut content: Vec<serde_json::Value> = Vec::new();
+        let mut reasoning_content = None;
+        let mut text_content = self.text_content;
+
+        if let (Some(thinking), Some(format)) = (self.thinking_content, self.thinking) {
+            match format {
+                ThinkingFormat::Thinking => content.push(json!({
+                    "type": "thinking",
+                    "thinking": thinking
+                })),
+                ThinkingFormat::Think => {
+                    text_content = format!("<think>\n{}\n</think>\n\n{}", thinking, text_content);
+                }
+                ThinkingFormat::ReasoningContent => reasoning_content = Some(thinking),
+            }
+        }
+
+        if !text_content.is_empty() {
+            content.push(json!({
+                "type": "text",
+                "text": text_content
+            }));
+        }
+
+        content.extend(self.tool_uses);
+
+        for guidance in self.soft_failures {
+            content.push(json!({
+                "type": "text",
+                "text": guidance
+            }));
+        }
 
-    if !text_content.is_empty() {
-        content.push(json!({
-            "type": "text",
-            "text": text_content
-        }));
+        // Estimate output tokens
+        let mut output_tokens = token::estimate_output_tokens(&content);
+        if let Some(reasoning) = &reasoning_content {
+            output_tokens += token::count_tokens(reasoning) as i32;
+        }
+
+        // Use input_tokens calculated from contextUsageEvent, fallback to estimate if not available
+        let input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
+
+        // Build Anthropic response
+        let mut body = json!({
+            "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
+            "type": "message",
+            "role": "assistant",
+            "content": content,
+            "model": self.model,
+            "stop_reason": stop_reason,
+            "stop_sequence": self.stop_state.stop_sequence(),
+            "usage": {
+                "input_tokens": input_tokens,
+                "output_tokens": output_tokens
+            }
+        });
+        if let Some(metering) = &self.metering {
+            body["usage"]["kiro_metering"] = metering.to_json();
+        }
+        if let Some(reasoning) = reasoning_content {
+            body["reasoning_content"] = json!(reasoning);
+        }
+
+        Ok(CollectedMessage {
+            body,
+            input_tokens,
+            output_tokens,
+            metering: self.metering,
+        })
     }
+}
+
+/// Scan the text appended since `start` for stop sequences, truncating it on a match
+fn scan_stop_sequences(scanner: &mut StopSequenceScanner, text: &mut String, start: usize) {
+    let appended = text.split_off(start);
+    text.push_str(&scanner.push(&appended));
+}
 
-    content.extend(tool_uses);
-
-    // Estimate output tokens
-    let output_tokens = token::estimate_output_tokens(&content);
-
-    // Use input_tokens calculated from contextUsageEvent, fallback to estimate if not available
-    let final_input_tokens = context_input_tokens.unwrap_or(input_tokens);
-
-    // Build Anthropic response
-    let response_body = json!({
-        "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
-        "type": "message",
-        "role": "assistant",
-        "content": content,
-        "model": model,
-        "stop_reason": stop_reason,
-        "stop_sequence": null,
-        "usage": {
-            "input_tokens": final_input_tokens,
-            "output_tokens": output_tokens
+/// Accumulate thinking parser output into the thinking and text buffers
+fn collect_thinking_chunks(
+    chunks: Vec<ThinkingChunk>,
+    thinking: &mut Option<String>,
+    text: &mut String,
+) {
+    for chunk in chunks {
+        match chunk {
+            ThinkingChunk::Text(s) => text.push_str(&s),
+            ThinkingChunk::ThinkingStart => {
+                thinking.get_or_insert_with(String::new);
+            }
+            ThinkingChunk::Thinking(s) => thinking.get_or_insert_with(String::new).push_str(&s),
+            ThinkingChunk::ThinkingEnd => {}
         }
-    });
+    }
+}
+
+/// Apply the thinking and agentic model name suffixes
+///
+/// Strips the suffixes from the model name, overrides the thinking config for the thinking
+/// suffix and injects the agentic prompt into the system prompt for `-agentic`.
+pub fn apply_model_suffixes(payload: &mut MessagesRequest, thinking_suffix: &str) {
+    override_thinking_from_model_name(payload, thinking_suffix);
 
-    (StatusCode::OK, Json(response_body)).into_response()
+    if detect_and_strip_agentic_suffix(payload) {
+        let current_system = payload
+            .system
+            .as_ref()
+            .map(|msgs| msgs.iter().map(|m| m.text.as_str()).collect::<Vec<_>>().join("\n"))
+            .unwrap_or_default();
+        let new_system = inject_agentic_prompt(&current_system);
+        payload.system = Some(vec![super::types::SystemMessage { text: new_system }]);
+    }
 }
 
 /// Detect if model name contains thinking suffix, if so override thinking config
 ///
-/// - Opus 4.6: Override to adaptive type
+/// - Catalog models with `adaptiveThinking` (Opus 4.6): Override to adaptive type
 /// - Other models: Override to enabled type
 /// - budget_tokens fixed at 20000
 /// - Removes the suffix from model name
@@ -772,12 +1320,11 @@ fn override_thinking_from_model_name(payload: &mut MessagesRequest, thinking_suf
 
     // Remove suffix from model name
     let actual_model = payload.model[..payload.model.len() - thinking_suffix.len()].to_string();
-    let actual_model_lower = actual_model.to_lowercase();
+    let adaptive = catalog::current()
+        .resolve(&actual_model)
+        .is_some_and(|entry| entry.adaptive_thinking);
 
-    let is_opus_4_6 =
-        actual_model_lower.contains("opus") && (actual_model_lower.contains("4-6") || actual_model_lower.contains("4.6"));
-
-    let thinking_type = if is_opus_4_6 {
+    let thinking_type = if adaptive {
         "adaptive"
     } else {
         "enabled"
@@ -798,7 +1345,7 @@ fn override_thinking_from_model_name(payload: &mut MessagesRequest, thinking_suf
         budget_tokens: 20000,
     });
     
-    if is_opus_4_6 {
+    if adaptive {
         payload.output_config = Some(OutputConfig {
             effort: "high".to_string(),
         });
@@ -832,8 +1379,12 @@ fn detect_and_strip_agentic_suffix(payload: &mut MessagesRequest) -> bool {
 ///
 /// Calculate the token count for messages
 pub async fn count_tokens(
+    request_model: Option<Extension<RequestModel>>,
     JsonExtractor(payload): JsonExtractor<CountTokensRequest>,
 ) -> impl IntoResponse {
+    if let Some(Extension(request_model)) = &request_model {
+        request_model.set(&payload.model);
+    }
     tracing::info!(
         model = %payload.model,
         message_count = %payload.messages.len(),
@@ -859,8 +1410,14 @@ pub async fn count_tokens(
 /// - input_tokens in message_start is the accurate value calculated from contextUsageEvent
 pub async fn post_messages_cc(
     State(state): State<AppState>,
+    client_key: Option<Extension<ClientKey>>,
+    request_model: Option<Extension<RequestModel>>,
     JsonExtractor(mut payload): JsonExtractor<MessagesRequest>,
 ) -> Response {
+    let client_key = client_key.map(|Extension(key)| key);
+    if let Some(Extension(request_model)) = &request_model {
+        request_model.set(&payload.model);
+    }
     tracing::info!(
         model = %payload.model,
         max_tokens = %payload.max_tokens,
@@ -885,20 +1442,13 @@ pub async fn post_messages_cc(
         }
     };
 
-    // Detect if model name contains thinking suffix, if so override thinking config
-    let thinking_suffix = state.config.thinking_suffix();
-    override_thinking_from_model_name(&mut payload, thinking_suffix);
+    // Apply thinking and agentic model name suffixes
+    let config = state.config.load();
+    apply_model_suffixes(&mut payload, config.thinking_suffix());
 
-    // Detect if model name contains agentic suffix, if so inject agentic prompt
-    let is_agentic = detect_and_strip_agentic_suffix(&mut payload);
-    if is_agentic {
-        let current_system = payload
-            .system
-            .as_ref()
-            .map(|msgs| msgs.iter().map(|m| m.text.as_str()).collect::<Vec<_>>().join("\n"))
-            .unwrap_or_default();
-        let new_system = inject_agentic_prompt(&current_system);
-        payload.system = Some(vec![super::types::SystemMessage { text: new_system }]);
+    // Check the client key's model allow-list
+    if let Some(resp) = reject_disallowed_model(client_key.as_ref(), &payload.model) {
+        return resp;
     }
 
     // Check if this is a WebSearch request
@@ -913,80 +1463,24 @@ pub async fn post_messages_cc(
             payload.tools.clone(),
         ) as i32;
 
+        metrics::record_tokens(&payload.model, input_tokens, 0);
+        if let Some(key) = &client_key {
+            key.record_usage(input_tokens, 0);
+        }
         return websearch::handle_websearch_request(provider, &payload, input_tokens).await;
     }
 
-    // Convert request
-    let conversion_result = match convert_request(&payload) {
-        Ok(result) => result,
-        Err(e) => {
-            let (error_type, message) = match &e {
-                ConversionError::UnsupportedModel(model) => {
-                    ("invalid_request_error", format!("Model not supported: {}", model))
-                }
-                ConversionError::EmptyMessages => {
-                    ("invalid_request_error", "Message list is empty".to_string())
-                }
-            };
-            tracing::warn!("Request conversion failed: {}", e);
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(error_type, message)),
-            )
-                .into_response();
-        }
-    };
-
-    // Log history message count for debugging
-    let history_count = conversion_result.conversation_state.history.len();
-    tracing::debug!(
-        original_messages = %payload.messages.len(),
-        converted_history = %history_count,
-        "Request conversion completed"
-    );
-
-    // Build Kiro request
-    let kiro_request = KiroRequest {
-        conversation_state: conversion_result.conversation_state,
-        profile_arn: state.profile_arn.clone(),
-    };
-
-    let request_body = match serde_json::to_string(&kiro_request) {
+    // Convert request and build Kiro request body
+    let request_body = match build_kiro_request_body(&state, &payload) {
         Ok(body) => body,
-        Err(e) => {
-            tracing::error!("Failed to serialize request: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "internal_error",
-                    format!("Failed to serialize request: {}", e),
-                )),
-            )
-                .into_response();
-        }
+        Err(resp) => return resp,
     };
 
-    // Request body size pre-check
-    let max_body = state.config.max_request_body_bytes;
-    if max_body > 0 && request_body.len() > max_body {
-        tracing::warn!(
-            request_body_bytes = request_body.len(),
-            threshold = max_body,
-            "Request too large ({} bytes, limit {}). Reduce conversation history or tool output.",
-            request_body.len(),
-            max_body
-        );
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(
-                "invalid_request_error",
-                "Input is too long for model context window.",
-            )),
-        )
-            .into_response();
-    }
+    // Record the request for offline replay (captureDir)
+    let mut capture = Capture::start(&config, CaptureEndpoint::ClaudeCode, &payload, &request_body);
 
-    tracing::debug!("Kiro request body: {}", request_body);
+    let tool_choice = payload.tool_choice_mode();
+    let stop_sequences = payload.stop_sequences.take().unwrap_or_default();
 
     // Estimate input tokens
     let input_tokens = token::count_all_tokens(
@@ -995,6 +1489,9 @@ pub async fn post_messages_cc(
         payload.messages,
         payload.tools,
     ) as i32;
+    if let Some(capture) = capture.as_mut() {
+        capture.set_input_tokens(input_tokens);
+    }
 
     // Check if thinking is enabled
     let thinking_enabled = payload
@@ -1011,11 +1508,28 @@ pub async fn post_messages_cc(
             &payload.model,
             input_tokens,
             thinking_enabled,
+            tool_choice,
+            client_key,
+            stop_sequences,
+            capture,
         )
         .await
     } else {
         // Non-streaming response (reuse existing logic, already uses correct input_tokens)
-        handle_non_stream_request(provider, &request_body, &payload.model, input_tokens).await
+        let thinking = thinking_enabled
+            .then(|| ThinkingFormat::from_config(config.thinking_format()));
+        handle_non_stream_request(
+            provider,
+            &request_body,
+            &payload.model,
+            input_tokens,
+            thinking,
+            tool_choice,
+            client_key,
+            stop_sequences,
+            capture,
+        )
+        .await
     }
 }
 
@@ -1023,27 +1537,38 @@ pub async fn post_messages_cc(
 ///
 /// Unlike `handle_stream_request`, this function buffers all events until stream ends,
 /// then generates message_start event with correct input_tokens calculated from contextUsageEvent.
+#[allow(clippy::too_many_arguments)]
 async fn handle_stream_request_buffered(
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
     request_body: &str,
     model: &str,
     estimated_input_tokens: i32,
     thinking_enabled: bool,
+    tool_choice: ToolChoice,
+    client_key: Option<ClientKey>,
+    stop_sequences: Vec<String>,
+    mut capture: Option<Capture>,
 ) -> Response {
-    // Call Kiro API (supports multi-credential failover)
-    let response = match provider.call_api_stream(request_body).await {
-        Ok(resp) => resp,
+    // Call Kiro API (supports multi-credential failover, also before the first content)
+    let (body, guard) = match call_api_stream_committed(&provider, request_body).await {
+        Ok(committed) => committed,
         Err(e) => {
             tracing::error!("Kiro API call failed: {}", e);
+            if let Some(capture) = capture.as_mut() {
+                capture.record_error(&e.to_string());
+            }
             return convert_kiro_error_to_response(&e.to_string());
         }
     };
 
     // Create buffered stream processing context
-    let ctx = BufferedStreamContext::new(model, estimated_input_tokens, thinking_enabled);
+    let ctx = BufferedStreamContext::new(model, estimated_input_tokens, thinking_enabled)
+        .with_tool_choice(tool_choice)
+        .with_client_key(client_key)
+        .with_stop_sequences(stop_sequences);
 
     // Create buffered SSE stream
-    let stream = create_buffered_sse_stream(response, ctx);
+    let stream = create_buffered_sse_stream(body, guard, ctx, capture);
 
     // Return SSE response
     Response::builder()
@@ -1062,12 +1587,12 @@ async fn handle_stream_request_buffered(
 /// 2. Process all Kiro events using StreamContext's event processing logic, cache results
 /// 3. After stream ends, correct message_start event with correct input_tokens
 /// 4. Send all events at once
-fn create_buffered_sse_stream(
-    response: reqwest::Response,
+pub(super) fn create_buffered_sse_stream(
+    body_stream: UpstreamBody,
+    guard: DisconnectGuard,
     ctx: BufferedStreamContext,
+    capture: Option<Capture>,
 ) -> impl Stream<Item = Result<Bytes, Infallible>> {
-    let body_stream = response.bytes_stream();
-
     stream::unfold(
         (
             body_stream,
@@ -1075,8 +1600,10 @@ fn create_buffered_sse_stream(
             EventStreamDecoder::new(),
             false,
             interval(Duration::from_secs(PING_INTERVAL_SECS)),
+            capture,
+            guard,
         ),
-        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval)| async move {
+        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, mut capture, mut guard)| async move {
             if finished {
                 return None;
             }
@@ -1091,51 +1618,71 @@ fn create_buffered_sse_stream(
                     _ = ping_interval.tick() => {
                         tracing::trace!("Sending ping keepalive event (buffered mode)");
                         let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
-                        return Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)));
+                        return Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, capture, guard)));
                     }
 
                     // Then process data stream
                     chunk_result = body_stream.next() => {
                         match chunk_result {
                             Some(Ok(chunk)) => {
-                                // Decode events
-                                if let Err(e) = decoder.feed(&chunk) {
-                                    tracing::warn!("Buffer overflow: {}", e);
+                                if let Some(capture) = capture.as_mut() {
+                                    capture.record_upstream(&chunk);
+                                }
+
+                                // Buffer events (reuse StreamContext's processing logic)
+                                process_stream_chunk(&mut decoder, &chunk, |event| {
+                                    ctx.process_and_buffer(event);
+                                });
+                                if decoder.is_stopped() {
+                                    ctx.fail("api_error", UNDECODABLE_STREAM_MESSAGE);
                                 }
 
-                                for result in decoder.decode_iter() {
-                                    match result {
-                                        Ok(frame) => {
-                                            if let Ok(event) = Event::from_frame(frame) {
-                                                // Buffer events (reuse StreamContext's processing logic)
-                                                ctx.process_and_buffer(&event);
-                                            }
-                                        }
-                                        Err(e) => {
-                                            tracing::warn!("Failed to decode event: {}", e);
-                                        }
+                                // Stop sequence matched or upstream error: return all events without reading the rest of the response
+                                if ctx.is_finished() {
+                                    tracing::debug!("Message finished early, dropping upstream response");
+                                    guard.finish(ctx.metering());
+                                    let all_events = ctx.finish_and_get_all_events();
+                                    if let Some(capture) = capture.as_mut() {
+                                        capture.record_events(&all_events);
                                     }
+                                    let bytes: Vec<Result<Bytes, Infallible>> = all_events
+                                        .into_iter()
+                                        .map(|e| Ok(Bytes::from(e.to_sse_string())))
+                                        .collect();
+                                    return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, capture, guard)));
                                 }
                                 // Continue reading next chunk, don't send any data
                             }
                             Some(Err(e)) => {
                                 tracing::error!("Failed to read response stream: {}", e);
-                                // Error occurred, finish processing and return all events
+                                if let Some(capture) = capture.as_mut() {
+                                    capture.record_error(&e.to_string());
+                                }
+                                // Error occurred, end the message with an error event and return all events
+                                guard.finish(ctx.metering());
+                                ctx.fail("api_error", &format!("Upstream stream interrupted: {}", e));
                                 let all_events = ctx.finish_and_get_all_events();
+                                if let Some(capture) = capture.as_mut() {
+                                    capture.record_events(&all_events);
+                                }
                                 let bytes: Vec<Result<Bytes, Infallible>> = all_events
                                     .into_iter()
                                     .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                     .collect();
-                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)));
+                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, capture, guard)));
                             }
                             None => {
                                 // Stream ended, finish processing and return all events (with corrected input_tokens)
+                                guard.finish(ctx.metering());
                                 let all_events = ctx.finish_and_get_all_events();
+                                if let Some(capture) = capture.as_mut() {
+                                    capture.record_events(&all_events);
+                                }
                                 let bytes: Vec<Result<Bytes, Infallible>> = all_events
                                     .into_iter()
                                     .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                     .collect();
-                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)));
+                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, capture, guard)));
                             }
                         }
                     }
@@ -0,0 +1,635 @@
+//! Client API keyring
+//!
+//! Additional client API keys besides the master `apiKey` in config.json.
+//! Each key has its own model allow-list, requests-per-minute limit, daily token budget
+//! and usage counters. Keys are persisted to `api_keys.json` next to config.json.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, bail};
+use chrono::Utc;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::common::{auth, encryption};
+
+/// Keyring file name (stored in the same directory as config.json)
+pub const KEYRING_FILE_NAME: &str = "api_keys.json";
+
+/// Sliding window for the requests-per-minute limit
+const RPM_WINDOW: Duration = Duration::from_secs(60);
+
+/// Usage counters persistence debounce interval
+const USAGE_SAVE_DEBOUNCE: Duration = Duration::from_secs(30);
+
+/// Per-key usage counters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyUsage {
+    /// Total authenticated requests
+    #[serde(default)]
+    pub total_requests: u64,
+    /// Total input tokens
+    #[serde(default)]
+    pub input_tokens: u64,
+    /// Total output tokens
+    #[serde(default)]
+    pub output_tokens: u64,
+    /// Current accounting day (UTC, YYYY-MM-DD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<String>,
+    /// Requests on the current day
+    #[serde(default)]
+    pub day_requests: u64,
+    /// Tokens (input + output) on the current day
+    #[serde(default)]
+    pub day_tokens: u64,
+    /// Last request time (RFC3339 format)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+}
+
+impl ApiKeyUsage {
+    /// Reset daily counters when the UTC day changed
+    fn roll_day(&mut self, today: &str) {
+        if self.day.as_deref() != Some(today) {
+            self.day = Some(today.to_string());
+            self.day_requests = 0;
+            self.day_tokens = 0;
+        }
+    }
+}
+
+/// Configurable settings of an API key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeySettings {
+    /// Display name (unique)
+    pub name: String,
+    /// Allowed models (None = all models, trailing `*` matches a prefix)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_models: Option<Vec<String>>,
+    /// Requests-per-minute limit (None = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpm_limit: Option<u32>,
+    /// Daily token budget, input + output (None = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_token_budget: Option<u64>,
+    /// Whether the key is enabled
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Persisted API key entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyEntry {
+    /// Key unique ID
+    pub id: u64,
+    /// Secret key value
+    pub key: String,
+    #[serde(flatten)]
+    pub settings: ApiKeySettings,
+    /// Creation time (RFC3339 format)
+    pub created_at: String,
+    /// Usage counters
+    #[serde(default)]
+    pub usage: ApiKeyUsage,
+}
+
+/// Reason an API key was recognized but rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyRejection {
+    /// Key is disabled
+    Disabled { name: String },
+    /// Requests-per-minute limit reached
+    RateLimited { name: String, limit: u32 },
+    /// Daily token budget exhausted
+    BudgetExceeded { name: String, budget: u64 },
+}
+
+impl fmt::Display for KeyRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyRejection::Disabled { name } => write!(f, "API key '{}' is disabled", name),
+            KeyRejection::RateLimited { name, limit } => write!(
+                f,
+                "API key '{}' exceeded its rate limit of {} requests per minute",
+                name, limit
+            ),
+            KeyRejection::BudgetExceeded { name, budget } => write!(
+                f,
+                "API key '{}' exhausted its daily token budget of {}",
+                name, budget
+            ),
+        }
+    }
+}
+
+impl KeyRejection {
+    /// Anthropic error type for the rejection
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            KeyRejection::Disabled { .. } => "permission_error",
+            KeyRejection::RateLimited { .. } | KeyRejection::BudgetExceeded { .. } => {
+                "rate_limit_error"
+            }
+        }
+    }
+
+    /// HTTP status code for the rejection
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        match self {
+            KeyRejection::Disabled { .. } => axum::http::StatusCode::FORBIDDEN,
+            KeyRejection::RateLimited { .. } | KeyRejection::BudgetExceeded { .. } => {
+                axum::http::StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+}
+
+/// Authenticated keyring key (inserted into request extensions by the auth middleware)
+#[derive(Clone)]
+pub struct ClientKey {
+    /// Key ID
+    pub id: u64,
+    /// Key name
+    pub name: String,
+    allowed_models: Option<Vec<String>>,
+    keyring: Arc<ApiKeyring>,
+}
+
+impl ClientKey {
+    /// Check if the key may use the given model
+    pub fn allows_model(&self, model: &str) -> bool {
+        model_allowed(self.allowed_models.as_deref(), model)
+    }
+
+    /// Record token usage of a finished request
+    pub fn record_usage(&self, input_tokens: i32, output_tokens: i32) {
+        self.keyring.record_usage(
+            self.id,
+            input_tokens.max(0) as u64,
+            output_tokens.max(0) as u64,
+        );
+    }
+}
+
+/// Check a model name against an allow-list
+fn model_allowed(allowed_models: Option<&[String]>, model: &str) -> bool {
+    let Some(allowed) = allowed_models else {
+        return true;
+    };
+    allowed
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => model.starts_with(prefix),
+            None => pattern == model,
+        })
+}
+
+/// Keyring entry with runtime state
+struct KeyState {
+    entry: ApiKeyEntry,
+    /// Request times within the RPM window
+    recent_requests: VecDeque<Instant>,
+}
+
+/// Client API keyring
+pub struct ApiKeyring {
+    /// Keyring file path (None = in-memory only)
+    path: Option<PathBuf>,
+    keys: Mutex<Vec<KeyState>>,
+    /// Last usage persistence time (for debounce)
+    last_save_at: Mutex<Option<Instant>>,
+    /// Whether usage counters have unsaved updates
+    dirty: AtomicBool,
+}
+
+impl Default for ApiKeyring {
+    fn default() -> Self {
+        Self::from_entries(None, Vec::new())
+    }
+}
+
+impl ApiKeyring {
+    fn from_entries(path: Option<PathBuf>, entries: Vec<ApiKeyEntry>) -> Self {
+        Self {
+            path,
+            keys: Mutex::new(
+                entries
+                    .into_iter()
+                    .map(|entry| KeyState {
+                        entry,
+                        recent_requests: VecDeque::new(),
+                    })
+                    .collect(),
+            ),
+            last_save_at: Mutex::new(None),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Keyring path for a config file (same directory as config.json)
+    pub fn path_for_config(config_path: &Path) -> PathBuf {
+        config_path
+            .parent()
+            .map(|dir| dir.join(KEYRING_FILE_NAME))
+            .unwrap_or_else(|| PathBuf::from(KEYRING_FILE_NAME))
+    }
+
+    /// Load keyring from file (a missing file yields an empty keyring)
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let entries: Vec<ApiKeyEntry> = if path.exists() {
+            let content = encryption::read_to_string(&path)
+                .with_context(|| format!("Failed to read keyring file: {}", path.display()))?;
+            if content.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse keyring file: {}", path.display()))?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Self::from_entries(Some(path), entries))
+    }
+
+    /// Number of configured keys
+    pub fn count(&self) -> usize {
+        self.keys.lock().len()
+    }
+
+    /// Snapshot of all key entries
+    pub fn list(&self) -> Vec<ApiKeyEntry> {
+        let today = today();
+        self.keys
+            .lock()
+            .iter()
+            .map(|k| {
+                let mut entry = k.entry.clone();
+                entry.usage.roll_day(&today);
+                entry
+            })
+            .collect()
+    }
+
+    /// Authenticate a client key
+    ///
+    /// # Returns
+    /// - `None` - Key is not in the keyring
+    /// - `Some(Err(_))` - Key is known but rejected (disabled, rate limited, budget exhausted)
+    /// - `Some(Ok(_))` - Key accepted, the request has been counted
+    pub fn authenticate(self: &Arc<Self>, key: &str) -> Option<Result<ClientKey, KeyRejection>> {
+        let result = {
+            let mut keys = self.keys.lock();
+            let state = keys
+                .iter_mut()
+                .find(|k| auth::constant_time_eq(key, &k.entry.key))?;
+
+            let now = Instant::now();
+            let today = today();
+            let name = state.entry.settings.name.clone();
+            state.entry.usage.roll_day(&today);
+
+            if !state.entry.settings.enabled {
+                return Some(Err(KeyRejection::Disabled { name }));
+            }
+
+            if let Some(budget) = state.entry.settings.daily_token_budget
+                && state.entry.usage.day_tokens >= budget
+            {
+                return Some(Err(KeyRejection::BudgetExceeded { name, budget }));
+            }
+
+            while state
+                .recent_requests
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= RPM_WINDOW)
+            {
+                state.recent_requests.pop_front();
+            }
+            if let Some(limit) = state.entry.settings.rpm_limit
+                && state.recent_requests.len() >= limit as usize
+            {
+                return Some(Err(KeyRejection::RateLimited { name, limit }));
+            }
+
+            state.recent_requests.push_back(now);
+            let usage = &mut state.entry.usage;
+            usage.total_requests += 1;
+            usage.day_requests += 1;
+            usage.last_used_at = Some(Utc::now().to_rfc3339());
+
+            ClientKey {
+                id: state.entry.id,
+                name,
+                allowed_models: state.entry.settings.allowed_models.clone(),
+                keyring: Arc::clone(self),
+            }
+        };
+
+        self.save_usage_debounced();
+        Some(Ok(result))
+    }
+
+    /// Add token usage to a key's counters
+    pub fn record_usage(&self, id: u64, input_tokens: u64, output_tokens: u64) {
+        {
+            let mut keys = self.keys.lock();
+            let Some(state) = keys.iter_mut().find(|k| k.entry.id == id) else {
+                return;
+            };
+            let usage = &mut state.entry.usage;
+            usage.roll_day(&today());
+            usage.input_tokens += input_tokens;
+            usage.output_tokens += output_tokens;
+            usage.day_tokens += input_tokens + output_tokens;
+        }
+        self.save_usage_debounced();
+    }
+
+    /// Create a new key
+    ///
+    /// Generates a random key when `key` is None.
+    pub fn create(
+        &self,
+        mut settings: ApiKeySettings,
+        key: Option<String>,
+    ) -> anyhow::Result<ApiKeyEntry> {
+        settings.name = settings.name.trim().to_string();
+        let key = match key {
+            Some(k) => k.trim().to_string(),
+            None => format!("sk-kiro-{}", Uuid::new_v4().simple()),
+        };
+        if key.is_empty() {
+            bail!("API key value is empty");
+        }
+
+        let entry = {
+            let mut keys = self.keys.lock();
+            validate_settings(&keys, &settings, None)?;
+            if keys
+                .iter()
+                .any(|k| auth::constant_time_eq(&key, &k.entry.key))
+            {
+                bail!("API key value already exists");
+            }
+
+            let id = keys.iter().map(|k| k.entry.id).max().unwrap_or(0) + 1;
+            let entry = ApiKeyEntry {
+                id,
+                key,
+                settings,
+                created_at: Utc::now().to_rfc3339(),
+                usage: ApiKeyUsage::default(),
+            };
+            keys.push(KeyState {
+                entry: entry.clone(),
+                recent_requests: VecDeque::new(),
+            });
+            entry
+        };
+
+        self.persist()?;
+        Ok(entry)
+    }
+
+    /// Replace the settings of a key (key value and usage are kept)
+    pub fn update(&self, id: u64, mut settings: ApiKeySettings) -> anyhow::Result<ApiKeyEntry> {
+        settings.name = settings.name.trim().to_string();
+        let entry = {
+            let mut keys = self.keys.lock();
+            validate_settings(&keys, &settings, Some(id))?;
+            let state = keys
+                .iter_mut()
+                .find(|k| k.entry.id == id)
+                .ok_or_else(|| anyhow::anyhow!("API key does not exist: {}", id))?;
+            state.entry.settings = settings;
+            state.entry.clone()
+        };
+
+        self.persist()?;
+        Ok(entry)
+    }
+
+    /// Delete a key
+    pub fn delete(&self, id: u64) -> anyhow::Result<()> {
+        {
+            let mut keys = self.keys.lock();
+            let before = keys.len();
+            keys.retain(|k| k.entry.id != id);
+            if keys.len() == before {
+                bail!("API key does not exist: {}", id);
+            }
+        }
+
+        self.persist()
+    }
+
+    /// Write the keyring to file
+    fn persist(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let entries: Vec<ApiKeyEntry> = self.keys.lock().iter().map(|k| k.entry.clone()).collect();
+        let json = serde_json::to_string_pretty(&entries).context("Failed to serialize keyring")?;
+
+        // Use block_in_place in Tokio runtime to avoid blocking worker
+        if tokio::runtime::Handle::try_current().is_ok() {
+            tokio::task::block_in_place(|| encryption::write(path, &json))
+        } else {
+            encryption::write(path, &json)
+        }
+        .with_context(|| format!("Failed to write keyring file: {}", path.display()))?;
+
+        self.dirty.store(false, Ordering::Relaxed);
+        *self.last_save_at.lock() = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Persist usage counters with debounce
+    fn save_usage_debounced(&self) {
+        self.dirty.store(true, Ordering::Relaxed);
+
+        let should_flush = match *self.last_save_at.lock() {
+            Some(last_saved_at) => last_saved_at.elapsed() >= USAGE_SAVE_DEBOUNCE,
+            None => true,
+        };
+
+        if should_flush && let Err(e) = self.persist() {
+            tracing::warn!("Failed to save API key usage: {}", e);
+        }
+    }
+}
+
+impl Drop for ApiKeyring {
+    fn drop(&mut self) {
+        if self.dirty.load(Ordering::Relaxed)
+            && let Err(e) = self.persist()
+        {
+            tracing::warn!("Failed to save API key usage: {}", e);
+        }
+    }
+}
+
+/// Validate settings against the other keys
+fn validate_settings(
+    keys: &[KeyState],
+    settings: &ApiKeySettings,
+    own_id: Option<u64>,
+) -> anyhow::Result<()> {
+    let name = settings.name.as_str();
+    if name.is_empty() {
+        bail!("API key name is empty");
+    }
+    if keys
+        .iter()
+        .any(|k| Some(k.entry.id) != own_id && k.entry.settings.name == name)
+    {
+        bail!("API key name already exists: {}", name);
+    }
+    if settings.rpm_limit == Some(0) {
+        bail!("rpmLimit must be greater than 0");
+    }
+    Ok(())
+}
+
+/// Current UTC day (YYYY-MM-DD)
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(name: &str) -> ApiKeySettings {
+        ApiKeySettings {
+            name: name.to_string(),
+            allowed_models: None,
+            rpm_limit: None,
+            daily_token_budget: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_model_allowed_exact_and_prefix() {
+        let allowed = vec!["claude-sonnet-4-5".to_string(), "claude-haiku*".to_string()];
+        assert!(model_allowed(Some(&allowed), "claude-sonnet-4-5"));
+        assert!(model_allowed(Some(&allowed), "claude-haiku-4-5-20251001"));
+        assert!(!model_allowed(Some(&allowed), "claude-opus-4-6"));
+        assert!(model_allowed(None, "claude-opus-4-6"));
+    }
+
+    #[test]
+    fn test_authenticate_counts_requests() {
+        let keyring = Arc::new(ApiKeyring::default());
+        let entry = keyring
+            .create(settings("alice"), Some("sk-alice".to_string()))
+            .unwrap();
+
+        assert!(keyring.authenticate("sk-unknown").is_none());
+        let client_key = keyring.authenticate("sk-alice").unwrap().unwrap();
+        assert_eq!(client_key.id, entry.id);
+        client_key.record_usage(100, 20);
+
+        let usage = &keyring.list()[0].usage;
+        assert_eq!(usage.total_requests, 1);
+        assert_eq!(usage.input_tokens, 100);
+        assert_eq!(usage.output_tokens, 20);
+        assert_eq!(usage.day_tokens, 120);
+    }
+
+    #[test]
+    fn test_authenticate_rejects_disabled_and_rpm() {
+        let keyring = Arc::new(ApiKeyring::default());
+        let mut disabled = settings("off");
+        disabled.enabled = false;
+        keyring
+            .create(disabled, Some("sk-off".to_string()))
+            .unwrap();
+        let mut limited = settings("limited");
+        limited.rpm_limit = Some(1);
+        keyring
+            .create(limited, Some("sk-limited".to_string()))
+            .unwrap();
+
+        assert!(matches!(
+            keyring.authenticate("sk-off"),
+            Some(Err(KeyRejection::Disabled { .. }))
+        ));
+        assert!(keyring.authenticate("sk-limited").unwrap().is_ok());
+        assert!(matches!(
+            keyring.authenticate("sk-limited"),
+            Some(Err(KeyRejection::RateLimited { limit: 1, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_authenticate_rejects_exhausted_budget() {
+        let keyring = Arc::new(ApiKeyring::default());
+        let mut budgeted = settings("budget");
+        budgeted.daily_token_budget = Some(100);
+        let entry = keyring
+            .create(budgeted, Some("sk-budget".to_string()))
+            .unwrap();
+
+        assert!(keyring.authenticate("sk-budget").unwrap().is_ok());
+        keyring.record_usage(entry.id, 80, 20);
+        assert!(matches!(
+            keyring.authenticate("sk-budget"),
+            Some(Err(KeyRejection::BudgetExceeded { budget: 100, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_create_rejects_duplicates() {
+        let keyring = ApiKeyring::default();
+        keyring
+            .create(settings("alice"), Some("sk-a".to_string()))
+            .unwrap();
+        assert!(keyring.create(settings("alice"), None).is_err());
+        assert!(
+            keyring
+                .create(settings("bob"), Some("sk-a".to_string()))
+                .is_err()
+        );
+        assert!(
+            keyring
+                .create(settings("bob"), None)
+                .unwrap()
+                .key
+                .starts_with("sk-kiro-")
+        );
+    }
+
+    #[test]
+    fn test_keyring_persists_entries() {
+        let path =
+            std::env::temp_dir().join(format!("kiro_keyring_{}.json", Uuid::new_v4().simple()));
+        {
+            let keyring = ApiKeyring::load(&path).unwrap();
+            keyring
+                .create(settings("alice"), Some("sk-alice".to_string()))
+                .unwrap();
+        }
+        let keyring = ApiKeyring::load(&path).unwrap();
+        assert_eq!(keyring.count(), 1);
+        assert_eq!(keyring.list()[0].settings.name, "alice");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 
 use axum::{
     body::Body,
-    extract::State,
+    extract::{MatchedPath, State},
     http::{Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Json, Response},
@@ -12,8 +12,10 @@ use axum::{
 
 use crate::common::auth;
 use crate::kiro::provider::KiroProvider;
-use crate::model::config::Config;
+use crate::metrics::{self, RequestModel};
+use crate::model::config::SharedConfig;
 
+use super::keyring::ApiKeyring;
 use super::types::ErrorResponse;
 
 /// Application shared state
@@ -24,20 +26,20 @@ pub struct AppState {
     /// Kiro Provider (optional, used for actual API calls)
     /// Internally uses MultiTokenManager, already supports thread-safe multi-credential management
     pub kiro_provider: Option<Arc<KiroProvider>>,
-    /// Profile ARN (optional, used for requests)
-    pub profile_arn: Option<String>,
-    /// Application config
-    pub config: Arc<Config>,
+    /// Additional client API keys (with per-key limits and usage)
+    pub keyring: Arc<ApiKeyring>,
+    /// Application config (swapped on hot reload)
+    pub config: SharedConfig,
 }
 
 impl AppState {
     /// Create new application state
-    pub fn new(api_key: impl Into<String>, config: Config) -> Self {
+    pub fn new(api_key: impl Into<String>, config: impl Into<SharedConfig>) -> Self {
         Self {
             api_key: api_key.into(),
             kiro_provider: None,
-            profile_arn: None,
-            config: Arc::new(config),
+            keyring: Arc::new(ApiKeyring::default()),
+            config: config.into(),
         }
     }
 
@@ -47,28 +49,70 @@ impl AppState {
         self
     }
 
-    /// Set Profile ARN
-    pub fn with_profile_arn(mut self, arn: impl Into<String>) -> Self {
-        self.profile_arn = Some(arn.into());
+    /// Set client API keyring
+    pub fn with_keyring(mut self, keyring: Arc<ApiKeyring>) -> Self {
+        self.keyring = keyring;
         self
     }
 }
 
 /// API Key authentication middleware
+///
+/// Accepts the master API key or an enabled keyring key. Keyring keys are checked
+/// against their RPM limit and daily token budget, and attached to the request as `ClientKey`.
 pub async fn auth_middleware(
     State(state): State<AppState>,
-    request: Request<Body>,
+    mut request: Request<Body>,
     next: Next,
 ) -> Response {
-    match auth::extract_api_key(&request) {
-        Some(key) if auth::constant_time_eq(&key, &state.api_key) => next.run(request).await,
-        _ => {
-            let error = ErrorResponse::authentication_error();
-            (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+    let Some(key) = auth::extract_api_key(&request) else {
+        return unauthorized();
+    };
+
+    if auth::constant_time_eq(&key, &state.api_key) {
+        return next.run(request).await;
+    }
+
+    match state.keyring.authenticate(&key) {
+        Some(Ok(client_key)) => {
+            request.extensions_mut().insert(client_key);
+            next.run(request).await
+        }
+        Some(Err(rejection)) => {
+            tracing::warn!("{}", rejection);
+            (
+                rejection.status_code(),
+                Json(ErrorResponse::new(rejection.error_type(), rejection.to_string())),
+            )
+                .into_response()
         }
+        None => unauthorized(),
     }
 }
 
+fn unauthorized() -> Response {
+    let error = ErrorResponse::authentication_error();
+    (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+}
+
+/// Request metrics middleware
+///
+/// Counts requests by matched route, model and response status.
+/// Handlers report the model through the `RequestModel` request extension.
+pub async fn metrics_middleware(mut request: Request<Body>, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let model = RequestModel::default();
+    request.extensions_mut().insert(model.clone());
+
+    let response = next.run(request).await;
+    metrics::record_request(&route, model.label(), response.status().as_u16());
+    response
+}
+
 /// CORS middleware layer
 ///
 /// **Security note**: Current configuration allows all origins (Any), this is to support public API services.
@@ -8,6 +8,7 @@
 //! - `GET /v1/models` - Get list of available models
 //! - `POST /v1/messages` - Create message (conversation)
 //! - `POST /v1/messages/count_tokens` - Calculate token count
+//! - `POST /v1/chat/completions` - OpenAI Chat Completions compatible endpoint
 //!
 //! ## Claude Code compatible endpoints (/cc/v1)
 //! - `POST /cc/v1/messages` - Create message (streaming response waits for contextUsageEvent before sending message_start, ensuring accurate input_tokens)
@@ -22,14 +23,21 @@
 //! axum::serve(listener, app).await?;
 //! ```
 
+pub mod capture;
 mod converter;
 mod handlers;
+pub mod keyring;
 mod middleware;
+mod openai;
 mod router;
+mod stop_sequence;
 mod stream;
+mod thinking;
 pub mod tool_compression;
 pub mod truncation;
 pub mod types;
 mod websearch;
 
+pub use converter::convert_request;
+pub use handlers::apply_model_suffixes;
 pub use router::create_router_with_provider;
@@ -0,0 +1,811 @@
+//! OpenAI Chat Completions compatibility layer
+//!
+//! Translates `POST /v1/chat/completions` requests into `MessagesRequest`, then reuses the
+//! Anthropic conversion pipeline (`convert_request` + `StreamContext`) and renders the result
+//! back as OpenAI `chat.completion` / `chat.completion.chunk` objects.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use axum::{
+    Json as JsonExtractor,
+    body::Body,
+    extract::{Extension, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use futures::{StreamExt, stream};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::metrics::RequestModel;
+use crate::token;
+
+use super::handlers::{
+    apply_model_suffixes, build_kiro_request_body, call_api_stream_committed,
+    collect_non_stream_message, convert_kiro_error_to_response, create_event_stream,
+    reject_disallowed_model,
+};
+use super::keyring::ClientKey;
+use super::middleware::AppState;
+use super::stream::{SseEvent, StreamContext};
+use super::thinking::ThinkingFormat;
+use super::types::{ErrorResponse, Message, MessagesRequest, SystemMessage, Tool};
+
+/// Default max_tokens when the client specifies neither `max_tokens` nor `max_completion_tokens`
+const DEFAULT_MAX_TOKENS: i32 = 8192;
+
+// === Request Types ===
+
+/// Chat Completions request body
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    pub max_tokens: Option<i32>,
+    pub max_completion_tokens: Option<i32>,
+    pub tools: Option<Vec<ChatTool>>,
+    pub tool_choice: Option<serde_json::Value>,
+    /// End-user identifier, used as session id for conversation continuity
+    pub user: Option<String>,
+}
+
+/// Chat message
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    /// Can be string, content part array or null (assistant message with tool_calls only)
+    #[serde(default)]
+    pub content: Option<serde_json::Value>,
+    pub tool_calls: Option<Vec<ChatToolCall>>,
+    pub tool_call_id: Option<String>,
+}
+
+/// Tool call made by the assistant
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatToolCall {
+    pub id: String,
+    pub function: ChatFunctionCall,
+}
+
+/// Function call name and JSON-encoded arguments
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatFunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+/// Tool definition (`{"type": "function", "function": {...}}`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatTool {
+    pub function: ChatFunction,
+}
+
+/// Function definition
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatFunction {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub parameters: HashMap<String, serde_json::Value>,
+}
+
+// === Request Conversion ===
+
+/// Convert Chat Completions request to Anthropic MessagesRequest
+///
+/// - `system` / `developer` messages become the system prompt
+/// - `tool` messages become `tool_result` blocks of a user message
+/// - assistant `tool_calls` become `tool_use` blocks
+/// - consecutive messages of the same role are merged
+pub fn convert_chat_request(req: ChatCompletionRequest) -> MessagesRequest {
+    let mut system = Vec::new();
+    let mut messages: Vec<Message> = Vec::new();
+
+    for msg in req.messages {
+        let (role, blocks) = match msg.role.as_str() {
+            "system" | "developer" => {
+                let text = content_to_text(msg.content.as_ref());
+                if !text.is_empty() {
+                    system.push(SystemMessage { text });
+                }
+                continue;
+            }
+            "tool" => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": msg.tool_call_id.unwrap_or_default(),
+                    "content": content_to_text(msg.content.as_ref())
+                });
+                ("user", vec![block])
+            }
+            "assistant" => {
+                let mut blocks = Vec::new();
+                let text = content_to_text(msg.content.as_ref());
+                if !text.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": text }));
+                }
+                for call in msg.tool_calls.unwrap_or_default() {
+                    let input: serde_json::Value = serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": input
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            _ => ("user", content_to_blocks(msg.content.as_ref())),
+        };
+
+        if blocks.is_empty() {
+            continue;
+        }
+
+        // Merge with previous message of the same role
+        if let Some(last) = messages.last_mut()
+            && last.role == role
+            && let serde_json::Value::Array(arr) = &mut last.content
+        {
+            arr.extend(blocks);
+            continue;
+        }
+
+        messages.push(Message {
+            role: role.to_string(),
+            content: serde_json::Value::Array(blocks),
+        });
+    }
+
+    let tools = req.tools.map(|tools| {
+        tools
+            .into_iter()
+            .map(|t| Tool {
+                tool_type: None,
+                name: t.function.name,
+                description: t.function.description,
+                input_schema: t.function.parameters,
+                max_uses: None,
+            })
+            .collect()
+    });
+
+    MessagesRequest {
+        model: req.model,
+        max_tokens: req
+            .max_completion_tokens
+            .or(req.max_tokens)
+            .unwrap_or(DEFAULT_MAX_TOKENS),
+        messages,
+        stream: req.stream,
+        system: if system.is_empty() {
+            None
+        } else {
+            Some(system)
+        },
+        tools,
+        tool_choice: req.tool_choice.and_then(|c| convert_tool_choice(&c)),
+        stop_sequences: None,
+        thinking: None,
+        output_config: None,
+        metadata: req.user.map(|user_id| super::types::Metadata {
+            user_id: Some(user_id),
+        }),
+    }
+}
+
+/// Convert OpenAI tool_choice to Anthropic tool_choice
+///
+/// - `"auto"` → `{"type": "auto"}`
+/// - `"none"` → `{"type": "none"}`
+/// - `"required"` → `{"type": "any"}`
+/// - `{"type": "function", "function": {"name": "X"}}` → `{"type": "tool", "name": "X"}`
+fn convert_tool_choice(choice: &serde_json::Value) -> Option<serde_json::Value> {
+    match choice {
+        serde_json::Value::String(s) => match s.as_str() {
+            "auto" => Some(json!({ "type": "auto" })),
+            "none" => Some(json!({ "type": "none" })),
+            "required" => Some(json!({ "type": "any" })),
+            _ => None,
+        },
+        serde_json::Value::Object(_) => choice
+            .pointer("/function/name")
+            .and_then(|n| n.as_str())
+            .map(|name| json!({ "type": "tool", "name": name })),
+        _ => None,
+    }
+}
+
+/// Extract plain text from OpenAI content (string or content part array)
+fn content_to_text(content: Option<&serde_json::Value>) -> String {
+    match content {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Convert OpenAI user content to Anthropic content blocks
+///
+/// Only base64 data URLs are supported for images, remote image URLs are dropped.
+fn content_to_blocks(content: Option<&serde_json::Value>) -> Vec<serde_json::Value> {
+    match content {
+        Some(serde_json::Value::String(s)) if !s.is_empty() => {
+            vec![json!({ "type": "text", "text": s })]
+        }
+        Some(serde_json::Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+                Some("text") => part
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .map(|text| json!({ "type": "text", "text": text })),
+                Some("image_url") => {
+                    let url = part.pointer("/image_url/url").and_then(|u| u.as_str())?;
+                    let block = parse_data_url(url);
+                    if block.is_none() {
+                        tracing::warn!(
+                            "Unsupported image URL (only data URLs are supported), skipped"
+                        );
+                    }
+                    block
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse `data:<media_type>;base64,<data>` into an Anthropic image block
+fn parse_data_url(url: &str) -> Option<serde_json::Value> {
+    let rest = url.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let media_type = meta.strip_suffix(";base64")?;
+    Some(json!({
+        "type": "image",
+        "source": {
+            "type": "base64",
+            "media_type": media_type,
+            "data": data
+        }
+    }))
+}
+
+// === Response Conversion ===
+
+/// Map Anthropic stop_reason to OpenAI finish_reason
+fn map_finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "tool_use" => "tool_calls",
+        "max_tokens" | "model_context_window_exceeded" => "length",
+        _ => "stop",
+    }
+}
+
+/// Build OpenAI usage object
+fn build_usage(input_tokens: i64, output_tokens: i64) -> serde_json::Value {
+    json!({
+        "prompt_tokens": input_tokens,
+        "completion_tokens": output_tokens,
+        "total_tokens": input_tokens + output_tokens
+    })
+}
+
+/// Convert Anthropic message body to OpenAI `chat.completion` body
+fn message_to_chat_completion(message: &serde_json::Value, model: &str) -> serde_json::Value {
+    let mut text = String::new();
+    let mut reasoning = String::new();
+    let mut tool_calls = Vec::new();
+
+    if let Some(blocks) = message.get("content").and_then(|c| c.as_array()) {
+        for block in blocks {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => {
+                    text.push_str(block.get("text").and_then(|t| t.as_str()).unwrap_or(""));
+                }
+                Some("thinking") => {
+                    reasoning
+                        .push_str(block.get("thinking").and_then(|t| t.as_str()).unwrap_or(""));
+                }
+                Some("tool_use") => {
+                    let arguments = block
+                        .get("input")
+                        .map(|i| i.to_string())
+                        .unwrap_or_else(|| "{}".to_string());
+                    tool_calls.push(json!({
+                        "id": block.get("id").cloned().unwrap_or(serde_json::Value::Null),
+                        "type": "function",
+                        "function": {
+                            "name": block.get("name").cloned().unwrap_or(serde_json::Value::Null),
+                            "arguments": arguments
+                        }
+                    }));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // thinkingFormat "reasoning_content" returns thinking as a top-level field
+    if let Some(r) = message.get("reasoning_content").and_then(|r| r.as_str()) {
+        reasoning.push_str(r);
+    }
+
+    let mut response_message = json!({
+        "role": "assistant",
+        "content": if text.is_empty() && !tool_calls.is_empty() {
+            serde_json::Value::Null
+        } else {
+            json!(text)
+        }
+    });
+    if !reasoning.is_empty() {
+        response_message["reasoning_content"] = json!(reasoning);
+    }
+    if !tool_calls.is_empty() {
+        response_message["tool_calls"] = json!(tool_calls);
+    }
+
+    let stop_reason = message
+        .get("stop_reason")
+        .and_then(|s| s.as_str())
+        .unwrap_or("end_turn");
+    let input_tokens = message
+        .pointer("/usage/input_tokens")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let output_tokens = message
+        .pointer("/usage/output_tokens")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+
+    json!({
+        "id": format!("chatcmpl-{}", Uuid::new_v4().simple()),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": response_message,
+            "finish_reason": map_finish_reason(stop_reason)
+        }],
+        "usage": build_usage(input_tokens, output_tokens)
+    })
+}
+
+/// Translates Anthropic SSE events into OpenAI `chat.completion.chunk` objects
+pub struct ChatCompletionChunkConverter {
+    id: String,
+    model: String,
+    created: i64,
+    /// Content block index -> tool_calls array index
+    tool_call_indices: HashMap<i64, usize>,
+    /// finish_reason taken from message_delta
+    finish_reason: Option<&'static str>,
+    /// Usage taken from message_delta
+    usage: Option<serde_json::Value>,
+}
+
+impl ChatCompletionChunkConverter {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", Uuid::new_v4().simple()),
+            model: model.into(),
+            created: chrono::Utc::now().timestamp(),
+            tool_call_indices: HashMap::new(),
+            finish_reason: None,
+            usage: None,
+        }
+    }
+
+    /// Build a chunk with a single choice
+    fn chunk(&self, delta: serde_json::Value, finish_reason: Option<&str>) -> serde_json::Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason
+            }]
+        })
+    }
+
+    /// Convert one Anthropic SSE event into zero or more chunks
+    pub fn convert(&mut self, event: &SseEvent) -> Vec<serde_json::Value> {
+        let data = &event.data;
+        match event.event.as_str() {
+            "message_start" => {
+                vec![self.chunk(json!({ "role": "assistant", "content": "" }), None)]
+            }
+            "content_block_start" => {
+                let block = &data["content_block"];
+                if block["type"] != "tool_use" {
+                    return Vec::new();
+                }
+                let block_index = data["index"].as_i64().unwrap_or(0);
+                let tool_index = self.tool_call_indices.len();
+                self.tool_call_indices.insert(block_index, tool_index);
+                vec![self.chunk(
+                    json!({
+                        "tool_calls": [{
+                            "index": tool_index,
+                            "id": block["id"],
+                            "type": "function",
+                            "function": { "name": block["name"], "arguments": "" }
+                        }]
+                    }),
+                    None,
+                )]
+            }
+            "content_block_delta" => {
+                let delta = &data["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        vec![self.chunk(json!({ "content": delta["text"] }), None)]
+                    }
+                    Some("thinking_delta") => {
+                        let thinking = delta["thinking"].as_str().unwrap_or("");
+                        if thinking.is_empty() {
+                            return Vec::new();
+                        }
+                        vec![self.chunk(json!({ "reasoning_content": thinking }), None)]
+                    }
+                    Some("input_json_delta") => {
+                        let block_index = data["index"].as_i64().unwrap_or(0);
+                        let Some(&tool_index) = self.tool_call_indices.get(&block_index) else {
+                            return Vec::new();
+                        };
+                        vec![self.chunk(
+                            json!({
+                                "tool_calls": [{
+                                    "index": tool_index,
+                                    "function": { "arguments": delta["partial_json"] }
+                                }]
+                            }),
+                            None,
+                        )]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "message_delta" => {
+                let stop_reason = data["delta"]["stop_reason"].as_str().unwrap_or("end_turn");
+                self.finish_reason = Some(map_finish_reason(stop_reason));
+                let input_tokens = data["usage"]["input_tokens"].as_i64().unwrap_or(0);
+                let output_tokens = data["usage"]["output_tokens"].as_i64().unwrap_or(0);
+                self.usage = Some(build_usage(input_tokens, output_tokens));
+                Vec::new()
+            }
+            "message_stop" => {
+                let mut chunk = self.chunk(json!({}), Some(self.finish_reason.unwrap_or("stop")));
+                if let Some(usage) = self.usage.take() {
+                    chunk["usage"] = usage;
+                }
+                vec![chunk]
+            }
+            // Mid-stream failure, reported the way OpenAI streams report errors
+            "error" => vec![json!({
+                "error": {
+                    "message": data["error"]["message"],
+                    "type": data["error"]["type"]
+                }
+            })],
+            _ => Vec::new(),
+        }
+    }
+}
+
+// === Handler ===
+
+/// POST /v1/chat/completions
+///
+/// OpenAI Chat Completions compatible endpoint
+pub async fn post_chat_completions(
+    State(state): State<AppState>,
+    client_key: Option<Extension<ClientKey>>,
+    request_model: Option<Extension<RequestModel>>,
+    JsonExtractor(request): JsonExtractor<ChatCompletionRequest>,
+) -> Response {
+    let client_key = client_key.map(|Extension(key)| key);
+    if let Some(Extension(request_model)) = &request_model {
+        request_model.set(&request.model);
+    }
+    tracing::info!(
+        model = %request.model,
+        stream = %request.stream,
+        message_count = %request.messages.len(),
+        "Received POST /v1/chat/completions request"
+    );
+
+    // Check if KiroProvider is available
+    let provider = match &state.kiro_provider {
+        Some(p) => p.clone(),
+        None => {
+            tracing::error!("KiroProvider not configured");
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse::new(
+                    "service_unavailable",
+                    "Kiro API provider not configured",
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    let mut payload = convert_chat_request(request);
+
+    // Apply thinking and agentic model name suffixes
+    let config = state.config.load();
+    apply_model_suffixes(&mut payload, config.thinking_suffix());
+
+    // Check the client key's model allow-list
+    if let Some(resp) = reject_disallowed_model(client_key.as_ref(), &payload.model) {
+        return resp;
+    }
+
+    // Convert request and build Kiro request body
+    let request_body = match build_kiro_request_body(&state, &payload) {
+        Ok(body) => body,
+        Err(resp) => return resp,
+    };
+
+    let tool_choice = payload.tool_choice_mode();
+
+    // Estimate input tokens
+    let input_tokens = token::count_all_tokens(
+        payload.model.clone(),
+        payload.system,
+        payload.messages,
+        payload.tools,
+    ) as i32;
+
+    let thinking_enabled = payload
+        .thinking
+        .as_ref()
+        .map(|t| t.is_enabled())
+        .unwrap_or(false);
+
+    if !payload.stream {
+        let thinking =
+            thinking_enabled.then(|| ThinkingFormat::from_config(config.thinking_format()));
+        return match collect_non_stream_message(
+            provider,
+            &request_body,
+            &payload.model,
+            input_tokens,
+            thinking,
+            &tool_choice,
+            client_key.as_ref(),
+            Vec::new(),
+            None,
+        )
+        .await
+        {
+            Ok(message) => {
+                Json(message_to_chat_completion(&message, &payload.model)).into_response()
+            }
+            Err(resp) => resp,
+        };
+    }
+
+    // Call Kiro API (supports multi-credential failover, also before the first content)
+    let (body, guard) = match call_api_stream_committed(&provider, &request_body).await {
+        Ok(committed) => committed,
+        Err(e) => {
+            tracing::error!("Kiro API call failed: {}", e);
+            return convert_kiro_error_to_response(&e.to_string());
+        }
+    };
+
+    let mut ctx = StreamContext::new_with_thinking(&payload.model, input_tokens, thinking_enabled)
+        .with_tool_choice(tool_choice)
+        .with_client_key(client_key);
+    let initial_events = ctx.generate_initial_events();
+    let mut converter = ChatCompletionChunkConverter::new(&payload.model);
+
+    let chunk_stream = create_event_stream(body, guard, ctx, initial_events, None)
+        .map(move |event| {
+            // OpenAI clients don't know the ping event, keep the connection alive with an SSE comment
+            if event.event == "ping" {
+                return Ok::<Bytes, Infallible>(Bytes::from(": ping\n\n"));
+            }
+            let chunks: String = converter
+                .convert(&event)
+                .iter()
+                .map(|chunk| format!("data: {}\n\n", chunk))
+                .collect();
+            Ok(Bytes::from(chunks))
+        })
+        .filter(|bytes| std::future::ready(!matches!(bytes, Ok(b) if b.is_empty())))
+        .chain(stream::once(async { Ok(Bytes::from("data: [DONE]\n\n")) }));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::CONNECTION, "keep-alive")
+        .body(Body::from_stream(chunk_stream))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_request(value: serde_json::Value) -> ChatCompletionRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_convert_chat_request_system_and_user() {
+        let req = parse_request(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Hello"}
+            ],
+            "max_tokens": 100
+        }));
+
+        let payload = convert_chat_request(req);
+        assert_eq!(payload.max_tokens, 100);
+        assert_eq!(payload.system.unwrap()[0].text, "Be brief");
+        assert_eq!(payload.messages.len(), 1);
+        assert_eq!(payload.messages[0].role, "user");
+        assert_eq!(payload.messages[0].content[0]["text"], "Hello");
+    }
+
+    #[test]
+    fn test_convert_chat_request_tool_round_trip() {
+        let req = parse_request(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "user", "content": "Weather?"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}},
+                    {"id": "call_2", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Rome\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "Sunny"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "Rainy"}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "get_weather",
+                "description": "Get weather",
+                "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }}],
+            "tool_choice": "required"
+        }));
+
+        let payload = convert_chat_request(req);
+        assert_eq!(payload.max_tokens, DEFAULT_MAX_TOKENS);
+        assert_eq!(payload.messages.len(), 3);
+
+        let assistant = &payload.messages[1];
+        assert_eq!(assistant.role, "assistant");
+        assert_eq!(assistant.content[0]["type"], "tool_use");
+        assert_eq!(assistant.content[0]["input"]["city"], "Paris");
+
+        // Consecutive tool messages are merged into one user message
+        let results = &payload.messages[2];
+        assert_eq!(results.role, "user");
+        assert_eq!(results.content.as_array().unwrap().len(), 2);
+        assert_eq!(results.content[1]["tool_use_id"], "call_2");
+
+        let tools = payload.tools.unwrap();
+        assert_eq!(tools[0].name, "get_weather");
+        assert!(tools[0].input_schema.contains_key("properties"));
+        assert_eq!(payload.tool_choice.unwrap()["type"], "any");
+    }
+
+    #[test]
+    fn test_convert_tool_choice() {
+        assert_eq!(convert_tool_choice(&json!("none")).unwrap()["type"], "none");
+        let forced =
+            convert_tool_choice(&json!({"type": "function", "function": {"name": "X"}})).unwrap();
+        assert_eq!(forced["type"], "tool");
+        assert_eq!(forced["name"], "X");
+        assert!(convert_tool_choice(&json!("unknown")).is_none());
+    }
+
+    #[test]
+    fn test_content_to_blocks_image_data_url() {
+        let content = json!([
+            {"type": "text", "text": "What is this?"},
+            {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}},
+            {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+        ]);
+        let blocks = content_to_blocks(Some(&content));
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1]["source"]["media_type"], "image/png");
+        assert_eq!(blocks[1]["source"]["data"], "AAAA");
+    }
+
+    #[test]
+    fn test_message_to_chat_completion() {
+        let message = json!({
+            "content": [
+                {"type": "text", "text": "Checking"},
+                {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Paris"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        });
+
+        let completion = message_to_chat_completion(&message, "claude-sonnet-4-5");
+        assert_eq!(completion["object"], "chat.completion");
+        let choice = &completion["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Checking");
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Paris\"}"
+        );
+        assert_eq!(completion["usage"]["total_tokens"], 15);
+    }
+
+    #[test]
+    fn test_chunk_converter_stream_sequence() {
+        let mut converter = ChatCompletionChunkConverter::new("claude-sonnet-4-5");
+
+        let start = converter.convert(&SseEvent::new(
+            "message_start",
+            json!({"type": "message_start"}),
+        ));
+        assert_eq!(start[0]["choices"][0]["delta"]["role"], "assistant");
+
+        let text = converter.convert(&SseEvent::new(
+            "content_block_delta",
+            json!({"index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+        ));
+        assert_eq!(text[0]["object"], "chat.completion.chunk");
+        assert_eq!(text[0]["choices"][0]["delta"]["content"], "Hi");
+
+        let tool_start = converter.convert(&SseEvent::new(
+            "content_block_start",
+            json!({"index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "get_weather"}}),
+        ));
+        let call = &tool_start[0]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(call["index"], 0);
+        assert_eq!(call["id"], "toolu_1");
+
+        let args = converter.convert(&SseEvent::new(
+            "content_block_delta",
+            json!({"index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"city\""}}),
+        ));
+        assert_eq!(
+            args[0]["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"],
+            "{\"city\""
+        );
+
+        assert!(converter
+            .convert(&SseEvent::new(
+                "message_delta",
+                json!({"delta": {"stop_reason": "tool_use"}, "usage": {"input_tokens": 3, "output_tokens": 4}}),
+            ))
+            .is_empty());
+
+        let stop = converter.convert(&SseEvent::new(
+            "message_stop",
+            json!({"type": "message_stop"}),
+        ));
+        assert_eq!(stop[0]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(stop[0]["usage"]["total_tokens"], 7);
+    }
+}
@@ -1,5 +1,7 @@
 //! Anthropic API routing configuration
 
+use std::sync::Arc;
+
 use axum::{
     Router,
     extract::DefaultBodyLimit,
@@ -8,11 +10,13 @@ use axum::{
 };
 
 use crate::kiro::provider::KiroProvider;
-use crate::model::config::Config;
+use crate::model::config::SharedConfig;
 
 use super::{
-    handlers::{count_tokens, get_models, post_messages, post_messages_cc},
-    middleware::{AppState, auth_middleware, cors_layer},
+    handlers::{count_tokens, get_metrics, get_models, post_messages, post_messages_cc},
+    keyring::ApiKeyring,
+    middleware::{AppState, auth_middleware, cors_layer, metrics_middleware},
+    openai::post_chat_completions,
 };
 
 /// Maximum request body size limit (50MB)
@@ -24,53 +28,67 @@ const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
 /// - `GET /v1/models` - Get list of available models
 /// - `POST /v1/messages` - Create message (conversation)
 /// - `POST /v1/messages/count_tokens` - Calculate token count
+/// - `POST /v1/chat/completions` - OpenAI Chat Completions compatible endpoint
+/// - `GET /metrics` - Prometheus metrics
 ///
 /// # Authentication
-/// All `/v1` paths require API Key authentication, supporting:
+/// All `/v1` paths and `/metrics` require API Key authentication, supporting:
 /// - `x-api-key` header
 /// - `Authorization: Bearer <token>` header
 ///
+/// Both the master API key and keyring keys are accepted.
+///
 /// # Parameters
 /// - `api_key`: API key for validating client requests
 /// - `kiro_provider`: Optional KiroProvider for calling upstream API
-/// - `config`: Application configuration
+/// - `keyring`: Additional client API keys
+/// - `config`: Application configuration (a `SharedConfig` observes hot reloads)
 
 /// Create Anthropic API router with KiroProvider
 pub fn create_router_with_provider(
     api_key: impl Into<String>,
     kiro_provider: Option<KiroProvider>,
-    profile_arn: Option<String>,
-    config: Config,
+    keyring: Arc<ApiKeyring>,
+    config: impl Into<SharedConfig>,
 ) -> Router {
-    let mut state = AppState::new(api_key, config);
+    let mut state = AppState::new(api_key, config).with_keyring(keyring);
     if let Some(provider) = kiro_provider {
         state = state.with_kiro_provider(provider);
     }
-    if let Some(arn) = profile_arn {
-        state = state.with_profile_arn(arn);
-    }
 
     // Authenticated /v1 routes
     let v1_routes = Router::new()
         .route("/models", get(get_models))
         .route("/messages", post(post_messages))
         .route("/messages/count_tokens", post(count_tokens))
+        .route("/chat/completions", post(post_chat_completions))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
-        ));
+        ))
+        .route_layer(middleware::from_fn(metrics_middleware));
 
     // Authenticated /cc/v1 routes (Claude Code compatible endpoints)
     // Difference from /v1: streaming response waits for contextUsageEvent before sending message_start
     let cc_v1_routes = Router::new()
         .route("/messages", post(post_messages_cc))
         .route("/messages/count_tokens", post(count_tokens))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .route_layer(middleware::from_fn(metrics_middleware));
+
+    // Authenticated metrics route
+    let metrics_routes = Router::new()
+        .route("/metrics", get(get_metrics))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
         ));
 
     Router::new()
+        .merge(metrics_routes)
         .nest("/v1", v1_routes)
         .nest("/cc/v1", cc_v1_routes)
         .layer(cors_layer())
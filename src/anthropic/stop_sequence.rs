@@ -0,0 +1,148 @@
+//! Stop sequence enforcement module
+//!
+//! Kiro API does not support stop sequences, so `stop_sequences` from the request are
+//! matched against the generated text here. Text that could be the start of a stop sequence
+//! is held back until the next chunk arrives, so matches spanning chunk boundaries are found.
+
+/// Incremental stop sequence scanner
+#[derive(Debug, Default)]
+pub struct StopSequenceScanner {
+    /// Stop sequences from the request (empty ones removed)
+    sequences: Vec<String>,
+    /// Text held back because it may be the start of a stop sequence
+    held: String,
+    /// Stop sequence that matched, no more text is returned afterwards
+    matched: Option<String>,
+}
+
+impl StopSequenceScanner {
+    pub fn new(sequences: Vec<String>) -> Self {
+        Self {
+            sequences: sequences.into_iter().filter(|s| !s.is_empty()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Stop sequence that matched
+    pub fn matched(&self) -> Option<&str> {
+        self.matched.as_deref()
+    }
+
+    /// Scan the next piece of text and return the part that can be emitted
+    ///
+    /// On a match, returns the text before the stop sequence and records the match.
+    pub fn push(&mut self, text: &str) -> String {
+        if self.matched.is_some() {
+            return String::new();
+        }
+        if self.sequences.is_empty() {
+            return text.to_string();
+        }
+
+        self.held.push_str(text);
+
+        // Earliest match wins, the longer sequence on a tie
+        let earliest = self
+            .sequences
+            .iter()
+            .filter_map(|seq| self.held.find(seq.as_str()).map(|pos| (pos, seq)))
+            .min_by(|(a_pos, a), (b_pos, b)| a_pos.cmp(b_pos).then(b.len().cmp(&a.len())));
+        if let Some((pos, seq)) = earliest {
+            self.matched = Some(seq.clone());
+            let mut emitted = std::mem::take(&mut self.held);
+            emitted.truncate(pos);
+            return emitted;
+        }
+
+        // Hold back the longest tail that is a prefix of a stop sequence
+        let keep = self
+            .sequences
+            .iter()
+            .filter_map(|seq| {
+                (1..seq.len())
+                    .rev()
+                    .filter(|&len| seq.is_char_boundary(len))
+                    .find(|&len| self.held.ends_with(&seq[..len]))
+            })
+            .max()
+            .unwrap_or(0);
+        let held = self.held.split_off(self.held.len() - keep);
+        std::mem::replace(&mut self.held, held)
+    }
+
+    /// Release the held back text at the end of the text (stream end or before a tool call)
+    pub fn flush(&mut self) -> String {
+        std::mem::take(&mut self.held)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(sequences: &[&str], chunks: &[&str]) -> (String, Option<String>) {
+        let mut scanner =
+            StopSequenceScanner::new(sequences.iter().map(|s| s.to_string()).collect());
+        let mut text: String = chunks.iter().map(|c| scanner.push(c)).collect();
+        text.push_str(&scanner.flush());
+        (text, scanner.matched().map(str::to_string))
+    }
+
+    #[test]
+    fn test_no_stop_sequences_passes_through() {
+        assert_eq!(
+            scan(&[], &["Hello", " world"]),
+            ("Hello world".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_match_within_chunk() {
+        assert_eq!(
+            scan(&["STOP"], &["Hello STOP world"]),
+            ("Hello ".to_string(), Some("STOP".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_match_across_chunks() {
+        let mut scanner = StopSequenceScanner::new(vec!["</answer>".to_string()]);
+        assert_eq!(scanner.push("42</ans"), "42");
+        assert_eq!(scanner.push("wer> trailing"), "");
+        assert_eq!(scanner.matched(), Some("</answer>"));
+        assert_eq!(scanner.push("more"), "");
+        assert_eq!(scanner.flush(), "");
+    }
+
+    #[test]
+    fn test_partial_prefix_released_when_not_matched() {
+        assert_eq!(
+            scan(&["\n\nHuman:"], &["a\n", "\nHum", "ble"]),
+            ("a\n\nHumble".to_string(), None)
+        );
+        // Held back text is returned by flush at the end
+        let mut scanner = StopSequenceScanner::new(vec!["END".to_string()]);
+        assert_eq!(scanner.push("the EN"), "the ");
+        assert_eq!(scanner.flush(), "EN");
+    }
+
+    #[test]
+    fn test_earliest_match_wins() {
+        assert_eq!(
+            scan(&["world", "lo"], &["Hello world"]),
+            ("Hel".to_string(), Some("lo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_multibyte_prefix() {
+        assert_eq!(
+            scan(&["你好吗"], &["说你", "好吗？"]),
+            ("说".to_string(), Some("你好吗".to_string()))
+        );
+        assert_eq!(
+            scan(&["你好吗"], &["说你", "好"]),
+            ("说你好".to_string(), None)
+        );
+    }
+}
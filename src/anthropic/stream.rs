@@ -7,150 +7,14 @@ use std::collections::HashMap;
 use serde_json::json;
 use uuid::Uuid;
 
-use crate::kiro::model::events::Event;
+use crate::kiro::model::events::{Event, MeteringUsage};
+use crate::metrics;
 
-/// Quote characters to skip
-///
-/// When thinking tags are wrapped by these characters, they are considered as quoting the tag rather than actual tags:
-/// - Backtick (`): inline code
-/// - Double quote ("): string
-/// - Single quote ('): string
-const QUOTE_CHARS: &[u8] = &[
-    b'`', b'"', b'\'', b'\\', b'#', b'!', b'@', b'$', b'%', b'^', b'&', b'*', b'(', b')', b'-',
-    b'_', b'=', b'+', b'[', b']', b'{', b'}', b';', b':', b'<', b'>', b',', b'.', b'?', b'/',
-];
-
-/// Check if the character at the specified position is a quote character
-fn is_quote_char(buffer: &str, pos: usize) -> bool {
-    buffer
-        .as_bytes()
-        .get(pos)
-        .map(|c| QUOTE_CHARS.contains(c))
-        .unwrap_or(false)
-}
-
-/// Find the real thinking end tag (not wrapped by quote characters, and followed by double newline)
-///
-/// When the model mentions `</thinking>` during thinking, it's usually wrapped by backticks, quotes, etc.,
-/// or has other content on the same line (like "about </thinking> tag").
-/// This function skips these cases and only returns the position of the real end tag.
-///
-/// Skipped cases:
-/// - Wrapped by quote characters (backticks, quotes, etc.)
-/// - Not followed by double newline (real end tag is followed by `\n\n`)
-/// - Tag at buffer end (need to wait for more content during streaming)
-///
-/// # Arguments
-/// - `buffer`: The string to search
-///
-/// # Returns
-/// - `Some(pos)`: Starting position of the real end tag
-/// - `None`: No real end tag found
-fn find_real_thinking_end_tag(buffer: &str) -> Option<usize> {
-    const TAG: &str = "</thinking>";
-    let mut search_start = 0;
-
-    while let Some(pos) = buffer[search_start..].find(TAG) {
-        let absolute_pos = search_start + pos;
-
-        // Check if preceded by quote character
-        let has_quote_before = absolute_pos > 0 && is_quote_char(buffer, absolute_pos - 1);
-
-        // Check if followed by quote character
-        let after_pos = absolute_pos + TAG.len();
-        let has_quote_after = is_quote_char(buffer, after_pos);
-
-        // If wrapped by quote characters, skip
-        if has_quote_before || has_quote_after {
-            search_start = absolute_pos + 1;
-            continue;
-        }
-
-        // Check content after the tag
-        let after_content = &buffer[after_pos..];
-
-        // If content after tag is insufficient to determine if there's double newline, wait for more content
-        if after_content.len() < 2 {
-            return None;
-        }
-
-        // Real thinking end tag is followed by double newline `\n\n`
-        if after_content.starts_with("\n\n") {
-            return Some(absolute_pos);
-        }
-
-        // Not double newline, skip and continue searching
-        search_start = absolute_pos + 1;
-    }
-
-    None
-}
-
-/// Find thinking end tag at buffer end (allowing only whitespace after it)
-///
-/// Used for "boundary event" scenarios: e.g., when thinking ends and immediately enters tool_use, or stream ends,
-/// where `</thinking>` may not be followed by `\n\n`, but the end tag should still be recognized and filtered.
-///
-/// Constraint: Only consider it as end tag when everything after `</thinking>` is whitespace,
-/// to avoid false positives when `</thinking>` is mentioned (not as end tag) within thinking content.
-fn find_real_thinking_end_tag_at_buffer_end(buffer: &str) -> Option<usize> {
-    const TAG: &str = "</thinking>";
-    let mut search_start = 0;
-
-    while let Some(pos) = buffer[search_start..].find(TAG) {
-        let absolute_pos = search_start + pos;
-
-        // Check if preceded by quote character
-        let has_quote_before = absolute_pos > 0 && is_quote_char(buffer, absolute_pos - 1);
-
-        // Check if followed by quote character
-        let after_pos = absolute_pos + TAG.len();
-        let has_quote_after = is_quote_char(buffer, after_pos);
-
-        if has_quote_before || has_quote_after {
-            search_start = absolute_pos + 1;
-            continue;
-        }
-
-        // Only consider it as end tag when everything after is whitespace
-        if buffer[after_pos..].trim().is_empty() {
-            return Some(absolute_pos);
-        }
-
-        search_start = absolute_pos + 1;
-    }
-
-    None
-}
-
-/// Find the real thinking start tag (not wrapped by quote characters)
-///
-/// Similar to `find_real_thinking_end_tag`, skips start tags wrapped by quote characters.
-fn find_real_thinking_start_tag(buffer: &str) -> Option<usize> {
-    const TAG: &str = "<thinking>";
-    let mut search_start = 0;
-
-    while let Some(pos) = buffer[search_start..].find(TAG) {
-        let absolute_pos = search_start + pos;
-
-        // Check if preceded by quote character
-        let has_quote_before = absolute_pos > 0 && is_quote_char(buffer, absolute_pos - 1);
-
-        // Check if followed by quote character
-        let after_pos = absolute_pos + TAG.len();
-        let has_quote_after = is_quote_char(buffer, after_pos);
-
-        // If not wrapped by quote characters, it's the real start tag
-        if !has_quote_before && !has_quote_after {
-            return Some(absolute_pos);
-        }
-
-        // Continue searching for next match
-        search_start = absolute_pos + 1;
-    }
-
-    None
-}
+use super::keyring::ClientKey;
+use super::stop_sequence::StopSequenceScanner;
+use super::thinking::{ThinkingChunk, ThinkingParser};
+use super::truncation::{build_soft_failure_result, parse_tool_input};
+use super::types::{ToolChoice, get_context_window_size};
 
 /// SSE event
 #[derive(Debug, Clone)]
@@ -216,6 +80,8 @@ pub struct SseStateManager {
     next_block_index: i32,
     /// Current stop_reason
     stop_reason: Option<String>,
+    /// Stop sequence that ended the message
+    stop_sequence: Option<String>,
     /// Whether there are tool calls
     has_tool_use: bool,
 }
@@ -235,6 +101,7 @@ impl SseStateManager {
             message_ended: false,
             next_block_index: 0,
             stop_reason: None,
+            stop_sequence: None,
             has_tool_use: false,
         }
     }
@@ -260,6 +127,7 @@ impl SseStateManager {
 
     /// stop_reason priority (lower index = higher priority)
     const STOP_REASON_PRIORITY: &'static [&'static str] = &[
+        "stop_sequence",
         "model_context_window_exceeded",
         "max_tokens",
         "tool_use",
@@ -276,7 +144,7 @@ impl SseStateManager {
 
     /// Set stop_reason (higher priority reason can override lower priority)
     ///
-    /// Priority from high to low: model_context_window_exceeded > max_tokens > tool_use > end_turn
+    /// Priority from high to low: stop_sequence > model_context_window_exceeded > max_tokens > tool_use > end_turn
     pub fn set_stop_reason(&mut self, reason: impl Into<String>) {
         let reason = reason.into();
         let new_priority = Self::stop_reason_priority(&reason);
@@ -289,6 +157,17 @@ impl SseStateManager {
         }
     }
 
+    /// Record the stop sequence that ended the message (stop_reason becomes stop_sequence)
+    pub fn set_stop_sequence(&mut self, sequence: impl Into<String>) {
+        self.stop_sequence = Some(sequence.into());
+        self.set_stop_reason("stop_sequence");
+    }
+
+    /// Stop sequence that ended the message
+    pub fn stop_sequence(&self) -> Option<&str> {
+        self.stop_sequence.as_deref()
+    }
+
     /// Check if there are non-thinking type content blocks (like text or tool_use)
     fn has_non_thinking_blocks(&self) -> bool {
         self.active_blocks
@@ -406,11 +285,57 @@ impl SseStateManager {
         None
     }
 
+    /// Whether the message has ended (message_stop or error sent)
+    pub fn is_message_ended(&self) -> bool {
+        self.message_ended
+    }
+
+    /// Generate error event sequence for a failed stream
+    ///
+    /// Closes open blocks, then ends the message with an `error` event instead of
+    /// message_delta/message_stop, as the Claude API does for mid-stream failures.
+    pub fn generate_error_events(&mut self, error_type: &str, message: &str) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        if self.message_ended {
+            return events;
+        }
+
+        for (index, block) in self.active_blocks.iter_mut() {
+            if block.started && !block.stopped {
+                events.push(SseEvent::new(
+                    "content_block_stop",
+                    json!({
+                        "type": "content_block_stop",
+                        "index": index
+                    }),
+                ));
+                block.stopped = true;
+            }
+        }
+
+        self.message_delta_sent = true;
+        self.message_ended = true;
+        events.push(SseEvent::new(
+            "error",
+            json!({
+                "type": "error",
+                "error": {
+                    "type": error_type,
+                    "message": message
+                }
+            }),
+        ));
+        events
+    }
+
     /// Generate final event sequence
+    ///
+    /// `metering` is the usage reported by Kiro, added to `usage` as `kiro_metering`.
     pub fn generate_final_events(
         &mut self,
         input_tokens: i32,
         output_tokens: i32,
+        metering: Option<&MeteringUsage>,
     ) -> Vec<SseEvent> {
         let mut events = Vec::new();
 
@@ -431,20 +356,21 @@ impl SseStateManager {
         // Send message_delta
         if !self.message_delta_sent {
             self.message_delta_sent = true;
-            events.push(SseEvent::new(
-                "message_delta",
-                json!({
-                    "type": "message_delta",
-                    "delta": {
-                        "stop_reason": self.get_stop_reason(),
-                        "stop_sequence": null
-                    },
-                    "usage": {
-                        "input_tokens": input_tokens,
-                        "output_tokens": output_tokens
-                    }
-                }),
-            ));
+            let mut data = json!({
+                "type": "message_delta",
+                "delta": {
+                    "stop_reason": self.get_stop_reason(),
+                    "stop_sequence": self.stop_sequence
+                },
+                "usage": {
+                    "input_tokens": input_tokens,
+                    "output_tokens": output_tokens
+                }
+            });
+            if let Some(metering) = metering {
+                data["usage"]["kiro_metering"] = metering.to_json();
+            }
+            events.push(SseEvent::new("message_delta", data));
         }
 
         // Send message_stop
@@ -460,8 +386,13 @@ impl SseStateManager {
     }
 }
 
-/// Context window size (200k tokens)
-const CONTEXT_WINDOW_SIZE: i32 = 200_000;
+/// Tool call whose input JSON is still being received
+#[derive(Debug, Clone)]
+pub struct PendingToolUse {
+    pub tool_use_id: String,
+    pub name: String,
+    pub input: String,
+}
 
 /// Stream processing context
 pub struct StreamContext {
@@ -469,6 +400,8 @@ pub struct StreamContext {
     pub state_manager: SseStateManager,
     /// Requested model name
     pub model: String,
+    /// Context window of the requested model, for the contextUsageEvent → input_tokens math
+    context_window: i32,
     /// Message ID
     pub message_id: String,
     /// Input tokens (estimated value)
@@ -477,23 +410,32 @@ pub struct StreamContext {
     pub context_input_tokens: Option<i32>,
     /// Output tokens accumulated
     pub output_tokens: i32,
-    /// Tool block index mapping (tool_id -> block_index)
+    /// Tool block index mapping (tool_id -> block_index), only for emitted tool calls
     pub tool_block_indices: HashMap<String, i32>,
+    /// Tool calls still receiving input, buffered until complete to detect truncation
+    pub pending_tool_uses: Vec<PendingToolUse>,
+    /// Number of tool calls replaced by soft-failure guidance due to truncation
+    pub truncated_tool_calls: usize,
     /// Whether thinking is enabled
     pub thinking_enabled: bool,
-    /// Thinking content buffer
-    pub thinking_buffer: String,
-    /// Whether inside thinking block
-    pub in_thinking_block: bool,
-    /// Whether thinking block extraction is complete
-    pub thinking_extracted: bool,
+    /// `<thinking>` tag parser for assistant content
+    thinking_parser: ThinkingParser,
     /// Thinking block index
     pub thinking_block_index: Option<i32>,
     /// Text block index (dynamically allocated when thinking is enabled)
     pub text_block_index: Option<i32>,
-    /// Whether to strip leading newline from thinking content
-    /// When model outputs `<thinking>\n`, `\n` may be in the same chunk or next chunk as the tag
-    strip_thinking_leading_newline: bool,
+    /// Requested tool_choice, verified at stream end
+    pub tool_choice: ToolChoice,
+    /// Names of tools called in this response
+    pub called_tools: Vec<String>,
+    /// Keyring key to charge the usage to
+    pub client_key: Option<ClientKey>,
+    /// Whether usage was recorded (at the message end, or on drop after a client disconnect)
+    usage_recorded: bool,
+    /// Request stop_sequences, matched against the text deltas
+    stop_sequences: StopSequenceScanner,
+    /// Usage reported by meteringEvents
+    metering: Option<MeteringUsage>,
 }
 
 impl StreamContext {
@@ -503,24 +445,78 @@ impl StreamContext {
         input_tokens: i32,
         thinking_enabled: bool,
     ) -> Self {
+        let model = model.into();
         Self {
             state_manager: SseStateManager::new(),
-            model: model.into(),
+            context_window: get_context_window_size(&model),
+            model,
             message_id: format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
             input_tokens,
             context_input_tokens: None,
             output_tokens: 0,
             tool_block_indices: HashMap::new(),
+            pending_tool_uses: Vec::new(),
+            truncated_tool_calls: 0,
             thinking_enabled,
-            thinking_buffer: String::new(),
-            in_thinking_block: false,
-            thinking_extracted: false,
+            thinking_parser: ThinkingParser::new(),
             thinking_block_index: None,
             text_block_index: None,
-            strip_thinking_leading_newline: false,
+            tool_choice: ToolChoice::Auto,
+            called_tools: Vec::new(),
+            client_key: None,
+            usage_recorded: false,
+            stop_sequences: StopSequenceScanner::default(),
+            metering: None,
+        }
+    }
+
+    /// Set the tool_choice that the response must satisfy
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = tool_choice;
+        self
+    }
+
+    /// Set the keyring key that the usage is recorded for
+    pub fn with_client_key(mut self, client_key: Option<ClientKey>) -> Self {
+        self.client_key = client_key;
+        self
+    }
+
+    /// Record input and output tokens to metrics and the client key (once per message)
+    fn record_usage(&mut self) {
+        if self.usage_recorded {
+            return;
+        }
+        self.usage_recorded = true;
+        let final_input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
+        metrics::record_tokens(&self.model, final_input_tokens, self.output_tokens);
+        if let Some(key) = self.client_key.take() {
+            key.record_usage(final_input_tokens, self.output_tokens);
         }
     }
 
+    /// Set the stop sequences that end the text output
+    pub fn with_stop_sequences(mut self, stop_sequences: Vec<String>) -> Self {
+        self.stop_sequences = StopSequenceScanner::new(stop_sequences);
+        self
+    }
+
+    /// Whether a stop sequence matched; the rest of the upstream response is not needed
+    pub fn stop_sequence_matched(&self) -> bool {
+        self.stop_sequences.matched().is_some()
+    }
+
+    /// Whether the message is complete (stop sequence matched or upstream failed);
+    /// the rest of the upstream response is not needed
+    pub fn is_finished(&self) -> bool {
+        self.stop_sequence_matched() || self.state_manager.is_message_ended()
+    }
+
+    /// Usage reported by Kiro meteringEvents so far
+    pub fn metering(&self) -> Option<&MeteringUsage> {
+        self.metering.as_ref()
+    }
+
     /// Generate message_start event
     pub fn create_message_start_event(&self) -> serde_json::Value {
         json!({
@@ -582,14 +578,19 @@ impl StreamContext {
 
     /// Process Kiro event and convert to Anthropic SSE events
     pub fn process_kiro_event(&mut self, event: &Event) -> Vec<SseEvent> {
+        // Output after a stop sequence or an upstream error is discarded
+        if self.is_finished() {
+            return Vec::new();
+        }
+
         match event {
             Event::AssistantResponse(resp) => self.process_assistant_response(&resp.content),
             Event::ToolUse(tool_use) => self.process_tool_use(tool_use),
             Event::ContextUsage(context_usage) => {
                 // Calculate actual input_tokens from context usage percentage
-                // Formula: percentage * 200000 / 100 = percentage * 2000
+                // Formula: percentage * context window / 100
                 let actual_input_tokens = (context_usage.context_usage_percentage
-                    * (CONTEXT_WINDOW_SIZE as f64)
+                    * (self.context_window as f64)
                     / 100.0) as i32;
                 self.context_input_tokens = Some(actual_input_tokens);
                 // When context usage reaches 100%, set stop_reason to model_context_window_exceeded
@@ -604,23 +605,35 @@ impl StreamContext {
                 );
                 Vec::new()
             }
+            Event::Metering(metering) => {
+                tracing::debug!("Received meteringEvent: {}", metering);
+                self.metering.get_or_insert_default().add(metering);
+                Vec::new()
+            }
             Event::Error {
                 error_code,
                 error_message,
             } => {
                 tracing::error!("Received error event: {} - {}", error_code, error_message);
-                Vec::new()
+                self.generate_error_events(
+                    upstream_error_type(error_code, error_message),
+                    &format!("{}: {}", error_code, error_message),
+                )
             }
             Event::Exception {
                 exception_type,
                 message,
             } => {
-                // Handle ContentLengthExceededException
+                tracing::warn!("Received exception event: {} - {}", exception_type, message);
+                // ContentLengthExceededException ends the output normally with max_tokens
                 if exception_type == "ContentLengthExceededException" {
                     self.state_manager.set_stop_reason("max_tokens");
+                    return Vec::new();
                 }
-                tracing::warn!("Received exception event: {} - {}", exception_type, message);
-                Vec::new()
+                self.generate_error_events(
+                    upstream_error_type(exception_type, message),
+                    &format!("{}: {}", exception_type, message),
+                )
             }
             _ => Vec::new(),
         }
@@ -647,29 +660,18 @@ impl StreamContext {
 
     /// Process content containing thinking block
     fn process_content_with_thinking(&mut self, content: &str) -> Vec<SseEvent> {
-        let mut events = Vec::new();
-
-        // Add content to buffer for processing
-        self.thinking_buffer.push_str(content);
-
-        loop {
-            if !self.in_thinking_block && !self.thinking_extracted {
-                // Find <thinking> start tag (skip those wrapped by backticks)
-                if let Some(start_pos) = find_real_thinking_start_tag(&self.thinking_buffer) {
-                    // Send content before <thinking> as text_delta
-                    // Note: If preceding content is only whitespace (like \n\n returned by adaptive mode), skip it,
-                    // to avoid creating meaningless text block before thinking block causing client parsing failure
-                    let before_thinking = self.thinking_buffer[..start_pos].to_string();
-                    if !before_thinking.is_empty() && !before_thinking.trim().is_empty() {
-                        events.extend(self.create_text_delta_events(&before_thinking));
-                    }
+        let chunks = self.thinking_parser.push(content);
+        self.create_thinking_chunk_events(chunks)
+    }
 
-                    // Enter thinking block
-                    self.in_thinking_block = true;
-                    self.strip_thinking_leading_newline = true;
-                    self.thinking_buffer =
-                        self.thinking_buffer[start_pos + "<thinking>".len()..].to_string();
+    /// Convert thinking parser output to SSE events
+    fn create_thinking_chunk_events(&mut self, chunks: Vec<ThinkingChunk>) -> Vec<SseEvent> {
+        let mut events = Vec::new();
 
+        for chunk in chunks {
+            match chunk {
+                ThinkingChunk::Text(text) => events.extend(self.create_text_delta_events(&text)),
+                ThinkingChunk::ThinkingStart => {
                     // Create thinking block content_block_start event
                     let thinking_index = self.state_manager.next_block_index();
                     self.thinking_block_index = Some(thinking_index);
@@ -686,58 +688,13 @@ impl StreamContext {
                         }),
                     );
                     events.extend(start_events);
-                } else {
-                    // No <thinking> found, check if it might be partial tag
-                    // Keep content that might be partial tag
-                    let target_len = self
-                        .thinking_buffer
-                        .len()
-                        .saturating_sub("<thinking>".len());
-                    let safe_len = self.thinking_buffer.floor_char_boundary(target_len);
-                    if safe_len > 0 {
-                        let safe_content = self.thinking_buffer[..safe_len].to_string();
-                        // If thinking hasn't been extracted yet, and safe content is only whitespace,
-                        // don't send as text_delta, keep in buffer waiting for more content.
-                        // This avoids the issue in 4.6 model where <thinking> tag splits across events,
-                        // causing leading whitespace (like "\n\n") to be incorrectly created as text block,
-                        // resulting in text block appearing before thinking block.
-                        if !safe_content.is_empty() && !safe_content.trim().is_empty() {
-                            events.extend(self.create_text_delta_events(&safe_content));
-                            self.thinking_buffer = self.thinking_buffer[safe_len..].to_string();
-                        }
-                    }
-                    break;
                 }
-            } else if self.in_thinking_block {
-                // Strip newline immediately following <thinking> tag (may span chunks)
-                if self.strip_thinking_leading_newline {
-                    if self.thinking_buffer.starts_with('\n') {
-                        self.thinking_buffer = self.thinking_buffer[1..].to_string();
-                        self.strip_thinking_leading_newline = false;
-                    } else if !self.thinking_buffer.is_empty() {
-                        // buffer is not empty but doesn't start with \n, no longer need to strip
-                        self.strip_thinking_leading_newline = false;
+                ThinkingChunk::Thinking(thinking) => {
+                    if let Some(thinking_index) = self.thinking_block_index {
+                        events.push(self.create_thinking_delta_event(thinking_index, &thinking));
                     }
-                    // Keep flag when buffer is empty, wait for next chunk
                 }
-
-                // Inside thinking block, find </thinking> end tag (skip those wrapped by backticks)
-                if let Some(end_pos) = find_real_thinking_end_tag(&self.thinking_buffer) {
-                    // Extract thinking content
-                    let thinking_content = self.thinking_buffer[..end_pos].to_string();
-                    if !thinking_content.is_empty() {
-                        if let Some(thinking_index) = self.thinking_block_index {
-                            events.push(
-                                self.create_thinking_delta_event(thinking_index, &thinking_content),
-                            );
-                        }
-                    }
-
-                    // End thinking block
-                    self.in_thinking_block = false;
-                    self.thinking_extracted = true;
-
-                    // Send empty thinking_delta event, then send content_block_stop event
+                ThinkingChunk::ThinkingEnd => {
                     if let Some(thinking_index) = self.thinking_block_index {
                         // First send empty thinking_delta
                         events.push(self.create_thinking_delta_event(thinking_index, ""));
@@ -748,43 +705,7 @@ impl StreamContext {
                             events.push(stop_event);
                         }
                     }
-
-                    // Strip `</thinking>\n\n` (find_real_thinking_end_tag already confirmed \n\n exists)
-                    self.thinking_buffer =
-                        self.thinking_buffer[end_pos + "</thinking>\n\n".len()..].to_string();
-                } else {
-                    // No end tag found, send current buffer content as thinking_delta.
-                    // Keep tail content that might be partial `</thinking>\n\n`:
-                    // find_real_thinking_end_tag requires `\n\n` after tag to return Some,
-                    // so reserved area must cover full length of `</thinking>\n\n` (13 bytes),
-                    // otherwise when `</thinking>` is in buffer but `\n\n` hasn't arrived,
-                    // first few characters of tag would be incorrectly sent as thinking_delta.
-                    let target_len = self
-                        .thinking_buffer
-                        .len()
-                        .saturating_sub("</thinking>\n\n".len());
-                    let safe_len = self.thinking_buffer.floor_char_boundary(target_len);
-                    if safe_len > 0 {
-                        let safe_content = self.thinking_buffer[..safe_len].to_string();
-                        if !safe_content.is_empty() {
-                            if let Some(thinking_index) = self.thinking_block_index {
-                                events.push(
-                                    self.create_thinking_delta_event(thinking_index, &safe_content),
-                                );
-                            }
-                        }
-                        self.thinking_buffer = self.thinking_buffer[safe_len..].to_string();
-                    }
-                    break;
                 }
-            } else {
-                // thinking extraction complete, remaining content as text_delta
-                if !self.thinking_buffer.is_empty() {
-                    let remaining = self.thinking_buffer.clone();
-                    self.thinking_buffer.clear();
-                    events.extend(self.create_text_delta_events(&remaining));
-                }
-                break;
             }
         }
 
@@ -793,11 +714,35 @@ impl StreamContext {
 
     /// Create text_delta events
     ///
+    /// Text is scanned for stop sequences first: text that may start a stop sequence is held back
+    /// until the next delta, and on a match the text is truncated and the stop sequence recorded.
+    fn create_text_delta_events(&mut self, text: &str) -> Vec<SseEvent> {
+        let text = self.stop_sequences.push(text);
+        if let Some(sequence) = self.stop_sequences.matched() {
+            self.state_manager.set_stop_sequence(sequence);
+        }
+        if text.is_empty() {
+            return Vec::new();
+        }
+        self.emit_text_delta_events(&text)
+    }
+
+    /// Emit text held back by the stop sequence scanner
+    fn flush_stop_sequence_text(&mut self) -> Vec<SseEvent> {
+        let text = self.stop_sequences.flush();
+        if text.is_empty() {
+            return Vec::new();
+        }
+        self.emit_text_delta_events(&text)
+    }
+
+    /// Emit text_delta events without stop sequence scanning
+    ///
     /// If text block hasn't been created yet, will create text block first.
     /// When tool_use occurs, state machine will auto-close current text block; subsequent text will auto-create new text block to continue output.
     ///
     /// Return value includes possible content_block_start event and content_block_delta event.
-    fn create_text_delta_events(&mut self, text: &str) -> Vec<SseEvent> {
+    fn emit_text_delta_events(&mut self, text: &str) -> Vec<SseEvent> {
         let mut events = Vec::new();
 
         // If current text_block_index points to a block that has been closed (e.g., auto-stopped when tool_use starts),
@@ -873,69 +818,74 @@ impl StreamContext {
     ) -> Vec<SseEvent> {
         let mut events = Vec::new();
 
-        self.state_manager.set_has_tool_use(true);
-
         // tool_use must occur after thinking ends.
-        // But when `</thinking>` is not followed by `\n\n` (e.g., immediately followed by tool_use or stream ends),
-        // thinking end tag will remain in thinking_buffer, causing subsequent flush to output `</thinking>` as content.
-        // Here we do a "boundary scenario" end tag recognition and filtering before starting tool_use block.
-        if self.thinking_enabled && self.in_thinking_block {
-            if let Some(end_pos) = find_real_thinking_end_tag_at_buffer_end(&self.thinking_buffer) {
-                let thinking_content = self.thinking_buffer[..end_pos].to_string();
-                if !thinking_content.is_empty() {
-                    if let Some(thinking_index) = self.thinking_block_index {
-                        events.push(
-                            self.create_thinking_delta_event(thinking_index, &thinking_content),
-                        );
-                    }
-                }
+        // Recognize a trailing `</thinking>` not followed by `\n\n`, and release text held back
+        // to detect `<thinking>`, so neither is swallowed by the tool_use block.
+        if self.thinking_enabled {
+            let chunks = self.thinking_parser.flush_before_tool_use();
+            events.extend(self.create_thinking_chunk_events(chunks));
+        }
 
-                // End thinking block
-                self.in_thinking_block = false;
-                self.thinking_extracted = true;
-
-                if let Some(thinking_index) = self.thinking_block_index {
-                    // First send empty thinking_delta
-                    events.push(self.create_thinking_delta_event(thinking_index, ""));
-                    // Then send content_block_stop
-                    if let Some(stop_event) =
-                        self.state_manager.handle_content_block_stop(thinking_index)
-                    {
-                        events.push(stop_event);
-                    }
-                }
+        // Tool call already emitted, ignore late events for the same id
+        if self.tool_block_indices.contains_key(&tool_use.tool_use_id) {
+            return events;
+        }
 
-                // Treat content after end tag as regular text (usually empty or whitespace)
-                let after_pos = end_pos + "</thinking>".len();
-                let remaining = self.thinking_buffer[after_pos..].trim_start().to_string();
-                self.thinking_buffer.clear();
-                if !remaining.is_empty() {
-                    events.extend(self.create_text_delta_events(&remaining));
-                }
-            }
+        if !tool_use.input.is_empty() {
+            self.output_tokens += (tool_use.input.len() as i32 + 3) / 4; // Estimate token
         }
 
-        // In thinking mode, process_content_with_thinking may temporarily store a small tail text to detect `<thinking>`.
-        // If tool_use starts directly at this point, state machine will auto-close text block, causing this "pending output text" to appear swallowed by tool_use.
-        // Constraint: Only flush buffer as regular text when not yet entered thinking block and thinking hasn't been extracted.
-        if self.thinking_enabled
-            && !self.in_thinking_block
-            && !self.thinking_extracted
-            && !self.thinking_buffer.is_empty()
+        // Buffer tool input until the call is complete, so truncated JSON is never forwarded
+        let pending_pos = match self
+            .pending_tool_uses
+            .iter()
+            .position(|p| p.tool_use_id == tool_use.tool_use_id)
         {
-            let buffered = std::mem::take(&mut self.thinking_buffer);
-            events.extend(self.create_text_delta_events(&buffered));
+            Some(pos) => pos,
+            None => {
+                self.pending_tool_uses.push(PendingToolUse {
+                    tool_use_id: tool_use.tool_use_id.clone(),
+                    name: tool_use.name.clone(),
+                    input: String::new(),
+                });
+                self.pending_tool_uses.len() - 1
+            }
+        };
+        self.pending_tool_uses[pending_pos]
+            .input
+            .push_str(&tool_use.input);
+
+        // If this is a complete tool call (stop=true), validate and emit it
+        if tool_use.stop {
+            let pending = self.pending_tool_uses.remove(pending_pos);
+            events.extend(self.finish_tool_use(pending));
         }
 
-        // Get or allocate block index
-        let block_index = if let Some(&idx) = self.tool_block_indices.get(&tool_use.tool_use_id) {
-            idx
-        } else {
-            let idx = self.state_manager.next_block_index();
-            self.tool_block_indices
-                .insert(tool_use.tool_use_id.clone(), idx);
-            idx
-        };
+        events
+    }
+
+    /// Validate a complete tool call and emit it as a tool_use block
+    ///
+    /// Truncated tool calls are replaced by a text block with soft-failure guidance,
+    /// so the client never executes a tool with incomplete input.
+    fn finish_tool_use(&mut self, pending: PendingToolUse) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+
+        if let Err(info) = parse_tool_input(&pending.name, &pending.tool_use_id, &pending.input) {
+            self.truncated_tool_calls += 1;
+            events.extend(self.create_text_delta_events(&build_soft_failure_result(&info)));
+            return events;
+        }
+
+        // Text held back for stop sequence matching comes before the tool_use block
+        events.extend(self.flush_stop_sequence_text());
+
+        self.state_manager.set_has_tool_use(true);
+        self.called_tools.push(pending.name.clone());
+
+        let block_index = self.state_manager.next_block_index();
+        self.tool_block_indices
+            .insert(pending.tool_use_id.clone(), block_index);
 
         // Send content_block_start
         let start_events = self.state_manager.handle_content_block_start(
@@ -946,18 +896,16 @@ impl StreamContext {
                 "index": block_index,
                 "content_block": {
                     "type": "tool_use",
-                    "id": tool_use.tool_use_id,
-                    "name": tool_use.name,
+                    "id": pending.tool_use_id,
+                    "name": pending.name,
                     "input": {}
                 }
             }),
         );
         events.extend(start_events);
 
-        // Send parameter delta (ToolUseEvent.input is String type)
-        if !tool_use.input.is_empty() {
-            self.output_tokens += (tool_use.input.len() as i32 + 3) / 4; // Estimate token
-
+        // Send complete parameter JSON as a single delta
+        if !pending.input.is_empty() {
             if let Some(delta_event) = self.state_manager.handle_content_block_delta(
                 block_index,
                 json!({
@@ -965,7 +913,7 @@ impl StreamContext {
                     "index": block_index,
                     "delta": {
                         "type": "input_json_delta",
-                        "partial_json": tool_use.input
+                        "partial_json": pending.input
                     }
                 }),
             ) {
@@ -973,80 +921,72 @@ impl StreamContext {
             }
         }
 
-        // If this is a complete tool call (stop=true), send content_block_stop
-        if tool_use.stop {
-            if let Some(stop_event) = self.state_manager.handle_content_block_stop(block_index) {
-                events.push(stop_event);
-            }
+        if let Some(stop_event) = self.state_manager.handle_content_block_stop(block_index) {
+            events.push(stop_event);
         }
 
         events
     }
 
+    /// Generate error event sequence when the upstream stream fails mid-response
+    ///
+    /// Content received so far is flushed and usage is recorded like a normal end,
+    /// then the message ends with an `error` event of the given Anthropic error type.
+    pub fn generate_error_events(&mut self, error_type: &str, message: &str) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        if self.state_manager.is_message_ended() {
+            return events;
+        }
+
+        if self.thinking_enabled {
+            let chunks = self.thinking_parser.finish();
+            events.extend(self.create_thinking_chunk_events(chunks));
+        }
+        events.extend(self.flush_stop_sequence_text());
+
+        // Incomplete tool calls can't be used, drop them
+        for pending in std::mem::take(&mut self.pending_tool_uses) {
+            tracing::warn!(
+                "Tool call dropped by upstream error: tool={} id={}",
+                pending.name,
+                pending.tool_use_id
+            );
+        }
+
+        self.record_usage();
+
+        events.extend(self.state_manager.generate_error_events(error_type, message));
+        events
+    }
+
     /// Generate final event sequence
     pub fn generate_final_events(&mut self) -> Vec<SseEvent> {
         let mut events = Vec::new();
+        // Already ended by an upstream error
+        if self.state_manager.is_message_ended() {
+            return events;
+        }
 
-        // Flush remaining content in thinking_buffer
-        if self.thinking_enabled && !self.thinking_buffer.is_empty() {
-            if self.in_thinking_block {
-                // End may have residual `</thinking>` (e.g., immediately followed by tool_use or stream ends), need to filter out end tag during flush.
-                if let Some(end_pos) =
-                    find_real_thinking_end_tag_at_buffer_end(&self.thinking_buffer)
-                {
-                    let thinking_content = self.thinking_buffer[..end_pos].to_string();
-                    if !thinking_content.is_empty() {
-                        if let Some(thinking_index) = self.thinking_block_index {
-                            events.push(
-                                self.create_thinking_delta_event(thinking_index, &thinking_content),
-                            );
-                        }
-                    }
-
-                    // Close thinking block: first send empty thinking_delta, then send content_block_stop
-                    if let Some(thinking_index) = self.thinking_block_index {
-                        events.push(self.create_thinking_delta_event(thinking_index, ""));
-                        if let Some(stop_event) =
-                            self.state_manager.handle_content_block_stop(thinking_index)
-                        {
-                            events.push(stop_event);
-                        }
-                    }
+        // Flush remaining content held back by the thinking parser
+        if self.thinking_enabled {
+            let chunks = self.thinking_parser.finish();
+            events.extend(self.create_thinking_chunk_events(chunks));
+        }
+        events.extend(self.flush_stop_sequence_text());
+
+        // Tool calls that never received stop=true were cut off by the stream end
+        for pending in std::mem::take(&mut self.pending_tool_uses) {
+            tracing::warn!(
+                "Tool call incomplete at stream end: tool={} id={}",
+                pending.name,
+                pending.tool_use_id
+            );
+            events.extend(self.finish_tool_use(pending));
+        }
 
-                    // Treat content after end tag as regular text (usually empty or whitespace)
-                    let after_pos = end_pos + "</thinking>".len();
-                    let remaining = self.thinking_buffer[after_pos..].trim_start().to_string();
-                    self.thinking_buffer.clear();
-                    self.in_thinking_block = false;
-                    self.thinking_extracted = true;
-                    if !remaining.is_empty() {
-                        events.extend(self.create_text_delta_events(&remaining));
-                    }
-                } else {
-                    // If still inside thinking block, send remaining content as thinking_delta
-                    if let Some(thinking_index) = self.thinking_block_index {
-                        events.push(
-                            self.create_thinking_delta_event(thinking_index, &self.thinking_buffer),
-                        );
-                    }
-                    // Close thinking block: first send empty thinking_delta, then send content_block_stop
-                    if let Some(thinking_index) = self.thinking_block_index {
-                        // First send empty thinking_delta
-                        events.push(self.create_thinking_delta_event(thinking_index, ""));
-                        // Then send content_block_stop
-                        if let Some(stop_event) =
-                            self.state_manager.handle_content_block_stop(thinking_index)
-                        {
-                            events.push(stop_event);
-                        }
-                    }
-                }
-            } else {
-                // Otherwise send remaining content as text_delta
-                let buffer_content = self.thinking_buffer.clone();
-                events.extend(self.create_text_delta_events(&buffer_content));
-            }
-            self.thinking_buffer.clear();
+        // Truncated tool calls without any usable tool call: output limit was reached
+        if self.truncated_tool_calls > 0 && self.tool_block_indices.is_empty() {
+            self.state_manager.set_stop_reason("max_tokens");
         }
 
         // If entire stream only produced thinking block, no text and no tool_use,
@@ -1057,21 +997,43 @@ impl StreamContext {
             && !self.state_manager.has_non_thinking_blocks()
         {
             self.state_manager.set_stop_reason("max_tokens");
-            events.extend(self.create_text_delta_events(" "));
+            events.extend(self.emit_text_delta_events(" "));
+        }
+
+        // Forced tool_choice not honored: end the message with an error event instead of
+        // message_delta, so clients fail loudly instead of receiving a plain text answer
+        if !self
+            .tool_choice
+            .is_satisfied_by(self.called_tools.iter().map(String::as_str))
+        {
+            let message = self.tool_choice.unsatisfied_message();
+            tracing::warn!("{}", message);
+            events.extend(self.generate_error_events("api_error", &message));
+            return events;
         }
 
         // Use input_tokens calculated from contextUsageEvent, fallback to estimate if not available
         let final_input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
+        self.record_usage();
 
         // Generate final events
-        events.extend(
-            self.state_manager
-                .generate_final_events(final_input_tokens, self.output_tokens),
-        );
+        events.extend(self.state_manager.generate_final_events(
+            final_input_tokens,
+            self.output_tokens,
+            self.metering.as_ref(),
+        ));
         events
     }
 }
 
+impl Drop for StreamContext {
+    /// A stream dropped before the message end (client disconnect) is charged for the
+    /// output generated so far
+    fn drop(&mut self) {
+        self.record_usage();
+    }
+}
+
 /// Buffered stream processing context - for /cc/v1/messages streaming requests
 ///
 /// Unlike `StreamContext`, this context buffers all events until stream ends,
@@ -1110,6 +1072,45 @@ impl BufferedStreamContext {
         }
     }
 
+    /// Set the tool_choice that the response must satisfy
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.inner.tool_choice = tool_choice;
+        self
+    }
+
+    /// Set the keyring key that the final usage is recorded for
+    pub fn with_client_key(mut self, client_key: Option<ClientKey>) -> Self {
+        self.inner.client_key = client_key;
+        self
+    }
+
+    /// Set the stop sequences that end the text output
+    pub fn with_stop_sequences(mut self, stop_sequences: Vec<String>) -> Self {
+        self.inner = self.inner.with_stop_sequences(stop_sequences);
+        self
+    }
+
+    /// Whether the message is complete (stop sequence matched or upstream failed)
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    /// Usage reported by Kiro meteringEvents so far
+    pub fn metering(&self) -> Option<&MeteringUsage> {
+        self.inner.metering()
+    }
+
+    /// End the message with an error event when the upstream stream fails
+    pub fn fail(&mut self, error_type: &str, message: &str) {
+        if !self.initial_events_generated {
+            let initial_events = self.inner.generate_initial_events();
+            self.event_buffer.extend(initial_events);
+            self.initial_events_generated = true;
+        }
+        let events = self.inner.generate_error_events(error_type, message);
+        self.event_buffer.extend(events);
+    }
+
     /// Process Kiro event and buffer results
     ///
     /// Reuses StreamContext's event processing logic, but caches results instead of sending immediately.
@@ -1165,6 +1166,32 @@ impl BufferedStreamContext {
     }
 }
 
+/// Map a Kiro error code or exception type to an Anthropic error type
+pub(super) fn upstream_error_type(code: &str, message: &str) -> &'static str {
+    let code = code.to_lowercase();
+    let message = message.to_lowercase();
+    if code.contains("throttl")
+        || code.contains("quota")
+        || code.contains("limit")
+        || message.contains("rate limit")
+    {
+        "rate_limit_error"
+    } else if code.contains("unavailable")
+        || code.contains("capacity")
+        || code.contains("overload")
+        || message.contains("overload")
+        || message.contains("capacity")
+    {
+        "overloaded_error"
+    } else if code.contains("validation") {
+        "invalid_request_error"
+    } else if code.contains("accessdenied") {
+        "permission_error"
+    } else {
+        "api_error"
+    }
+}
+
 /// Simple token estimation
 fn estimate_tokens(text: &str) -> i32 {
     let chars: Vec<char> = text.chars().collect();
@@ -1255,7 +1282,7 @@ mod tests {
             name: "test_tool".to_string(),
             tool_use_id: "tool_1".to_string(),
             input: "{}".to_string(),
-            stop: false,
+            stop: true,
         });
         assert!(
             tool_events.iter().any(|e| {
@@ -1317,7 +1344,7 @@ mod tests {
             name: "Write".to_string(),
             tool_use_id: "tool_1".to_string(),
             input: "{}".to_string(),
-            stop: false,
+            stop: true,
         });
 
         let text_start_index = events.iter().find_map(|e| {
@@ -1379,129 +1406,6 @@ mod tests {
         assert!(estimate_tokens("Hello 你好") > 0);
     }
 
-    #[test]
-    fn test_find_real_thinking_start_tag_basic() {
-        // Basic case: normal start tag
-        assert_eq!(find_real_thinking_start_tag("<thinking>"), Some(0));
-        assert_eq!(find_real_thinking_start_tag("prefix<thinking>"), Some(6));
-    }
-
-    #[test]
-    fn test_find_real_thinking_start_tag_with_backticks() {
-        // Wrapped by backticks should be skipped
-        assert_eq!(find_real_thinking_start_tag("`<thinking>`"), None);
-        assert_eq!(find_real_thinking_start_tag("use `<thinking>` tag"), None);
-
-        // First has wrapped one, then has real start tag
-        assert_eq!(
-            find_real_thinking_start_tag("about `<thinking>` tag<thinking>content"),
-            Some(22)
-        );
-    }
-
-    #[test]
-    fn test_find_real_thinking_start_tag_with_quotes() {
-        // Wrapped by double quotes should be skipped
-        assert_eq!(find_real_thinking_start_tag("\"<thinking>\""), None);
-        assert_eq!(find_real_thinking_start_tag("the \"<thinking>\" tag"), None);
-
-        // Wrapped by single quotes should be skipped
-        assert_eq!(find_real_thinking_start_tag("'<thinking>'"), None);
-
-        // Mixed case
-        assert_eq!(
-            find_real_thinking_start_tag("about \"<thinking>\" and '<thinking>' then<thinking>"),
-            Some(40)
-        );
-    }
-
-    #[test]
-    fn test_find_real_thinking_end_tag_basic() {
-        // Basic case: normal end tag followed by double newline
-        assert_eq!(find_real_thinking_end_tag("</thinking>\n\n"), Some(0));
-        assert_eq!(
-            find_real_thinking_end_tag("content</thinking>\n\n"),
-            Some(7)
-        );
-        assert_eq!(
-            find_real_thinking_end_tag("some text</thinking>\n\nmore text"),
-            Some(9)
-        );
-
-        // Case without double newline
-        assert_eq!(find_real_thinking_end_tag("</thinking>"), None);
-        assert_eq!(find_real_thinking_end_tag("</thinking>\n"), None);
-        assert_eq!(find_real_thinking_end_tag("</thinking> more"), None);
-    }
-
-    #[test]
-    fn test_find_real_thinking_end_tag_with_backticks() {
-        // Wrapped by backticks should be skipped
-        assert_eq!(find_real_thinking_end_tag("`</thinking>`\n\n"), None);
-        assert_eq!(
-            find_real_thinking_end_tag("mention `</thinking>` in code\n\n"),
-            None
-        );
-
-        // Only has backtick before
-        assert_eq!(find_real_thinking_end_tag("`</thinking>\n\n"), None);
-
-        // Only has backtick after
-        assert_eq!(find_real_thinking_end_tag("</thinking>`\n\n"), None);
-    }
-
-    #[test]
-    fn test_find_real_thinking_end_tag_with_quotes() {
-        // Wrapped by double quotes should be skipped
-        assert_eq!(find_real_thinking_end_tag("\"</thinking>\"\n\n"), None);
-        assert_eq!(
-            find_real_thinking_end_tag("the string \"</thinking>\" is a tag\n\n"),
-            None
-        );
-
-        // Wrapped by single quotes should be skipped
-        assert_eq!(find_real_thinking_end_tag("'</thinking>'\n\n"), None);
-        assert_eq!(
-            find_real_thinking_end_tag("use '</thinking>' as marker\n\n"),
-            None
-        );
-
-        // Mixed case: double quote wrapped then has real tag
-        assert_eq!(
-            find_real_thinking_end_tag("about \"</thinking>\" tag</thinking>\n\n"),
-            Some(23)
-        );
-
-        // Mixed case: single quote wrapped then has real tag
-        assert_eq!(
-            find_real_thinking_end_tag("about '</thinking>' tag</thinking>\n\n"),
-            Some(23)
-        );
-    }
-
-    #[test]
-    fn test_find_real_thinking_end_tag_mixed() {
-        // First has wrapped one, then has real end tag
-        assert_eq!(
-            find_real_thinking_end_tag("discussing `</thinking>` tag</thinking>\n\n"),
-            Some(28)
-        );
-
-        // Multiple wrapped ones, last one is real
-        assert_eq!(
-            find_real_thinking_end_tag("`</thinking>` and `</thinking>` done</thinking>\n\n"),
-            Some(36)
-        );
-
-        // Multiple quote character types mixed
-        assert_eq!(
-            find_real_thinking_end_tag(
-                "`</thinking>` and \"</thinking>\" and '</thinking>' done</thinking>\n\n"
-            ),
-            Some(54)
-        );
-    }
-
     #[test]
     fn test_tool_use_immediately_after_thinking_filters_end_tag_and_closes_thinking_block() {
         let mut ctx = StreamContext::new_with_thinking("test-model", 1, true);
@@ -1899,4 +1803,308 @@ mod tests {
             "stop_reason should be tool_use when tool_use is present"
         );
     }
+
+    #[test]
+    fn test_forced_tool_choice_not_called_emits_error() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false)
+            .with_tool_choice(ToolChoice::Tool("Write".to_string()));
+        let _initial_events = ctx.generate_initial_events();
+
+        let mut all_events = Vec::new();
+        all_events.extend(ctx.process_assistant_response("plain text answer"));
+        all_events.extend(ctx.generate_final_events());
+
+        let error = all_events
+            .iter()
+            .find(|e| e.event == "error")
+            .expect("should have error event");
+        assert!(error.data["error"]["message"].as_str().unwrap().contains("Write"));
+
+        // The text block is closed and the error event ends the message
+        let names: Vec<_> = all_events.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(&names[names.len() - 2..], ["content_block_stop", "error"]);
+        assert!(!names.contains(&"message_delta"));
+        assert!(!names.contains(&"message_stop"));
+    }
+
+    #[test]
+    fn test_forced_tool_choice_called_no_error() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false)
+            .with_tool_choice(ToolChoice::Any);
+        let _initial_events = ctx.generate_initial_events();
+
+        let mut all_events = Vec::new();
+        all_events.extend(ctx.process_tool_use(&crate::kiro::model::events::ToolUseEvent {
+            name: "Write".to_string(),
+            tool_use_id: "tool_1".to_string(),
+            input: "{}".to_string(),
+            stop: true,
+        }));
+        all_events.extend(ctx.generate_final_events());
+
+        assert!(all_events.iter().all(|e| e.event != "error"));
+        assert_eq!(ctx.called_tools, vec!["Write".to_string()]);
+    }
+
+    #[test]
+    fn test_truncated_tool_use_replaced_by_soft_failure() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let _initial_events = ctx.generate_initial_events();
+
+        let mut all_events = Vec::new();
+        all_events.extend(ctx.process_tool_use(&crate::kiro::model::events::ToolUseEvent {
+            name: "Write".to_string(),
+            tool_use_id: "tool_1".to_string(),
+            input: r#"{"file_path": "/a.txt", "content": "par"#.to_string(),
+            stop: false,
+        }));
+        // partial JSON is never forwarded
+        assert!(all_events.iter().all(|e| e.data["delta"]["type"] != "input_json_delta"));
+
+        // stream ends before stop=true
+        all_events.extend(ctx.generate_final_events());
+
+        assert!(all_events.iter().all(|e| e.data["content_block"]["type"] != "tool_use"));
+        assert!(all_events.iter().any(|e| {
+            e.data["delta"]["type"] == "text_delta"
+                && e.data["delta"]["text"]
+                    .as_str()
+                    .is_some_and(|t| t.contains("TOOL_CALL_INCOMPLETE"))
+        }));
+
+        let message_delta = all_events
+            .iter()
+            .find(|e| e.event == "message_delta")
+            .expect("should have message_delta event");
+        assert_eq!(message_delta.data["delta"]["stop_reason"], "max_tokens");
+    }
+
+    #[test]
+    fn test_complete_tool_use_forwarded_after_stop() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let _initial_events = ctx.generate_initial_events();
+
+        let first = ctx.process_tool_use(&crate::kiro::model::events::ToolUseEvent {
+            name: "Bash".to_string(),
+            tool_use_id: "tool_1".to_string(),
+            input: r#"{"command": "#.to_string(),
+            stop: false,
+        });
+        assert!(first.iter().all(|e| e.data["content_block"]["type"] != "tool_use"));
+
+        let second = ctx.process_tool_use(&crate::kiro::model::events::ToolUseEvent {
+            name: "Bash".to_string(),
+            tool_use_id: "tool_1".to_string(),
+            input: r#""ls"}"#.to_string(),
+            stop: true,
+        });
+        assert!(second.iter().any(|e| e.data["content_block"]["type"] == "tool_use"));
+        assert!(second.iter().any(|e| {
+            e.data["delta"]["type"] == "input_json_delta"
+                && e.data["delta"]["partial_json"] == r#"{"command": "ls"}"#
+        }));
+        assert_eq!(ctx.state_manager.get_stop_reason(), "tool_use");
+    }
+
+    #[test]
+    fn test_dropped_stream_charges_usage_so_far() {
+        let keyring = std::sync::Arc::new(crate::anthropic::keyring::ApiKeyring::default());
+        let settings = serde_json::from_value(json!({"name": "alice"})).unwrap();
+        keyring
+            .create(settings, Some("sk-alice".to_string()))
+            .unwrap();
+        let client_key = keyring.authenticate("sk-alice").unwrap().unwrap();
+
+        let mut ctx = StreamContext::new_with_thinking("test-model", 10, false)
+            .with_client_key(Some(client_key));
+        let _initial_events = ctx.generate_initial_events();
+        let _events = ctx.process_assistant_response("Partial answer before the client left");
+        let output_tokens = ctx.output_tokens;
+        assert!(output_tokens > 0);
+        assert_eq!(keyring.list()[0].usage.output_tokens, 0);
+
+        // Client disconnects before message_stop
+        drop(ctx);
+
+        let usage = &keyring.list()[0].usage;
+        assert_eq!(usage.input_tokens, 10);
+        assert_eq!(usage.output_tokens, output_tokens as u64);
+    }
+
+    #[test]
+    fn test_claude_code_edit_and_glob_forwarded_unchanged() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let mut all_events = ctx.generate_initial_events();
+
+        let inputs = [
+            (
+                "Edit",
+                r#"{"file_path":"/repo/src/main.rs","old_string":"let a = 1;","new_string":"let a = 2;"}"#,
+            ),
+            ("Glob", r#"{"pattern":"**/*.rs","path":"/repo"}"#),
+        ];
+        for (i, (name, input)) in inputs.iter().enumerate() {
+            let tool_use = crate::kiro::model::events::ToolUseEvent {
+                name: name.to_string(),
+                tool_use_id: format!("tool_{}", i),
+                input: input.to_string(),
+                stop: true,
+            };
+            all_events.extend(ctx.process_tool_use(&tool_use));
+        }
+        all_events.extend(ctx.generate_final_events());
+
+        assert_eq!(ctx.truncated_tool_calls, 0);
+        assert_eq!(ctx.called_tools, ["Edit", "Glob"]);
+        let partial_json: Vec<_> = all_events
+            .iter()
+            .filter(|e| e.data["delta"]["type"] == "input_json_delta")
+            .map(|e| e.data["delta"]["partial_json"].as_str().unwrap())
+            .collect();
+        assert_eq!(partial_json, inputs.map(|(_, input)| input));
+        assert!(collect_text_content(&all_events).is_empty());
+        assert_eq!(ctx.state_manager.get_stop_reason(), "tool_use");
+    }
+
+    #[test]
+    fn test_stop_sequence_across_deltas_ends_message() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false)
+            .with_stop_sequences(vec!["\nUser:".to_string()]);
+        let mut all_events = ctx.generate_initial_events();
+        all_events.extend(ctx.process_assistant_response("Answer.\nUs"));
+        assert!(!ctx.stop_sequence_matched());
+        all_events.extend(ctx.process_assistant_response("er: next turn"));
+        assert!(ctx.stop_sequence_matched());
+        // Events after the match are ignored
+        all_events.extend(ctx.process_assistant_response("more"));
+        all_events.extend(ctx.generate_final_events());
+
+        assert_eq!(collect_text_content(&all_events), "Answer.");
+        let message_delta = all_events
+            .iter()
+            .find(|e| e.event == "message_delta")
+            .expect("should have message_delta event");
+        assert_eq!(message_delta.data["delta"]["stop_reason"], "stop_sequence");
+        assert_eq!(message_delta.data["delta"]["stop_sequence"], "\nUser:");
+    }
+
+    #[test]
+    fn test_stop_sequence_held_text_flushed_before_tool_use() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false)
+            .with_stop_sequences(vec!["STOP".to_string()]);
+        let _initial_events = ctx.generate_initial_events();
+
+        let mut all_events = ctx.process_assistant_response("Calling ST");
+        all_events.extend(ctx.process_tool_use(&crate::kiro::model::events::ToolUseEvent {
+            name: "test_tool".to_string(),
+            tool_use_id: "tool_1".to_string(),
+            input: "{}".to_string(),
+            stop: true,
+        }));
+        all_events.extend(ctx.generate_final_events());
+
+        let tool_start = all_events
+            .iter()
+            .position(|e| {
+                e.event == "content_block_start" && e.data["content_block"]["type"] == "tool_use"
+            })
+            .expect("should have tool_use block");
+        assert_eq!(collect_text_content(&all_events[..tool_start]), "Calling ST");
+        let message_delta = all_events
+            .iter()
+            .find(|e| e.event == "message_delta")
+            .expect("should have message_delta event");
+        assert_eq!(message_delta.data["delta"]["stop_reason"], "tool_use");
+        assert!(message_delta.data["delta"]["stop_sequence"].is_null());
+    }
+
+    #[test]
+    fn test_upstream_exception_closes_blocks_and_emits_error() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let mut all_events = ctx.generate_initial_events();
+        all_events.extend(ctx.process_assistant_response("partial"));
+        all_events.extend(ctx.process_kiro_event(&Event::Exception {
+            exception_type: "ThrottlingException".to_string(),
+            message: "Too many requests".to_string(),
+        }));
+        assert!(ctx.is_finished());
+        // Later events and final events are ignored
+        all_events.extend(ctx.process_kiro_event(&Event::AssistantResponse(
+            serde_json::from_value(json!({ "content": "more" })).unwrap(),
+        )));
+        all_events.extend(ctx.generate_final_events());
+
+        assert_eq!(collect_text_content(&all_events), "partial");
+        let names: Vec<_> = all_events.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(
+            &names[names.len() - 2..],
+            ["content_block_stop", "error"]
+        );
+        assert!(!names.contains(&"message_delta"));
+        assert!(!names.contains(&"message_stop"));
+        let error = &all_events.last().unwrap().data;
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["error"]["type"], "rate_limit_error");
+        assert_eq!(
+            error["error"]["message"],
+            "ThrottlingException: Too many requests"
+        );
+    }
+
+    #[test]
+    fn test_content_length_exception_still_ends_with_max_tokens() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let _initial_events = ctx.generate_initial_events();
+        let events = ctx.process_kiro_event(&Event::Exception {
+            exception_type: "ContentLengthExceededException".to_string(),
+            message: "Output too long".to_string(),
+        });
+        assert!(events.is_empty());
+        assert!(!ctx.is_finished());
+
+        let final_events = ctx.generate_final_events();
+        assert!(!final_events.iter().any(|e| e.event == "error"));
+        let message_delta = final_events
+            .iter()
+            .find(|e| e.event == "message_delta")
+            .expect("should have message_delta event");
+        assert_eq!(message_delta.data["delta"]["stop_reason"], "max_tokens");
+    }
+
+    #[test]
+    fn test_upstream_error_type_mapping() {
+        assert_eq!(upstream_error_type("ThrottlingException", ""), "rate_limit_error");
+        assert_eq!(
+            upstream_error_type("ServiceUnavailableException", ""),
+            "overloaded_error"
+        );
+        assert_eq!(
+            upstream_error_type("InternalServerException", "Model is at capacity"),
+            "overloaded_error"
+        );
+        assert_eq!(
+            upstream_error_type("ValidationException", ""),
+            "invalid_request_error"
+        );
+        assert_eq!(
+            upstream_error_type("AccessDeniedException", ""),
+            "permission_error"
+        );
+        assert_eq!(upstream_error_type("InternalServerException", ""), "api_error");
+    }
+
+    #[test]
+    fn test_buffered_fail_ends_with_error_event() {
+        let mut ctx = BufferedStreamContext::new("test-model", 1, false);
+        ctx.process_and_buffer(&Event::AssistantResponse(
+            serde_json::from_value(json!({ "content": "partial" })).unwrap(),
+        ));
+        ctx.fail("api_error", "Upstream stream interrupted: connection reset");
+        assert!(ctx.is_finished());
+
+        let events = ctx.finish_and_get_all_events();
+        assert_eq!(events.first().unwrap().event, "message_start");
+        assert_eq!(events.last().unwrap().event, "error");
+        assert!(!events.iter().any(|e| e.event == "message_stop"));
+    }
 }
@@ -0,0 +1,598 @@
+//! Thinking block extraction module
+//!
+//! Kiro returns thinking content inline as `<thinking>...</thinking>` markup inside
+//! assistant text. This module splits that text into thinking and text segments,
+//! shared by the streaming (`StreamContext`) and non-streaming response paths.
+
+/// Quote characters to skip
+///
+/// When thinking tags are wrapped by these characters, they are considered as quoting the tag rather than actual tags:
+/// - Backtick (`): inline code
+/// - Double quote ("): string
+/// - Single quote ('): string
+const QUOTE_CHARS: &[u8] = &[
+    b'`', b'"', b'\'', b'\\', b'#', b'!', b'@', b'$', b'%', b'^', b'&', b'*', b'(', b')', b'-',
+    b'_', b'=', b'+', b'[', b']', b'{', b'}', b';', b':', b'<', b'>', b',', b'.', b'?', b'/',
+];
+
+/// Check if the character at the specified position is a quote character
+fn is_quote_char(buffer: &str, pos: usize) -> bool {
+    buffer
+        .as_bytes()
+        .get(pos)
+        .map(|c| QUOTE_CHARS.contains(c))
+        .unwrap_or(false)
+}
+
+/// Find the real thinking end tag (not wrapped by quote characters, and followed by double newline)
+///
+/// When the model mentions `</thinking>` during thinking, it's usually wrapped by backticks, quotes, etc.,
+/// or has other content on the same line (like "about </thinking> tag").
+/// This function skips these cases and only returns the position of the real end tag.
+///
+/// Skipped cases:
+/// - Wrapped by quote characters (backticks, quotes, etc.)
+/// - Not followed by double newline (real end tag is followed by `\n\n`)
+/// - Tag at buffer end (need to wait for more content during streaming)
+///
+/// # Arguments
+/// - `buffer`: The string to search
+///
+/// # Returns
+/// - `Some(pos)`: Starting position of the real end tag
+/// - `None`: No real end tag found
+fn find_real_thinking_end_tag(buffer: &str) -> Option<usize> {
+    const TAG: &str = "</thinking>";
+    let mut search_start = 0;
+
+    while let Some(pos) = buffer[search_start..].find(TAG) {
+        let absolute_pos = search_start + pos;
+
+        // Check if preceded by quote character
+        let has_quote_before = absolute_pos > 0 && is_quote_char(buffer, absolute_pos - 1);
+
+        // Check if followed by quote character
+        let after_pos = absolute_pos + TAG.len();
+        let has_quote_after = is_quote_char(buffer, after_pos);
+
+        // If wrapped by quote characters, skip
+        if has_quote_before || has_quote_after {
+            search_start = absolute_pos + 1;
+            continue;
+        }
+
+        // Check content after the tag
+        let after_content = &buffer[after_pos..];
+
+        // If content after tag is insufficient to determine if there's double newline, wait for more content
+        if after_content.len() < 2 {
+            return None;
+        }
+
+        // Real thinking end tag is followed by double newline `\n\n`
+        if after_content.starts_with("\n\n") {
+            return Some(absolute_pos);
+        }
+
+        // Not double newline, skip and continue searching
+        search_start = absolute_pos + 1;
+    }
+
+    None
+}
+
+/// Find thinking end tag at buffer end (allowing only whitespace after it)
+///
+/// Used for "boundary event" scenarios: e.g., when thinking ends and immediately enters tool_use, or stream ends,
+/// where `</thinking>` may not be followed by `\n\n`, but the end tag should still be recognized and filtered.
+///
+/// Constraint: Only consider it as end tag when everything after `</thinking>` is whitespace,
+/// to avoid false positives when `</thinking>` is mentioned (not as end tag) within thinking content.
+fn find_real_thinking_end_tag_at_buffer_end(buffer: &str) -> Option<usize> {
+    const TAG: &str = "</thinking>";
+    let mut search_start = 0;
+
+    while let Some(pos) = buffer[search_start..].find(TAG) {
+        let absolute_pos = search_start + pos;
+
+        // Check if preceded by quote character
+        let has_quote_before = absolute_pos > 0 && is_quote_char(buffer, absolute_pos - 1);
+
+        // Check if followed by quote character
+        let after_pos = absolute_pos + TAG.len();
+        let has_quote_after = is_quote_char(buffer, after_pos);
+
+        if has_quote_before || has_quote_after {
+            search_start = absolute_pos + 1;
+            continue;
+        }
+
+        // Only consider it as end tag when everything after is whitespace
+        if buffer[after_pos..].trim().is_empty() {
+            return Some(absolute_pos);
+        }
+
+        search_start = absolute_pos + 1;
+    }
+
+    None
+}
+
+/// Find the real thinking start tag (not wrapped by quote characters)
+///
+/// Similar to `find_real_thinking_end_tag`, skips start tags wrapped by quote characters.
+fn find_real_thinking_start_tag(buffer: &str) -> Option<usize> {
+    const TAG: &str = "<thinking>";
+    let mut search_start = 0;
+
+    while let Some(pos) = buffer[search_start..].find(TAG) {
+        let absolute_pos = search_start + pos;
+
+        // Check if preceded by quote character
+        let has_quote_before = absolute_pos > 0 && is_quote_char(buffer, absolute_pos - 1);
+
+        // Check if followed by quote character
+        let after_pos = absolute_pos + TAG.len();
+        let has_quote_after = is_quote_char(buffer, after_pos);
+
+        // If not wrapped by quote characters, it's the real start tag
+        if !has_quote_before && !has_quote_after {
+            return Some(absolute_pos);
+        }
+
+        // Continue searching for next match
+        search_start = absolute_pos + 1;
+    }
+
+    None
+}
+
+/// Segment produced by `ThinkingParser`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThinkingChunk {
+    /// Regular text outside the thinking block
+    Text(String),
+    /// Thinking block started
+    ThinkingStart,
+    /// Thinking content
+    Thinking(String),
+    /// Thinking block ended
+    ThinkingEnd,
+}
+
+/// Incremental `<thinking>` tag parser
+///
+/// Content is fed chunk by chunk; text that might still be part of a tag is held back
+/// until more content arrives or the response ends. Only the first thinking block is
+/// extracted, everything after it is regular text.
+#[derive(Debug, Default)]
+pub struct ThinkingParser {
+    /// Content not yet emitted
+    buffer: String,
+    /// Whether inside thinking block
+    in_thinking_block: bool,
+    /// Whether thinking block extraction is complete
+    extracted: bool,
+    /// Whether to strip leading newline from thinking content
+    /// When model outputs `<thinking>\n`, `\n` may be in the same chunk or next chunk as the tag
+    strip_leading_newline: bool,
+}
+
+impl ThinkingParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed assistant content
+    pub fn push(&mut self, content: &str) -> Vec<ThinkingChunk> {
+        let mut chunks = Vec::new();
+
+        // Add content to buffer for processing
+        self.buffer.push_str(content);
+
+        loop {
+            if !self.in_thinking_block && !self.extracted {
+                // Find <thinking> start tag (skip those wrapped by backticks)
+                if let Some(start_pos) = find_real_thinking_start_tag(&self.buffer) {
+                    // Send content before <thinking> as text
+                    // Note: If preceding content is only whitespace (like \n\n returned by adaptive mode), skip it,
+                    // to avoid creating meaningless text block before thinking block causing client parsing failure
+                    let before_thinking = &self.buffer[..start_pos];
+                    if !before_thinking.trim().is_empty() {
+                        chunks.push(ThinkingChunk::Text(before_thinking.to_string()));
+                    }
+
+                    // Enter thinking block
+                    self.in_thinking_block = true;
+                    self.strip_leading_newline = true;
+                    self.buffer = self.buffer[start_pos + "<thinking>".len()..].to_string();
+                    chunks.push(ThinkingChunk::ThinkingStart);
+                } else {
+                    // No <thinking> found, check if it might be partial tag
+                    // Keep content that might be partial tag
+                    let target_len = self.buffer.len().saturating_sub("<thinking>".len());
+                    let safe_len = self.buffer.floor_char_boundary(target_len);
+                    if safe_len > 0 {
+                        let safe_content = &self.buffer[..safe_len];
+                        // If thinking hasn't been extracted yet, and safe content is only whitespace,
+                        // don't send as text, keep in buffer waiting for more content.
+                        // This avoids the issue in 4.6 model where <thinking> tag splits across events,
+                        // causing leading whitespace (like "\n\n") to be incorrectly created as text block,
+                        // resulting in text block appearing before thinking block.
+                        if !safe_content.trim().is_empty() {
+                            chunks.push(ThinkingChunk::Text(safe_content.to_string()));
+                            self.buffer = self.buffer[safe_len..].to_string();
+                        }
+                    }
+                    break;
+                }
+            } else if self.in_thinking_block {
+                // Strip newline immediately following <thinking> tag (may span chunks)
+                if self.strip_leading_newline {
+                    if self.buffer.starts_with('\n') {
+                        self.buffer = self.buffer[1..].to_string();
+                        self.strip_leading_newline = false;
+                    } else if !self.buffer.is_empty() {
+                        // buffer is not empty but doesn't start with \n, no longer need to strip
+                        self.strip_leading_newline = false;
+                    }
+                    // Keep flag when buffer is empty, wait for next chunk
+                }
+
+                // Inside thinking block, find </thinking> end tag (skip those wrapped by backticks)
+                if let Some(end_pos) = find_real_thinking_end_tag(&self.buffer) {
+                    let thinking_content = &self.buffer[..end_pos];
+                    if !thinking_content.is_empty() {
+                        chunks.push(ThinkingChunk::Thinking(thinking_content.to_string()));
+                    }
+
+                    // End thinking block
+                    self.in_thinking_block = false;
+                    self.extracted = true;
+                    chunks.push(ThinkingChunk::ThinkingEnd);
+
+                    // Strip `</thinking>\n\n` (find_real_thinking_end_tag already confirmed \n\n exists)
+                    self.buffer = self.buffer[end_pos + "</thinking>\n\n".len()..].to_string();
+                } else {
+                    // No end tag found, send current buffer content as thinking.
+                    // Keep tail content that might be partial `</thinking>\n\n`:
+                    // find_real_thinking_end_tag requires `\n\n` after tag to return Some,
+                    // so reserved area must cover full length of `</thinking>\n\n` (13 bytes),
+                    // otherwise when `</thinking>` is in buffer but `\n\n` hasn't arrived,
+                    // first few characters of tag would be incorrectly sent as thinking.
+                    let target_len = self.buffer.len().saturating_sub("</thinking>\n\n".len());
+                    let safe_len = self.buffer.floor_char_boundary(target_len);
+                    if safe_len > 0 {
+                        chunks.push(ThinkingChunk::Thinking(self.buffer[..safe_len].to_string()));
+                        self.buffer = self.buffer[safe_len..].to_string();
+                    }
+                    break;
+                }
+            } else {
+                // thinking extraction complete, remaining content as text
+                if !self.buffer.is_empty() {
+                    chunks.push(ThinkingChunk::Text(std::mem::take(&mut self.buffer)));
+                }
+                break;
+            }
+        }
+
+        chunks
+    }
+
+    /// Flush held-back content before a tool_use block starts
+    ///
+    /// When `</thinking>` is not followed by `\n\n` (e.g., immediately followed by tool_use),
+    /// the end tag remains in the buffer; recognize it here as a boundary end tag.
+    /// Text held back to detect a split `<thinking>` tag is released as regular text,
+    /// so it is not swallowed by the tool_use block.
+    pub fn flush_before_tool_use(&mut self) -> Vec<ThinkingChunk> {
+        let mut chunks = Vec::new();
+
+        if self.in_thinking_block {
+            if let Some(end_pos) = find_real_thinking_end_tag_at_buffer_end(&self.buffer) {
+                chunks.extend(self.close_at_end_tag(end_pos));
+            }
+        } else if !self.extracted && !self.buffer.is_empty() {
+            chunks.push(ThinkingChunk::Text(std::mem::take(&mut self.buffer)));
+        }
+
+        chunks
+    }
+
+    /// Flush remaining content at response end
+    pub fn finish(&mut self) -> Vec<ThinkingChunk> {
+        let mut chunks = Vec::new();
+        if self.buffer.is_empty() {
+            return chunks;
+        }
+
+        if self.in_thinking_block {
+            // End may have residual `</thinking>` (e.g., stream ends right after it), filter out end tag during flush
+            if let Some(end_pos) = find_real_thinking_end_tag_at_buffer_end(&self.buffer) {
+                chunks.extend(self.close_at_end_tag(end_pos));
+            } else {
+                // Still inside thinking block, remaining content is thinking
+                chunks.push(ThinkingChunk::Thinking(std::mem::take(&mut self.buffer)));
+                chunks.push(ThinkingChunk::ThinkingEnd);
+                self.in_thinking_block = false;
+                self.extracted = true;
+            }
+        } else {
+            chunks.push(ThinkingChunk::Text(std::mem::take(&mut self.buffer)));
+        }
+
+        chunks
+    }
+
+    /// Close the thinking block at a boundary end tag found by `find_real_thinking_end_tag_at_buffer_end`
+    fn close_at_end_tag(&mut self, end_pos: usize) -> Vec<ThinkingChunk> {
+        let mut chunks = Vec::new();
+
+        let thinking_content = &self.buffer[..end_pos];
+        if !thinking_content.is_empty() {
+            chunks.push(ThinkingChunk::Thinking(thinking_content.to_string()));
+        }
+        chunks.push(ThinkingChunk::ThinkingEnd);
+
+        // Treat content after end tag as regular text (usually empty or whitespace)
+        let remaining = self.buffer[end_pos + "</thinking>".len()..]
+            .trim_start()
+            .to_string();
+        self.buffer.clear();
+        self.in_thinking_block = false;
+        self.extracted = true;
+        if !remaining.is_empty() {
+            chunks.push(ThinkingChunk::Text(remaining));
+        }
+
+        chunks
+    }
+}
+
+/// How extracted thinking content is returned in non-streaming responses (`thinkingFormat`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThinkingFormat {
+    /// `thinking` content block (Anthropic format)
+    Thinking,
+    /// Text block wrapped in `<think>...</think>` tags
+    Think,
+    /// Separate `reasoning_content` field (OpenAI/DeepSeek compatible)
+    ReasoningContent,
+}
+
+impl ThinkingFormat {
+    /// Parse the `thinkingFormat` config value, unknown values fall back to `thinking`
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "think" => Self::Think,
+            "reasoning_content" => Self::ReasoningContent,
+            "thinking" => Self::Thinking,
+            other => {
+                tracing::warn!("Unknown thinkingFormat '{}', using 'thinking'", other);
+                Self::Thinking
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_real_thinking_start_tag_basic() {
+        // Basic case: normal start tag
+        assert_eq!(find_real_thinking_start_tag("<thinking>"), Some(0));
+        assert_eq!(find_real_thinking_start_tag("prefix<thinking>"), Some(6));
+    }
+
+    #[test]
+    fn test_find_real_thinking_start_tag_with_backticks() {
+        // Wrapped by backticks should be skipped
+        assert_eq!(find_real_thinking_start_tag("`<thinking>`"), None);
+        assert_eq!(find_real_thinking_start_tag("use `<thinking>` tag"), None);
+
+        // First has wrapped one, then has real start tag
+        assert_eq!(
+            find_real_thinking_start_tag("about `<thinking>` tag<thinking>content"),
+            Some(22)
+        );
+    }
+
+    #[test]
+    fn test_find_real_thinking_start_tag_with_quotes() {
+        // Wrapped by double quotes should be skipped
+        assert_eq!(find_real_thinking_start_tag("\"<thinking>\""), None);
+        assert_eq!(find_real_thinking_start_tag("the \"<thinking>\" tag"), None);
+
+        // Wrapped by single quotes should be skipped
+        assert_eq!(find_real_thinking_start_tag("'<thinking>'"), None);
+
+        // Mixed case
+        assert_eq!(
+            find_real_thinking_start_tag("about \"<thinking>\" and '<thinking>' then<thinking>"),
+            Some(40)
+        );
+    }
+
+    #[test]
+    fn test_find_real_thinking_end_tag_basic() {
+        // Basic case: normal end tag followed by double newline
+        assert_eq!(find_real_thinking_end_tag("</thinking>\n\n"), Some(0));
+        assert_eq!(
+            find_real_thinking_end_tag("content</thinking>\n\n"),
+            Some(7)
+        );
+        assert_eq!(
+            find_real_thinking_end_tag("some text</thinking>\n\nmore text"),
+            Some(9)
+        );
+
+        // Case without double newline
+        assert_eq!(find_real_thinking_end_tag("</thinking>"), None);
+        assert_eq!(find_real_thinking_end_tag("</thinking>\n"), None);
+        assert_eq!(find_real_thinking_end_tag("</thinking> more"), None);
+    }
+
+    #[test]
+    fn test_find_real_thinking_end_tag_with_backticks() {
+        // Wrapped by backticks should be skipped
+        assert_eq!(find_real_thinking_end_tag("`</thinking>`\n\n"), None);
+        assert_eq!(
+            find_real_thinking_end_tag("mention `</thinking>` in code\n\n"),
+            None
+        );
+
+        // Only has backtick before
+        assert_eq!(find_real_thinking_end_tag("`</thinking>\n\n"), None);
+
+        // Only has backtick after
+        assert_eq!(find_real_thinking_end_tag("</thinking>`\n\n"), None);
+    }
+
+    #[test]
+    fn test_find_real_thinking_end_tag_with_quotes() {
+        // Wrapped by double quotes should be skipped
+        assert_eq!(find_real_thinking_end_tag("\"</thinking>\"\n\n"), None);
+        assert_eq!(
+            find_real_thinking_end_tag("the string \"</thinking>\" is a tag\n\n"),
+            None
+        );
+
+        // Wrapped by single quotes should be skipped
+        assert_eq!(find_real_thinking_end_tag("'</thinking>'\n\n"), None);
+        assert_eq!(
+            find_real_thinking_end_tag("use '</thinking>' as marker\n\n"),
+            None
+        );
+
+        // Mixed case: double quote wrapped then has real tag
+        assert_eq!(
+            find_real_thinking_end_tag("about \"</thinking>\" tag</thinking>\n\n"),
+            Some(23)
+        );
+
+        // Mixed case: single quote wrapped then has real tag
+        assert_eq!(
+            find_real_thinking_end_tag("about '</thinking>' tag</thinking>\n\n"),
+            Some(23)
+        );
+    }
+
+    #[test]
+    fn test_find_real_thinking_end_tag_mixed() {
+        // First has wrapped one, then has real end tag
+        assert_eq!(
+            find_real_thinking_end_tag("discussing `</thinking>` tag</thinking>\n\n"),
+            Some(28)
+        );
+
+        // Multiple wrapped ones, last one is real
+        assert_eq!(
+            find_real_thinking_end_tag("`</thinking>` and `</thinking>` done</thinking>\n\n"),
+            Some(36)
+        );
+
+        // Multiple quote character types mixed
+        assert_eq!(
+            find_real_thinking_end_tag(
+                "`</thinking>` and \"</thinking>\" and '</thinking>' done</thinking>\n\n"
+            ),
+            Some(54)
+        );
+    }
+
+    /// Feed chunks and finish, concatenating (thinking, text)
+    fn parse_all(parts: &[&str]) -> (String, String) {
+        let mut parser = ThinkingParser::new();
+        let mut chunks = Vec::new();
+        for part in parts {
+            chunks.extend(parser.push(part));
+        }
+        chunks.extend(parser.finish());
+
+        let mut thinking = String::new();
+        let mut text = String::new();
+        for chunk in chunks {
+            match chunk {
+                ThinkingChunk::Thinking(s) => thinking.push_str(&s),
+                ThinkingChunk::Text(s) => text.push_str(&s),
+                _ => {}
+            }
+        }
+        (thinking, text)
+    }
+
+    #[test]
+    fn test_parser_splits_thinking_and_text() {
+        let (thinking, text) = parse_all(&["<thinking>\nplan</thinking>\n\nanswer"]);
+        assert_eq!(thinking, "plan");
+        assert_eq!(text, "answer");
+    }
+
+    #[test]
+    fn test_parser_split_across_chunks_matches_single_chunk() {
+        let (thinking, text) = parse_all(&[
+            "\n\n<thi",
+            "nking>",
+            "\nst",
+            "ep</thinking>",
+            "\n",
+            "\nfinal",
+        ]);
+        assert_eq!(thinking, "step");
+        assert_eq!(text, "final");
+    }
+
+    #[test]
+    fn test_parser_without_thinking_tag_is_text() {
+        let (thinking, text) = parse_all(&["plain ", "answer"]);
+        assert_eq!(thinking, "");
+        assert_eq!(text, "plain answer");
+    }
+
+    #[test]
+    fn test_parser_finish_closes_unterminated_thinking() {
+        let mut parser = ThinkingParser::new();
+        parser.push("<thinking>cut off");
+        let chunks = parser.finish();
+        assert_eq!(chunks.last(), Some(&ThinkingChunk::ThinkingEnd));
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn test_parser_flush_before_tool_use_filters_end_tag() {
+        let mut parser = ThinkingParser::new();
+        let mut chunks = parser.push("<thinking>abc</thinking>");
+        chunks.extend(parser.flush_before_tool_use());
+        assert_eq!(
+            chunks
+                .iter()
+                .filter_map(|c| match c {
+                    ThinkingChunk::Thinking(s) => Some(s.as_str()),
+                    _ => None,
+                })
+                .collect::<String>(),
+            "abc"
+        );
+        assert_eq!(chunks.last(), Some(&ThinkingChunk::ThinkingEnd));
+    }
+
+    #[test]
+    fn test_thinking_format_from_config() {
+        assert_eq!(
+            ThinkingFormat::from_config("thinking"),
+            ThinkingFormat::Thinking
+        );
+        assert_eq!(ThinkingFormat::from_config("think"), ThinkingFormat::Think);
+        assert_eq!(
+            ThinkingFormat::from_config("reasoning_content"),
+            ThinkingFormat::ReasoningContent
+        );
+        assert_eq!(
+            ThinkingFormat::from_config("other"),
+            ThinkingFormat::Thinking
+        );
+    }
+}
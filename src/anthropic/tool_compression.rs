@@ -6,10 +6,51 @@
 //! 1. Simplify input_schema (keep only type/enum/required)
 //! 2. Proportionally compress description (minimum 50 characters)
 
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use serde::Serialize;
+
 use crate::kiro::model::requests::tool::{InputSchema, Tool, ToolSpecification};
 
-/// Tool compression target size (20KB)
-const TOOL_COMPRESSION_TARGET_SIZE: usize = 20 * 1024;
+/// Default tool compression target size (20KB)
+pub const TOOL_COMPRESSION_TARGET_SIZE: usize = 20 * 1024;
+
+/// Configured compression threshold in bytes (0 = disabled)
+static COMPRESSION_THRESHOLD: AtomicUsize = AtomicUsize::new(TOOL_COMPRESSION_TARGET_SIZE);
+
+/// Number of requests whose tools were compressed
+static COMPRESSED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Total bytes removed by compression
+static BYTES_SAVED: AtomicU64 = AtomicU64::new(0);
+
+/// Initialize compression threshold
+///
+/// Should be called at application startup with `Config::tool_compression_threshold_bytes`
+pub fn init_config(threshold_bytes: usize) {
+    COMPRESSION_THRESHOLD.store(threshold_bytes, Ordering::Relaxed);
+}
+
+/// Tool compression statistics
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCompressionStats {
+    /// Configured threshold in bytes (0 = disabled)
+    pub threshold_bytes: usize,
+    /// Number of requests whose tools were compressed
+    pub compressed_requests: u64,
+    /// Total bytes removed by compression
+    pub bytes_saved: u64,
+}
+
+/// Get tool compression statistics since startup
+pub fn stats() -> ToolCompressionStats {
+    ToolCompressionStats {
+        threshold_bytes: COMPRESSION_THRESHOLD.load(Ordering::Relaxed),
+        compressed_requests: COMPRESSED_REQUESTS.load(Ordering::Relaxed),
+        bytes_saved: BYTES_SAVED.load(Ordering::Relaxed),
+    }
+}
 
 /// Minimum description length after compression
 const MIN_TOOL_DESCRIPTION_LENGTH: usize = 50;
@@ -100,28 +141,44 @@ fn compress_description(description: &str, target_length: usize) -> String {
     format!("{}...", &description[..safe_len])
 }
 
-/// Compress tools if total size exceeds threshold
+/// Compress tools if total size exceeds the configured threshold
 ///
-/// Returns compressed tool list (or clone of original if no compression needed)
-pub fn compress_tools_if_needed(tools: &[Tool]) -> Vec<Tool> {
-    if tools.is_empty() {
-        return tools.to_vec();
+/// Returns compressed tool list (or original if no compression needed)
+pub fn compress_tools_if_needed(tools: Vec<Tool>) -> Vec<Tool> {
+    let threshold = COMPRESSION_THRESHOLD.load(Ordering::Relaxed);
+    if threshold == 0 || tools.is_empty() {
+        return tools;
     }
 
-    let original_size = calculate_tools_size(tools);
-    if original_size <= TOOL_COMPRESSION_TARGET_SIZE {
+    let original_size = calculate_tools_size(&tools);
+    if original_size <= threshold {
         tracing::debug!(
             "Tool size {} bytes within target {} bytes, no compression needed",
             original_size,
-            TOOL_COMPRESSION_TARGET_SIZE
+            threshold
         );
-        return tools.to_vec();
+        return tools;
     }
 
+    let compressed = compress_tools(&tools, original_size, threshold);
+
+    let final_size = calculate_tools_size(&compressed);
+    COMPRESSED_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    BYTES_SAVED.fetch_add(
+        original_size.saturating_sub(final_size) as u64,
+        Ordering::Relaxed,
+    );
+
+    compressed
+}
+
+/// Compress tool list toward target size
+fn compress_tools(tools: &[Tool], original_size: usize, target_size: usize) -> Vec<Tool> {
     tracing::info!(
-        "Tool size {} bytes exceeds target {} bytes, starting compression",
+        "Tool size {} bytes exceeds target {} bytes, starting compression ({} tools)",
         original_size,
-        TOOL_COMPRESSION_TARGET_SIZE
+        target_size,
+        tools.len()
     );
 
     // Step 1: Simplify input_schema
@@ -148,7 +205,7 @@ pub fn compress_tools_if_needed(tools: &[Tool]) -> Vec<Tool> {
         original_size - size_after_schema
     );
 
-    if size_after_schema <= TOOL_COMPRESSION_TARGET_SIZE {
+    if size_after_schema <= target_size {
         tracing::info!(
             "Schema simplification achieved target, final size: {} bytes",
             size_after_schema
@@ -157,7 +214,7 @@ pub fn compress_tools_if_needed(tools: &[Tool]) -> Vec<Tool> {
     }
 
     // Step 2: Proportionally compress descriptions
-    let size_to_reduce = size_after_schema - TOOL_COMPRESSION_TARGET_SIZE;
+    let size_to_reduce = size_after_schema - target_size;
     let total_desc_len: usize = compressed
         .iter()
         .map(|t| t.tool_specification.description.len())
@@ -213,6 +270,36 @@ mod tests {
         assert!(compressed.ends_with("...") || compressed.len() <= desc.len());
     }
 
+    fn large_tool(name: &str) -> Tool {
+        Tool {
+            tool_specification: ToolSpecification {
+                name: name.to_string(),
+                description: "x".repeat(4000),
+                input_schema: InputSchema {
+                    json: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {"type": "string", "description": "y".repeat(1000)}
+                        }
+                    }),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_compress_tools_reaches_target() {
+        let tools: Vec<Tool> = (0..10).map(|i| large_tool(&format!("tool_{}", i))).collect();
+        let original_size = calculate_tools_size(&tools);
+        let target = 20 * 1024;
+        assert!(original_size > target);
+
+        let compressed = compress_tools(&tools, original_size, target);
+        assert_eq!(compressed.len(), tools.len());
+        assert!(calculate_tools_size(&compressed) <= target + tools.len() * 3);
+        assert_eq!(compressed[3].tool_specification.name, "tool_3");
+    }
+
     #[test]
     fn test_simplify_input_schema() {
         let schema = serde_json::json!({
@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::model::catalog;
+
 // === Error Response ===
 
 /// API error response
@@ -72,20 +74,13 @@ pub struct ModelsResponse {
 /// Maximum thinking budget tokens
 const MAX_BUDGET_TOKENS: i32 = 128_000;
 
-/// Get context window size based on model name
+/// Get context window size based on model name (from the model catalog)
 pub fn get_context_window_size(model: &str) -> i32 {
-    let model_lower = model.to_lowercase();
-    // Opus 4.6 with -1m suffix gets 1M context
-    if model_lower.contains("opus") && model_lower.contains("-1m") {
-        1_000_000
-    } else {
-        // All other models get 200K
-        200_000
-    }
+    catalog::current().context_window(model)
 }
 
 /// Thinking configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Thinking {
     #[serde(rename = "type")]
     pub thinking_type: String,
@@ -115,7 +110,7 @@ where
 }
 
 /// OutputConfig configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct OutputConfig {
     #[serde(default = "default_effort")]
     pub effort: String,
@@ -126,30 +121,101 @@ fn default_effort() -> String {
 }
 
 /// Metadata in Claude Code request
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Metadata {
     /// User ID, format like: user_xxx_account__session_0b4445e1-f5be-49e1-87ce-62bbc28ad705
     pub user_id: Option<String>,
 }
 
 /// Messages request body
-#[derive(Debug, Deserialize)]
+///
+/// Serialized only for request capture bundles.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct MessagesRequest {
     pub model: String,
     pub max_tokens: i32,
     pub messages: Vec<Message>,
     #[serde(default)]
     pub stream: bool,
-    #[serde(default, deserialize_with = "deserialize_system")]
+    #[serde(
+        default,
+        deserialize_with = "deserialize_system",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub system: Option<Vec<SystemMessage>>,
     pub tools: Option<Vec<Tool>>,
     pub tool_choice: Option<serde_json::Value>,
+    /// Enforced by the proxy, Kiro API does not support stop sequences
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
     pub thinking: Option<Thinking>,
     pub output_config: Option<OutputConfig>,
     /// Metadata in Claude Code request, contains session information
     pub metadata: Option<Metadata>,
 }
 
+impl MessagesRequest {
+    /// Parsed tool_choice mode (defaults to auto)
+    pub fn tool_choice_mode(&self) -> ToolChoice {
+        ToolChoice::from_value(self.tool_choice.as_ref())
+    }
+}
+
+/// Tool choice mode
+///
+/// - `{"type": "auto"}`: model decides whether to call tools (default)
+/// - `{"type": "any"}`: model must call at least one tool
+/// - `{"type": "tool", "name": "X"}`: model must call tool X
+/// - `{"type": "none"}`: model must not call tools
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ToolChoice {
+    #[default]
+    Auto,
+    Any,
+    Tool(String),
+    None,
+}
+
+impl ToolChoice {
+    /// Parse tool_choice value, unknown or malformed values fall back to auto
+    pub fn from_value(value: Option<&serde_json::Value>) -> Self {
+        let Some(value) = value else {
+            return Self::Auto;
+        };
+        match value.get("type").and_then(|t| t.as_str()) {
+            Some("any") => Self::Any,
+            Some("none") => Self::None,
+            Some("tool") => match value.get("name").and_then(|n| n.as_str()) {
+                Some(name) => Self::Tool(name.to_string()),
+                None => Self::Auto,
+            },
+            _ => Self::Auto,
+        }
+    }
+
+    /// Check if the called tools satisfy this tool choice
+    ///
+    /// Only `any` and `tool` modes impose a requirement.
+    pub fn is_satisfied_by<'a>(&self, called_tools: impl IntoIterator<Item = &'a str>) -> bool {
+        match self {
+            Self::Any => called_tools.into_iter().next().is_some(),
+            Self::Tool(name) => called_tools.into_iter().any(|t| t == name),
+            Self::Auto | Self::None => true,
+        }
+    }
+
+    /// Error message reported when the model did not honor the forced tool choice
+    pub fn unsatisfied_message(&self) -> String {
+        match self {
+            Self::Tool(name) => format!(
+                "Model did not call the required tool '{}' (tool_choice type \"tool\")",
+                name
+            ),
+            _ => "Model did not call any tool (tool_choice type \"any\")".to_string(),
+        }
+    }
+}
+
 /// Deserialize system field, supports string or array format
 fn deserialize_system<'de, D>(deserializer: D) -> Result<Option<Vec<SystemMessage>>, D::Error>
 where
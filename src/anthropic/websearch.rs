@@ -539,6 +539,7 @@ mod tests {
                 max_uses: Some(8),
             }]),
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: None,
@@ -577,6 +578,7 @@ mod tests {
                 },
             ]),
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: None,
@@ -604,6 +606,7 @@ mod tests {
             system: None,
             tools: None,
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: None,
@@ -629,6 +632,7 @@ mod tests {
             system: None,
             tools: None,
             tool_choice: None,
+            stop_sequences: None,
             thinking: None,
             output_config: None,
             metadata: None,
@@ -0,0 +1,342 @@
+//! Command line subcommands
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+
+use crate::admin::BALANCE_CACHE_FILE_NAME;
+use crate::anthropic::capture;
+use crate::anthropic::keyring::ApiKeyring;
+use crate::anthropic::types::MessagesRequest;
+use crate::common::encryption::{self, FileCipher, MigrateOutcome};
+use crate::debug;
+use crate::kiro::credential_sources::{self, LoadedCredentials};
+use crate::kiro::model::events::Event;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::token_manager::{LOAD_BALANCING_MODES, MultiTokenManager, STATS_FILE_NAME};
+use crate::model::arg::DeviceAuthMethod;
+use crate::model::config::Config;
+use crate::oauth::SsoOidcClient;
+use crate::token;
+
+/// Chunk size for feeding raw files to the decoder
+const DECODE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Encrypt (or with `decrypt`, decrypt) credentials, keyring and cache files in place
+pub fn migrate_encryption(
+    cipher: Option<&FileCipher>,
+    decrypt: bool,
+    config: &Config,
+    config_path: &str,
+    credentials_path: &str,
+) -> anyhow::Result<()> {
+    let Some(cipher) = cipher else {
+        anyhow::bail!(
+            "No encryption key configured (set {} or encryptionKeyFile in config.json)",
+            encryption::KEY_ENV_VAR
+        );
+    };
+
+    let credentials_path = PathBuf::from(credentials_path);
+    let cache_dir = credentials_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let mut paths = vec![
+        credentials_path,
+        ApiKeyring::path_for_config(Path::new(config_path)),
+        cache_dir.join(STATS_FILE_NAME),
+        cache_dir.join(BALANCE_CACHE_FILE_NAME),
+    ];
+    if let Some(dir) = &config.credentials_dir
+        && !config.credentials_dir_read_only
+    {
+        paths.extend(credential_sources::credential_files(Path::new(dir))?);
+    }
+
+    let action = if decrypt { "decrypted" } else { "encrypted" };
+    for (path, outcome) in encryption::migrate_files(&paths, cipher, decrypt)? {
+        match outcome {
+            MigrateOutcome::Migrated => println!("{}: {}", path.display(), action),
+            MigrateOutcome::Unchanged => println!("{}: already {}", path.display(), action),
+            MigrateOutcome::Missing => println!("{}: not found, skipped", path.display()),
+        }
+    }
+    Ok(())
+}
+
+/// Replay capture bundles (files or directories of bundles) offline and print the differences
+///
+/// Token counting and tool compression use the settings in config.json, as the server does.
+pub fn replay(config: &Config, paths: &[String]) -> anyhow::Result<()> {
+    token::init_config(token::CountTokensConfig::from_config(config));
+    crate::anthropic::tool_compression::init_config(config.tool_compression_threshold_bytes);
+
+    let mut bundles = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            bundles.extend(capture::bundle_files(&path)?);
+        } else {
+            bundles.push(path);
+        }
+    }
+
+    let mut failed = 0;
+    for path in &bundles {
+        let differences = capture::replay(&capture::load_bundle(path)?)?;
+        if differences.is_empty() {
+            println!("{}: ok", path.display());
+            continue;
+        }
+        failed += 1;
+        println!("{}: {} differences", path.display(), differences.len());
+        for difference in differences {
+            println!("  {}", difference);
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} bundles differ", failed, bundles.len());
+    }
+    Ok(())
+}
+
+/// Token manager over the loaded credentials, writing back like the server does
+fn token_manager(
+    config: &Config,
+    loaded: &LoadedCredentials,
+    credentials_path: &str,
+) -> anyhow::Result<Arc<MultiTokenManager>> {
+    let manager = MultiTokenManager::new(
+        config.clone(),
+        loaded.credentials.clone(),
+        config.proxy_config(),
+        Some(credentials_path.into()),
+        loaded.is_multiple_format,
+    )?;
+    Ok(Arc::new(manager))
+}
+
+/// Validate config and credentials, refresh every token and print usage limits
+pub async fn check(
+    config: &Config,
+    config_path: &str,
+    loaded: &LoadedCredentials,
+    credentials_path: &str,
+) -> anyhow::Result<()> {
+    let mut problems = 0;
+
+    println!("Config: {}", config_path);
+    if config.api_key.is_none() {
+        println!("  apiKey is not set, the server will not start");
+        problems += 1;
+    }
+    if !LOAD_BALANCING_MODES.contains(&config.load_balancing_mode.as_str()) {
+        println!(
+            "  invalid loadBalancingMode: {}",
+            config.load_balancing_mode
+        );
+        problems += 1;
+    }
+
+    println!("Credentials: {} loaded", loaded.credentials.len());
+    if loaded.credentials.is_empty() {
+        problems += 1;
+    }
+    let manager = token_manager(config, loaded, credentials_path)?;
+    for entry in manager.snapshot().entries {
+        println!(
+            "#{} ({}, {}{})",
+            entry.id,
+            entry.source,
+            entry.auth_method.as_deref().unwrap_or("social"),
+            if entry.disabled { ", disabled" } else { "" }
+        );
+
+        if let Err(e) = manager.force_refresh_token(entry.id).await {
+            println!("  refresh: failed: {}", e);
+            problems += 1;
+            continue;
+        }
+        println!("  refresh: ok");
+
+        match manager.get_usage_limits_for(entry.id).await {
+            Ok(usage) => {
+                if let Some(email) = usage.email() {
+                    println!("  email: {}", email);
+                }
+                println!(
+                    "  subscription: {}",
+                    usage.subscription_title().unwrap_or("unknown")
+                );
+                println!(
+                    "  usage: {:.2} / {:.2} (remaining {:.2})",
+                    usage.current_usage(),
+                    usage.usage_limit(),
+                    usage.remaining()
+                );
+                if let Some(reset) = usage
+                    .next_reset_at()
+                    .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+                {
+                    println!("  next reset: {}", reset.to_rfc3339());
+                }
+            }
+            Err(e) => {
+                println!("  usage limits: failed: {}", e);
+                problems += 1;
+            }
+        }
+    }
+
+    if problems > 0 {
+        anyhow::bail!("{} problems found", problems);
+    }
+    Ok(())
+}
+
+/// Add a credential through the Builder ID or IdC device flow in the terminal
+pub async fn add_credential(
+    config: &Config,
+    loaded: &LoadedCredentials,
+    credentials_path: &str,
+    method: DeviceAuthMethod,
+    start_url: Option<&str>,
+    region: Option<&str>,
+) -> anyhow::Result<()> {
+    let start_url = match method {
+        DeviceAuthMethod::BuilderId => SsoOidcClient::builder_id_start_url(),
+        DeviceAuthMethod::Idc => start_url.context("--start-url is required for IdC")?,
+    };
+    let region = region.unwrap_or(SsoOidcClient::default_region());
+    let manager = token_manager(config, loaded, credentials_path)?;
+    let client = SsoOidcClient::new(config.proxy_config(), config.tls_backend);
+
+    let registration = client
+        .register_client(region)
+        .await
+        .context("Failed to register client")?;
+    let authorization = client
+        .start_device_authorization(
+            &registration.client_id,
+            &registration.client_secret,
+            start_url,
+            region,
+        )
+        .await
+        .context("Failed to start device authorization")?;
+
+    println!("Open this URL and confirm the code to sign in:");
+    println!();
+    println!("  {}", authorization.verification_uri_complete);
+    println!("  Code: {}", authorization.user_code);
+    println!();
+    println!(
+        "Waiting for authorization (expires in {} seconds)...",
+        authorization.expires_in
+    );
+
+    let token = client
+        .wait_for_token(
+            &registration.client_id,
+            &registration.client_secret,
+            &authorization.device_code,
+            region,
+            authorization.interval.unwrap_or(5),
+            authorization.expires_in,
+        )
+        .await?;
+    let credentials = client
+        .device_credentials(
+            token,
+            method.as_str(),
+            region,
+            &registration.client_id,
+            &registration.client_secret,
+        )
+        .await;
+
+    let id = manager.add_credential(credentials).await?;
+    println!("Added credential #{} to {}", id, credentials_path);
+    Ok(())
+}
+
+/// Print the events of an event-stream capture
+///
+/// Accepts a raw response body or a capture bundle (`.jsonl`, see `captureDir`).
+pub fn decode(path: &str) -> anyhow::Result<()> {
+    let path = Path::new(path);
+    let chunks = if path.extension().is_some_and(|ext| ext == "jsonl") {
+        capture::upstream_chunks(&capture::load_bundle(path)?)?
+    } else {
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        data.chunks(DECODE_CHUNK_SIZE).map(<[u8]>::to_vec).collect()
+    };
+    let data = chunks.concat();
+
+    let mut decoder = EventStreamDecoder::new();
+    for chunk in &chunks {
+        decoder.feed(chunk)?;
+        for result in decoder.decode_iter() {
+            let frame = match result {
+                Ok(frame) => frame,
+                Err(e) => {
+                    println!("\n[Decode error] {}", e);
+                    continue;
+                }
+            };
+            let event_type = frame.event_type().unwrap_or("-").to_string();
+            let payload = frame.payload.clone();
+            match Event::from_frame(frame) {
+                Ok(event) => {
+                    debug::print_event_verbose(&event);
+                    if matches!(event, Event::Unknown {}) {
+                        println!("  event_type: {:?}", event_type);
+                        println!("  payload ({} bytes):", payload.len());
+                        debug::print_hex(&payload);
+                    }
+                }
+                Err(e) => {
+                    println!("\n[Invalid event] {}: {}", event_type, e);
+                    debug::print_hex(&payload);
+                }
+            }
+        }
+    }
+
+    println!(
+        "\n{} frames decoded, {} errors, {} bytes skipped",
+        decoder.frames_decoded(),
+        decoder.error_count(),
+        decoder.bytes_skipped()
+    );
+    if decoder.buffer_len() > 0 {
+        debug::print_frame_summary(&data[data.len() - decoder.buffer_len()..]);
+    }
+    Ok(())
+}
+
+/// Print the Kiro request the server would send for an Anthropic request body
+///
+/// Model name suffixes and tool compression follow config.json, as in the server. The profile
+/// ARN is left out; it is added per credential when the request is sent.
+pub fn convert(config: &Config, path: &str) -> anyhow::Result<()> {
+    crate::anthropic::tool_compression::init_config(config.tool_compression_threshold_bytes);
+
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let mut payload: MessagesRequest =
+        serde_json::from_str(&content).context("Invalid Anthropic request")?;
+    crate::anthropic::apply_model_suffixes(&mut payload, config.thinking_suffix());
+
+    let result = crate::anthropic::convert_request(&payload)?;
+    let request = KiroRequest {
+        conversation_state: result.conversation_state,
+        profile_arn: None,
+    };
+    println!("{}", serde_json::to_string_pretty(&request)?);
+    Ok(())
+}
@@ -0,0 +1,302 @@
+//! Encryption at rest for credentials and cache files
+//!
+//! When a key is configured (`KIRO_ENCRYPTION_KEY` env var or `encryptionKeyFile` in config.json),
+//! credentials.json, the API keyring and the caches in the credentials directory are written as
+//! `kiro-enc:v1:<base64(nonce || AES-256-GCM ciphertext)>`. Plain JSON files are still read, so
+//! existing files keep working and are encrypted on their next write (or by `migrate-encryption`).
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Context, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+
+/// Environment variable holding the encryption key
+pub const KEY_ENV_VAR: &str = "KIRO_ENCRYPTION_KEY";
+
+/// Prefix of encrypted files
+const ENCRYPTED_PREFIX: &str = "kiro-enc:v1:";
+
+/// AES-GCM nonce size
+const NONCE_SIZE: usize = 12;
+
+/// Minimum key length (characters), to rule out obviously weak keys
+const MIN_KEY_LENGTH: usize = 16;
+
+/// File cipher (AES-256-GCM with a key derived from the configured key by SHA-256)
+pub struct FileCipher {
+    cipher: Aes256Gcm,
+}
+
+impl FileCipher {
+    /// Cipher for a key string
+    pub fn new(key: &str) -> anyhow::Result<Self> {
+        let key = key.trim();
+        if key.len() < MIN_KEY_LENGTH {
+            bail!(
+                "Encryption key is too short (at least {} characters required)",
+                MIN_KEY_LENGTH
+            );
+        }
+        let digest = Sha256::digest(key.as_bytes());
+        let cipher = Aes256Gcm::new_from_slice(&digest).expect("SHA-256 digest is a valid key");
+        Ok(Self { cipher })
+    }
+
+    /// Load the configured key: `KIRO_ENCRYPTION_KEY` takes precedence over `key_file`
+    ///
+    /// Returns None when neither is set (encryption disabled).
+    pub fn from_env_or_file(key_file: Option<&str>) -> anyhow::Result<Option<Self>> {
+        if let Ok(key) = std::env::var(KEY_ENV_VAR)
+            && !key.trim().is_empty()
+        {
+            return Self::new(&key).map(Some);
+        }
+        let Some(key_file) = key_file else {
+            return Ok(None);
+        };
+        let key = std::fs::read_to_string(key_file)
+            .with_context(|| format!("Failed to read encryption key file: {}", key_file))?;
+        Self::new(&key).map(Some)
+    }
+
+    /// Encrypt file contents
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .expect("AES-GCM encryption of in-memory data cannot fail");
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(data))
+    }
+
+    /// Decrypt file contents produced by `encrypt`
+    pub fn decrypt(&self, content: &str) -> anyhow::Result<String> {
+        let encoded = content
+            .trim()
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .context("Content is not encrypted")?;
+        let data = STANDARD
+            .decode(encoded)
+            .context("Encrypted content is not valid base64")?;
+        if data.len() < NONCE_SIZE {
+            bail!("Encrypted content is truncated");
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Decryption failed (wrong encryption key?)"))?;
+        String::from_utf8(plaintext).context("Decrypted content is not valid UTF-8")
+    }
+}
+
+/// Whether file contents are encrypted
+pub fn is_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Global cipher (None = encryption disabled)
+static CIPHER: RwLock<Option<Arc<FileCipher>>> = RwLock::new(None);
+
+/// Initialize the global cipher
+///
+/// Called at application startup, before any credentials or cache file is read
+pub fn init(cipher: Option<FileCipher>) {
+    *CIPHER.write() = cipher.map(Arc::new);
+}
+
+fn cipher() -> Option<Arc<FileCipher>> {
+    CIPHER.read().clone()
+}
+
+/// Read a file, decrypting it if encrypted
+pub fn read_to_string(path: &Path) -> anyhow::Result<String> {
+    read_to_string_with(path, cipher().as_deref())
+}
+
+/// Write a file, encrypting it if encryption is enabled
+pub fn write(path: &Path, contents: &str) -> anyhow::Result<()> {
+    write_with(path, contents, cipher().as_deref())
+}
+
+fn read_to_string_with(path: &Path, cipher: Option<&FileCipher>) -> anyhow::Result<String> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if !is_encrypted(&content) {
+        if cipher.is_some() {
+            tracing::warn!(
+                "{} is not encrypted yet, it will be encrypted on its next write (or run `kiro-rs migrate-encryption`)",
+                path.display()
+            );
+        }
+        return Ok(content);
+    }
+    let Some(cipher) = cipher else {
+        bail!(
+            "{} is encrypted but no encryption key is configured (set {} or encryptionKeyFile)",
+            path.display(),
+            KEY_ENV_VAR
+        );
+    };
+    cipher
+        .decrypt(&content)
+        .with_context(|| format!("Failed to decrypt {}", path.display()))
+}
+
+fn write_with(path: &Path, contents: &str, cipher: Option<&FileCipher>) -> anyhow::Result<()> {
+    let result = match cipher {
+        Some(cipher) => std::fs::write(path, cipher.encrypt(contents)),
+        None => std::fs::write(path, contents),
+    };
+    result.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Result of migrating one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrateOutcome {
+    /// File was rewritten
+    Migrated,
+    /// File was already in the target format
+    Unchanged,
+    /// File does not exist
+    Missing,
+}
+
+/// Rewrite files encrypted (or decrypted with `decrypt`) in place
+///
+/// Every file is read before any is written, so a file that can't be decrypted leaves all
+/// files untouched.
+pub fn migrate_files(
+    paths: &[PathBuf],
+    cipher: &FileCipher,
+    decrypt: bool,
+) -> anyhow::Result<Vec<(PathBuf, MigrateOutcome)>> {
+    let mut contents = Vec::with_capacity(paths.len());
+    for path in paths {
+        let content = if path.exists() {
+            let raw = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let encrypted = is_encrypted(&raw);
+            let plaintext = if encrypted {
+                cipher
+                    .decrypt(&raw)
+                    .with_context(|| format!("Failed to decrypt {}", path.display()))?
+            } else {
+                raw
+            };
+            Some((plaintext, encrypted))
+        } else {
+            None
+        };
+        contents.push(content);
+    }
+
+    let mut outcomes = Vec::with_capacity(paths.len());
+    for (path, content) in paths.iter().zip(contents) {
+        let outcome = match content {
+            None => MigrateOutcome::Missing,
+            Some((_, encrypted)) if encrypted != decrypt => MigrateOutcome::Unchanged,
+            Some((plaintext, _)) => {
+                write_with(path, &plaintext, (!decrypt).then_some(cipher))?;
+                MigrateOutcome::Migrated
+            }
+        };
+        outcomes.push((path.clone(), outcome));
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kiro-encryption-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let cipher = FileCipher::new("0123456789abcdef-secret").unwrap();
+        let encrypted = cipher.encrypt(r#"[{"refreshToken":"secret"}]"#);
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("secret"));
+        // Random nonce per write
+        assert_ne!(cipher.encrypt("same"), cipher.encrypt("same"));
+        assert_eq!(
+            cipher.decrypt(&encrypted).unwrap(),
+            r#"[{"refreshToken":"secret"}]"#
+        );
+
+        let other = FileCipher::new("another-key-of-16-chars").unwrap();
+        assert!(other.decrypt(&encrypted).is_err());
+        assert!(FileCipher::new("short").is_err());
+    }
+
+    #[test]
+    fn test_read_accepts_plain_and_encrypted_files() {
+        let dir = temp_dir();
+        let cipher = FileCipher::new("0123456789abcdef-secret").unwrap();
+        let plain = dir.join("plain.json");
+        let encrypted = dir.join("encrypted.json");
+        std::fs::write(&plain, "[]").unwrap();
+        write_with(&encrypted, "[1]", Some(&cipher)).unwrap();
+
+        assert_eq!(read_to_string_with(&plain, Some(&cipher)).unwrap(), "[]");
+        assert_eq!(
+            read_to_string_with(&encrypted, Some(&cipher)).unwrap(),
+            "[1]"
+        );
+        assert_eq!(read_to_string_with(&plain, None).unwrap(), "[]");
+        let err = read_to_string_with(&encrypted, None).unwrap_err();
+        assert!(err.to_string().contains("no encryption key"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_files_encrypts_and_decrypts() {
+        let dir = temp_dir();
+        let cipher = FileCipher::new("0123456789abcdef-secret").unwrap();
+        let credentials = dir.join("credentials.json");
+        let stats = dir.join("kiro_stats.json");
+        let missing = dir.join("kiro_balance_cache.json");
+        std::fs::write(&credentials, "[]").unwrap();
+        write_with(&stats, "{}", Some(&cipher)).unwrap();
+        let paths = vec![credentials.clone(), stats.clone(), missing.clone()];
+
+        let outcomes = migrate_files(&paths, &cipher, false).unwrap();
+        assert_eq!(
+            outcomes.iter().map(|(_, o)| *o).collect::<Vec<_>>(),
+            vec![
+                MigrateOutcome::Migrated,
+                MigrateOutcome::Unchanged,
+                MigrateOutcome::Missing
+            ]
+        );
+        assert!(is_encrypted(
+            &std::fs::read_to_string(&credentials).unwrap()
+        ));
+
+        // A wrong key fails before anything is written
+        let other = FileCipher::new("another-key-of-16-chars").unwrap();
+        assert!(migrate_files(&paths, &other, true).is_err());
+
+        migrate_files(&paths, &cipher, true).unwrap();
+        assert_eq!(std::fs::read_to_string(&credentials).unwrap(), "[]");
+        assert_eq!(std::fs::read_to_string(&stats).unwrap(), "{}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
 //! Common utilities module
 
 pub mod auth;
+pub mod encryption;
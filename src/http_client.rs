@@ -8,7 +8,7 @@ use std::time::Duration;
 use crate::model::config::TlsBackend;
 
 /// Proxy configuration
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ProxyConfig {
     /// Proxy URL, supports http/https/socks5
     pub url: String,
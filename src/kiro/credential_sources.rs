@@ -0,0 +1,280 @@
+//! Credential sources
+//!
+//! Credentials are loaded from up to three sources:
+//! - the credentials file (`--credentials`, single object or array format);
+//! - a directory with one JSON object per `*.json` file (`credentialsDir`), e.g. mounted secrets;
+//! - environment variables `{prefix}{FIELD}_{n}` (`credentialsEnvPrefix`, default `KIRO_`),
+//!   e.g. `KIRO_REFRESH_TOKEN_1`, `KIRO_AUTH_METHOD_1`. Without `{prefix}ID_{n}` the ID is
+//!   derived from the index (`ENV_ID_BASE + n`), so it stays the same across restarts.
+//!
+//! Each credential records its source (`KiroCredentials::source`), so write-back goes to the file it
+//! came from and read-only sources (environment, read-only directory) are never written.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde_json::{Map, Value};
+
+use crate::common::encryption;
+use crate::kiro::model::credentials::{CredentialSource, CredentialsConfig, KiroCredentials};
+use crate::model::config::Config;
+
+/// Default environment variable prefix
+pub const DEFAULT_ENV_PREFIX: &str = "KIRO_";
+
+/// Credential fields settable through environment variables (`{prefix}{FIELD}_{n}`)
+const ENV_FIELDS: &[(&str, &str)] = &[
+    ("ID", "id"),
+    ("REFRESH_TOKEN", "refreshToken"),
+    ("AUTH_METHOD", "authMethod"),
+    ("CLIENT_ID", "clientId"),
+    ("CLIENT_SECRET", "clientSecret"),
+    ("PRIORITY", "priority"),
+    ("REGION", "region"),
+    ("AUTH_REGION", "authRegion"),
+    ("API_REGION", "apiRegion"),
+    ("MACHINE_ID", "machineId"),
+    ("PROFILE_ARN", "profileArn"),
+    ("EMAIL", "email"),
+];
+
+/// Numeric fields (parsed from the environment variable string)
+const ENV_NUMERIC_FIELDS: &[&str] = &["id", "priority"];
+
+/// Base of the IDs derived from the index of environment credentials without `{prefix}ID_{n}`
+pub const ENV_ID_BASE: u64 = 1_000_000;
+
+/// Credentials loaded from all sources
+#[derive(Debug, Default)]
+pub struct LoadedCredentials {
+    /// Credentials sorted by priority
+    pub credentials: Vec<KiroCredentials>,
+    /// Whether the credentials file uses the array format (only then is it written back)
+    pub is_multiple_format: bool,
+}
+
+/// Load credentials from the credentials file, the credentials directory and the environment
+///
+/// Fails when two credentials share an ID, so a reload rejects the files before applying anything.
+pub fn load_all(config: &Config, credentials_path: &Path) -> anyhow::Result<LoadedCredentials> {
+    let file_config = CredentialsConfig::load(credentials_path).with_context(|| {
+        format!(
+            "Failed to load credentials file: {}",
+            credentials_path.display()
+        )
+    })?;
+    let is_multiple_format = file_config.is_multiple();
+    let mut credentials = file_config.into_sorted_credentials();
+
+    if let Some(dir) = &config.credentials_dir {
+        credentials.extend(load_directory(
+            Path::new(dir),
+            config.credentials_dir_read_only,
+        )?);
+    }
+
+    let prefix = config
+        .credentials_env_prefix
+        .as_deref()
+        .unwrap_or(DEFAULT_ENV_PREFIX);
+    if !prefix.is_empty() {
+        credentials.extend(load_env(prefix, std::env::vars())?);
+    }
+
+    let mut seen_ids = HashSet::new();
+    let duplicate_ids: Vec<u64> = credentials
+        .iter()
+        .filter_map(|c| c.id)
+        .filter(|id| !seen_ids.insert(*id))
+        .collect();
+    if !duplicate_ids.is_empty() {
+        anyhow::bail!("Duplicate credential IDs detected: {:?}", duplicate_ids);
+    }
+
+    // Stable sort keeps source order for equal priorities
+    credentials.sort_by_key(|c| c.priority);
+    Ok(LoadedCredentials {
+        credentials,
+        is_multiple_format,
+    })
+}
+
+/// Credential files (`*.json`) in `dir`, sorted by file name
+pub fn credential_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read credentials directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Load one credential per file in `dir` (see `credential_files`)
+pub fn load_directory(dir: &Path, read_only: bool) -> anyhow::Result<Vec<KiroCredentials>> {
+    credential_files(dir)?
+        .into_iter()
+        .map(|path| {
+            let content = encryption::read_to_string(&path)?;
+            let mut cred: KiroCredentials = serde_json::from_str(&content)
+                .with_context(|| format!("Invalid credential file: {}", path.display()))?;
+            cred.canonicalize_auth_method();
+            cred.source = CredentialSource::Directory { path, read_only };
+            Ok(cred)
+        })
+        .collect()
+}
+
+/// Load credentials from `{prefix}{FIELD}_{n}` environment variables
+///
+/// Every index needs a `{prefix}REFRESH_TOKEN_{n}`; variables that don't match a known field
+/// with a numeric index are ignored. Credentials without `{prefix}ID_{n}` get `ENV_ID_BASE + n`,
+/// as their ID can't be written back.
+pub fn load_env(
+    prefix: &str,
+    vars: impl Iterator<Item = (String, String)>,
+) -> anyhow::Result<Vec<KiroCredentials>> {
+    let mut groups: BTreeMap<u32, Map<String, Value>> = BTreeMap::new();
+    for (name, value) in vars {
+        let Some((field, index)) = name
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.rsplit_once('_'))
+            .and_then(|(field, index)| Some((field, index.parse::<u32>().ok()?)))
+        else {
+            continue;
+        };
+        let Some(&(_, key)) = ENV_FIELDS.iter().find(|(f, _)| *f == field) else {
+            continue;
+        };
+
+        let value = if ENV_NUMERIC_FIELDS.contains(&key) {
+            let number: u64 = value
+                .trim()
+                .parse()
+                .with_context(|| format!("{} must be a number", name))?;
+            Value::from(number)
+        } else {
+            Value::from(value)
+        };
+        groups
+            .entry(index)
+            .or_default()
+            .insert(key.to_string(), value);
+    }
+
+    groups
+        .into_iter()
+        .map(|(index, fields)| {
+            if !fields.contains_key("refreshToken") {
+                anyhow::bail!(
+                    "{}REFRESH_TOKEN_{} is missing for credential variables with index {}",
+                    prefix,
+                    index,
+                    index
+                );
+            }
+            let mut cred: KiroCredentials = serde_json::from_value(Value::Object(fields))
+                .with_context(|| format!("Invalid credential variables with index {}", index))?;
+            cred.canonicalize_auth_method();
+            cred.id.get_or_insert(ENV_ID_BASE + u64::from(index));
+            cred.source = CredentialSource::Env { index };
+            Ok(cred)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_load_env_groups_by_index() {
+        let creds = load_env(
+            "KIRO_",
+            vars(&[
+                ("KIRO_REFRESH_TOKEN_2", "rt-2"),
+                ("KIRO_ID_2", "7"),
+                ("KIRO_AUTH_METHOD_2", "builder-id"),
+                ("KIRO_CLIENT_ID_2", "cid"),
+                ("KIRO_PRIORITY_2", "3"),
+                ("KIRO_REFRESH_TOKEN_1", "rt-1"),
+                ("KIRO_ENCRYPTION_KEY", "not a credential"),
+                ("KIRO_UNKNOWN_1", "ignored"),
+                ("OTHER_REFRESH_TOKEN_1", "ignored"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(creds.len(), 2);
+        assert_eq!(creds[0].refresh_token.as_deref(), Some("rt-1"));
+        assert_eq!(creds[0].source, CredentialSource::Env { index: 1 });
+        // Derived from the index when not set, so it is stable across restarts
+        assert_eq!(creds[0].id, Some(ENV_ID_BASE + 1));
+        assert_eq!(creds[1].id, Some(7));
+        assert_eq!(creds[1].auth_method.as_deref(), Some("idc"));
+        assert_eq!(creds[1].client_id.as_deref(), Some("cid"));
+        assert_eq!(creds[1].priority, 3);
+        assert!(!creds[1].source.is_writable());
+    }
+
+    #[test]
+    fn test_load_env_rejects_incomplete_groups() {
+        let err = load_env("KIRO_", vars(&[("KIRO_CLIENT_ID_1", "cid")])).unwrap_err();
+        assert!(err.to_string().contains("KIRO_REFRESH_TOKEN_1"));
+
+        assert!(load_env("KIRO_", vars(&[("KIRO_PRIORITY_1", "high")])).is_err());
+    }
+
+    #[test]
+    fn test_load_all_merges_sources_by_priority() {
+        let dir = std::env::temp_dir().join(format!("kiro-sources-{}", uuid::Uuid::new_v4()));
+        let secrets = dir.join("secrets");
+        std::fs::create_dir_all(&secrets).unwrap();
+        let credentials_path = dir.join("credentials.json");
+        std::fs::write(
+            &credentials_path,
+            r#"[{"id": 1, "refreshToken": "file", "priority": 2}]"#,
+        )
+        .unwrap();
+        std::fs::write(
+            secrets.join("b.json"),
+            r#"{"refreshToken": "dir-b", "priority": 1}"#,
+        )
+        .unwrap();
+        std::fs::write(secrets.join("a.json"), r#"{"refreshToken": "dir-a"}"#).unwrap();
+        std::fs::write(secrets.join("notes.txt"), "ignored").unwrap();
+
+        let mut config = Config::default();
+        config.credentials_dir = Some(secrets.to_string_lossy().into_owned());
+        config.credentials_dir_read_only = true;
+        config.credentials_env_prefix = Some(String::new());
+        let loaded = load_all(&config, &credentials_path).unwrap();
+
+        assert!(loaded.is_multiple_format);
+        let tokens: Vec<_> = loaded
+            .credentials
+            .iter()
+            .map(|c| c.refresh_token.as_deref().unwrap())
+            .collect();
+        assert_eq!(tokens, vec!["dir-a", "dir-b", "file"]);
+        assert_eq!(
+            loaded.credentials[0].source,
+            CredentialSource::Directory {
+                path: secrets.join("a.json"),
+                read_only: true
+            }
+        );
+        assert_eq!(loaded.credentials[2].source, CredentialSource::File);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
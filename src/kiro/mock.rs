@@ -0,0 +1,895 @@
+//! In-process mock of the Kiro upstream
+//!
+//! Serves generateAssistantResponse, MCP, social token refresh, IdC OIDC token refresh and
+//! getUsageLimits on a local port, so `KiroProvider`, `MultiTokenManager` and the whole proxy
+//! can be exercised end to end without network access.
+//!
+//! Replies are scripted per endpoint with `MockUpstream::enqueue`; when an endpoint's queue is
+//! empty it falls back to a default success reply.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde_json::json;
+
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::model::events::Event;
+use crate::kiro::parser::encoder::{FrameBuilder, encode_event};
+use crate::model::config::Config;
+
+/// Mocked upstream endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockEndpoint {
+    GenerateAssistantResponse,
+    Mcp,
+    /// Social token refresh (`/refreshToken`)
+    RefreshToken,
+    /// IdC token refresh (`/token`)
+    OidcToken,
+    UsageLimits,
+}
+
+/// Scripted reply
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    /// 200 with an AWS event-stream body (concatenated frames)
+    EventStream(Vec<u8>),
+    /// 200 with a JSON body
+    Json(serde_json::Value),
+    /// Error status with a raw body
+    Error(u16, String),
+    /// 200 with an event-stream body that never completes after the given bytes
+    Stalled(Vec<u8>),
+}
+
+impl MockReply {
+    /// Event-stream reply made of the given frames
+    pub fn frames(frames: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        Self::EventStream(frames.into_iter().flatten().collect())
+    }
+
+    /// Event-stream reply with a single text response and context usage
+    pub fn text(text: &str) -> Self {
+        Self::frames([assistant_frame(text), context_usage_frame(1.0)])
+    }
+
+    /// 402 with MONTHLY_REQUEST_COUNT (quota exhausted)
+    pub fn quota_exhausted() -> Self {
+        Self::Error(
+            402,
+            json!({
+                "message": "You have reached the limit for requests.",
+                "reason": "MONTHLY_REQUEST_COUNT"
+            })
+            .to_string(),
+        )
+    }
+
+    /// 403 access denied
+    pub fn forbidden() -> Self {
+        Self::Error(
+            403,
+            json!({ "message": "The bearer token included in the request is invalid." })
+                .to_string(),
+        )
+    }
+
+    /// 429 throttling
+    pub fn throttled() -> Self {
+        Self::Error(
+            429,
+            json!({ "message": "Too many requests", "__type": "ThrottlingException" }).to_string(),
+        )
+    }
+}
+
+/// Request received by the mock
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub endpoint: MockEndpoint,
+    pub authorization: Option<String>,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    replies: Mutex<HashMap<MockEndpoint, VecDeque<MockReply>>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+    /// Stalled replies whose connection was closed by the client
+    cancelled: AtomicUsize,
+}
+
+/// Counts a stalled reply as cancelled when its body is dropped
+struct CancelOnDrop(Arc<MockState>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancelled.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Running mock upstream, stopped on drop
+pub struct MockUpstream {
+    base_url: String,
+    state: Arc<MockState>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl MockUpstream {
+    /// Start the mock on a random local port
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState::default());
+        let router = Router::new()
+            .route(
+                "/generateAssistantResponse",
+                post(generate_assistant_response),
+            )
+            .route("/mcp", post(mcp))
+            .route("/refreshToken", post(refresh_token))
+            .route("/token", post(oidc_token))
+            .route("/getUsageLimits", get(usage_limits))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind mock upstream");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+
+        Self {
+            base_url,
+            state,
+            server,
+        }
+    }
+
+    /// Config with all upstream endpoints pointing at the mock
+    pub fn config(&self) -> Config {
+        let mut config = Config::default();
+        config.api_endpoint = Some(self.base_url.clone());
+        config.auth_endpoint = Some(self.base_url.clone());
+        config.oidc_endpoint = Some(self.base_url.clone());
+        config
+    }
+
+    /// Queue a reply for the next request to the endpoint
+    pub fn enqueue(&self, endpoint: MockEndpoint, reply: MockReply) {
+        self.state
+            .replies
+            .lock()
+            .entry(endpoint)
+            .or_default()
+            .push_back(reply);
+    }
+
+    /// Number of stalled replies whose connection was closed by the client
+    pub fn cancelled(&self) -> usize {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Requests received by the endpoint so far
+    pub fn requests(&self, endpoint: MockEndpoint) -> Vec<RecordedRequest> {
+        self.state
+            .requests
+            .lock()
+            .iter()
+            .filter(|r| r.endpoint == endpoint)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Drop for MockUpstream {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Social credential with a valid access token (no refresh needed)
+pub fn credentials(id: u64, access_token: &str) -> KiroCredentials {
+    KiroCredentials {
+        id: Some(id),
+        access_token: Some(access_token.to_string()),
+        refresh_token: Some(format!("{}-{}", id, "r".repeat(120))),
+        profile_arn: Some("arn:aws:codewhisperer:us-east-1:000000000000:profile/MOCK".to_string()),
+        expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+        auth_method: Some("social".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_reply(endpoint: MockEndpoint) -> MockReply {
+    match endpoint {
+        MockEndpoint::GenerateAssistantResponse => MockReply::text("Hello from mock"),
+        MockEndpoint::Mcp => MockReply::Json(json!({
+            "jsonrpc": "2.0",
+            "id": "mock",
+            "result": { "content": [], "isError": false }
+        })),
+        MockEndpoint::RefreshToken => MockReply::Json(json!({
+            "accessToken": "mock-refreshed-access-token",
+            "refreshToken": format!("mock-{}", "r".repeat(120)),
+            "expiresIn": 3600
+        })),
+        MockEndpoint::OidcToken => MockReply::Json(json!({
+            "accessToken": "mock-oidc-access-token",
+            "refreshToken": format!("mock-oidc-{}", "r".repeat(120)),
+            "expiresIn": 3600
+        })),
+        MockEndpoint::UsageLimits => MockReply::Json(json!({
+            "subscriptionInfo": { "subscriptionTitle": "KIRO PRO" },
+            "userInfo": { "email": "mock@example.com", "userId": "mock-user" },
+            "usageBreakdownList": [{
+                "currentUsage": 10,
+                "currentUsageWithPrecision": 10.0,
+                "usageLimit": 1000,
+                "usageLimitWithPrecision": 1000.0
+            }]
+        })),
+    }
+}
+
+async fn respond(
+    state: Arc<MockState>,
+    endpoint: MockEndpoint,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    state.requests.lock().push(RecordedRequest {
+        endpoint,
+        authorization: headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        body,
+    });
+
+    let reply = state
+        .replies
+        .lock()
+        .get_mut(&endpoint)
+        .and_then(VecDeque::pop_front)
+        .unwrap_or_else(|| default_reply(endpoint));
+
+    match reply {
+        MockReply::EventStream(bytes) => (
+            [(header::CONTENT_TYPE, "application/vnd.amazon.eventstream")],
+            Body::from(bytes),
+        )
+            .into_response(),
+        MockReply::Json(value) => (
+            [(header::CONTENT_TYPE, "application/json")],
+            value.to_string(),
+        )
+            .into_response(),
+        MockReply::Stalled(bytes) => {
+            let cancel = CancelOnDrop(state.clone());
+            let body = futures::stream::once(async move { Ok::<_, Infallible>(bytes) })
+                .chain(futures::stream::pending())
+                .map(move |chunk| {
+                    let _ = &cancel;
+                    chunk
+                });
+            (
+                [(header::CONTENT_TYPE, "application/vnd.amazon.eventstream")],
+                Body::from_stream(body),
+            )
+                .into_response()
+        }
+        MockReply::Error(status, body) => (
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            [(header::CONTENT_TYPE, "application/json")],
+            body,
+        )
+            .into_response(),
+    }
+}
+
+async fn generate_assistant_response(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    respond(
+        state,
+        MockEndpoint::GenerateAssistantResponse,
+        headers,
+        body,
+    )
+    .await
+}
+
+async fn mcp(State(state): State<Arc<MockState>>, headers: HeaderMap, body: String) -> Response {
+    respond(state, MockEndpoint::Mcp, headers, body).await
+}
+
+async fn refresh_token(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    respond(state, MockEndpoint::RefreshToken, headers, body).await
+}
+
+async fn oidc_token(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    respond(state, MockEndpoint::OidcToken, headers, body).await
+}
+
+async fn usage_limits(State(state): State<Arc<MockState>>, headers: HeaderMap) -> Response {
+    respond(state, MockEndpoint::UsageLimits, headers, String::new()).await
+}
+
+// === Frames ===
+
+/// `event` frame with a JSON payload
+pub fn event_frame(event_type: &str, payload: &serde_json::Value) -> Vec<u8> {
+    FrameBuilder::event(event_type)
+        .json_payload(payload)
+        .and_then(|builder| builder.encode())
+        .expect("mock frame")
+}
+
+/// assistantResponseEvent frame
+pub fn assistant_frame(content: &str) -> Vec<u8> {
+    event_frame("assistantResponseEvent", &json!({ "content": content }))
+}
+
+/// toolUseEvent frame
+pub fn tool_use_frame(tool_use_id: &str, name: &str, input: &str, stop: bool) -> Vec<u8> {
+    event_frame(
+        "toolUseEvent",
+        &json!({ "toolUseId": tool_use_id, "name": name, "input": input, "stop": stop }),
+    )
+}
+
+/// meteringEvent frame reporting credits
+pub fn metering_frame(credits: f64) -> Vec<u8> {
+    event_frame(
+        "meteringEvent",
+        &json!({ "unit": "credit", "unitPlural": "credits", "usage": credits }),
+    )
+}
+
+/// contextUsageEvent frame
+pub fn context_usage_frame(percentage: f64) -> Vec<u8> {
+    event_frame(
+        "contextUsageEvent",
+        &json!({ "contextUsagePercentage": percentage }),
+    )
+}
+
+/// `exception` frame
+pub fn exception_frame(exception_type: &str, message: &str) -> Vec<u8> {
+    encode_event(&Event::Exception {
+        exception_type: exception_type.to_string(),
+        message: message.to_string(),
+    })
+    .expect("mock frame")
+}
+
+/// Frame cut off after `len` bytes, as when the upstream connection drops mid-frame
+pub fn truncated(mut frame: Vec<u8>, len: usize) -> Vec<u8> {
+    frame.truncate(len);
+    frame
+}
+
+/// Frame with a corrupted message CRC
+pub fn with_bad_crc(mut frame: Vec<u8>) -> Vec<u8> {
+    if let Some(last) = frame.last_mut() {
+        *last ^= 0xff;
+    }
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anthropic::create_router_with_provider;
+    use crate::anthropic::keyring::ApiKeyring;
+    use crate::kiro::parser::decoder::EventStreamDecoder;
+    use crate::kiro::provider::KiroProvider;
+    use crate::kiro::token_manager::MultiTokenManager;
+
+    const TEST_BODY: &str = r#"{"conversationState":{"currentMessage":{"userInputMessage":{"content":"hi","modelId":"claude-sonnet-4.5"}}}}"#;
+
+    fn provider(mock: &MockUpstream, credentials: Vec<KiroCredentials>) -> KiroProvider {
+        let manager =
+            MultiTokenManager::new(mock.config(), credentials, None, None, false).unwrap();
+        KiroProvider::new(Arc::new(manager))
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<Event> {
+        let mut decoder = EventStreamDecoder::new();
+        decoder.feed(bytes).unwrap();
+        decoder
+            .decode_iter()
+            .filter_map(|r| r.ok())
+            .filter_map(|f| Event::from_frame(f).ok())
+            .collect()
+    }
+
+    /// Start the proxy on a random port, returning its base URL
+    async fn start_proxy(mock: &MockUpstream) -> (String, tokio::task::JoinHandle<()>) {
+        start_proxy_with(mock, provider(mock, vec![credentials(1, "token-1")])).await
+    }
+
+    async fn start_proxy_with(
+        mock: &MockUpstream,
+        provider: KiroProvider,
+    ) -> (String, tokio::task::JoinHandle<()>) {
+        let app = create_router_with_provider(
+            "test-key",
+            Some(provider),
+            Arc::new(ApiKeyring::default()),
+            mock.config(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (url, handle)
+    }
+
+    /// Start the proxy with credentials `token-1`..`token-{count}`, returning their manager too
+    async fn start_streaming_proxy(
+        mock: &MockUpstream,
+        config: Config,
+        count: u64,
+    ) -> (String, Arc<MultiTokenManager>, tokio::task::JoinHandle<()>) {
+        let credentials = (1..=count)
+            .map(|id| credentials(id, &format!("token-{}", id)))
+            .collect();
+        let manager =
+            Arc::new(MultiTokenManager::new(config, credentials, None, None, false).unwrap());
+        let (url, handle) = start_proxy_with(mock, KiroProvider::new(manager.clone())).await;
+        (url, manager, handle)
+    }
+
+    /// Send a one-message conversation to `path`
+    async fn post_messages(proxy_url: &str, path: &str, stream: bool) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}{}", proxy_url, path))
+            .header("x-api-key", "test-key")
+            .json(&json!({
+                "model": "claude-sonnet-4-5-20250929",
+                "max_tokens": 100,
+                "stream": stream,
+                "messages": [{ "role": "user", "content": "hi" }]
+            }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    /// Send a streaming request to `path` and read the SSE body
+    async fn post_stream(proxy_url: &str, path: &str) -> String {
+        post_messages(proxy_url, path, true)
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_provider_streams_mock_frames() {
+        let mock = MockUpstream::start().await;
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::frames([
+                assistant_frame("Hello"),
+                tool_use_frame("tool-1", "Read", r#"{"path":"a"}"#, true),
+            ]),
+        );
+
+        let provider = provider(&mock, vec![credentials(1, "token-1")]);
+        let bytes = provider
+            .call_api(TEST_BODY)
+            .await
+            .unwrap()
+            .response
+            .bytes()
+            .await
+            .unwrap();
+        let events = decode(&bytes);
+
+        assert!(matches!(&events[0], Event::AssistantResponse(r) if r.content == "Hello"));
+        assert!(matches!(&events[1], Event::ToolUse(t) if t.name == "Read" && t.stop));
+
+        let requests = mock.requests(MockEndpoint::GenerateAssistantResponse);
+        assert_eq!(requests[0].authorization.as_deref(), Some("Bearer token-1"));
+        assert!(requests[0].body.contains("profileArn"));
+    }
+
+    #[tokio::test]
+    async fn test_quota_exhausted_fails_over_to_next_credential() {
+        let mock = MockUpstream::start().await;
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::quota_exhausted(),
+        );
+
+        let provider = provider(
+            &mock,
+            vec![credentials(1, "token-1"), credentials(2, "token-2")],
+        );
+        provider.call_api(TEST_BODY).await.unwrap();
+
+        let requests = mock.requests(MockEndpoint::GenerateAssistantResponse);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].authorization.as_deref(), Some("Bearer token-2"));
+        let snapshot = provider.token_manager().snapshot();
+        assert!(snapshot.entries.iter().any(|e| e.id == 1 && e.disabled));
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_and_throttled_are_retried() {
+        let mock = MockUpstream::start().await;
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::forbidden(),
+        );
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::throttled(),
+        );
+
+        let provider = provider(
+            &mock,
+            vec![credentials(1, "token-1"), credentials(2, "token-2")],
+        );
+        provider.call_api(TEST_BODY).await.unwrap();
+
+        assert_eq!(
+            mock.requests(MockEndpoint::GenerateAssistantResponse).len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decoder_recovers_from_bad_crc_and_truncated_frame() {
+        let mock = MockUpstream::start().await;
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::frames([
+                with_bad_crc(assistant_frame("corrupted")),
+                assistant_frame("kept"),
+                truncated(assistant_frame("cut off"), 20),
+            ]),
+        );
+
+        let provider = provider(&mock, vec![credentials(1, "token-1")]);
+        let bytes = provider
+            .call_api(TEST_BODY)
+            .await
+            .unwrap()
+            .response
+            .bytes()
+            .await
+            .unwrap();
+        let events = decode(&bytes);
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], Event::AssistantResponse(r) if r.content == "kept"));
+    }
+
+    #[tokio::test]
+    async fn test_social_and_idc_token_refresh() {
+        let mock = MockUpstream::start().await;
+
+        let social = KiroCredentials {
+            access_token: None,
+            expires_at: None,
+            ..credentials(1, "unused")
+        };
+        let idc = KiroCredentials {
+            access_token: None,
+            expires_at: None,
+            auth_method: Some("idc".to_string()),
+            client_id: Some("client".to_string()),
+            client_secret: Some("secret".to_string()),
+            ..credentials(2, "unused")
+        };
+        let manager =
+            MultiTokenManager::new(mock.config(), vec![social, idc], None, None, false).unwrap();
+
+        manager.force_refresh_token(1).await.unwrap();
+        manager.force_refresh_token(2).await.unwrap();
+
+        assert_eq!(mock.requests(MockEndpoint::RefreshToken).len(), 1);
+        let oidc = mock.requests(MockEndpoint::OidcToken);
+        assert_eq!(oidc.len(), 1);
+        assert!(oidc[0].body.contains("\"clientId\":\"client\""));
+    }
+
+    #[tokio::test]
+    async fn test_usage_limits_from_mock() {
+        let mock = MockUpstream::start().await;
+        let manager = MultiTokenManager::new(
+            mock.config(),
+            vec![credentials(1, "token-1")],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        let usage = manager.get_usage_limits_for(1).await.unwrap();
+        assert_eq!(usage.usage_limit(), 1000.0);
+        assert_eq!(usage.email(), Some("mock@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_end_to_end_non_stream_and_stream() {
+        let mock = MockUpstream::start().await;
+        let (proxy_url, server) = start_proxy(&mock).await;
+        let client = reqwest::Client::new();
+        let request = json!({
+            "model": "claude-sonnet-4-5-20250929",
+            "max_tokens": 100,
+            "messages": [{ "role": "user", "content": "hi" }]
+        });
+
+        let response: serde_json::Value = client
+            .post(format!("{}/v1/messages", proxy_url))
+            .header("x-api-key", "test-key")
+            .json(&request)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["content"][0]["text"], "Hello from mock");
+        assert_eq!(response["stop_reason"], "end_turn");
+
+        let mut stream_request = request.clone();
+        stream_request["stream"] = json!(true);
+        let sse = client
+            .post(format!("{}/v1/messages", proxy_url))
+            .header("x-api-key", "test-key")
+            .json(&stream_request)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(sse.contains("event: message_start"));
+        assert!(sse.contains("\"text\":\"Hello from mock\""));
+        assert!(sse.contains("event: message_stop"));
+
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::frames([
+                assistant_frame("partial"),
+                exception_frame("ContentLengthExceededException", "Output too long"),
+            ]),
+        );
+        let response: serde_json::Value = client
+            .post(format!("{}/v1/messages", proxy_url))
+            .header("x-api-key", "test-key")
+            .json(&request)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["stop_reason"], "max_tokens");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_cancels_upstream() {
+        for path in ["/v1/messages", "/cc/v1/messages"] {
+            let mock = MockUpstream::start().await;
+            mock.enqueue(
+                MockEndpoint::GenerateAssistantResponse,
+                MockReply::Stalled(assistant_frame("partial")),
+            );
+            let (proxy_url, manager, server) = start_streaming_proxy(&mock, mock.config(), 1).await;
+
+            let response = post_messages(&proxy_url, path, true).await;
+            assert!(response.status().is_success());
+            drop(response);
+
+            // The proxy drops the upstream response once it notices the closed connection
+            for _ in 0..50 {
+                if mock.cancelled() > 0 {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            assert_eq!(mock.cancelled(), 1, "{} kept the upstream open", path);
+            let snapshot = manager.snapshot();
+            assert_eq!(snapshot.entries[0].cancelled_count, 1);
+
+            server.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_fails_over_before_first_content() {
+        for path in ["/v1/messages", "/cc/v1/messages"] {
+            let mock = MockUpstream::start().await;
+            // Exception before any content on the first credential
+            mock.enqueue(
+                MockEndpoint::GenerateAssistantResponse,
+                MockReply::frames([
+                    context_usage_frame(1.0),
+                    exception_frame("InternalServerException", "Encountered an unexpected error"),
+                ]),
+            );
+            // Stall before any content on the second credential
+            mock.enqueue(
+                MockEndpoint::GenerateAssistantResponse,
+                MockReply::Stalled(Vec::new()),
+            );
+            let mut config = mock.config();
+            config.first_content_timeout_secs = 1;
+            let (proxy_url, manager, server) = start_streaming_proxy(&mock, config, 2).await;
+
+            let sse = post_stream(&proxy_url, path).await;
+            assert!(sse.contains("\"text\":\"Hello from mock\""), "{}", path);
+            assert!(!sse.contains("event: error"), "{}", path);
+            assert_eq!(sse.matches("event: message_start").count(), 1, "{}", path);
+
+            let requests = mock.requests(MockEndpoint::GenerateAssistantResponse);
+            let tokens: Vec<_> = requests
+                .iter()
+                .map(|r| r.authorization.as_deref().unwrap())
+                .collect();
+            assert_eq!(
+                tokens,
+                ["Bearer token-1", "Bearer token-2", "Bearer token-1"],
+                "{}",
+                path
+            );
+            assert_eq!(mock.cancelled(), 1, "{}", path);
+
+            // Only the attempt that produced content counts as a success
+            let snapshot = manager.snapshot();
+            let successes: Vec<_> = snapshot.entries.iter().map(|e| e.success_count).collect();
+            assert_eq!(successes, [1, 0], "{}", path);
+
+            server.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_content_length_exceeded_is_not_retried() {
+        let mock = MockUpstream::start().await;
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::frames([
+                context_usage_frame(1.0),
+                exception_frame("ContentLengthExceededException", "Input too long"),
+            ]),
+        );
+        let (proxy_url, _, server) = start_streaming_proxy(&mock, mock.config(), 2).await;
+
+        let sse = post_stream(&proxy_url, "/v1/messages").await;
+        assert!(sse.contains("\"stop_reason\":\"max_tokens\""));
+        assert!(!sse.contains("event: error"));
+        assert_eq!(
+            mock.requests(MockEndpoint::GenerateAssistantResponse).len(),
+            1
+        );
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_mid_stream_exception_emits_error_event() {
+        for path in ["/v1/messages", "/cc/v1/messages"] {
+            let mock = MockUpstream::start().await;
+            mock.enqueue(
+                MockEndpoint::GenerateAssistantResponse,
+                MockReply::frames([
+                    assistant_frame("partial answer"),
+                    exception_frame("ThrottlingException", "Too many requests"),
+                    assistant_frame("never sent"),
+                ]),
+            );
+            let (proxy_url, server) = start_proxy(&mock).await;
+
+            let sse = post_stream(&proxy_url, path).await;
+            assert!(sse.contains("\"text\":\"partial answer\""), "{}", path);
+            assert!(!sse.contains("never sent"), "{}", path);
+            assert!(sse.contains("event: content_block_stop"), "{}", path);
+            assert!(sse.contains("event: error"), "{}", path);
+            assert!(sse.contains("\"type\":\"rate_limit_error\""), "{}", path);
+            assert!(!sse.contains("event: message_stop"), "{}", path);
+
+            server.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_stream_claude_code_edit_and_glob_pass_through() {
+        let mock = MockUpstream::start().await;
+        let edit = r#"{"file_path":"/repo/src/main.rs","old_string":"let a = 1;","new_string":"let a = 2;"}"#;
+        let glob = r#"{"pattern":"**/*.rs","path":"/repo"}"#;
+        mock.enqueue(
+            MockEndpoint::GenerateAssistantResponse,
+            MockReply::frames([
+                tool_use_frame("tool-1", "Edit", edit, true),
+                tool_use_frame("tool-2", "Glob", glob, true),
+            ]),
+        );
+        let (proxy_url, server) = start_proxy(&mock).await;
+
+        let body: serde_json::Value = post_messages(&proxy_url, "/v1/messages", false)
+            .await
+            .json()
+            .await
+            .unwrap();
+        let tool_uses: Vec<_> = body["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|block| block["type"] == "tool_use")
+            .map(|block| (block["name"].clone(), block["input"].clone()))
+            .collect();
+        let input = |raw: &str| serde_json::from_str::<serde_json::Value>(raw).unwrap();
+        assert_eq!(
+            tool_uses,
+            [(json!("Edit"), input(edit)), (json!("Glob"), input(glob))]
+        );
+        assert!(!body.to_string().contains("TOOL_CALL_INCOMPLETE"));
+        assert_eq!(body["stop_reason"], "tool_use");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_metering_reported_in_usage_and_stats() {
+        let mock = MockUpstream::start().await;
+        for _ in 0..2 {
+            mock.enqueue(
+                MockEndpoint::GenerateAssistantResponse,
+                MockReply::frames([
+                    assistant_frame("Hello"),
+                    metering_frame(0.25),
+                    context_usage_frame(1.0),
+                ]),
+            );
+        }
+        let (proxy_url, manager, server) = start_streaming_proxy(&mock, mock.config(), 1).await;
+
+        let body: serde_json::Value = post_messages(&proxy_url, "/v1/messages", false)
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["usage"]["kiro_metering"]["credits"], 0.25);
+        assert_eq!(body["usage"]["kiro_metering"]["unit"], "credit");
+
+        let sse = post_stream(&proxy_url, "/v1/messages").await;
+        assert!(sse.contains("\"kiro_metering\":{\"credits\":0.25"));
+
+        assert_eq!(manager.snapshot().entries[0].credits_used, 0.5);
+
+        server.abort();
+    }
+}
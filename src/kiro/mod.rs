@@ -1,7 +1,10 @@
 //! Kiro API client module
 
+pub mod credential_sources;
 pub mod errors;
 pub mod machine_id;
+#[cfg(test)]
+pub mod mock;
 pub mod model;
 pub mod parser;
 pub mod provider;
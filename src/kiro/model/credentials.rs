@@ -4,13 +4,14 @@
 //! Supports single credential and multi-credential configuration formats
 
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
+use crate::common::encryption;
 use crate::model::config::Config;
 
 /// Kiro OAuth credentials
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct KiroCredentials {
     /// Credential unique identifier (auto-increment ID)
@@ -75,12 +76,49 @@ pub struct KiroCredentials {
     /// Subscription title (KIRO PRO+ / KIRO FREE etc.)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subscription_title: Option<String>,
+
+    /// Where this credential was loaded from (not serialized)
+    #[serde(skip)]
+    pub source: CredentialSource,
+}
+
+/// Source a credential was loaded from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// Main credentials file (credentials.json)
+    #[default]
+    File,
+    /// Per-credential JSON file in `credentialsDir`
+    Directory { path: PathBuf, read_only: bool },
+    /// Environment variables with this index (e.g. `KIRO_REFRESH_TOKEN_1`), always read-only
+    Env { index: u32 },
+}
+
+impl CredentialSource {
+    /// Whether write-back to this source is allowed
+    pub fn is_writable(&self) -> bool {
+        match self {
+            CredentialSource::File => true,
+            CredentialSource::Directory { read_only, .. } => !read_only,
+            CredentialSource::Env { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialSource::File => write!(f, "file"),
+            CredentialSource::Directory { path, .. } => write!(f, "dir:{}", path.display()),
+            CredentialSource::Env { index } => write!(f, "env:{}", index),
+        }
+    }
 }
 
 impl KiroCredentials {
-    /// Check if this credential supports Opus model
+    /// Check if this credential supports paid tier models (e.g. Opus)
     /// Returns false if subscription contains "FREE", otherwise true
-    pub fn supports_opus(&self) -> bool {
+    pub fn supports_paid_models(&self) -> bool {
         match &self.subscription_title {
             Some(title) => !title.to_uppercase().contains("FREE"),
             None => true, // Assume supports if unknown
@@ -129,7 +167,7 @@ impl CredentialsConfig {
             return Ok(CredentialsConfig::Multiple(vec![]));
         }
 
-        let content = fs::read_to_string(path)?;
+        let content = encryption::read_to_string(path)?;
 
         // Return empty array if file is empty
         if content.trim().is_empty() {
@@ -210,7 +248,7 @@ impl KiroCredentials {
 
     /// Load credentials from file
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let content = fs::read_to_string(path.as_ref())?;
+        let content = encryption::read_to_string(path.as_ref())?;
         if content.is_empty() {
             anyhow::bail!("Credentials file is empty: {:?}", path.as_ref());
         }
@@ -288,6 +326,7 @@ mod tests {
             machine_id: None,
             email: None,
             subscription_title: None,
+            source: CredentialSource::File,
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -402,6 +441,7 @@ mod tests {
             machine_id: None,
             email: None,
             subscription_title: None,
+            source: CredentialSource::File,
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -428,6 +468,7 @@ mod tests {
             machine_id: None,
             email: None,
             subscription_title: None,
+            source: CredentialSource::File,
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -536,6 +577,7 @@ mod tests {
             machine_id: Some("c".repeat(64)),
             email: None,
             subscription_title: None,
+            source: CredentialSource::File,
         };
 
         let json = original.to_pretty_json().unwrap();
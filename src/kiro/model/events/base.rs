@@ -68,7 +68,7 @@ pub enum Event {
     /// Tool use
     ToolUse(super::ToolUseEvent),
     /// Metering
-    Metering(()),
+    Metering(super::MeteringEvent),
     /// Context usage
     ContextUsage(super::ContextUsageEvent),
     /// Unknown event (preserves original frame data)
@@ -116,7 +116,10 @@ impl Event {
                 let payload = super::ToolUseEvent::from_frame(&frame)?;
                 Ok(Self::ToolUse(payload))
             }
-            EventType::Metering => Ok(Self::Metering(())),
+            EventType::Metering => {
+                let payload = super::MeteringEvent::from_frame(&frame)?;
+                Ok(Self::Metering(payload))
+            }
             EventType::ContextUsage => {
                 let payload = super::ContextUsageEvent::from_frame(&frame)?;
                 Ok(Self::ContextUsage(payload))
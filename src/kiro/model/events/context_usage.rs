@@ -2,7 +2,7 @@
 //!
 //! Handles contextUsageEvent type events
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::kiro::parser::error::ParseResult;
 use crate::kiro::parser::frame::Frame;
@@ -12,7 +12,7 @@ use super::base::EventPayload;
 /// Context usage event
 ///
 /// Contains the current context window usage percentage
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ContextUsageEvent {
     /// Context usage percentage (0-100)
@@ -0,0 +1,110 @@
+//! Metering event
+//!
+//! Handles meteringEvent type events
+
+use serde::{Deserialize, Serialize};
+
+use crate::kiro::parser::error::ParseResult;
+use crate::kiro::parser::frame::Frame;
+
+use super::base::EventPayload;
+
+/// Metering event
+///
+/// Usage billed for the request, for example:
+/// `{"unit": "credit", "unitPlural": "credits", "usage": 0.0421}`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeteringEvent {
+    /// Usage amount in `unit`
+    #[serde(default)]
+    pub usage: f64,
+    /// Usage unit (singular), e.g. "credit"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Usage unit (plural), e.g. "credits"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_plural: Option<String>,
+}
+
+impl EventPayload for MeteringEvent {
+    fn from_frame(frame: &Frame) -> ParseResult<Self> {
+        frame.payload_as_json()
+    }
+}
+
+/// Metered usage summed over all meteringEvents of one response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeteringUsage {
+    /// Total usage amount
+    pub usage: f64,
+    /// Usage unit, from the first event that reported one
+    pub unit: Option<String>,
+}
+
+impl MeteringUsage {
+    /// Add a meteringEvent
+    pub fn add(&mut self, event: &MeteringEvent) {
+        self.usage += event.usage;
+        if self.unit.is_none() {
+            self.unit = event.unit.clone();
+        }
+    }
+
+    /// Credits consumed (usage in credits, Kiro's default unit when none is given)
+    pub fn credits(&self) -> f64 {
+        match self.unit.as_deref() {
+            None => self.usage,
+            Some(unit) if unit.eq_ignore_ascii_case("credit") => self.usage,
+            Some(_) => 0.0,
+        }
+    }
+
+    /// Extra `usage` fields for Anthropic responses
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "usage": self.usage,
+            "unit": self.unit,
+            "credits": self.credits()
+        })
+    }
+}
+
+impl std::fmt::Display for MeteringEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.unit, &self.unit_plural) {
+            (_, Some(plural)) if self.usage != 1.0 => write!(f, "{} {}", self.usage, plural),
+            (Some(unit), _) => write!(f, "{} {}", self.usage, unit),
+            _ => write!(f, "{}", self.usage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metering_usage_sums_credits() {
+        let event: MeteringEvent =
+            serde_json::from_str(r#"{"unit":"credit","unitPlural":"credits","usage":0.25}"#)
+                .unwrap();
+        assert_eq!(event.to_string(), "0.25 credits");
+
+        let mut total = MeteringUsage::default();
+        total.add(&event);
+        total.add(&MeteringEvent {
+            usage: 0.5,
+            ..Default::default()
+        });
+        assert_eq!(total.usage, 0.75);
+        assert_eq!(total.unit.as_deref(), Some("credit"));
+        assert_eq!(total.credits(), 0.75);
+
+        let other = MeteringUsage {
+            usage: 3.0,
+            unit: Some("request".to_string()),
+        };
+        assert_eq!(other.credits(), 0.0);
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Handles toolUseEvent type events
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::kiro::parser::error::ParseResult;
 use crate::kiro::parser::frame::Frame;
@@ -12,7 +12,7 @@ use super::base::EventPayload;
 /// Tool use event
 ///
 /// Contains streaming data for tool calls
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolUseEvent {
     /// Tool name
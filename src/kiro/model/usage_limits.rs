@@ -221,4 +221,16 @@ impl UsageLimitsResponse {
 
         total
     }
+    /// Get remaining allowance (usage limit minus current usage, never negative)
+    pub fn remaining(&self) -> f64 {
+        (self.usage_limit() - self.current_usage()).max(0.0)
+    }
+
+    /// Get next reset date (Unix timestamp)
+    ///
+    /// Prefers the top-level value, falls back to the primary usage breakdown
+    pub fn next_reset_at(&self) -> Option<f64> {
+        self.next_date_reset
+            .or_else(|| self.primary_breakdown().and_then(|b| b.next_date_reset))
+    }
 }
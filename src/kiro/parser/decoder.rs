@@ -33,6 +33,7 @@
 use super::error::{ParseError, ParseResult};
 use super::frame::{Frame, PRELUDE_SIZE, parse_frame};
 use bytes::{Buf, BytesMut};
+use crate::metrics;
 
 /// Default maximum buffer size (16 MB)
 pub const DEFAULT_MAX_BUFFER_SIZE: usize = 16 * 1024 * 1024;
@@ -204,6 +205,7 @@ impl EventStreamDecoder {
             }
             Err(e) => {
                 self.error_count += 1;
+                metrics::record_decoder_error();
                 let error_msg = e.to_string();
 
                 // Check if exceeded maximum errors
@@ -221,7 +223,9 @@ impl EventStreamDecoder {
                 }
 
                 // Apply different recovery strategies based on error type
+                let skipped_before = self.bytes_skipped;
                 self.try_recover(&e);
+                metrics::record_decoder_skipped_bytes(self.bytes_skipped - skipped_before);
                 self.state = DecoderState::Recovering;
                 Err(e)
             }
@@ -378,11 +382,10 @@ impl<'a> Iterator for DecodeIter<'a> {
     type Item = ParseResult<Frame>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // If in Stopped or Recovering state, stop iteration
-        match self.decoder.state {
-            DecoderState::Stopped => return None,
-            DecoderState::Recovering => return None,
-            _ => {}
+        // If stopped, stop iteration. In Recovering state the corrupted bytes were already
+        // skipped, keep decoding so frames after a corrupted one in the same buffer are not lost
+        if self.decoder.state == DecoderState::Stopped {
+            return None;
         }
 
         match self.decoder.decode() {
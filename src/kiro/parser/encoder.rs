@@ -0,0 +1,310 @@
+//! AWS Event Stream frame encoding
+//!
+//! Inverse of `parse_frame`: builds frames with typed headers and writes them with the
+//! prelude and message CRC32s. `encode_event` writes decoded `Event` values back to bytes.
+//!
+//! The module is only built for tests: the proxy never produces event streams, and capture
+//! bundles store the raw upstream bytes rather than re-encoded events, so replay sees exactly
+//! what Kiro sent. It builds the frames served by the mock upstream and the upstream chunks
+//! fed to the parser, capture and replay tests.
+
+use serde::Serialize;
+
+use super::crc::crc32;
+use super::error::{EncodeError, EncodeResult};
+use super::frame::{Frame, MAX_MESSAGE_SIZE, PRELUDE_SIZE};
+use super::header::HeaderValue;
+use crate::kiro::model::events::Event;
+
+/// Frame builder
+///
+/// Headers are written in insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct FrameBuilder {
+    headers: Vec<(String, HeaderValue)>,
+    payload: Vec<u8>,
+}
+
+impl FrameBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder for an `event` message with a JSON payload
+    pub fn event(event_type: &str) -> Self {
+        Self::new()
+            .string_header(":message-type", "event")
+            .string_header(":event-type", event_type)
+            .string_header(":content-type", "application/json")
+    }
+
+    /// Add a header (a header with the same name is replaced)
+    pub fn header(mut self, name: impl Into<String>, value: HeaderValue) -> Self {
+        let name = name.into();
+        self.headers.retain(|(n, _)| *n != name);
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Add a string header
+    pub fn string_header(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.header(name, HeaderValue::String(value.into()))
+    }
+
+    /// Set raw payload
+    pub fn payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+
+    /// Set payload to the JSON serialization of `value`
+    pub fn json_payload<T: Serialize + ?Sized>(self, value: &T) -> EncodeResult<Self> {
+        let payload = serde_json::to_vec(value)?;
+        Ok(self.payload(payload))
+    }
+
+    /// Encode to bytes
+    pub fn encode(&self) -> EncodeResult<Vec<u8>> {
+        let header_bytes = encode_headers(
+            self.headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value)),
+        )?;
+        encode_message(&header_bytes, &self.payload)
+    }
+}
+
+/// Encode a frame to bytes
+///
+/// Headers are written sorted by name so the output is deterministic.
+pub fn encode_frame(frame: &Frame) -> EncodeResult<Vec<u8>> {
+    let mut headers: Vec<_> = frame.headers.iter().collect();
+    headers.sort_by(|a, b| a.0.cmp(b.0));
+    let header_bytes = encode_headers(headers.into_iter())?;
+    encode_message(&header_bytes, &frame.payload)
+}
+
+/// Build the frame for an event
+///
+/// `Unknown` carries no data and is written with an empty JSON object payload.
+pub fn event_frame(event: &Event) -> EncodeResult<FrameBuilder> {
+    let builder = match event {
+        Event::AssistantResponse(e) => {
+            FrameBuilder::event("assistantResponseEvent").json_payload(e)?
+        }
+        Event::ToolUse(e) => FrameBuilder::event("toolUseEvent").json_payload(e)?,
+        Event::ContextUsage(e) => FrameBuilder::event("contextUsageEvent").json_payload(e)?,
+        Event::Metering(e) => FrameBuilder::event("meteringEvent").json_payload(e)?,
+        Event::Unknown {} => FrameBuilder::event("unknown").payload(b"{}".to_vec()),
+        Event::Error {
+            error_code,
+            error_message,
+        } => FrameBuilder::new()
+            .string_header(":message-type", "error")
+            .string_header(":error-code", error_code.as_str())
+            .payload(error_message.as_bytes().to_vec()),
+        Event::Exception {
+            exception_type,
+            message,
+        } => FrameBuilder::new()
+            .string_header(":message-type", "exception")
+            .string_header(":exception-type", exception_type.as_str())
+            .string_header(":content-type", "application/json")
+            .payload(message.as_bytes().to_vec()),
+    };
+    Ok(builder)
+}
+
+/// Encode an event to bytes
+pub fn encode_event(event: &Event) -> EncodeResult<Vec<u8>> {
+    event_frame(event)?.encode()
+}
+
+/// Encode headers section
+fn encode_headers<'a>(
+    headers: impl Iterator<Item = (&'a str, &'a HeaderValue)>,
+) -> EncodeResult<Vec<u8>> {
+    let mut buf = Vec::new();
+
+    for (name, value) in headers {
+        if name.is_empty() || name.len() > u8::MAX as usize {
+            return Err(EncodeError::InvalidHeaderName(name.to_string()));
+        }
+        buf.push(name.len() as u8);
+        buf.extend_from_slice(name.as_bytes());
+        buf.push(value.value_type() as u8);
+
+        match value {
+            HeaderValue::Bool(_) => {}
+            HeaderValue::Byte(v) => buf.push(*v as u8),
+            HeaderValue::Short(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            HeaderValue::Integer(v) => buf.extend_from_slice(&v.to_be_bytes()),
+            HeaderValue::Long(v) | HeaderValue::Timestamp(v) => {
+                buf.extend_from_slice(&v.to_be_bytes())
+            }
+            HeaderValue::ByteArray(bytes) => write_variable(&mut buf, name, bytes)?,
+            HeaderValue::String(s) => write_variable(&mut buf, name, s.as_bytes())?,
+            HeaderValue::Uuid(uuid) => buf.extend_from_slice(uuid),
+        }
+    }
+
+    Ok(buf)
+}
+
+/// Write a length-prefixed (u16) header value
+fn write_variable(buf: &mut Vec<u8>, name: &str, bytes: &[u8]) -> EncodeResult<()> {
+    if bytes.len() > u16::MAX as usize {
+        return Err(EncodeError::HeaderValueTooLong {
+            name: name.to_string(),
+            length: bytes.len(),
+        });
+    }
+    buf.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Write prelude, headers, payload and both CRCs
+fn encode_message(header_bytes: &[u8], payload: &[u8]) -> EncodeResult<Vec<u8>> {
+    let total_length = PRELUDE_SIZE + header_bytes.len() + payload.len() + 4;
+    if total_length > MAX_MESSAGE_SIZE as usize {
+        return Err(EncodeError::MessageTooLarge {
+            length: total_length,
+            max: MAX_MESSAGE_SIZE,
+        });
+    }
+
+    let mut buf = Vec::with_capacity(total_length);
+    buf.extend_from_slice(&(total_length as u32).to_be_bytes());
+    buf.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+    let prelude_crc = crc32(&buf);
+    buf.extend_from_slice(&prelude_crc.to_be_bytes());
+    buf.extend_from_slice(header_bytes);
+    buf.extend_from_slice(payload);
+    let message_crc = crc32(&buf);
+    buf.extend_from_slice(&message_crc.to_be_bytes());
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::events::{ContextUsageEvent, ToolUseEvent};
+    use crate::kiro::parser::frame::parse_frame;
+
+    fn all_header_values() -> Vec<HeaderValue> {
+        vec![
+            HeaderValue::Bool(true),
+            HeaderValue::Bool(false),
+            HeaderValue::Byte(-5),
+            HeaderValue::Short(-1234),
+            HeaderValue::Integer(123_456),
+            HeaderValue::Long(-9_876_543_210),
+            HeaderValue::ByteArray(vec![0, 1, 2, 255]),
+            HeaderValue::String("hello".to_string()),
+            HeaderValue::Timestamp(1_700_000_000_000),
+            HeaderValue::Uuid([7u8; 16]),
+        ]
+    }
+
+    #[test]
+    fn test_all_header_types_round_trip() {
+        let mut builder = FrameBuilder::new();
+        for (i, value) in all_header_values().into_iter().enumerate() {
+            builder = builder.header(format!("h{}", i), value);
+        }
+        let bytes = builder.payload(b"payload".to_vec()).encode().unwrap();
+
+        let (frame, consumed) = parse_frame(&bytes).unwrap().unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(frame.payload, b"payload");
+        for (i, value) in all_header_values().into_iter().enumerate() {
+            assert_eq!(frame.headers.get(&format!("h{}", i)), Some(&value));
+        }
+    }
+
+    #[test]
+    fn test_encode_frame_round_trip_is_stable() {
+        let bytes = FrameBuilder::event("assistantResponseEvent")
+            .payload(br#"{"content":"hi"}"#.to_vec())
+            .encode()
+            .unwrap();
+        let (frame, _) = parse_frame(&bytes).unwrap().unwrap();
+
+        let first = encode_frame(&frame).unwrap();
+        let (reparsed, _) = parse_frame(&first).unwrap().unwrap();
+        assert_eq!(encode_frame(&reparsed).unwrap(), first);
+        assert_eq!(reparsed.event_type(), Some("assistantResponseEvent"));
+    }
+
+    #[test]
+    fn test_random_frames_round_trip() {
+        let mut rng = fastrand::Rng::with_seed(42);
+        for _ in 0..200 {
+            let values = all_header_values();
+            let mut builder = FrameBuilder::new();
+            let mut expected = Vec::new();
+            for i in 0..rng.usize(0..6) {
+                let value = values[rng.usize(..values.len())].clone();
+                expected.push((format!("n{}", i), value.clone()));
+                builder = builder.header(format!("n{}", i), value);
+            }
+            let payload: Vec<u8> = (0..rng.usize(0..256)).map(|_| rng.u8(..)).collect();
+            let bytes = builder.payload(payload.clone()).encode().unwrap();
+
+            let (frame, consumed) = parse_frame(&bytes).unwrap().unwrap();
+            assert_eq!(consumed, bytes.len());
+            assert_eq!(frame.payload, payload);
+            for (name, value) in &expected {
+                assert_eq!(frame.headers.get(name), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_round_trip() {
+        let events = vec![
+            Event::AssistantResponse(serde_json::from_str(r#"{"content":"Hello"}"#).unwrap()),
+            Event::ToolUse(ToolUseEvent {
+                name: "Read".to_string(),
+                tool_use_id: "tool-1".to_string(),
+                input: r#"{"path":"a"}"#.to_string(),
+                stop: true,
+            }),
+            Event::ContextUsage(ContextUsageEvent {
+                context_usage_percentage: 42.5,
+            }),
+            Event::Exception {
+                exception_type: "ContentLengthExceededException".to_string(),
+                message: "too long".to_string(),
+            },
+            Event::Error {
+                error_code: "InternalError".to_string(),
+                error_message: "boom".to_string(),
+            },
+        ];
+
+        for event in events {
+            let bytes = encode_event(&event).unwrap();
+            let (frame, _) = parse_frame(&bytes).unwrap().unwrap();
+            let decoded = Event::from_frame(frame).unwrap();
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", event));
+        }
+    }
+
+    #[test]
+    fn test_invalid_headers_rejected() {
+        let long_name = FrameBuilder::new().string_header("x".repeat(256), "v");
+        assert!(matches!(
+            long_name.encode(),
+            Err(EncodeError::InvalidHeaderName(_))
+        ));
+
+        let long_value = FrameBuilder::new().string_header("name", "v".repeat(70_000));
+        assert!(matches!(
+            long_value.encode(),
+            Err(EncodeError::HeaderValueTooLong { .. })
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-//! AWS Event Stream parsing error definitions
+//! AWS Event Stream parsing and encoding error definitions
 
 use std::fmt;
 
@@ -92,3 +92,49 @@ impl From<serde_json::Error> for ParseError {
 
 /// Parse result type
 pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Encode error types
+#[cfg(test)]
+#[derive(Debug)]
+pub enum EncodeError {
+    /// Header name is empty or longer than 255 bytes
+    InvalidHeaderName(String),
+    /// String or byte array header value longer than 65535 bytes
+    HeaderValueTooLong { name: String, length: usize },
+    /// Encoded message exceeds the maximum message size
+    MessageTooLarge { length: usize, max: u32 },
+    /// Payload serialization failed
+    PayloadSerialize(serde_json::Error),
+}
+
+#[cfg(test)]
+impl std::error::Error for EncodeError {}
+
+#[cfg(test)]
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeaderName(name) => {
+                write!(f, "Invalid header name (must be 1-255 bytes): {:?}", name)
+            }
+            Self::HeaderValueTooLong { name, length } => {
+                write!(f, "Header value too long: {} ({} bytes, max 65535)", name, length)
+            }
+            Self::MessageTooLarge { length, max } => {
+                write!(f, "Message length exceeds limit: {} bytes (max {})", length, max)
+            }
+            Self::PayloadSerialize(e) => write!(f, "Payload serialization failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+impl From<serde_json::Error> for EncodeError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::PayloadSerialize(e)
+    }
+}
+
+/// Encode result type
+#[cfg(test)]
+pub type EncodeResult<T> = Result<T, EncodeError>;
@@ -60,6 +60,23 @@ pub enum HeaderValue {
 }
 
 impl HeaderValue {
+    /// Wire type of this value
+    #[cfg(test)]
+    pub fn value_type(&self) -> HeaderValueType {
+        match self {
+            Self::Bool(true) => HeaderValueType::BoolTrue,
+            Self::Bool(false) => HeaderValueType::BoolFalse,
+            Self::Byte(_) => HeaderValueType::Byte,
+            Self::Short(_) => HeaderValueType::Short,
+            Self::Integer(_) => HeaderValueType::Integer,
+            Self::Long(_) => HeaderValueType::Long,
+            Self::ByteArray(_) => HeaderValueType::ByteArray,
+            Self::String(_) => HeaderValueType::String,
+            Self::Timestamp(_) => HeaderValueType::Timestamp,
+            Self::Uuid(_) => HeaderValueType::Uuid,
+        }
+    }
+
     /// Try to get string value
     pub fn as_str(&self) -> Option<&str> {
         match self {
@@ -93,6 +110,12 @@ impl Headers {
         self.inner.get(name)
     }
 
+    /// Iterate over all headers (in no particular order)
+    #[cfg(test)]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &HeaderValue)> {
+        self.inner.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
     /// Get string type header value
     pub fn get_string(&self, name: &str) -> Option<&str> {
         self.get(name).and_then(|v| v.as_str())
@@ -1,10 +1,13 @@
 //! AWS Event Stream parser
 //!
-//! Provides parsing support for the AWS Event Stream protocol,
+//! Provides parsing support for the AWS Event Stream protocol (plus a test-only encoder),
 //! used to handle streaming responses from the generateAssistantResponse endpoint
 
 pub mod crc;
 pub mod decoder;
+/// Test fixtures only (see the module docs)
+#[cfg(test)]
+pub mod encoder;
 pub mod error;
 pub mod frame;
 pub mod header;
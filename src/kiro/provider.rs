@@ -6,8 +6,9 @@
 
 use reqwest::Client;
 use reqwest::header::{AUTHORIZATION, CONNECTION, CONTENT_TYPE, HOST, HeaderMap, HeaderValue};
+use parking_lot::RwLock;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 use uuid::Uuid;
 
@@ -16,6 +17,15 @@ use crate::kiro::errors::enhance_kiro_error;
 use crate::kiro::machine_id;
 use crate::kiro::model::credentials::KiroCredentials;
 use crate::kiro::token_manager::{CallContext, MultiTokenManager};
+use crate::metrics;
+use crate::model::config::url_host;
+
+/// Successful upstream response and the credential that served it
+pub struct ApiResponse {
+    pub response: reqwest::Response,
+    /// Credential ID (from CallContext)
+    pub credential_id: u64,
+}
 
 /// Maximum retries per credential
 const MAX_RETRIES_PER_CREDENTIAL: usize = 3;
@@ -57,13 +67,44 @@ fn extract_model_from_request(request_body: &str) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Extract conversation ID from Kiro API request body
+///
+/// Looks for conversationState.conversationId
+fn extract_conversation_id_from_request(request_body: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(request_body).ok()?;
+    json.get("conversationState")?
+        .get("conversationId")?
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+/// Inject the credential's profileArn into a Kiro API request body
+///
+/// Returns the body unchanged when there is no profileArn or the body is not a JSON object.
+fn inject_profile_arn(request_body: &str, profile_arn: Option<&str>) -> String {
+    let Some(profile_arn) = profile_arn else {
+        return request_body.to_string();
+    };
+    match serde_json::from_str::<serde_json::Value>(request_body) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert(
+                "profileArn".to_string(),
+                serde_json::Value::String(profile_arn.to_string()),
+            );
+            serde_json::Value::Object(map).to_string()
+        }
+        _ => request_body.to_string(),
+    }
+}
+
 /// Kiro API Provider
 ///
 /// Core component responsible for communicating with the Kiro API
 /// Supports multi-credential failover and retry mechanism
 pub struct KiroProvider {
     token_manager: Arc<MultiTokenManager>,
-    client: Client,
+    /// HTTP client and the proxy it was built with (rebuilt when the proxy is hot reloaded)
+    client: RwLock<(Option<ProxyConfig>, Client)>,
 }
 
 impl KiroProvider {
@@ -79,7 +120,31 @@ impl KiroProvider {
 
         Self {
             token_manager,
-            client,
+            client: RwLock::new((proxy, client)),
+        }
+    }
+
+    /// Get the HTTP client for the token manager's current proxy
+    fn client(&self) -> Client {
+        let proxy = self.token_manager.proxy();
+        {
+            let current = self.client.read();
+            if current.0 == proxy {
+                return current.1.clone();
+            }
+        }
+
+        let tls_backend = self.token_manager.config().tls_backend;
+        match build_client(proxy.as_ref(), 720, tls_backend) {
+            Ok(client) => {
+                tracing::info!("Proxy changed, rebuilt Kiro API HTTP client");
+                *self.client.write() = (proxy, client.clone());
+                client
+            }
+            Err(e) => {
+                tracing::warn!("Failed to rebuild HTTP client for new proxy, keeping previous: {}", e);
+                self.client.read().1.clone()
+            }
         }
     }
 
@@ -90,47 +155,44 @@ impl KiroProvider {
 
     /// Get API base URL (using config-level api_region)
     pub fn base_url(&self) -> String {
+        let config = self.token_manager.config();
         format!(
-            "https://q.{}.amazonaws.com/generateAssistantResponse",
-            self.token_manager.config().effective_api_region()
+            "{}/generateAssistantResponse",
+            config.api_base_url(config.effective_api_region())
         )
     }
 
     /// Get MCP API URL (using config-level api_region)
     pub fn mcp_url(&self) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/mcp",
-            self.token_manager.config().effective_api_region()
-        )
+        let config = self.token_manager.config();
+        format!("{}/mcp", config.api_base_url(config.effective_api_region()))
     }
 
     /// Get API base domain (using config-level api_region)
     pub fn base_domain(&self) -> String {
-        format!("q.{}.amazonaws.com", self.token_manager.config().effective_api_region())
+        let config = self.token_manager.config();
+        url_host(&config.api_base_url(config.effective_api_region())).to_string()
+    }
+
+    /// Get credential-level API endpoint (`apiEndpoint` override or region-based)
+    fn api_base_url_for(&self, credentials: &KiroCredentials) -> String {
+        let config = self.token_manager.config();
+        config.api_base_url(credentials.effective_api_region(&config))
     }
 
     /// Get credential-level API base URL
     fn base_url_for(&self, credentials: &KiroCredentials) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/generateAssistantResponse",
-            credentials.effective_api_region(self.token_manager.config())
-        )
+        format!("{}/generateAssistantResponse", self.api_base_url_for(credentials))
     }
 
     /// Get credential-level MCP API URL
     fn mcp_url_for(&self, credentials: &KiroCredentials) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/mcp",
-            credentials.effective_api_region(self.token_manager.config())
-        )
+        format!("{}/mcp", self.api_base_url_for(credentials))
     }
 
     /// Get credential-level API base domain
     fn base_domain_for(&self, credentials: &KiroCredentials) -> String {
-        format!(
-            "q.{}.amazonaws.com",
-            credentials.effective_api_region(self.token_manager.config())
-        )
+        url_host(&self.api_base_url_for(credentials)).to_string()
     }
 
     /// Build request headers
@@ -140,7 +202,7 @@ impl KiroProvider {
     fn build_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
         let config = self.token_manager.config();
 
-        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
+        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, &config)
             .ok_or_else(|| anyhow::anyhow!("Failed to generate machine_id, please check credential configuration"))?;
 
         let kiro_version = &config.kiro_version;
@@ -192,7 +254,7 @@ impl KiroProvider {
     fn build_mcp_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
         let config = self.token_manager.config();
 
-        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
+        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, &config)
             .ok_or_else(|| anyhow::anyhow!("Failed to generate machine_id, please check credential configuration"))?;
 
         let kiro_version = &config.kiro_version;
@@ -245,9 +307,9 @@ impl KiroProvider {
     /// * `request_body` - JSON formatted request body string
     ///
     /// # Returns
-    /// Returns raw HTTP Response without parsing
-    pub async fn call_api(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, false).await
+    /// Returns raw HTTP Response without parsing and the serving credential
+    pub async fn call_api(&self, request_body: &str) -> anyhow::Result<ApiResponse> {
+        self.call_api_with_retry(request_body, false, None).await
     }
 
     /// Send streaming API request
@@ -262,9 +324,22 @@ impl KiroProvider {
     /// * `request_body` - JSON formatted request body string
     ///
     /// # Returns
-    /// Returns raw HTTP Response, caller is responsible for handling streaming data
-    pub async fn call_api_stream(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, true).await
+    /// Returns raw HTTP Response and the serving credential, caller is responsible for handling streaming data.
+    /// The attempt is not reported as a success yet: the caller reports it once the stream produced
+    /// content (see `report_success`), since it can still fail before that
+    pub async fn call_api_stream(&self, request_body: &str) -> anyhow::Result<ApiResponse> {
+        self.call_api_with_retry(request_body, true, None).await
+    }
+
+    /// Send a streaming API request again after the response of `failed_id` broke before any content
+    ///
+    /// Same failover as `call_api_stream`, but another credential is used when one is available.
+    pub async fn retry_api_stream(
+        &self,
+        request_body: &str,
+        failed_id: u64,
+    ) -> anyhow::Result<ApiResponse> {
+        self.call_api_with_retry(request_body, true, Some(failed_id)).await
     }
 
     /// Send MCP API request
@@ -288,7 +363,7 @@ impl KiroProvider {
 
         for attempt in 0..max_retries {
             // Get call context (MCP doesn't need model filtering)
-            let ctx = match self.token_manager.acquire_context(None).await {
+            let ctx = match self.token_manager.acquire_context(None, None).await {
                 Ok(c) => c,
                 Err(e) => {
                     last_error = Some(e);
@@ -307,7 +382,7 @@ impl KiroProvider {
 
             // Send request
             let response = match self
-                .client
+                .client()
                 .post(&url)
                 .headers(headers)
                 .body(request_body.to_string())
@@ -413,11 +488,28 @@ impl KiroProvider {
     /// - Each credential retries up to MAX_RETRIES_PER_CREDENTIAL times
     /// - Total retries = min(credential count × retries per credential, MAX_TOTAL_RETRIES)
     /// - Hard limit of 9 times to prevent infinite retries
+    /// - `avoid` is a credential to move away from, used only when no other is available
     async fn call_api_with_retry(
         &self,
         request_body: &str,
         is_stream: bool,
-    ) -> anyhow::Result<reqwest::Response> {
+        avoid: Option<u64>,
+    ) -> anyhow::Result<ApiResponse> {
+        let api_type = if is_stream { "streaming" } else { "non-streaming" };
+        let started_at = Instant::now();
+        let result = self.call_api_attempts(request_body, is_stream, avoid).await;
+        let outcome = if result.is_ok() { "success" } else { "error" };
+        metrics::observe_upstream_duration(api_type, outcome, started_at.elapsed());
+        result
+    }
+
+    /// Retry loop of `call_api_with_retry`
+    async fn call_api_attempts(
+        &self,
+        request_body: &str,
+        is_stream: bool,
+        avoid: Option<u64>,
+    ) -> anyhow::Result<ApiResponse> {
         let total_credentials = self.token_manager.total_count();
         let max_retries = (total_credentials * MAX_RETRIES_PER_CREDENTIAL).min(MAX_TOTAL_RETRIES);
         let mut last_error: Option<anyhow::Error> = None;
@@ -425,10 +517,15 @@ impl KiroProvider {
 
         // Extract model from request for credential filtering
         let model = extract_model_from_request(request_body);
+        // Conversation ID for session affinity
+        let conversation_id = extract_conversation_id_from_request(request_body);
 
         for attempt in 0..max_retries {
             // Get call context (binds index, credentials, token)
-            let ctx = match self.token_manager.acquire_context(model.as_deref()).await {
+            let ctx = match self
+                .token_manager
+                .acquire_context_avoiding(model.as_deref(), conversation_id.as_deref(), avoid)
+                .await {
                 Ok(c) => c,
                 Err(e) => {
                     last_error = Some(e);
@@ -445,12 +542,17 @@ impl KiroProvider {
                 }
             };
 
+            // profileArn belongs to the selected credential, not the first one
+            let profile_arn = self.token_manager.resolve_profile_arn(&ctx).await;
+            let body = inject_profile_arn(request_body, profile_arn.as_deref());
+
             // Send request
+            let attempt_started_at = Instant::now();
             let response = match self
-                .client
+                .client()
                 .post(&url)
                 .headers(headers)
-                .body(request_body.to_string())
+                .body(body)
                 .send()
                 .await
             {
@@ -462,6 +564,7 @@ impl KiroProvider {
                         max_retries,
                         e
                     );
+                    metrics::record_upstream_retry("network");
                     // Network errors are usually upstream/link transient issues, should not cause "disable credential" or "switch credential"
                     // (Otherwise network jitter would mistakenly disable all credentials, requiring restart to recover)
                     last_error = Some(e.into());
@@ -482,8 +585,15 @@ impl KiroProvider {
                     credential_email = credential_info,
                     "API request succeeded"
                 );
-                self.token_manager.report_success(ctx.id);
-                return Ok(response);
+                metrics::observe_upstream_ttfb(api_type, attempt_started_at.elapsed());
+                // Streaming responses are reported by the caller once content arrived
+                if !is_stream {
+                    self.token_manager.report_success(ctx.id);
+                }
+                return Ok(ApiResponse {
+                    response,
+                    credential_id: ctx.id,
+                });
             }
 
             // Failed response: read body for logging/error messages
@@ -498,6 +608,7 @@ impl KiroProvider {
                     status,
                     body
                 );
+                metrics::record_upstream_retry("quota_exhausted");
 
                 let has_available = self.token_manager.report_quota_exhausted(ctx.id);
                 if !has_available {
@@ -540,6 +651,7 @@ impl KiroProvider {
                     status,
                     body
                 );
+                metrics::record_upstream_retry("auth_error");
 
                 let has_available = self.token_manager.report_failure(ctx.id);
                 if !has_available {
@@ -570,6 +682,7 @@ impl KiroProvider {
                     status,
                     body
                 );
+                metrics::record_upstream_retry("transient");
                 last_error = Some(anyhow::anyhow!(
                     "{} API request failed: {} {}",
                     api_type,
@@ -596,6 +709,7 @@ impl KiroProvider {
                 status,
                 body
             );
+            metrics::record_upstream_retry("other");
             last_error = Some(anyhow::anyhow!(
                 "{} API request failed: {} {}",
                 api_type,
@@ -681,6 +795,19 @@ mod tests {
         assert_eq!(provider.base_domain(), "q.us-east-1.amazonaws.com");
     }
 
+    #[test]
+    fn test_api_endpoint_override() {
+        let mut config = Config::default();
+        config.api_endpoint = Some("http://127.0.0.1:9000/".to_string());
+        let provider = create_test_provider(config, KiroCredentials::default());
+        assert_eq!(
+            provider.base_url(),
+            "http://127.0.0.1:9000/generateAssistantResponse"
+        );
+        assert_eq!(provider.mcp_url(), "http://127.0.0.1:9000/mcp");
+        assert_eq!(provider.base_domain(), "127.0.0.1:9000");
+    }
+
     #[test]
     fn test_build_headers() {
         let mut config = Config::default();
@@ -713,6 +840,48 @@ mod tests {
         assert_eq!(headers.get(CONNECTION).unwrap(), "close");
     }
 
+    #[test]
+    fn test_inject_profile_arn_sets_field() {
+        let body = r#"{"conversationState":{"conversationId":"c1"}}"#;
+        let injected = inject_profile_arn(body, Some("arn:aws:codewhisperer:us-east-1:1:profile/B"));
+        let json: serde_json::Value = serde_json::from_str(&injected).unwrap();
+        assert_eq!(json["profileArn"], "arn:aws:codewhisperer:us-east-1:1:profile/B");
+        assert_eq!(json["conversationState"]["conversationId"], "c1");
+    }
+
+    #[test]
+    fn test_inject_profile_arn_overrides_existing() {
+        let body = r#"{"conversationState":{},"profileArn":"arn:first"}"#;
+        let injected = inject_profile_arn(body, Some("arn:second"));
+        let json: serde_json::Value = serde_json::from_str(&injected).unwrap();
+        assert_eq!(json["profileArn"], "arn:second");
+    }
+
+    #[test]
+    fn test_inject_profile_arn_none_keeps_body() {
+        let body = r#"{"conversationState":{}}"#;
+        assert_eq!(inject_profile_arn(body, None), body);
+        assert_eq!(inject_profile_arn("not json", Some("arn:x")), "not json");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_profile_arn_uses_context_credentials() {
+        let credentials = KiroCredentials {
+            profile_arn: Some("arn:aws:sso::123456789:profile/test".to_string()),
+            ..Default::default()
+        };
+        let provider = create_test_provider(Config::default(), credentials.clone());
+        let ctx = CallContext {
+            id: 1,
+            credentials,
+            token: "test_token".to_string(),
+        };
+        assert_eq!(
+            provider.token_manager().resolve_profile_arn(&ctx).await.as_deref(),
+            Some("arn:aws:sso::123456789:profile/test")
+        );
+    }
+
     #[test]
     fn test_is_monthly_request_limit_detects_reason() {
         let body = r#"{"message":"You have reached the limit.","reason":"MONTHLY_REQUEST_COUNT"}"#;
@@ -5,25 +5,31 @@
 
 use anyhow::bail;
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
-use chrono::{DateTime, Duration, Utc};
-use parking_lot::Mutex;
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tokio::sync::Mutex as TokioMutex;
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration as StdDuration, Instant};
 
+use crate::common::encryption;
 use crate::http_client::{ProxyConfig, build_client};
 use crate::kiro::machine_id;
-use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::model::credentials::{CredentialSource, KiroCredentials};
 use crate::kiro::model::token_refresh::{
     IdcRefreshRequest, IdcRefreshResponse, RefreshRequest, RefreshResponse,
 };
 use crate::kiro::model::usage_limits::UsageLimitsResponse;
-use crate::model::config::Config;
+use crate::metrics;
+use crate::model::catalog::{self, ModelTier};
+use crate::model::config::{Config, SharedConfig, url_host};
+use crate::oauth::SsoOidcClient;
+use crate::reload::{FileVersion, file_version};
 
 /// JWT claims structure for extracting email
 #[derive(Debug, Deserialize)]
@@ -151,7 +157,7 @@ pub(crate) fn is_token_expiring_soon(credentials: &KiroCredentials) -> bool {
     is_token_expiring_within(credentials, 10).unwrap_or(false)
 }
 
-fn sha256_hex(input: &str) -> String {
+pub(crate) fn sha256_hex(input: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(input.as_bytes());
     let result = hasher.finalize();
@@ -199,14 +205,16 @@ pub(crate) async fn refresh_token(
         }
     });
 
-    if auth_method.eq_ignore_ascii_case("idc")
+    let result = if auth_method.eq_ignore_ascii_case("idc")
         || auth_method.eq_ignore_ascii_case("builder-id")
         || auth_method.eq_ignore_ascii_case("iam")
     {
         refresh_idc_token(credentials, config, proxy).await
     } else {
         refresh_social_token(credentials, config, proxy).await
-    }
+    };
+    metrics::record_token_refresh(result.is_ok());
+    result
 }
 
 /// Refresh Social Token
@@ -221,8 +229,9 @@ async fn refresh_social_token(
     // Priority: credential.auth_region > credential.region > config.auth_region > config.region
     let region = credentials.effective_auth_region(config);
 
-    let refresh_url = format!("https://prod.{}.auth.desktop.kiro.dev/refreshToken", region);
-    let refresh_domain = format!("prod.{}.auth.desktop.kiro.dev", region);
+    let auth_base_url = config.auth_base_url(region);
+    let refresh_url = format!("{}/refreshToken", auth_base_url);
+    let refresh_domain = url_host(&auth_base_url);
     let machine_id = machine_id::generate_from_credentials(credentials, config)
         .ok_or_else(|| anyhow::anyhow!("Unable to generate machineId"))?;
     let kiro_version = &config.kiro_version;
@@ -241,7 +250,7 @@ async fn refresh_social_token(
             format!("KiroIDE-{}-{}", kiro_version, machine_id),
         )
         .header("Accept-Encoding", "gzip, compress, deflate, br")
-        .header("host", &refresh_domain)
+        .header("host", refresh_domain)
         .header("Connection", "close")
         .json(&body)
         .send()
@@ -312,7 +321,8 @@ async fn refresh_idc_token(
 
     // Priority: credential.auth_region > credential.region > config.auth_region > config.region
     let region = credentials.effective_auth_region(config);
-    let refresh_url = format!("https://oidc.{}.amazonaws.com/token", region);
+    let oidc_base_url = config.oidc_base_url(region);
+    let refresh_url = format!("{}/token", oidc_base_url);
 
     let client = build_client(proxy, 60, config.tls_backend)?;
     let body = IdcRefreshRequest {
@@ -325,7 +335,7 @@ async fn refresh_idc_token(
     let response = client
         .post(&refresh_url)
         .header("Content-Type", "application/json")
-        .header("Host", format!("oidc.{}.amazonaws.com", region))
+        .header("Host", url_host(&oidc_base_url))
         .header("Connection", "keep-alive")
         .header("x-amz-user-agent", IDC_AMZ_USER_AGENT)
         .header("Accept", "*/*")
@@ -389,15 +399,16 @@ pub(crate) async fn get_usage_limits(
 
     // Priority: credential.api_region > config.api_region > config.region
     let region = credentials.effective_api_region(config);
-    let host = format!("q.{}.amazonaws.com", region);
+    let api_base_url = config.api_base_url(region);
+    let host = url_host(&api_base_url);
     let machine_id = machine_id::generate_from_credentials(credentials, config)
         .ok_or_else(|| anyhow::anyhow!("Unable to generate machineId"))?;
     let kiro_version = &config.kiro_version;
 
     // Build URL
     let mut url = format!(
-        "https://{}/getUsageLimits?isEmailRequired=true&origin=AI_EDITOR&resourceType=AGENTIC_REQUEST",
-        host
+        "{}/getUsageLimits?isEmailRequired=true&origin=AI_EDITOR&resourceType=AGENTIC_REQUEST",
+        api_base_url
     );
 
     // profileArn is optional
@@ -422,7 +433,7 @@ pub(crate) async fn get_usage_limits(
         .get(&url)
         .header("x-amz-user-agent", &amz_user_agent)
         .header("User-Agent", &user_agent)
-        .header("host", &host)
+        .header("host", host)
         .header("amz-sdk-invocation-id", uuid::Uuid::new_v4().to_string())
         .header("amz-sdk-request", "attempt=1; max=1")
         .header("Authorization", format!("Bearer {}", token))
@@ -463,6 +474,16 @@ pub(crate) async fn get_usage_limits(
 // Multi-credential Token Manager
 // ============================================================================
 
+/// Whether a credential may serve `model` in the given load balancing mode
+///
+/// In balanced and quota mode, FREE tier accounts don't serve models the catalog marks as paid
+fn can_serve_model(credentials: &KiroCredentials, mode: &str, model: Option<&str>) -> bool {
+    let requires_paid = model
+        .and_then(|m| catalog::current().resolve(m).map(|e| e.tier == ModelTier::Paid))
+        .unwrap_or(false);
+    mode == "priority" || !requires_paid || credentials.supports_paid_models()
+}
+
 /// Single credential entry state
 struct CredentialEntry {
     /// Credential unique ID
@@ -477,8 +498,278 @@ struct CredentialEntry {
     disabled_reason: Option<DisabledReason>,
     /// API call success count
     success_count: u64,
+    /// Streaming responses abandoned because the client disconnected
+    cancelled_count: u64,
+    /// Credits consumed, as reported by Kiro meteringEvents
+    credits_used: f64,
     /// Last API call time (RFC3339 format)
     last_used_at: Option<String>,
+    /// Whether a lazy profileArn lookup has already been attempted (avoids repeating failed lookups)
+    profile_arn_lookup_attempted: bool,
+    /// Allowance from the last usage query (quota mode)
+    usage: Option<UsageInfo>,
+    /// When a quota-disabled credential is next probed for recovery
+    quota_reset: Option<QuotaReset>,
+    /// Recent disable/enable transitions (oldest first)
+    history: VecDeque<CredentialEvent>,
+    /// Outcome of recent token refreshes
+    refresh_health: RefreshHealth,
+    /// Earliest time of the next background refresh attempt (backoff after failures)
+    refresh_retry_at: Option<DateTime<Utc>>,
+    /// Refresh tokens this process rotated away from (oldest first), to spot stale files on reload
+    rotated_refresh_tokens: Vec<String>,
+}
+
+/// Token refresh health of a credential
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshHealth {
+    /// Last successful refresh (RFC3339 format)
+    pub last_refresh_at: Option<String>,
+    /// Consecutive failed refreshes
+    pub consecutive_failures: u32,
+    /// Error of the last failed refresh (cleared on success)
+    pub last_error: Option<String>,
+    /// Next background refresh attempt while backing off (RFC3339 format)
+    pub next_retry_at: Option<String>,
+}
+
+/// Write a credentials file (encrypted if enabled)
+///
+/// Uses block_in_place in Tokio runtime to avoid blocking worker
+fn write_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        tokio::task::block_in_place(|| encryption::write(path, contents))
+    } else {
+        encryption::write(path, contents)
+    }
+}
+
+/// Backoff before the next background refresh after `failures` consecutive failures
+fn refresh_backoff(failures: u32) -> Duration {
+    let minutes = 1i64 << failures.saturating_sub(1).min(5);
+    Duration::minutes(minutes.min(REFRESH_BACKOFF_MAX_MINUTES))
+}
+
+/// Recovery schedule of a quota-disabled credential
+#[derive(Debug, Clone, Copy)]
+struct QuotaReset {
+    /// Reset date (probe after this)
+    reset_at: DateTime<Utc>,
+    /// Whether `reset_at` comes from a usage query (otherwise it is the fallback date)
+    confirmed: bool,
+}
+
+/// Credential state transition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialEvent {
+    /// Time of the transition (RFC3339 format)
+    pub timestamp: String,
+    /// "disabled" or "enabled"
+    pub event: String,
+    /// Why: "manual", "too_many_failures", "quota_exceeded", "quota_reset", "self_healing" or
+    /// "new_refresh_token"
+    pub reason: String,
+    /// Additional information (e.g. the scheduled quota reset date)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// First day of the month after `now` (UTC), the fallback quota reset date
+fn first_day_of_next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now + Duration::days(30))
+}
+
+/// Convert a usage API timestamp (Unix seconds) to a date
+fn reset_date(timestamp: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp as i64, 0)
+}
+
+/// Allowance from a getUsageLimits query
+#[derive(Debug, Clone, Copy)]
+struct UsageInfo {
+    usage_limit: f64,
+    current_usage: f64,
+    /// Next reset date (Unix timestamp)
+    next_reset_at: Option<f64>,
+}
+
+impl UsageInfo {
+    fn from_response(usage: &UsageLimitsResponse) -> Self {
+        Self {
+            usage_limit: usage.usage_limit(),
+            current_usage: usage.current_usage(),
+            next_reset_at: usage.next_reset_at(),
+        }
+    }
+
+    fn remaining(&self) -> f64 {
+        (self.usage_limit - self.current_usage).max(0.0)
+    }
+
+    /// Whether the remaining allowance is below `QUOTA_LOW_WATERMARK` of the limit
+    fn is_low(&self) -> bool {
+        self.remaining() <= self.usage_limit * QUOTA_LOW_WATERMARK
+    }
+}
+
+/// Ordering for quota mode: credentials with usage data and allowance to spare first, then
+/// credentials without usage data, then credentials close to their limit. Within a group the
+/// most remaining allowance wins, ties go to priority.
+fn compare_by_quota(a: &CredentialEntry, b: &CredentialEntry) -> std::cmp::Ordering {
+    let group = |e: &CredentialEntry| match e.usage {
+        Some(usage) if !usage.is_low() => 0,
+        None => 1,
+        Some(_) => 2,
+    };
+    let remaining = |e: &CredentialEntry| e.usage.map(|u| u.remaining()).unwrap_or(0.0);
+
+    group(a)
+        .cmp(&group(b))
+        .then_with(|| remaining(b).total_cmp(&remaining(a)))
+        .then_with(|| a.credentials.priority.cmp(&b.credentials.priority))
+}
+
+impl CredentialEntry {
+    /// New entry with fresh runtime state
+    fn new(id: u64, credentials: KiroCredentials) -> Self {
+        Self {
+            id,
+            credentials,
+            failure_count: 0,
+            disabled: false,
+            disabled_reason: None,
+            success_count: 0,
+            cancelled_count: 0,
+            credits_used: 0.0,
+            last_used_at: None,
+            profile_arn_lookup_attempted: false,
+            usage: None,
+            quota_reset: None,
+            history: VecDeque::new(),
+            refresh_health: RefreshHealth::default(),
+            refresh_retry_at: None,
+            rotated_refresh_tokens: Vec::new(),
+        }
+    }
+
+    /// Replace the credentials with refreshed ones, remembering a rotated refresh token
+    fn set_refreshed(&mut self, credentials: KiroCredentials) {
+        if credentials.refresh_token != self.credentials.refresh_token
+            && let Some(old) = self.credentials.refresh_token.take()
+        {
+            if self.rotated_refresh_tokens.len() >= MAX_ROTATED_REFRESH_TOKENS {
+                self.rotated_refresh_tokens.remove(0);
+            }
+            self.rotated_refresh_tokens.push(old);
+        }
+        self.credentials = credentials;
+    }
+
+    /// Whether `token` is a refresh token this process already rotated away from
+    fn is_rotated_refresh_token(&self, token: Option<&str>) -> bool {
+        token.is_some_and(|token| self.rotated_refresh_tokens.iter().any(|t| t == token))
+    }
+
+    /// Record the outcome of a token refresh
+    fn record_refresh(&mut self, result: Result<(), String>) {
+        let now = Utc::now();
+        match result {
+            Ok(()) => {
+                self.refresh_health = RefreshHealth {
+                    last_refresh_at: Some(now.to_rfc3339()),
+                    ..Default::default()
+                };
+                self.refresh_retry_at = None;
+            }
+            Err(error) => {
+                let health = &mut self.refresh_health;
+                health.consecutive_failures += 1;
+                health.last_error = Some(error);
+                let retry_at = now + refresh_backoff(health.consecutive_failures);
+                health.next_retry_at = Some(retry_at.to_rfc3339());
+                self.refresh_retry_at = Some(retry_at);
+            }
+        }
+    }
+
+    /// Disable with a reason and record the transition
+    fn disable(&mut self, reason: DisabledReason, detail: Option<String>) {
+        self.disabled = true;
+        self.disabled_reason = Some(reason);
+        self.record_event("disabled", reason.as_str(), detail);
+    }
+
+    /// Re-enable with failure count reset, recording the transition if it was disabled
+    fn enable(&mut self, reason: &str) {
+        if self.disabled {
+            self.record_event("enabled", reason, None);
+        }
+        self.disabled = false;
+        self.disabled_reason = None;
+        self.failure_count = 0;
+        self.quota_reset = None;
+    }
+
+    fn record_event(&mut self, event: &str, reason: &str, detail: Option<String>) {
+        if self.history.len() >= MAX_HISTORY_PER_CREDENTIAL {
+            self.history.pop_front();
+        }
+        self.history.push_back(CredentialEvent {
+            timestamp: Utc::now().to_rfc3339(),
+            event: event.to_string(),
+            reason: reason.to_string(),
+            detail,
+        });
+    }
+
+    /// Apply a credential re-read from the credentials file (hot reload)
+    ///
+    /// Statistics are kept. While the refresh token is unchanged the live access token is at
+    /// least as fresh as the file's, so it is kept too; a new refresh token resets failures.
+    /// Fields filled in at runtime are kept when the file leaves them unset.
+    ///
+    /// Returns whether the credential changed.
+    fn apply_reloaded(&mut self, mut cred: KiroCredentials) -> bool {
+        let live = &self.credentials;
+        if cred.refresh_token == live.refresh_token {
+            cred.access_token = live.access_token.clone();
+            cred.expires_at = live.expires_at.clone();
+        }
+        if cred.profile_arn.is_none() {
+            cred.profile_arn = live.profile_arn.clone();
+        }
+        if cred.machine_id.is_none() {
+            cred.machine_id = live.machine_id.clone();
+        }
+        if cred.email.is_none() {
+            cred.email = live.email.clone();
+        }
+        if cred.subscription_title.is_none() {
+            cred.subscription_title = live.subscription_title.clone();
+        }
+
+        if cred == *live {
+            return false;
+        }
+        if cred.refresh_token != live.refresh_token {
+            self.enable("new_refresh_token");
+            self.profile_arn_lookup_attempted = false;
+            self.usage = None;
+            self.refresh_health = RefreshHealth::default();
+            self.refresh_retry_at = None;
+        }
+        self.credentials = cred;
+        true
+    }
 }
 
 /// Disabled reason
@@ -492,11 +783,27 @@ enum DisabledReason {
     QuotaExceeded,
 }
 
+impl DisabledReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DisabledReason::Manual => "manual",
+            DisabledReason::TooManyFailures => "too_many_failures",
+            DisabledReason::QuotaExceeded => "quota_exceeded",
+        }
+    }
+}
+
 /// Statistics persistence entry
 #[derive(Serialize, Deserialize)]
 struct StatsEntry {
     success_count: u64,
+    #[serde(default)]
+    cancelled_count: u64,
+    #[serde(default)]
+    credits_used: f64,
     last_used_at: Option<String>,
+    #[serde(default)]
+    history: Vec<CredentialEvent>,
 }
 
 // ============================================================================
@@ -527,8 +834,22 @@ pub struct CredentialEntrySnapshot {
     pub email: Option<String>,
     /// API call success count
     pub success_count: u64,
+    /// Streaming responses abandoned because the client disconnected
+    pub cancelled_count: u64,
+    /// Credits consumed, as reported by Kiro meteringEvents
+    pub credits_used: f64,
     /// Last API call time (RFC3339 format)
     pub last_used_at: Option<String>,
+    /// Conversations currently pinned to this credential (session affinity)
+    pub affinity_sessions: usize,
+    /// When a quota-disabled credential is next probed for recovery (RFC3339 format)
+    pub quota_reset_at: Option<String>,
+    /// Token refresh health
+    pub refresh_health: RefreshHealth,
+    /// Source the credential was loaded from ("file", "dir:<path>" or "env:<index>")
+    pub source: String,
+    /// Whether the source is read-only (changes are not written back)
+    pub read_only: bool,
 }
 
 /// Credential manager state snapshot
@@ -545,13 +866,34 @@ pub struct ManagerSnapshot {
     pub available: usize,
 }
 
+/// Result of reloading the credentials file
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialsReload {
+    /// IDs of credentials added from the file
+    pub added: Vec<u64>,
+    /// IDs of credentials whose file entry changed
+    pub updated: Vec<u64>,
+    /// IDs of credentials no longer in the file
+    pub removed: Vec<u64>,
+}
+
+impl CredentialsReload {
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
 /// Multi-credential Token manager
 ///
 /// Supports management of multiple credentials, implements fixed priority + failover strategy
 /// Failure statistics based on API call results, not Token refresh results
 pub struct MultiTokenManager {
-    config: Config,
-    proxy: Option<ProxyConfig>,
+    /// Application config (shared with the API state, swapped on hot reload)
+    config: SharedConfig,
+    /// Proxy configuration (swapped on hot reload)
+    proxy: RwLock<Option<ProxyConfig>>,
     /// Credential entry list
     entries: Mutex<Vec<CredentialEntry>>,
     /// Current active credential ID
@@ -568,12 +910,51 @@ pub struct MultiTokenManager {
     last_stats_save_at: Mutex<Option<Instant>>,
     /// Whether statistics data has unsaved updates
     stats_dirty: AtomicBool,
+    /// Conversation ID -> pinned credential (session affinity)
+    affinity: Mutex<HashMap<String, SessionPin>>,
+    /// Versions of the credential files this process last wrote (ignored by the reload watcher)
+    own_writes: Mutex<HashMap<PathBuf, FileVersion>>,
+}
+
+/// Credential a conversation is pinned to
+struct SessionPin {
+    credential_id: u64,
+    last_seen: Instant,
 }
 
+/// Load balancing modes accepted by `set_load_balancing_mode` and the config file
+pub const LOAD_BALANCING_MODES: &[&str] = &["priority", "balanced", "quota"];
+
 /// Maximum API call failures per credential
 const MAX_FAILURES_PER_CREDENTIAL: u32 = 3;
+/// Statistics cache file name (in the credentials directory)
+pub const STATS_FILE_NAME: &str = "kiro_stats.json";
+
 /// Statistics persistence debounce interval
 const STATS_SAVE_DEBOUNCE: StdDuration = StdDuration::from_secs(30);
+/// Maximum number of pinned conversations (oldest are evicted beyond this)
+const MAX_AFFINITY_SESSIONS: usize = 10_000;
+/// Interval between background usage queries (quota mode)
+const USAGE_REFRESH_INTERVAL: StdDuration = StdDuration::from_secs(300);
+/// Interval between checks for quota-disabled credentials due for a recovery probe
+const QUOTA_RECOVERY_INTERVAL: StdDuration = StdDuration::from_secs(60);
+/// Delay before probing again when a credential has no allowance after its reset date
+const QUOTA_PROBE_RETRY_MINUTES: i64 = 60;
+/// Number of transitions kept per credential
+const MAX_HISTORY_PER_CREDENTIAL: usize = 50;
+/// Number of rotated refresh tokens remembered per credential
+const MAX_ROTATED_REFRESH_TOKENS: usize = 16;
+/// Interval between background checks for tokens to refresh
+const PROACTIVE_REFRESH_INTERVAL: StdDuration = StdDuration::from_secs(60);
+/// Tokens expiring within this many minutes are refreshed in the background
+/// (before requests would refresh them lazily at 10 minutes)
+const PROACTIVE_REFRESH_MINUTES: i64 = 15;
+/// Random extra minutes added to the refresh window per check, so credentials don't refresh in lockstep
+const PROACTIVE_REFRESH_JITTER_MINUTES: i64 = 10;
+/// Upper bound of the backoff between failed background refreshes
+const REFRESH_BACKOFF_MAX_MINUTES: i64 = 30;
+/// Fraction of the usage limit below which a credential is considered close to its limit
+const QUOTA_LOW_WATERMARK: f64 = 0.05;
 
 /// API call context
 ///
@@ -631,15 +1012,7 @@ impl MultiTokenManager {
                         has_new_machine_ids = true;
                     }
                 }
-                CredentialEntry {
-                    id,
-                    credentials: cred,
-                    failure_count: 0,
-                    disabled: false,
-                    disabled_reason: None,
-                    success_count: 0,
-                    last_used_at: None,
-                }
+                CredentialEntry::new(id, cred)
             })
             .collect();
 
@@ -664,8 +1037,8 @@ impl MultiTokenManager {
 
         let load_balancing_mode = config.load_balancing_mode.clone();
         let manager = Self {
-            config,
-            proxy,
+            config: SharedConfig::new(config),
+            proxy: RwLock::new(proxy),
             entries: Mutex::new(entries),
             current_id: Mutex::new(initial_id),
             refresh_lock: TokioMutex::new(()),
@@ -674,6 +1047,8 @@ impl MultiTokenManager {
             load_balancing_mode: Mutex::new(load_balancing_mode),
             last_stats_save_at: Mutex::new(None),
             stats_dirty: AtomicBool::new(false),
+            affinity: Mutex::new(HashMap::new()),
+            own_writes: Mutex::new(HashMap::new()),
         };
 
         // If new IDs or machineIds were assigned, persist to config file immediately
@@ -691,9 +1066,31 @@ impl MultiTokenManager {
         Ok(manager)
     }
 
-    /// Get config reference
-    pub fn config(&self) -> &Config {
-        &self.config
+    /// Get current config snapshot
+    pub fn config(&self) -> Arc<Config> {
+        self.config.load()
+    }
+
+    /// Get shared config handle (observes hot reloads)
+    pub fn shared_config(&self) -> SharedConfig {
+        self.config.clone()
+    }
+
+    /// Get current proxy configuration
+    pub fn proxy(&self) -> Option<ProxyConfig> {
+        self.proxy.read().clone()
+    }
+
+    /// Apply a reloaded config
+    ///
+    /// Swaps the shared config and takes over its load balancing mode and proxy.
+    pub fn apply_config(&self, config: Config) {
+        *self.load_balancing_mode.lock() = config.load_balancing_mode.clone();
+        *self.proxy.write() = config.proxy_config();
+        if !config.session_affinity {
+            self.affinity.lock().clear();
+        }
+        self.config.store(config);
     }
 
     /// Get clone of current active credential
@@ -721,35 +1118,33 @@ impl MultiTokenManager {
     ///
     /// - priority mode: Select highest priority (lowest priority number) available credential
     /// - balanced mode: Round-robin select available credentials
-    /// - If model contains "opus", filter out FREE tier accounts in balanced mode
-    fn select_next_credential(&self, model: Option<&str>) -> Option<(u64, KiroCredentials)> {
+    /// - quota mode: Select credential with the most remaining allowance, credentials close to
+    ///   their limit last
+    /// - For paid tier models (e.g. Opus), filter out FREE tier accounts in balanced and quota mode
+    /// - `avoid` is skipped unless it is the only candidate
+    fn select_next_credential(
+        &self,
+        model: Option<&str>,
+        avoid: Option<u64>,
+    ) -> Option<(u64, KiroCredentials)> {
         let entries = self.entries.lock();
         let mode = self.load_balancing_mode.lock().clone();
         let mode = mode.as_str();
 
-        // Check if requesting Opus model
-        let is_opus = model
-            .map(|m| m.to_lowercase().contains("opus"))
-            .unwrap_or(false);
-
         // Filter available credentials
-        let available: Vec<_> = entries
+        let mut available: Vec<_> = entries
             .iter()
-            .filter(|e| {
-                if e.disabled {
-                    return false;
-                }
-                // In balanced mode, filter out FREE accounts for Opus requests
-                if mode == "balanced" && is_opus && !e.credentials.supports_opus() {
-                    return false;
-                }
-                true
-            })
+            .filter(|e| !e.disabled && can_serve_model(&e.credentials, mode, model))
             .collect();
 
         if available.is_empty() {
             return None;
         }
+        if let Some(avoid) = avoid
+            && available.iter().any(|e| e.id != avoid)
+        {
+            available.retain(|e| e.id != avoid);
+        }
 
         match mode {
             "balanced" => {
@@ -761,6 +1156,10 @@ impl MultiTokenManager {
 
                 Some((entry.id, entry.credentials.clone()))
             }
+            "quota" => {
+                let entry = available.iter().min_by(|a, b| compare_by_quota(a, b))?;
+                Some((entry.id, entry.credentials.clone()))
+            }
             _ => {
                 // priority mode (default): Select highest priority
                 let entry = available.iter().min_by_key(|e| e.credentials.priority)?;
@@ -777,11 +1176,38 @@ impl MultiTokenManager {
     /// Automatically refreshes if Token is expired or about to expire
     /// On Token refresh failure, tries next available credential (not counted as failure)
     ///
-    /// If model is provided and contains "opus", FREE tier accounts will be filtered out in balanced mode
-    pub async fn acquire_context(&self, model: Option<&str>) -> anyhow::Result<CallContext> {
+    /// If model is a paid tier model (e.g. Opus), FREE tier accounts will be filtered out in balanced mode
+    ///
+    /// With session affinity enabled, `session_id` (the conversation ID) keeps using the credential
+    /// it was pinned to while that credential is neither disabled nor failing; otherwise it fails
+    /// over to a different credential and is re-pinned
+    pub async fn acquire_context(
+        &self,
+        model: Option<&str>,
+        session_id: Option<&str>,
+    ) -> anyhow::Result<CallContext> {
+        self.acquire_context_avoiding(model, session_id, None).await
+    }
+
+    /// Get API call context, preferring a credential other than `avoid`
+    ///
+    /// Same as `acquire_context`; `avoid` is only used when no other credential is available.
+    pub async fn acquire_context_avoiding(
+        &self,
+        model: Option<&str>,
+        session_id: Option<&str>,
+        avoid: Option<u64>,
+    ) -> anyhow::Result<CallContext> {
         let total = self.total_count();
         let mut tried_count = 0;
 
+        let config = self.config();
+        let affinity_ttl = StdDuration::from_secs(config.session_affinity_ttl_secs);
+        let session_id = session_id.filter(|_| config.session_affinity);
+        let pinned = session_id.and_then(|s| self.session_pin(s, affinity_ttl));
+        // Credential to move away from (unhealthy pin, failed token refresh or caller's choice)
+        let mut avoid = avoid;
+
         loop {
             if tried_count >= total {
                 anyhow::bail!(
@@ -792,26 +1218,45 @@ impl MultiTokenManager {
             }
 
             let (id, credentials) = {
-                let is_balanced = self.load_balancing_mode.lock().as_str() == "balanced";
+                let mode = self.load_balancing_mode.lock().clone();
+                let is_per_request = mode != "priority";
+
+                // Session affinity: stay on the pinned credential while it is healthy
+                // and may serve the requested model
+                let pinned_hit = pinned.filter(|id| avoid != Some(*id)).and_then(|pinned_id| {
+                    let entries = self.entries.lock();
+                    entries
+                        .iter()
+                        .find(|e| {
+                            e.id == pinned_id
+                                && !e.disabled
+                                && e.failure_count == 0
+                                && can_serve_model(&e.credentials, &mode, model)
+                        })
+                        .map(|e| (e.id, e.credentials.clone()))
+                });
+                if pinned_hit.is_none() && avoid.is_none() {
+                    avoid = pinned;
+                }
 
-                // balanced mode: Round-robin select for each request, don't fix current_id
+                // balanced/quota mode: Select for each request, don't fix current_id
                 // priority mode: Prefer credential pointed by current_id
-                let current_hit = if is_balanced {
+                let current_hit = if pinned_hit.is_some() || is_per_request {
                     None
                 } else {
                     let entries = self.entries.lock();
                     let current_id = *self.current_id.lock();
                     entries
                         .iter()
-                        .find(|e| e.id == current_id && !e.disabled)
+                        .find(|e| e.id == current_id && !e.disabled && avoid != Some(e.id))
                         .map(|e| (e.id, e.credentials.clone()))
                 };
 
-                if let Some(hit) = current_hit {
+                if let Some(hit) = pinned_hit.or(current_hit) {
                     hit
                 } else {
                     // Current credential unavailable or balanced mode, select based on load balancing strategy
-                    let mut best = self.select_next_credential(model);
+                    let mut best = self.select_next_credential(model, avoid);
 
                     // No available credentials: if "all disabled due to auto-disable", do self-healing similar to restart
                     if best.is_none() {
@@ -824,13 +1269,11 @@ impl MultiTokenManager {
                             );
                             for e in entries.iter_mut() {
                                 if e.disabled_reason == Some(DisabledReason::TooManyFailures) {
-                                    e.disabled = false;
-                                    e.disabled_reason = None;
-                                    e.failure_count = 0;
+                                    e.enable("self_healing");
                                 }
                             }
                             drop(entries);
-                            best = self.select_next_credential(model);
+                            best = self.select_next_credential(model, avoid);
                         }
                     }
 
@@ -853,6 +1296,17 @@ impl MultiTokenManager {
             // Try to get/refresh Token
             match self.try_ensure_token(id, &credentials).await {
                 Ok(ctx) => {
+                    if let Some(session_id) = session_id {
+                        if let Some(from) = pinned.filter(|&from| from != ctx.id) {
+                            tracing::info!(
+                                "Session {} failed over from credential #{} to #{}",
+                                session_id,
+                                from,
+                                ctx.id
+                            );
+                        }
+                        self.pin_session(session_id, ctx.id, affinity_ttl);
+                    }
                     return Ok(ctx);
                 }
                 Err(e) => {
@@ -860,12 +1314,61 @@ impl MultiTokenManager {
 
                     // Token refresh failed, switch to next priority credential (not counted as failure)
                     self.switch_to_next_by_priority();
+                    avoid = Some(id);
                     tried_count += 1;
                 }
             }
         }
     }
 
+    /// Credential a session is pinned to (expired pins are dropped)
+    fn session_pin(&self, session_id: &str, ttl: StdDuration) -> Option<u64> {
+        let mut affinity = self.affinity.lock();
+        let pin = affinity.get(session_id)?;
+        if pin.last_seen.elapsed() < ttl {
+            Some(pin.credential_id)
+        } else {
+            affinity.remove(session_id);
+            None
+        }
+    }
+
+    /// Pin a session to a credential
+    fn pin_session(&self, session_id: &str, credential_id: u64, ttl: StdDuration) {
+        let mut affinity = self.affinity.lock();
+        if affinity.len() >= MAX_AFFINITY_SESSIONS && !affinity.contains_key(session_id) {
+            affinity.retain(|_, pin| pin.last_seen.elapsed() < ttl);
+            if affinity.len() >= MAX_AFFINITY_SESSIONS
+                && let Some(oldest) = affinity
+                    .iter()
+                    .min_by_key(|(_, pin)| pin.last_seen)
+                    .map(|(key, _)| key.clone())
+            {
+                affinity.remove(&oldest);
+            }
+        }
+        affinity.insert(
+            session_id.to_string(),
+            SessionPin {
+                credential_id,
+                last_seen: Instant::now(),
+            },
+        );
+    }
+
+    /// Number of live pinned sessions per credential (expired pins are dropped)
+    fn affinity_counts(&self) -> HashMap<u64, usize> {
+        let ttl = StdDuration::from_secs(self.config().session_affinity_ttl_secs);
+        let mut affinity = self.affinity.lock();
+        affinity.retain(|_, pin| pin.last_seen.elapsed() < ttl);
+
+        let mut counts = HashMap::new();
+        for pin in affinity.values() {
+            *counts.entry(pin.credential_id).or_insert(0) += 1;
+        }
+        counts
+    }
+
     /// Switch to next highest priority available credential (internal method)
     fn switch_to_next_by_priority(&self) {
         let entries = self.entries.lock();
@@ -928,47 +1431,8 @@ impl MultiTokenManager {
         let needs_refresh = is_token_expired(credentials) || is_token_expiring_soon(credentials);
 
         let creds = if needs_refresh {
-            // Acquire refresh lock to ensure only one refresh operation at a time
-            let _guard = self.refresh_lock.lock().await;
-
-            // Second check: Re-read credentials after acquiring lock, as other requests may have completed refresh
-            let current_creds = {
-                let entries = self.entries.lock();
-                entries
-                    .iter()
-                    .find(|e| e.id == id)
-                    .map(|e| e.credentials.clone())
-                    .ok_or_else(|| anyhow::anyhow!("Credential #{} does not exist", id))?
-            };
-
-            if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
-                // Actually need to refresh
-                let new_creds =
-                    refresh_token(&current_creds, &self.config, self.proxy.as_ref()).await?;
-
-                if is_token_expired(&new_creds) {
-                    anyhow::bail!("Refreshed Token is still invalid or expired");
-                }
-
-                // Update credentials
-                {
-                    let mut entries = self.entries.lock();
-                    if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
-                        entry.credentials = new_creds.clone();
-                    }
-                }
-
-                // Write back credentials to file (only for multiple credentials format), log warning on failure
-                if let Err(e) = self.persist_credentials() {
-                    tracing::warn!("Failed to persist after Token refresh (does not affect this request): {}", e);
-                }
-
-                new_creds
-            } else {
-                // Other request already completed refresh, use new credentials directly
-                tracing::debug!("Token already refreshed by another request, skipping refresh");
-                current_creds
-            }
+            self.refresh_if_needed(id, |c| is_token_expired(c) || is_token_expiring_soon(c))
+                .await?
         } else {
             credentials.clone()
         };
@@ -985,55 +1449,194 @@ impl MultiTokenManager {
         })
     }
 
-    /// Write credentials list back to source file
+    /// Refresh a credential's Token if `needs_refresh` holds
+    ///
+    /// Holds `refresh_lock` so only one refresh runs at a time, and re-checks `needs_refresh`
+    /// after acquiring it, as another request may have completed the refresh meanwhile.
+    /// Returns the (possibly refreshed) credentials.
+    async fn refresh_if_needed(
+        &self,
+        id: u64,
+        needs_refresh: impl Fn(&KiroCredentials) -> bool,
+    ) -> anyhow::Result<KiroCredentials> {
+        // Acquire refresh lock to ensure only one refresh operation at a time
+        let _guard = self.refresh_lock.lock().await;
+
+        // Second check: Re-read credentials after acquiring lock, as other requests may have completed refresh
+        let current_creds = {
+            let entries = self.entries.lock();
+            entries
+                .iter()
+                .find(|e| e.id == id)
+                .map(|e| e.credentials.clone())
+                .ok_or_else(|| anyhow::anyhow!("Credential #{} does not exist", id))?
+        };
+
+        if !needs_refresh(&current_creds) {
+            // Other request already completed refresh, use new credentials directly
+            tracing::debug!("Token already refreshed by another request, skipping refresh");
+            return Ok(current_creds);
+        }
+
+        let result =
+            match refresh_token(&current_creds, &self.config(), self.proxy().as_ref()).await {
+                Ok(new_creds) if is_token_expired(&new_creds) => Err(anyhow::anyhow!(
+                    "Refreshed Token is still invalid or expired"
+                )),
+                result => result,
+            };
+
+        // Update credentials and refresh health
+        {
+            let mut entries = self.entries.lock();
+            if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+                entry.record_refresh(result.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+                if let Ok(new_creds) = &result {
+                    entry.set_refreshed(new_creds.clone());
+                }
+            }
+        }
+        let new_creds = result?;
+
+        // Write back credentials to file (only for multiple credentials format), log warning on failure
+        if let Err(e) = self.persist_credentials() {
+            tracing::warn!("Failed to persist after Token refresh (does not affect this request): {}", e);
+        }
+
+        Ok(new_creds)
+    }
+
+    /// Start refreshing Tokens in the background ahead of expiry
+    pub fn spawn_proactive_refresh(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PROACTIVE_REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                manager.refresh_expiring_tokens().await;
+            }
+        });
+    }
+
+    /// Refresh Tokens of enabled credentials that expire soon
+    ///
+    /// Credentials without a known expiry are refreshed once to learn it, not on every pass.
+    async fn refresh_expiring_tokens(&self) {
+        let due = self.credentials_due_for_refresh(Utc::now(), |c| {
+            // Each credential draws its own jitter, so credentials loaded together drift apart
+            let window =
+                PROACTIVE_REFRESH_MINUTES + fastrand::i64(0..=PROACTIVE_REFRESH_JITTER_MINUTES);
+            is_token_expiring_within(c, window)
+        });
+        // Re-checked against the widest window, only a concurrent refresh makes it false
+        let max_window = PROACTIVE_REFRESH_MINUTES + PROACTIVE_REFRESH_JITTER_MINUTES;
+        let needs_refresh =
+            |c: &KiroCredentials| is_token_expiring_within(c, max_window).unwrap_or(true);
+
+        for id in due {
+            match self.refresh_if_needed(id, needs_refresh).await {
+                Ok(_) => tracing::debug!("Credential #{} Token refreshed in the background", id),
+                Err(e) => {
+                    tracing::warn!("Credential #{} background Token refresh failed: {}", id, e)
+                }
+            }
+        }
+    }
+
+    /// Enabled credentials that need a refresh and are not backing off
+    ///
+    /// `expiring` returns `None` when the expiry is unknown; such credentials are only due
+    /// until they were refreshed successfully once.
+    fn credentials_due_for_refresh(
+        &self,
+        now: DateTime<Utc>,
+        expiring: impl Fn(&KiroCredentials) -> Option<bool>,
+    ) -> Vec<u64> {
+        let entries = self.entries.lock();
+        entries
+            .iter()
+            .filter(|e| {
+                !e.disabled
+                    && e.refresh_retry_at.is_none_or(|retry_at| retry_at <= now)
+                    && expiring(&e.credentials)
+                        .unwrap_or(e.refresh_health.last_refresh_at.is_none())
+            })
+            .map(|e| e.id)
+            .collect()
+    }
+
+    /// Write credentials back to their sources
     ///
-    /// Only writes back when the following conditions are met:
-    /// - Source file is multiple credentials format (array)
-    /// - credentials_path is set
+    /// - Credentials file: file-sourced credentials, only when the file is in multiple
+    ///   credentials format (array) and credentials_path is set
+    /// - Credentials directory: each credential to its own file, unless the directory is read-only
+    /// - Environment variables: never written
     ///
     /// # Returns
-    /// - `Ok(true)` - Successfully wrote to file
-    /// - `Ok(false)` - Skipped write (not multiple credentials format or no path configured)
+    /// - `Ok(true)` - Wrote at least one file
+    /// - `Ok(false)` - Skipped write (nothing writable)
     /// - `Err(_)` - Write failed
     fn persist_credentials(&self) -> anyhow::Result<bool> {
         use anyhow::Context;
 
-        // Only write back for multiple credentials format
-        if !*self.is_multiple_format.lock() {
-            return Ok(false);
+        // Collect credentials per writable source
+        let mut file_credentials = Vec::new();
+        let mut directory_credentials = Vec::new();
+        {
+            let entries = self.entries.lock();
+            for entry in entries.iter() {
+                let mut cred = entry.credentials.clone();
+                cred.canonicalize_auth_method();
+                match &cred.source {
+                    CredentialSource::File => file_credentials.push(cred),
+                    CredentialSource::Directory {
+                        path,
+                        read_only: false,
+                    } => directory_credentials.push((path.clone(), cred)),
+                    _ => {}
+                }
+            }
         }
 
-        let path = match &self.credentials_path {
-            Some(p) => p,
-            None => return Ok(false),
-        };
+        let mut written = false;
 
-        // Collect all credentials
-        let credentials: Vec<KiroCredentials> = {
-            let entries = self.entries.lock();
-            entries
-                .iter()
-                .map(|e| {
-                    let mut cred = e.credentials.clone();
-                    cred.canonicalize_auth_method();
-                    cred
-                })
-                .collect()
-        };
+        // Credentials file (only for multiple credentials format, not created for other sources alone)
+        if let Some(path) = &self.credentials_path
+            && *self.is_multiple_format.lock()
+            && (!file_credentials.is_empty() || path.exists())
+        {
+            let json = serde_json::to_string_pretty(&file_credentials)
+                .context("Failed to serialize credentials")?;
+            write_file(path, &json)
+                .with_context(|| format!("Failed to write back credentials file: {:?}", path))?;
+            self.record_own_write(path);
+            tracing::debug!("Wrote back credentials to file: {:?}", path);
+            written = true;
+        }
 
-        // Serialize to pretty JSON
-        let json = serde_json::to_string_pretty(&credentials).context("Failed to serialize credentials")?;
+        // One file per credential in the credentials directory
+        for (path, cred) in directory_credentials {
+            let json =
+                serde_json::to_string_pretty(&cred).context("Failed to serialize credential")?;
+            write_file(&path, &json)
+                .with_context(|| format!("Failed to write back credential file: {:?}", path))?;
+            self.record_own_write(&path);
+            written = true;
+        }
 
-        // Write to file (use block_in_place in Tokio runtime to avoid blocking worker)
-        if tokio::runtime::Handle::try_current().is_ok() {
-            tokio::task::block_in_place(|| std::fs::write(path, &json))
-                .with_context(|| format!("Failed to write back credentials file: {:?}", path))?;
-        } else {
-            std::fs::write(path, &json).with_context(|| format!("Failed to write back credentials file: {:?}", path))?;
+        Ok(written)
+    }
+
+    /// Remember the version of a file just written, so the reload watcher skips it
+    fn record_own_write(&self, path: &Path) {
+        if let Some(version) = file_version(path) {
+            self.own_writes.lock().insert(path.to_path_buf(), version);
         }
+    }
 
-        tracing::debug!("Wrote back credentials to file: {:?}", path);
-        Ok(true)
+    /// Whether `version` of `path` was written by this process
+    pub fn is_own_write(&self, path: &Path, version: FileVersion) -> bool {
+        self.own_writes.lock().get(path) == Some(&version)
     }
 
     /// Get cache directory (directory containing credentials file)
@@ -1045,7 +1648,7 @@ impl MultiTokenManager {
 
     /// Statistics data file path
     fn stats_path(&self) -> Option<PathBuf> {
-        self.cache_dir().map(|d| d.join("kiro_stats.json"))
+        self.cache_dir().map(|d| d.join(STATS_FILE_NAME))
     }
 
     /// Load statistics data from disk and apply to current entries
@@ -1055,9 +1658,16 @@ impl MultiTokenManager {
             None => return,
         };
 
-        let content = match std::fs::read_to_string(&path) {
+        // File doesn't exist on first run
+        if !path.exists() {
+            return;
+        }
+        let content = match encryption::read_to_string(&path) {
             Ok(c) => c,
-            Err(_) => return, // File doesn't exist on first run
+            Err(e) => {
+                tracing::warn!("Failed to read statistics cache, will ignore: {:#}", e);
+                return;
+            }
         };
 
         let stats: HashMap<String, StatsEntry> = match serde_json::from_str(&content) {
@@ -1072,7 +1682,10 @@ impl MultiTokenManager {
         for entry in entries.iter_mut() {
             if let Some(s) = stats.get(&entry.id.to_string()) {
                 entry.success_count = s.success_count;
+                entry.cancelled_count = s.cancelled_count;
+                entry.credits_used = s.credits_used;
                 entry.last_used_at = s.last_used_at.clone();
+                entry.history = s.history.iter().cloned().collect();
             }
         }
         *self.last_stats_save_at.lock() = Some(Instant::now());
@@ -1096,7 +1709,10 @@ impl MultiTokenManager {
                         e.id.to_string(),
                         StatsEntry {
                             success_count: e.success_count,
+                            cancelled_count: e.cancelled_count,
+                            credits_used: e.credits_used,
                             last_used_at: e.last_used_at.clone(),
+                            history: e.history.iter().cloned().collect(),
                         },
                     )
                 })
@@ -1105,8 +1721,8 @@ impl MultiTokenManager {
 
         match serde_json::to_string_pretty(&stats) {
             Ok(json) => {
-                if let Err(e) = std::fs::write(&path, json) {
-                    tracing::warn!("Failed to save statistics cache: {}", e);
+                if let Err(e) = encryption::write(&path, &json) {
+                    tracing::warn!("Failed to save statistics cache: {:#}", e);
                 } else {
                     *self.last_stats_save_at.lock() = Some(Instant::now());
                     self.stats_dirty.store(false, Ordering::Relaxed);
@@ -1133,13 +1749,65 @@ impl MultiTokenManager {
         }
     }
 
-    /// Report specified credential API call success
+    /// Resolve the profileArn for the credential bound to a call context
     ///
-    /// Resets the credential's failure count
+    /// Returns the stored profileArn if present. Otherwise fetches it once via
+    /// ListProfiles (same as OAuth imports) and persists it to the credential;
+    /// failed lookups are not retried until the credential is reloaded.
     ///
     /// # Arguments
-    /// * `id` - Credential ID (from CallContext)
-    pub fn report_success(&self, id: u64) {
+    /// * `ctx` - API call context (credentials and valid token)
+    pub async fn resolve_profile_arn(&self, ctx: &CallContext) -> Option<String> {
+        if let Some(arn) = &ctx.credentials.profile_arn {
+            return Some(arn.clone());
+        }
+
+        {
+            let mut entries = self.entries.lock();
+            let entry = entries.iter_mut().find(|e| e.id == ctx.id)?;
+            if let Some(arn) = &entry.credentials.profile_arn {
+                return Some(arn.clone());
+            }
+            if entry.profile_arn_lookup_attempted {
+                return None;
+            }
+            entry.profile_arn_lookup_attempted = true;
+        }
+
+        let config = self.config();
+        let region = ctx.credentials.effective_api_region(&config);
+        let profile_arn = SsoOidcClient::new(self.proxy(), config.tls_backend)
+            .fetch_profile_arn(&ctx.token, region)
+            .await;
+
+        let Some(arn) = profile_arn else {
+            tracing::warn!("Credential #{} has no profileArn and lookup failed", ctx.id);
+            return None;
+        };
+
+        {
+            let mut entries = self.entries.lock();
+            if let Some(entry) = entries.iter_mut().find(|e| e.id == ctx.id) {
+                entry.credentials.profile_arn = Some(arn.clone());
+            }
+        }
+
+        if let Err(e) = self.persist_credentials() {
+            tracing::warn!("Failed to persist profileArn for credential #{}: {}", ctx.id, e);
+        } else {
+            tracing::info!("Fetched profileArn for credential #{}", ctx.id);
+        }
+
+        Some(arn)
+    }
+
+    /// Report specified credential API call success
+    ///
+    /// Resets the credential's failure count
+    ///
+    /// # Arguments
+    /// * `id` - Credential ID (from CallContext)
+    pub fn report_success(&self, id: u64) {
         {
             let mut entries = self.entries.lock();
             if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
@@ -1156,6 +1824,40 @@ impl MultiTokenManager {
         self.save_stats_debounced();
     }
 
+    /// Report a streaming response abandoned because the client disconnected
+    ///
+    /// Only counted in statistics, the credential itself is not at fault
+    ///
+    /// # Arguments
+    /// * `id` - Credential ID (from CallContext)
+    pub fn report_cancelled(&self, id: u64) {
+        {
+            let mut entries = self.entries.lock();
+            if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+                entry.cancelled_count += 1;
+            }
+        }
+        self.save_stats_debounced();
+    }
+
+    /// Add credits consumed by a response, as reported by Kiro meteringEvents
+    ///
+    /// # Arguments
+    /// * `id` - Credential ID (from CallContext)
+    /// * `credits` - Credits consumed
+    pub fn report_credits(&self, id: u64, credits: f64) {
+        if credits <= 0.0 {
+            return;
+        }
+        {
+            let mut entries = self.entries.lock();
+            if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+                entry.credits_used += credits;
+            }
+        }
+        self.save_stats_debounced();
+    }
+
     /// Report specified credential API call failure
     ///
     /// Increments failure count, disables credential and switches to highest priority available credential when threshold reached
@@ -1185,8 +1887,7 @@ impl MultiTokenManager {
             );
 
             if failure_count >= MAX_FAILURES_PER_CREDENTIAL {
-                entry.disabled = true;
-                entry.disabled_reason = Some(DisabledReason::TooManyFailures);
+                entry.disable(DisabledReason::TooManyFailures, None);
                 tracing::error!("Credential #{} has failed {} consecutive times, disabled", id, failure_count);
 
                 // Switch to highest priority available credential
@@ -1232,13 +1933,34 @@ impl MultiTokenManager {
                 return entries.iter().any(|e| !e.disabled);
             }
 
-            entry.disabled = true;
-            entry.disabled_reason = Some(DisabledReason::QuotaExceeded);
-            entry.last_used_at = Some(Utc::now().to_rfc3339());
+            // Probe after the reset date from the last usage query, or the first day of next month
+            let now = Utc::now();
+            let known_reset = entry
+                .usage
+                .and_then(|u| u.next_reset_at)
+                .and_then(reset_date)
+                .filter(|reset_at| *reset_at > now);
+            let quota_reset = QuotaReset {
+                reset_at: known_reset.unwrap_or_else(|| first_day_of_next_month(now)),
+                confirmed: known_reset.is_some(),
+            };
+            entry.quota_reset = Some(quota_reset);
+            entry.disable(
+                DisabledReason::QuotaExceeded,
+                Some(format!("resets at {}", quota_reset.reset_at.to_rfc3339())),
+            );
+            entry.last_used_at = Some(now.to_rfc3339());
+            if let Some(usage) = &mut entry.usage {
+                usage.current_usage = usage.usage_limit;
+            }
             // Set to threshold for intuitive display in admin panel that credential is unavailable
             entry.failure_count = MAX_FAILURES_PER_CREDENTIAL;
 
-            tracing::error!("Credential #{} quota exhausted (MONTHLY_REQUEST_COUNT), disabled", id);
+            tracing::error!(
+                "Credential #{} quota exhausted (MONTHLY_REQUEST_COUNT), disabled until {}",
+                id,
+                quota_reset.reset_at.to_rfc3339()
+            );
 
             // Switch to highest priority available credential
             if let Some(next) = entries
@@ -1290,22 +2012,142 @@ impl MultiTokenManager {
 
     /// Get usage limits information
     pub async fn get_usage_limits(&self) -> anyhow::Result<UsageLimitsResponse> {
-        let ctx = self.acquire_context(None).await?;
+        let ctx = self.acquire_context(None, None).await?;
         get_usage_limits(
             &ctx.credentials,
-            &self.config,
+            &self.config(),
             &ctx.token,
-            self.proxy.as_ref(),
+            self.proxy().as_ref(),
         )
         .await
     }
 
+    /// Start querying usage in the background while in quota mode
+    pub fn spawn_usage_refresher(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(USAGE_REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if manager.get_load_balancing_mode() == "quota" {
+                    manager.refresh_usage().await;
+                }
+            }
+        });
+    }
+
+    /// Query usage of all enabled credentials
+    async fn refresh_usage(&self) {
+        let ids: Vec<u64> = {
+            let entries = self.entries.lock();
+            entries.iter().filter(|e| !e.disabled).map(|e| e.id).collect()
+        };
+
+        for id in ids {
+            if let Err(e) = self.get_usage_limits_for(id).await {
+                tracing::warn!("Failed to query usage for credential #{}: {}", id, e);
+            }
+        }
+    }
+
+    /// Start probing quota-disabled credentials for recovery after their reset date
+    pub fn spawn_quota_recovery(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(QUOTA_RECOVERY_INTERVAL);
+            loop {
+                interval.tick().await;
+                manager.recover_quota_exceeded().await;
+            }
+        });
+    }
+
+    /// Probe quota-disabled credentials that are due
+    ///
+    /// A credential is due once its reset date has passed, or while its reset date is only the
+    /// fallback (the usage query then supplies the real one).
+    async fn recover_quota_exceeded(&self) {
+        let now = Utc::now();
+        let due: Vec<u64> = {
+            let entries = self.entries.lock();
+            entries
+                .iter()
+                .filter(|e| {
+                    e.disabled_reason == Some(DisabledReason::QuotaExceeded)
+                        && e.quota_reset
+                            .is_none_or(|r| !r.confirmed || r.reset_at <= now)
+                })
+                .map(|e| e.id)
+                .collect()
+        };
+
+        for id in due {
+            let usage = match self.get_usage_limits_for(id).await {
+                Ok(usage) => Some(UsageInfo::from_response(&usage)),
+                Err(e) => {
+                    tracing::warn!("Quota recovery probe failed for credential #{}: {}", id, e);
+                    None
+                }
+            };
+            self.apply_quota_probe(id, usage, now);
+        }
+    }
+
+    /// Apply the result of a recovery probe (`None` if the usage query failed)
+    ///
+    /// Re-enables the credential when its reset date has passed and it has allowance again;
+    /// otherwise schedules the next probe. Returns whether it was re-enabled.
+    fn apply_quota_probe(&self, id: u64, usage: Option<UsageInfo>, now: DateTime<Utc>) -> bool {
+        let recovered = {
+            let mut entries = self.entries.lock();
+            let Some(entry) = entries
+                .iter_mut()
+                .find(|e| e.id == id && e.disabled_reason == Some(DisabledReason::QuotaExceeded))
+            else {
+                return false;
+            };
+            let scheduled = entry.quota_reset.unwrap_or(QuotaReset {
+                reset_at: now,
+                confirmed: false,
+            });
+            let is_due = scheduled.reset_at <= now;
+
+            if is_due && usage.is_some_and(|u| u.remaining() > 0.0) {
+                entry.enable("quota_reset");
+                true
+            } else {
+                let next_reset = usage
+                    .and_then(|u| u.next_reset_at)
+                    .and_then(reset_date)
+                    .filter(|reset_at| *reset_at > now);
+                let reset_at = match next_reset {
+                    Some(reset_at) => reset_at,
+                    None if is_due => now + Duration::minutes(QUOTA_PROBE_RETRY_MINUTES),
+                    None => scheduled.reset_at,
+                };
+                entry.quota_reset = Some(QuotaReset {
+                    reset_at,
+                    confirmed: true,
+                });
+                false
+            }
+        };
+
+        if recovered {
+            tracing::info!("Credential #{} has allowance again after quota reset, re-enabled", id);
+            self.select_highest_priority();
+            self.save_stats_debounced();
+        }
+        recovered
+    }
+
     // ========================================================================
     // Admin API methods
     // ========================================================================
 
     /// Get manager state snapshot (for Admin API)
     pub fn snapshot(&self) -> ManagerSnapshot {
+        let affinity_counts = self.affinity_counts();
         let entries = self.entries.lock();
         let current_id = *self.current_id.lock();
         let available = entries.iter().filter(|e| !e.disabled).count();
@@ -1330,7 +2172,14 @@ impl MultiTokenManager {
                     refresh_token_hash: e.credentials.refresh_token.as_deref().map(sha256_hex),
                     email: e.credentials.email.clone(),
                     success_count: e.success_count,
+                    cancelled_count: e.cancelled_count,
+                    credits_used: e.credits_used,
                     last_used_at: e.last_used_at.clone(),
+                    affinity_sessions: affinity_counts.get(&e.id).copied().unwrap_or(0),
+                    quota_reset_at: e.quota_reset.map(|r| r.reset_at.to_rfc3339()),
+                    refresh_health: e.refresh_health.clone(),
+                    source: e.credentials.source.to_string(),
+                    read_only: !e.credentials.source.is_writable(),
                 })
                 .collect(),
             current_id,
@@ -1339,6 +2188,16 @@ impl MultiTokenManager {
         }
     }
 
+    /// Get credential state transitions, oldest first (Admin API)
+    pub fn credential_history(&self, id: u64) -> anyhow::Result<Vec<CredentialEvent>> {
+        let entries = self.entries.lock();
+        let entry = entries
+            .iter()
+            .find(|e| e.id == id)
+            .ok_or_else(|| anyhow::anyhow!("Credential does not exist: {}", id))?;
+        Ok(entry.history.iter().cloned().collect())
+    }
+
     /// Set credential disabled status (Admin API)
     pub fn set_disabled(&self, id: u64, disabled: bool) -> anyhow::Result<()> {
         {
@@ -1347,15 +2206,14 @@ impl MultiTokenManager {
                 .iter_mut()
                 .find(|e| e.id == id)
                 .ok_or_else(|| anyhow::anyhow!("Credential does not exist: {}", id))?;
-            entry.disabled = disabled;
             if !disabled {
                 // Reset failure count when enabling
-                entry.failure_count = 0;
-                entry.disabled_reason = None;
-            } else {
-                entry.disabled_reason = Some(DisabledReason::Manual);
+                entry.enable("manual");
+            } else if entry.disabled_reason != Some(DisabledReason::Manual) {
+                entry.disable(DisabledReason::Manual, None);
             }
         }
+        self.save_stats_debounced();
         // Persist changes
         self.persist_credentials()?;
         Ok(())
@@ -1389,10 +2247,9 @@ impl MultiTokenManager {
                 .iter_mut()
                 .find(|e| e.id == id)
                 .ok_or_else(|| anyhow::anyhow!("Credential does not exist: {}", id))?;
-            entry.failure_count = 0;
-            entry.disabled = false;
-            entry.disabled_reason = None;
+            entry.enable("manual");
         }
+        self.save_stats_debounced();
         // Persist changes
         self.persist_credentials()?;
         Ok(())
@@ -1409,63 +2266,26 @@ impl MultiTokenManager {
                 .ok_or_else(|| anyhow::anyhow!("Credential does not exist: {}", id))?
         };
 
-        // Check if token needs refresh
-        let needs_refresh = is_token_expired(&credentials) || is_token_expiring_soon(&credentials);
-
-        let token = if needs_refresh {
-            let _guard = self.refresh_lock.lock().await;
-            let current_creds = {
-                let entries = self.entries.lock();
-                entries
-                    .iter()
-                    .find(|e| e.id == id)
-                    .map(|e| e.credentials.clone())
-                    .ok_or_else(|| anyhow::anyhow!("Credential does not exist: {}", id))?
-            };
-
-            if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
-                let new_creds =
-                    refresh_token(&current_creds, &self.config, self.proxy.as_ref()).await?;
-                {
-                    let mut entries = self.entries.lock();
-                    if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
-                        entry.credentials = new_creds.clone();
-                    }
-                }
-                // Log warning on persistence failure, does not affect this request
-                if let Err(e) = self.persist_credentials() {
-                    tracing::warn!("Failed to persist after Token refresh (does not affect this request): {}", e);
-                }
-                new_creds
-                    .access_token
-                    .ok_or_else(|| anyhow::anyhow!("No access_token after refresh"))?
-            } else {
-                current_creds
-                    .access_token
-                    .ok_or_else(|| anyhow::anyhow!("Credential has no access_token"))?
-            }
+        // Refresh through the shared path, which records refresh health
+        let needs_refresh = |c: &KiroCredentials| is_token_expired(c) || is_token_expiring_soon(c);
+        let credentials = if needs_refresh(&credentials) {
+            self.refresh_if_needed(id, needs_refresh).await?
         } else {
             credentials
-                .access_token
-                .ok_or_else(|| anyhow::anyhow!("Credential has no access_token"))?
-        };
-
-        let credentials = {
-            let entries = self.entries.lock();
-            entries
-                .iter()
-                .find(|e| e.id == id)
-                .map(|e| e.credentials.clone())
-                .ok_or_else(|| anyhow::anyhow!("Credential does not exist: {}", id))?
         };
+        let token = credentials
+            .access_token
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Credential has no access_token"))?;
 
-        let usage = get_usage_limits(&credentials, &self.config, &token, self.proxy.as_ref()).await?;
+        let usage = get_usage_limits(&credentials, &self.config(), &token, self.proxy().as_ref()).await?;
 
         // Update subscription_title and email in credential if available
         let mut needs_persist = false;
         {
             let mut entries = self.entries.lock();
             if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+                entry.usage = Some(UsageInfo::from_response(&usage));
                 // Update subscription_title
                 if let Some(title) = usage.subscription_title() {
                     if entry.credentials.subscription_title.as_deref() != Some(title) {
@@ -1515,13 +2335,13 @@ impl MultiTokenManager {
 
         let _guard = self.refresh_lock.lock().await;
 
-        let new_creds = refresh_token(&credentials, &self.config, self.proxy.as_ref()).await?;
+        let new_creds = refresh_token(&credentials, &self.config(), self.proxy().as_ref()).await?;
 
         // Update credentials in entries
         {
             let mut entries = self.entries.lock();
             if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
-                entry.credentials = new_creds.clone();
+                entry.set_refreshed(new_creds.clone());
             }
         }
 
@@ -1576,7 +2396,7 @@ impl MultiTokenManager {
 
         // 3. Try to refresh Token to validate credential
         let mut validated_cred =
-            refresh_token(&new_cred, &self.config, self.proxy.as_ref()).await?;
+            refresh_token(&new_cred, &self.config(), self.proxy().as_ref()).await?;
 
         // 4. Assign new ID
         let new_id = {
@@ -1604,15 +2424,7 @@ impl MultiTokenManager {
 
         {
             let mut entries = self.entries.lock();
-            entries.push(CredentialEntry {
-                id: new_id,
-                credentials: validated_cred,
-                failure_count: 0,
-                disabled: false,
-                disabled_reason: None,
-                success_count: 0,
-                last_used_at: None,
-            });
+            entries.push(CredentialEntry::new(new_id, validated_cred));
         }
 
         // 6. Upgrade to multiple credentials format and persist
@@ -1633,6 +2445,7 @@ impl MultiTokenManager {
     ///
     /// # Preconditions
     /// - Credential must be disabled (disabled = true)
+    /// - Credential must come from a writable source (not environment variables or a read-only directory)
     ///
     /// # Behavior
     /// 1. Verify credential exists
@@ -1640,13 +2453,13 @@ impl MultiTokenManager {
     /// 3. Remove from entries
     /// 4. If deleted credential was current, switch to highest priority available credential
     /// 5. If no credentials remain after deletion, reset current_id to 0
-    /// 6. Persist to file
+    /// 6. Persist to file (a credential from the credentials directory has its file removed)
     ///
     /// # Returns
     /// - `Ok(())` - Delete successful
     /// - `Err(_)` - Credential does not exist, not disabled, or persistence failed
     pub fn delete_credential(&self, id: u64) -> anyhow::Result<()> {
-        let was_current = {
+        let (was_current, source) = {
             let mut entries = self.entries.lock();
 
             // Find credential
@@ -1660,6 +2473,16 @@ impl MultiTokenManager {
                 anyhow::bail!("Can only delete disabled credentials (please disable credential #{} first)", id);
             }
 
+            // Read-only sources would bring the credential back on the next load
+            let source = entry.credentials.source.clone();
+            if !source.is_writable() {
+                anyhow::bail!(
+                    "Credential #{} comes from a read-only source ({}), remove it there",
+                    id,
+                    source
+                );
+            }
+
             // Record if it's current credential
             let current_id = *self.current_id.lock();
             let was_current = current_id == id;
@@ -1667,7 +2490,7 @@ impl MultiTokenManager {
             // Delete credential
             entries.retain(|e| e.id != id);
 
-            was_current
+            (was_current, source)
         };
 
         // If deleted credential was current, switch to highest priority available credential
@@ -1687,11 +2510,131 @@ impl MultiTokenManager {
 
         // Persist changes
         self.persist_credentials()?;
+        if let CredentialSource::Directory { path, .. } = &source {
+            std::fs::remove_file(path)
+                .map_err(|e| anyhow::anyhow!("Failed to remove credential file {:?}: {}", path, e))?;
+        }
 
         tracing::info!("Deleted credential #{}", id);
         Ok(())
     }
 
+    /// Reload credentials from the credentials file (hot reload)
+    ///
+    /// File entries are matched to live entries by ID, or by refreshToken for entries without ID;
+    /// environment credentials by their variable index and ID.
+    /// Matched entries keep their statistics and runtime state (see `CredentialEntry::apply_reloaded`),
+    /// unmatched file entries are added and live entries missing from the file are removed.
+    /// IDs and machineIds assigned to new entries are written back to the file.
+    /// The credentials must have unique IDs (checked by `credential_sources::load_all`).
+    ///
+    /// Holds `refresh_lock`, so a refresh in flight is not lost. A file still holding a refresh
+    /// token this process already rotated is stale: the live tokens are kept and written back.
+    pub async fn reload_credentials(
+        &self,
+        credentials: Vec<KiroCredentials>,
+        is_multiple_format: bool,
+    ) -> anyhow::Result<CredentialsReload> {
+        let _guard = self.refresh_lock.lock().await;
+
+        let config = self.config();
+        let mut summary = CredentialsReload::default();
+        let mut needs_persist = false;
+        {
+            let mut entries = self.entries.lock();
+            let mut next_id = entries
+                .iter()
+                .map(|e| e.id)
+                .chain(credentials.iter().filter_map(|c| c.id))
+                .max()
+                .unwrap_or(0)
+                + 1;
+
+            let mut live: Vec<CredentialEntry> = std::mem::take(&mut *entries);
+            let mut reloaded = Vec::with_capacity(credentials.len());
+            for mut cred in credentials {
+                cred.canonicalize_auth_method();
+                let position = match (&cred.source, cred.id) {
+                    // Environment credentials are identified by their variables, never by token
+                    (CredentialSource::Env { .. }, id) => live
+                        .iter()
+                        .position(|e| e.credentials.source == cred.source && Some(e.id) == id),
+                    (_, Some(id)) => live.iter().position(|e| e.id == id),
+                    (_, None) => cred.refresh_token.as_deref().and_then(|token| {
+                        live.iter().position(|e| {
+                            e.credentials.refresh_token.as_deref() == Some(token)
+                                || e.is_rotated_refresh_token(Some(token))
+                        })
+                    }),
+                };
+
+                match position {
+                    Some(position) => {
+                        let mut entry = live.remove(position);
+                        if cred.id.is_none() {
+                            cred.id = Some(entry.id);
+                            needs_persist = true;
+                        }
+                        if entry.is_rotated_refresh_token(cred.refresh_token.as_deref()) {
+                            tracing::info!(
+                                "Credential #{} in the file has an already rotated refresh token, keeping the live one",
+                                entry.id
+                            );
+                            cred.refresh_token = entry.credentials.refresh_token.clone();
+                            needs_persist = true;
+                        }
+                        if entry.apply_reloaded(cred) {
+                            summary.updated.push(entry.id);
+                        }
+                        reloaded.push(entry);
+                    }
+                    None => {
+                        let id = *cred.id.get_or_insert_with(|| {
+                            needs_persist = true;
+                            let id = next_id;
+                            next_id += 1;
+                            id
+                        });
+                        if cred.machine_id.is_none() {
+                            cred.machine_id = machine_id::generate_from_credentials(&cred, &config);
+                            needs_persist |= cred.machine_id.is_some();
+                        }
+                        summary.added.push(id);
+                        reloaded.push(CredentialEntry::new(id, cred));
+                    }
+                }
+            }
+
+            summary.removed = live.iter().map(|e| e.id).collect();
+            *entries = reloaded;
+        }
+        *self.is_multiple_format.lock() = is_multiple_format;
+
+        if summary.is_empty() {
+            if needs_persist {
+                self.persist_credentials()?;
+            }
+            return Ok(summary);
+        }
+
+        if self.entries.lock().is_empty() {
+            *self.current_id.lock() = 0;
+        } else {
+            self.select_highest_priority();
+        }
+        if needs_persist {
+            self.persist_credentials()?;
+        }
+
+        tracing::info!(
+            "Reloaded credentials: added {:?}, updated {:?}, removed {:?}",
+            summary.added,
+            summary.updated,
+            summary.removed
+        );
+        Ok(summary)
+    }
+
     /// Get load balancing mode (Admin API)
     pub fn get_load_balancing_mode(&self) -> String {
         self.load_balancing_mode.lock().clone()
@@ -1700,7 +2643,7 @@ impl MultiTokenManager {
     fn persist_load_balancing_mode(&self, mode: &str) -> anyhow::Result<()> {
         use anyhow::Context;
 
-        let config_path = match self.config.config_path() {
+        let config_path = match self.config().config_path() {
             Some(path) => path.to_path_buf(),
             None => {
                 tracing::warn!("Config file path unknown, load balancing mode only effective in current process: {}", mode);
@@ -1721,7 +2664,7 @@ impl MultiTokenManager {
     /// Set load balancing mode (Admin API)
     pub fn set_load_balancing_mode(&self, mode: String) -> anyhow::Result<()> {
         // Validate mode value
-        if mode != "priority" && mode != "balanced" {
+        if !LOAD_BALANCING_MODES.contains(&mode.as_str()) {
             anyhow::bail!("Invalid load balancing mode: {}", mode);
         }
 
@@ -2030,6 +2973,117 @@ mod tests {
         std::fs::remove_file(&config_path).unwrap();
     }
 
+    #[tokio::test]
+    async fn test_reload_credentials_keeps_live_token_for_same_refresh_token() {
+        let live = KiroCredentials {
+            id: Some(1),
+            refresh_token: Some("r1".to_string()),
+            access_token: Some("refreshed".to_string()),
+            email: Some("a@example.com".to_string()),
+            machine_id: Some("m1".to_string()),
+            ..Default::default()
+        };
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![live], None, None, false).unwrap();
+        manager.report_success(1);
+
+        let from_file = KiroCredentials {
+            id: Some(1),
+            refresh_token: Some("r1".to_string()),
+            access_token: Some("stale".to_string()),
+            priority: 3,
+            ..Default::default()
+        };
+        let summary = manager
+            .reload_credentials(vec![from_file.clone()], false)
+            .await
+            .unwrap();
+        assert_eq!(summary.updated, vec![1]);
+
+        {
+            let entries = manager.entries.lock();
+            let entry = &entries[0];
+            assert_eq!(entry.credentials.access_token.as_deref(), Some("refreshed"));
+            assert_eq!(entry.credentials.email.as_deref(), Some("a@example.com"));
+            assert_eq!(entry.credentials.priority, 3);
+            assert_eq!(entry.success_count, 1);
+        }
+
+        // Same content again is a no-op
+        let summary = manager
+            .reload_credentials(vec![from_file], false)
+            .await
+            .unwrap();
+        assert!(summary.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reload_matches_env_credentials_by_source_and_id() {
+        use crate::kiro::credential_sources::ENV_ID_BASE;
+
+        let env_cred = |token: &str| KiroCredentials {
+            id: Some(ENV_ID_BASE + 1),
+            refresh_token: Some(token.to_string()),
+            source: CredentialSource::Env { index: 1 },
+            ..Default::default()
+        };
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![env_cred("r1")], None, None, false)
+                .unwrap();
+        manager.report_success(ENV_ID_BASE + 1);
+
+        // A new token in the variable updates the same credential
+        let summary = manager
+            .reload_credentials(vec![env_cred("r2")], false)
+            .await
+            .unwrap();
+        assert_eq!(summary.updated, vec![ENV_ID_BASE + 1]);
+        assert!(summary.added.is_empty() && summary.removed.is_empty());
+        assert_eq!(manager.snapshot().entries[0].success_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reload_credentials_keeps_rotated_refresh_token() {
+        let live = KiroCredentials {
+            id: Some(1),
+            refresh_token: Some("r1".to_string()),
+            ..Default::default()
+        };
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![live], None, None, false).unwrap();
+        // A refresh rotated the refresh token after the file was written
+        manager.entries.lock()[0].set_refreshed(KiroCredentials {
+            id: Some(1),
+            refresh_token: Some("r2".to_string()),
+            access_token: Some("fresh".to_string()),
+            ..Default::default()
+        });
+        for _ in 0..3 {
+            manager.report_failure(1);
+        }
+
+        let from_file = KiroCredentials {
+            id: Some(1),
+            refresh_token: Some("r1".to_string()),
+            priority: 2,
+            ..Default::default()
+        };
+        let summary = manager
+            .reload_credentials(vec![from_file], false)
+            .await
+            .unwrap();
+        assert_eq!(summary.updated, vec![1]);
+
+        let entries = manager.entries.lock();
+        let entry = &entries[0];
+        assert_eq!(entry.credentials.refresh_token.as_deref(), Some("r2"));
+        assert_eq!(entry.credentials.access_token.as_deref(), Some("fresh"));
+        assert_eq!(entry.credentials.priority, 2);
+        // Not mistaken for a new refresh token: the credential stays disabled
+        assert!(entry.disabled);
+        assert_eq!(entry.failure_count, 3);
+    }
+
     #[tokio::test]
     async fn test_multi_token_manager_acquire_context_auto_recovers_all_disabled() {
         let config = Config::default();
@@ -2054,7 +3108,7 @@ mod tests {
         assert_eq!(manager.available_count(), 0);
 
         // Should trigger self-healing: reset failure counts and re-enable, avoiding need to restart process
-        let ctx = manager.acquire_context(None).await.unwrap();
+        let ctx = manager.acquire_context(None, None).await.unwrap();
         assert!(ctx.token == "t1" || ctx.token == "t2");
         assert_eq!(manager.available_count(), 2);
     }
@@ -2078,8 +3132,186 @@ mod tests {
         assert_eq!(manager.available_count(), 0);
     }
 
+    fn set_usage(manager: &MultiTokenManager, id: u64, usage_limit: f64, current_usage: f64) {
+        let mut entries = manager.entries.lock();
+        let entry = entries.iter_mut().find(|e| e.id == id).unwrap();
+        entry.usage = Some(UsageInfo {
+            usage_limit,
+            current_usage,
+            next_reset_at: None,
+        });
+    }
+
+    #[test]
+    fn test_quota_mode_prefers_most_remaining_allowance() {
+        let mut config = Config::default();
+        config.load_balancing_mode = "quota".to_string();
+        let creds = (0..4)
+            .map(|i| KiroCredentials {
+                priority: i,
+                ..Default::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+
+        // #1 close to its limit, #2 no usage data yet, #3 and #4 with allowance
+        set_usage(&manager, 1, 1000.0, 990.0);
+        set_usage(&manager, 3, 50.0, 10.0);
+        set_usage(&manager, 4, 1000.0, 500.0);
+        assert_eq!(manager.select_next_credential(None, None).unwrap().0, 4);
+
+        set_usage(&manager, 4, 1000.0, 980.0);
+        assert_eq!(manager.select_next_credential(None, None).unwrap().0, 3);
+
+        set_usage(&manager, 3, 50.0, 50.0);
+        assert_eq!(manager.select_next_credential(None, None).unwrap().0, 2);
+        // Among credentials close to their limit, the most remaining wins
+        manager.set_disabled(2, true).unwrap();
+        assert_eq!(manager.select_next_credential(None, None).unwrap().0, 4);
+    }
+
+    #[test]
+    fn test_quota_exceeded_recovers_when_allowance_is_back() {
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![KiroCredentials::default(), KiroCredentials::default()],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        set_usage(&manager, 1, 1000.0, 100.0);
+        manager.report_quota_exhausted(1);
+        assert_eq!(manager.available_count(), 1);
+
+        // Still spent after the reset date: stays disabled and is probed again later
+        let due = first_day_of_next_month(Utc::now());
+        let spent = UsageInfo {
+            usage_limit: 1000.0,
+            current_usage: 1000.0,
+            next_reset_at: None,
+        };
+        assert!(!manager.apply_quota_probe(1, Some(spent), due));
+        assert_eq!(manager.available_count(), 1);
+
+        // Usage query after the reset shows allowance again
+        let retry_at = due + Duration::minutes(QUOTA_PROBE_RETRY_MINUTES);
+        let refilled = UsageInfo {
+            current_usage: 0.0,
+            ..spent
+        };
+        assert!(manager.apply_quota_probe(1, Some(refilled), retry_at));
+        assert_eq!(manager.available_count(), 2);
+        assert_eq!(manager.snapshot().entries[0].failure_count, 0);
+
+        // Manually disabled credentials are left alone
+        manager.set_disabled(2, true).unwrap();
+        assert!(!manager.apply_quota_probe(2, Some(refilled), retry_at));
+        assert_eq!(manager.available_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_affinity_sticks_until_credential_fails() {
+        let mut config = Config::default();
+        config.load_balancing_mode = "balanced".to_string();
+        config.session_affinity = true;
+        let creds = (1..=2)
+            .map(|i| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+
+        // Least-used would alternate, affinity keeps the conversation on its credential
+        let first = manager.acquire_context(None, Some("s1")).await.unwrap().id;
+        manager.report_success(first);
+        let again = manager.acquire_context(None, Some("s1")).await.unwrap().id;
+        assert_eq!(again, first);
+        let other = manager.acquire_context(None, Some("s2")).await.unwrap().id;
+        assert_ne!(other, first);
+
+        let affinity = |id: u64| {
+            manager
+                .snapshot()
+                .entries
+                .into_iter()
+                .find(|e| e.id == id)
+                .unwrap()
+                .affinity_sessions
+        };
+        assert_eq!(affinity(first), 1);
+        assert_eq!(affinity(other), 1);
+
+        // Auth failure on the pinned credential moves the conversation
+        manager.report_failure(first);
+        let moved = manager.acquire_context(None, Some("s1")).await.unwrap().id;
+        assert_eq!(moved, other);
+        assert_eq!(affinity(first), 0);
+        assert_eq!(affinity(other), 2);
+
+        // Quota exhaustion moves it as well
+        manager.report_quota_exhausted(other);
+        let moved = manager.acquire_context(None, Some("s2")).await.unwrap().id;
+        assert_eq!(moved, first);
+    }
+
     #[tokio::test]
-    async fn test_multi_token_manager_quota_disabled_is_not_auto_recovered() {
+    async fn test_session_affinity_skips_free_tier_for_opus() {
+        let mut config = Config::default();
+        config.load_balancing_mode = "balanced".to_string();
+        config.session_affinity = true;
+        let creds = ["KIRO FREE", "KIRO PRO"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, title)| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                subscription_title: Some(title.to_string()),
+                priority: i as u32,
+                ..Default::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+
+        // Conversation pinned to the FREE credential by a Sonnet request
+        let first = manager
+            .acquire_context(Some("claude-sonnet-4-5"), Some("s1"))
+            .await
+            .unwrap()
+            .id;
+        assert_eq!(first, 1);
+
+        // An Opus request in the same conversation moves to the paid credential
+        let opus = manager
+            .acquire_context(Some("claude-opus-4-5"), Some("s1"))
+            .await
+            .unwrap()
+            .id;
+        assert_eq!(opus, 2);
+    }
+
+    #[tokio::test]
+    async fn test_session_affinity_pins_expire() {
+        let mut config = Config::default();
+        config.session_affinity = true;
+        config.session_affinity_ttl_secs = 0;
+        let cred = KiroCredentials {
+            access_token: Some("t1".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        let manager = MultiTokenManager::new(config, vec![cred], None, None, false).unwrap();
+
+        manager.acquire_context(None, Some("s1")).await.unwrap();
+        assert_eq!(manager.snapshot().entries[0].affinity_sessions, 0);
+        assert!(manager.affinity.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_multi_token_manager_quota_disabled_recovers_after_reset() {
         let config = Config::default();
         let cred1 = KiroCredentials::default();
         let cred2 = KiroCredentials::default();
@@ -2091,13 +3323,259 @@ mod tests {
         manager.report_quota_exhausted(2);
         assert_eq!(manager.available_count(), 0);
 
-        let err = manager.acquire_context(None).await.err().unwrap().to_string();
+        // Requests don't self-heal quota-disabled credentials
+        let err = manager.acquire_context(None, None).await.err().unwrap().to_string();
         assert!(
             err.contains("All credentials are disabled"),
             "Error should indicate all credentials disabled, actual: {}",
             err
         );
         assert_eq!(manager.available_count(), 0);
+
+        // Without usage data the reset falls back to the first day of next month
+        let now = Utc::now();
+        let fallback = first_day_of_next_month(now).to_rfc3339();
+        assert_eq!(
+            manager.snapshot().entries[0].quota_reset_at.as_deref(),
+            Some(fallback.as_str())
+        );
+
+        // First probe supplies the real reset date
+        let reset_at = now + Duration::days(3);
+        let spent = UsageInfo {
+            usage_limit: 1000.0,
+            current_usage: 1000.0,
+            next_reset_at: Some(reset_at.timestamp() as f64),
+        };
+        assert!(!manager.apply_quota_probe(1, Some(spent), now));
+        let reset_at = reset_date(reset_at.timestamp() as f64).unwrap();
+        assert_eq!(
+            manager.snapshot().entries[0].quota_reset_at,
+            Some(reset_at.to_rfc3339())
+        );
+
+        // Allowance is only trusted after the reset date
+        let refilled = UsageInfo {
+            current_usage: 0.0,
+            ..spent
+        };
+        assert!(!manager.apply_quota_probe(1, Some(refilled), now));
+        assert!(manager.apply_quota_probe(1, Some(refilled), reset_at));
+        assert_eq!(manager.available_count(), 1);
+
+        let history = manager.credential_history(1).unwrap();
+        let events: Vec<_> = history
+            .iter()
+            .map(|e| (e.event.as_str(), e.reason.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![("disabled", "quota_exceeded"), ("enabled", "quota_reset")]
+        );
+
+        // A failed probe after the reset date retries later
+        let due = first_day_of_next_month(now);
+        assert!(!manager.apply_quota_probe(2, None, due));
+        assert_eq!(
+            manager.snapshot().entries[1].quota_reset_at,
+            Some((due + Duration::minutes(QUOTA_PROBE_RETRY_MINUTES)).to_rfc3339())
+        );
+        assert_eq!(manager.available_count(), 1);
+    }
+
+    #[test]
+    fn test_first_day_of_next_month() {
+        let date = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 12, 30, 0).unwrap();
+        assert_eq!(
+            first_day_of_next_month(date(2025, 1, 31)),
+            Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            first_day_of_next_month(date(2025, 12, 15)),
+            Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_credential_history_records_transitions() {
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![KiroCredentials::default(), KiroCredentials::default()],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        for _ in 0..MAX_FAILURES_PER_CREDENTIAL {
+            manager.report_failure(1);
+        }
+        manager.set_disabled(1, true).unwrap();
+        manager.set_disabled(1, true).unwrap();
+        manager.reset_and_enable(1).unwrap();
+        manager.reset_and_enable(1).unwrap();
+
+        let events: Vec<_> = manager
+            .credential_history(1)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.event, e.reason))
+            .collect();
+        let expected = [
+            ("disabled", "too_many_failures"),
+            ("disabled", "manual"),
+            ("enabled", "manual"),
+        ];
+        assert_eq!(
+            events,
+            expected.map(|(e, r)| (e.to_string(), r.to_string()))
+        );
+        assert!(manager.credential_history(2).unwrap().is_empty());
+        assert!(manager.credential_history(9).is_err());
+    }
+
+    #[test]
+    fn test_proactive_refresh_selects_expiring_and_backs_off() {
+        let expiring_in = |minutes| KiroCredentials {
+            access_token: Some("token".to_string()),
+            expires_at: Some((Utc::now() + Duration::minutes(minutes)).to_rfc3339()),
+            ..Default::default()
+        };
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![
+                expiring_in(5),
+                expiring_in(120),
+                expiring_in(5),
+                KiroCredentials {
+                    access_token: Some("token".to_string()),
+                    ..Default::default()
+                },
+            ],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+        manager.set_disabled(3, true).unwrap();
+        let needs_refresh =
+            |c: &KiroCredentials| is_token_expiring_within(c, PROACTIVE_REFRESH_MINUTES);
+
+        let now = Utc::now();
+        assert_eq!(
+            manager.credentials_due_for_refresh(now, needs_refresh),
+            vec![1, 4]
+        );
+
+        // Unknown expiry is refreshed once, not on every pass
+        manager.entries.lock()[3].record_refresh(Ok(()));
+        assert_eq!(
+            manager.credentials_due_for_refresh(now, needs_refresh),
+            vec![1]
+        );
+
+        // A failure backs off exponentially and is reported in the snapshot
+        for _ in 0..2 {
+            let mut entries = manager.entries.lock();
+            entries[0].record_refresh(Err("invalid_grant".to_string()));
+        }
+        let health = manager.snapshot().entries[0].refresh_health.clone();
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.last_error.as_deref(), Some("invalid_grant"));
+        assert!(
+            manager
+                .credentials_due_for_refresh(now, needs_refresh)
+                .is_empty()
+        );
+        assert_eq!(
+            manager.credentials_due_for_refresh(now + Duration::minutes(3), needs_refresh),
+            vec![1]
+        );
+
+        // Success clears the failure state
+        manager.entries.lock()[0].record_refresh(Ok(()));
+        let health = manager.snapshot().entries[0].refresh_health.clone();
+        assert_eq!(health.consecutive_failures, 0);
+        assert!(health.last_error.is_none() && health.next_retry_at.is_none());
+        assert!(health.last_refresh_at.is_some());
+
+        assert_eq!(refresh_backoff(1), Duration::minutes(1));
+        assert_eq!(refresh_backoff(3), Duration::minutes(4));
+        assert_eq!(
+            refresh_backoff(20),
+            Duration::minutes(REFRESH_BACKOFF_MAX_MINUTES)
+        );
+    }
+
+    #[test]
+    fn test_persist_writes_back_to_owning_source() {
+        let dir = std::env::temp_dir().join(format!("kiro-persist-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let credentials_path = dir.join("credentials.json");
+        let writable = dir.join("a.json");
+        let read_only = dir.join("b.json");
+        std::fs::write(&credentials_path, "[]").unwrap();
+        std::fs::write(&read_only, r#"{"refreshToken":"dir-b"}"#).unwrap();
+
+        let cred = |token: &str, source| KiroCredentials {
+            refresh_token: Some(token.to_string()),
+            source,
+            ..Default::default()
+        };
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![
+                cred("file", CredentialSource::File),
+                cred(
+                    "dir-a",
+                    CredentialSource::Directory {
+                        path: writable.clone(),
+                        read_only: false,
+                    },
+                ),
+                cred(
+                    "dir-b",
+                    CredentialSource::Directory {
+                        path: read_only.clone(),
+                        read_only: true,
+                    },
+                ),
+                cred("env", CredentialSource::Env { index: 1 }),
+            ],
+            None,
+            Some(credentials_path.clone()),
+            true,
+        )
+        .unwrap();
+
+        // New IDs are written to the owning writable source only
+        let written: Vec<KiroCredentials> =
+            serde_json::from_str(&std::fs::read_to_string(&credentials_path).unwrap()).unwrap();
+        assert_eq!(written.len(), 1);
+        assert_eq!(written[0].refresh_token.as_deref(), Some("file"));
+        let dir_cred: KiroCredentials =
+            serde_json::from_str(&std::fs::read_to_string(&writable).unwrap()).unwrap();
+        assert_eq!(dir_cred.id, Some(2));
+        assert_eq!(
+            std::fs::read_to_string(&read_only).unwrap(),
+            r#"{"refreshToken":"dir-b"}"#
+        );
+
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.entries[3].source, "env:1");
+        assert!(snapshot.entries[3].read_only);
+
+        // Read-only credentials can't be deleted, directory credentials lose their file
+        for id in [2, 3, 4] {
+            manager.set_disabled(id, true).unwrap();
+        }
+        let err = manager.delete_credential(4).unwrap_err();
+        assert!(err.to_string().contains("read-only source"));
+        assert!(manager.delete_credential(3).is_err());
+        manager.delete_credential(2).unwrap();
+        assert!(!writable.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     // ============ Credential-level Region priority tests ============
@@ -1,20 +1,25 @@
 mod admin;
 mod admin_ui;
 mod anthropic;
+mod cli;
 mod common;
+mod debug;
 mod http_client;
 mod kiro;
+mod metrics;
 mod model;
 mod oauth;
+mod reload;
 pub mod token;
 
 use std::sync::Arc;
 
 use clap::Parser;
-use kiro::model::credentials::{CredentialsConfig, KiroCredentials};
+use common::encryption::{self, FileCipher};
+use kiro::model::credentials::KiroCredentials;
 use kiro::provider::KiroProvider;
 use kiro::token_manager::MultiTokenManager;
-use model::arg::Args;
+use model::arg::{Args, Command};
 use model::config::Config;
 
 #[tokio::main]
@@ -39,20 +44,106 @@ async fn main() {
         std::process::exit(1);
     });
 
+    // Model catalog (before anything converts a request)
+    if let Some(path) = &config.model_catalog {
+        match model::catalog::ModelCatalog::load(std::path::Path::new(path)) {
+            Ok(catalog) => {
+                tracing::info!("Loaded {} models from {}", catalog.models().len(), path);
+                model::catalog::init(catalog);
+            }
+            Err(e) => {
+                tracing::error!("{:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Load credentials (supports single object or array format)
     let credentials_path = args
         .credentials
         .unwrap_or_else(|| KiroCredentials::default_credentials_path().to_string());
-    let credentials_config = CredentialsConfig::load(&credentials_path).unwrap_or_else(|e| {
-        tracing::error!("Failed to load credentials: {}", e);
-        std::process::exit(1);
-    });
+
+    // Encryption at rest (set up before capture bundles, credentials and caches are read)
+    let cipher = FileCipher::from_env_or_file(config.encryption_key_file.as_deref())
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load encryption key: {:#}", e);
+            std::process::exit(1);
+        });
+
+    if let Some(Command::MigrateEncryption { decrypt }) = &args.command {
+        if let Err(e) = cli::migrate_encryption(
+            cipher.as_ref(),
+            *decrypt,
+            &config,
+            &config_path,
+            &credentials_path,
+        ) {
+            tracing::error!("Migration failed: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if cipher.is_some() {
+        tracing::info!("Encryption at rest enabled for credentials and caches");
+    }
+    encryption::init(cipher);
+
+    // Offline subcommands (no credentials needed)
+    let offline_result = match &args.command {
+        Some(Command::Replay { paths }) => Some(cli::replay(&config, paths)),
+        Some(Command::Decode { file }) => Some(cli::decode(file)),
+        Some(Command::Convert { request }) => Some(cli::convert(&config, request)),
+        _ => None,
+    };
+    if let Some(result) = offline_result {
+        if let Err(e) = result {
+            tracing::error!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Credentials file, credentials directory and environment variables
+    let loaded = kiro::credential_sources::load_all(&config, std::path::Path::new(&credentials_path))
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load credentials: {:#}", e);
+            std::process::exit(1);
+        });
+
+    // Subcommands working on the loaded credentials
+    let credentials_result = match &args.command {
+        Some(Command::Check) => Some(cli::check(&config, &config_path, &loaded, &credentials_path).await),
+        Some(Command::AddCredential {
+            method,
+            start_url,
+            region,
+        }) => Some(
+            cli::add_credential(
+                &config,
+                &loaded,
+                &credentials_path,
+                *method,
+                start_url.as_deref(),
+                region.as_deref(),
+            )
+            .await,
+        ),
+        _ => None,
+    };
+    if let Some(result) = credentials_result {
+        if let Err(e) = result {
+            tracing::error!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     // Determine if it's multi-credential format (for write-back after refresh)
-    let is_multiple_format = credentials_config.is_multiple();
+    let is_multiple_format = loaded.is_multiple_format;
 
-    // Convert to priority-sorted credentials list
-    let credentials_list = credentials_config.into_sorted_credentials();
+    // Priority-sorted credentials list
+    let credentials_list = loaded.credentials;
     tracing::info!("Loaded {} credential configurations", credentials_list.len());
 
     // Get first credential for logging display
@@ -66,13 +157,7 @@ async fn main() {
     });
 
     // Build proxy configuration
-    let proxy_config = config.proxy_url.as_ref().map(|url| {
-        let mut proxy = http_client::ProxyConfig::new(url);
-        if let (Some(username), Some(password)) = (&config.proxy_username, &config.proxy_password) {
-            proxy = proxy.with_auth(username, password);
-        }
-        proxy
-    });
+    let proxy_config = config.proxy_config();
 
     if proxy_config.is_some() {
         tracing::info!("HTTP proxy configured: {}", config.proxy_url.as_ref().unwrap());
@@ -83,7 +168,7 @@ async fn main() {
         config.clone(),
         credentials_list,
         proxy_config.clone(),
-        Some(credentials_path.into()),
+        Some(credentials_path.clone().into()),
         is_multiple_format,
     )
     .unwrap_or_else(|e| {
@@ -91,23 +176,45 @@ async fn main() {
         std::process::exit(1);
     });
     let token_manager = Arc::new(token_manager);
+    // Background usage queries for quota load balancing
+    token_manager.spawn_usage_refresher();
+    // Re-enable quota-exhausted credentials after their quota resets
+    token_manager.spawn_quota_recovery();
+    // Refresh Tokens ahead of expiry so requests rarely wait on a refresh
+    token_manager.spawn_proactive_refresh();
     let kiro_provider = KiroProvider::with_proxy(token_manager.clone(), proxy_config.clone());
 
     // Initialize count_tokens configuration
-    token::init_config(token::CountTokensConfig {
-        api_url: config.count_tokens_api_url.clone(),
-        api_key: config.count_tokens_api_key.clone(),
-        auth_type: config.count_tokens_auth_type.clone(),
-        proxy: proxy_config.clone(),
-        tls_backend: config.tls_backend,
+    token::init_config(token::CountTokensConfig::from_config(&config));
+
+    // Initialize tool compression threshold
+    anthropic::tool_compression::init_config(config.tool_compression_threshold_bytes);
+
+    // Load client API keyring (stored next to config.json)
+    let keyring_path = anthropic::keyring::ApiKeyring::path_for_config(std::path::Path::new(&config_path));
+    let keyring = anthropic::keyring::ApiKeyring::load(&keyring_path).unwrap_or_else(|e| {
+        tracing::error!("Failed to load API keyring: {}", e);
+        std::process::exit(1);
     });
+    tracing::info!("Loaded {} client API keys from {}", keyring.count(), keyring_path.display());
+    let keyring = Arc::new(keyring);
 
-    // Build Anthropic API router (get profile_arn from first credential)
+    // Hot reload of config.json and credentials.json (file changes, SIGHUP, Admin API)
+    let reloader = Arc::new(reload::Reloader::new(
+        &config_path,
+        &credentials_path,
+        token_manager.clone(),
+    ));
+    reloader.spawn_file_watcher();
+    reloader.spawn_signal_handler();
+
+    // Build Anthropic API router (profileArn is injected per credential by KiroProvider)
+    // Shares the token manager's config so runtime-safe fields follow hot reloads
     let anthropic_app = anthropic::create_router_with_provider(
         &api_key,
         Some(kiro_provider),
-        first_credentials.profile_arn.clone(),
-        config.clone(),
+        keyring.clone(),
+        token_manager.shared_config(),
     );
 
     // Build Admin API router (if non-empty admin_api_key is configured)
@@ -118,20 +225,21 @@ async fn main() {
         .map(|k| !k.trim().is_empty())
         .unwrap_or(false);
 
-    // Create OAuth Web handler
-    let oauth_handler = Arc::new(oauth::OAuthWebHandler::new(
-        config.clone(),
-        proxy_config.clone(),
-        token_manager.clone(),
-    ));
-    let oauth_app = oauth::create_oauth_router(oauth_handler);
+    // OAuth Web routes are protected by the admin key, so they require one
+    let oauth_web_enabled = admin_key_valid && config.oauth_web_enabled;
+    if !config.oauth_web_enabled {
+        tracing::info!("OAuth Web disabled by config (oauthWebEnabled = false)");
+    } else if !admin_key_valid {
+        tracing::warn!("adminApiKey is not configured, OAuth Web not enabled");
+    }
 
     let app = if let Some(admin_key) = &config.admin_api_key {
         if admin_key.trim().is_empty() {
             tracing::warn!("admin_api_key is empty, Admin API not enabled");
-            anthropic_app.nest("/v0/oauth", oauth_app)
+            anthropic_app
         } else {
-            let admin_service = admin::AdminService::new(token_manager.clone());
+            let admin_service = admin::AdminService::new(token_manager.clone(), keyring.clone())
+                .with_reloader(reloader.clone());
             let admin_state = admin::AdminState::new(admin_key, admin_service);
             let admin_app = admin::create_admin_router(admin_state);
 
@@ -140,14 +248,27 @@ async fn main() {
 
             tracing::info!("Admin API enabled");
             tracing::info!("Admin UI enabled: /admin");
-            tracing::info!("OAuth Web enabled: /v0/oauth/kiro");
-            anthropic_app
+            let app = anthropic_app
                 .nest("/api/admin", admin_app)
-                .nest("/admin", admin_ui_app)
-                .nest("/v0/oauth", oauth_app)
+                .nest("/admin", admin_ui_app);
+
+            if oauth_web_enabled {
+                // Create OAuth Web handler
+                let oauth_handler = Arc::new(oauth::OAuthWebHandler::new(
+                    config.clone(),
+                    token_manager.clone(),
+                ));
+                tracing::info!("OAuth Web enabled: /v0/oauth/kiro");
+                app.nest(
+                    "/v0/oauth",
+                    oauth::create_oauth_router(oauth_handler, admin_key),
+                )
+            } else {
+                app
+            }
         }
     } else {
-        anthropic_app.nest("/v0/oauth", oauth_app)
+        anthropic_app
     };
 
     // Start server
@@ -160,8 +281,12 @@ async fn main() {
     tracing::info!("  GET  /v1/models");
     tracing::info!("  POST /v1/messages");
     tracing::info!("  POST /v1/messages/count_tokens");
-    tracing::info!("OAuth Web:");
-    tracing::info!("  GET  /v0/oauth/kiro");
+    tracing::info!("  GET  /metrics");
+    if oauth_web_enabled {
+        tracing::info!("OAuth Web (admin key or login session):");
+        tracing::info!("  GET  /v0/oauth/kiro");
+        tracing::info!("  GET  /v0/oauth/kiro/login");
+    }
     if admin_key_valid {
         tracing::info!("Admin API:");
         tracing::info!("  GET  /api/admin/credentials");
@@ -169,10 +294,18 @@ async fn main() {
         tracing::info!("  POST /api/admin/credentials/:index/priority");
         tracing::info!("  POST /api/admin/credentials/:index/reset");
         tracing::info!("  GET  /api/admin/credentials/:index/balance");
+        tracing::info!("  GET  /api/admin/credentials/:index/history");
+        tracing::info!("  GET  /api/admin/keys");
+        tracing::info!("  POST /api/admin/config/reload");
         tracing::info!("Admin UI:");
         tracing::info!("  GET  /admin");
     }
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,375 @@
+//! Prometheus metrics
+//!
+//! Process-wide metrics registry rendered by `GET /metrics` in the Prometheus text format.
+//! Credential gauges are refreshed from `MultiTokenManager::snapshot` at scrape time.
+
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::kiro::token_manager::ManagerSnapshot;
+use crate::model::catalog;
+
+/// Latency histogram buckets (seconds), upstream calls can take minutes
+const LATENCY_BUCKETS: &[f64] = &[
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(collector: T) -> T {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("Failed to register metric");
+    collector
+}
+
+static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "kiro_http_requests_total",
+                "Client requests by route, model and status",
+            ),
+            &["route", "model", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+static UPSTREAM_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "kiro_upstream_request_duration_seconds",
+                "Time spent in the upstream retry loop until a response or final error",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["api", "outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+static UPSTREAM_TTFB: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "kiro_upstream_time_to_first_byte_seconds",
+                "Time until upstream response headers for successful attempts",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["api"],
+        )
+        .unwrap(),
+    )
+});
+
+static UPSTREAM_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "kiro_upstream_retries_total",
+                "Failed upstream attempts by reason",
+            ),
+            &["reason"],
+        )
+        .unwrap(),
+    )
+});
+
+static TOKEN_REFRESHES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "kiro_token_refresh_total",
+                "Token refresh attempts by outcome",
+            ),
+            &["outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+static DECODER_ERRORS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(
+        IntCounter::new(
+            "kiro_decoder_errors_total",
+            "Event stream frame parse errors",
+        )
+        .unwrap(),
+    )
+});
+
+static DECODER_SKIPPED_BYTES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(
+        IntCounter::new(
+            "kiro_decoder_skipped_bytes_total",
+            "Bytes skipped by event stream error recovery",
+        )
+        .unwrap(),
+    )
+});
+
+static CLIENT_DISCONNECTS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(
+        IntCounter::new(
+            "kiro_client_disconnects_total",
+            "Streaming responses cancelled because the client disconnected",
+        )
+        .unwrap(),
+    )
+});
+
+static TOKENS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "kiro_tokens_total",
+                "Estimated tokens by model and direction",
+            ),
+            &["model", "direction"],
+        )
+        .unwrap(),
+    )
+});
+
+static CREDENTIALS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new("kiro_credentials", "Number of credentials by state"),
+            &["state"],
+        )
+        .unwrap(),
+    )
+});
+
+static CURRENT_CREDENTIAL: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(IntGauge::new("kiro_current_credential_id", "Current active credential ID").unwrap())
+});
+
+static CREDENTIAL_DISABLED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "kiro_credential_disabled",
+                "Whether the credential is disabled (1) or not (0)",
+            ),
+            &["id"],
+        )
+        .unwrap(),
+    )
+});
+
+static CREDENTIAL_FAILURES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "kiro_credential_failure_count",
+                "Consecutive API call failures per credential",
+            ),
+            &["id"],
+        )
+        .unwrap(),
+    )
+});
+
+static CREDENTIAL_SUCCESSES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "kiro_credential_success_count",
+                "Successful API calls per credential",
+            ),
+            &["id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Model of the current request, filled in by the handler once the request body is parsed
+///
+/// Inserted into request extensions by the metrics middleware so the model label can be
+/// attached to the request counter after the handler returns.
+#[derive(Clone, Default)]
+pub struct RequestModel(Arc<OnceLock<String>>);
+
+impl RequestModel {
+    /// Set the model (first call wins)
+    pub fn set(&self, model: &str) {
+        let _ = self.0.set(model_label(model));
+    }
+
+    /// Model label ("unknown" if the handler did not set it)
+    pub fn label(&self) -> &str {
+        self.0.get().map(String::as_str).unwrap_or("unknown")
+    }
+}
+
+/// Model label for a client-supplied model name
+///
+/// Names are resolved through the model catalog so label cardinality stays bounded:
+/// known models are reported by their catalog ID, anything else as `"other"`.
+pub fn model_label(model: &str) -> String {
+    catalog::current()
+        .resolve(model)
+        .map(|entry| entry.id.clone())
+        .unwrap_or_else(|| "other".to_string())
+}
+
+/// Record a finished client request
+pub fn record_request(route: &str, model: &str, status: u16) {
+    HTTP_REQUESTS
+        .with_label_values(&[route, model, &status.to_string()])
+        .inc();
+}
+
+/// Record an upstream retry loop that returned a response (`outcome = "success"`) or gave up
+pub fn observe_upstream_duration(api: &str, outcome: &str, duration: Duration) {
+    UPSTREAM_DURATION
+        .with_label_values(&[api, outcome])
+        .observe(duration.as_secs_f64());
+}
+
+/// Record time to response headers of a successful upstream attempt
+pub fn observe_upstream_ttfb(api: &str, duration: Duration) {
+    UPSTREAM_TTFB
+        .with_label_values(&[api])
+        .observe(duration.as_secs_f64());
+}
+
+/// Record a failed upstream attempt
+///
+/// Reasons: `quota_exhausted` (402), `auth_error` (401/403), `transient` (408/429/5xx),
+/// `network` (send failed) and `other` (unexpected status)
+pub fn record_upstream_retry(reason: &str) {
+    UPSTREAM_RETRIES.with_label_values(&[reason]).inc();
+}
+
+/// Record a token refresh outcome
+pub fn record_token_refresh(success: bool) {
+    let outcome = if success { "success" } else { "failure" };
+    TOKEN_REFRESHES.with_label_values(&[outcome]).inc();
+}
+
+/// Record an event stream frame parse error
+pub fn record_decoder_error() {
+    DECODER_ERRORS.inc();
+}
+
+/// Record bytes skipped by event stream error recovery
+pub fn record_decoder_skipped_bytes(bytes: usize) {
+    DECODER_SKIPPED_BYTES.inc_by(bytes as u64);
+}
+
+/// Record a streaming response cancelled because the client disconnected
+pub fn record_client_disconnect() {
+    CLIENT_DISCONNECTS.inc();
+}
+
+/// Record estimated token usage of a response
+pub fn record_tokens(model: &str, input_tokens: i32, output_tokens: i32) {
+    let model = model_label(model);
+    let model = model.as_str();
+    TOKENS
+        .with_label_values(&[model, "input"])
+        .inc_by(input_tokens.max(0) as u64);
+    TOKENS
+        .with_label_values(&[model, "output"])
+        .inc_by(output_tokens.max(0) as u64);
+}
+
+/// Refresh credential gauges from a token manager snapshot
+pub fn update_credentials(snapshot: &ManagerSnapshot) {
+    CREDENTIALS
+        .with_label_values(&["available"])
+        .set(snapshot.available as i64);
+    CREDENTIALS
+        .with_label_values(&["disabled"])
+        .set((snapshot.total - snapshot.available) as i64);
+    CURRENT_CREDENTIAL.set(snapshot.current_id as i64);
+
+    // Reset per-credential series so deleted credentials disappear
+    CREDENTIAL_DISABLED.reset();
+    CREDENTIAL_FAILURES.reset();
+    CREDENTIAL_SUCCESSES.reset();
+    for entry in &snapshot.entries {
+        let id = entry.id.to_string();
+        CREDENTIAL_DISABLED
+            .with_label_values(&[&id])
+            .set(entry.disabled as i64);
+        CREDENTIAL_FAILURES
+            .with_label_values(&[&id])
+            .set(entry.failure_count as i64);
+        CREDENTIAL_SUCCESSES
+            .with_label_values(&[&id])
+            .set(entry.success_count as i64);
+    }
+}
+
+/// Render all metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    // Touch lazily registered metrics so they are exported before their first observation
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&UPSTREAM_DURATION);
+    LazyLock::force(&UPSTREAM_TTFB);
+    LazyLock::force(&UPSTREAM_RETRIES);
+    LazyLock::force(&TOKEN_REFRESHES);
+    LazyLock::force(&DECODER_ERRORS);
+    LazyLock::force(&DECODER_SKIPPED_BYTES);
+    LazyLock::force(&CLIENT_DISCONNECTS);
+    LazyLock::force(&TOKENS);
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        tracing::warn!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_contains_recorded_metrics() {
+        record_request("/v1/messages", "claude-sonnet-4-5", 200);
+        record_upstream_retry("network");
+        record_tokens("claude-sonnet-4-5-thinking", 10, 5);
+        record_decoder_skipped_bytes(3);
+
+        let output = render();
+        assert!(output.contains(
+            r#"kiro_http_requests_total{model="claude-sonnet-4-5",route="/v1/messages",status="200"}"#
+        ));
+        assert!(output.contains(r#"kiro_upstream_retries_total{reason="network"}"#));
+        assert!(output.contains(
+            r#"kiro_tokens_total{direction="output",model="claude-sonnet-4-5-20250929"}"#
+        ));
+        assert!(output.contains("kiro_decoder_skipped_bytes_total"));
+    }
+
+    #[test]
+    fn test_request_model_defaults_to_unknown() {
+        let model = RequestModel::default();
+        assert_eq!(model.label(), "unknown");
+        model.set("claude-opus-4-6");
+        model.set("ignored");
+        assert_eq!(model.label(), "claude-opus-4-6");
+    }
+
+    #[test]
+    fn test_model_label_is_bounded_by_catalog() {
+        assert_eq!(
+            model_label("claude-sonnet-4-5-thinking"),
+            "claude-sonnet-4-5-20250929"
+        );
+        assert_eq!(model_label("Claude-3-5-Sonnet-Latest"), "claude-sonnet");
+        assert_eq!(model_label("gpt-4o"), "other");
+        assert_eq!(model_label(&"x".repeat(1000)), "other");
+    }
+}
@@ -1,14 +1,121 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 /// Anthropic <-> Kiro API Client
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
     /// Configuration file path
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub config: Option<String>,
 
     /// Credentials file path
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub credentials: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands (the server is started when none is given)
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Encrypt credentials, API keyring and cache files in place with the configured key
+    MigrateEncryption {
+        /// Write the files back as plain JSON instead
+        #[arg(long)]
+        decrypt: bool,
+    },
+    /// Replay capture bundles offline and report differences to the captured output
+    Replay {
+        /// Bundle files or capture directories
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Validate config and credentials, refresh every token and print usage limits
+    Check,
+    /// Add a credential by signing in with Builder ID or IdC (device flow)
+    AddCredential {
+        /// Sign-in method
+        #[arg(long, value_enum, default_value_t = DeviceAuthMethod::BuilderId)]
+        method: DeviceAuthMethod,
+        /// IdC start URL (required for idc)
+        #[arg(long)]
+        start_url: Option<String>,
+        /// OIDC region (default: us-east-1)
+        #[arg(long)]
+        region: Option<String>,
+    },
+    /// Print the events of an event-stream capture (raw response body or capture bundle)
+    Decode {
+        /// Capture file
+        file: String,
+    },
+    /// Print the Kiro request the server would send for an Anthropic request body
+    Convert {
+        /// Anthropic Messages request JSON file
+        request: String,
+    },
+}
+
+/// Device flow sign-in method
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceAuthMethod {
+    BuilderId,
+    Idc,
+}
+
+impl DeviceAuthMethod {
+    /// Credential `authMethod` value
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceAuthMethod::BuilderId => "builder-id",
+            DeviceAuthMethod::Idc => "idc",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_add_credential() {
+        let args = Args::parse_from([
+            "kiro-rs",
+            "-c",
+            "config.json",
+            "add-credential",
+            "--method",
+            "idc",
+            "--start-url",
+            "https://example.awsapps.com/start",
+        ]);
+        assert_eq!(args.config.as_deref(), Some("config.json"));
+        match args.command {
+            Some(Command::AddCredential {
+                method, start_url, ..
+            }) => {
+                assert_eq!(method, DeviceAuthMethod::Idc);
+                assert_eq!(method.as_str(), "idc");
+                assert_eq!(
+                    start_url.as_deref(),
+                    Some("https://example.awsapps.com/start")
+                );
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_add_credential_defaults_to_builder_id() {
+        let args = Args::parse_from(["kiro-rs", "add-credential"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::AddCredential {
+                method: DeviceAuthMethod::BuilderId,
+                start_url: None,
+                region: None,
+            })
+        ));
+    }
 }
@@ -0,0 +1,248 @@
+//! Model catalog
+//!
+//! Maps the model names clients send to Kiro model IDs and describes each model
+//! (context window, max output, thinking support, required subscription tier).
+//! The built-in catalog is `models.example.json`; `modelCatalog` in config.json points at a
+//! file that replaces it, so new models don't need a code change.
+
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+
+use anyhow::Context;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::Deserialize;
+
+/// Built-in catalog
+const BUILTIN_CATALOG: &str = include_str!("../../models.example.json");
+
+/// Context window used for models not in the catalog
+pub const DEFAULT_CONTEXT_WINDOW: i32 = 200_000;
+
+/// Name suffixes selecting a request mode rather than a model
+const VARIANT_SUFFIXES: [&str; 2] = ["-thinking", "-agentic"];
+
+/// Subscription tier a model requires
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelTier {
+    /// Available to every account
+    #[default]
+    Free,
+    /// Not available to FREE tier accounts
+    Paid,
+}
+
+/// Catalog entry
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelEntry {
+    /// Public model ID (listed in /v1/models)
+    pub id: String,
+    /// Model ID sent to Kiro
+    pub kiro_id: String,
+    /// Display name
+    pub display_name: String,
+    /// Release time (Unix seconds)
+    #[serde(default)]
+    pub created: i64,
+    /// Other names matched exactly (case-insensitive)
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Regexes matched against the lowercased model name, in catalog order
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Context window size (tokens)
+    #[serde(default = "default_context_window")]
+    pub context_window: i32,
+    /// Maximum output tokens
+    #[serde(default = "default_max_output")]
+    pub max_output: i32,
+    /// Whether thinking is supported
+    #[serde(default)]
+    pub thinking: bool,
+    /// Whether the thinking suffix selects adaptive thinking (high effort) instead of a budget
+    #[serde(default)]
+    pub adaptive_thinking: bool,
+    /// Required subscription tier
+    #[serde(default)]
+    pub tier: ModelTier,
+    /// Whether the model is listed in /v1/models (unlisted entries only map names)
+    #[serde(default = "default_listed")]
+    pub listed: bool,
+}
+
+fn default_context_window() -> i32 {
+    DEFAULT_CONTEXT_WINDOW
+}
+
+fn default_max_output() -> i32 {
+    64_000
+}
+
+fn default_listed() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct CatalogFile {
+    models: Vec<ModelEntry>,
+}
+
+/// Loaded model catalog
+#[derive(Debug)]
+pub struct ModelCatalog {
+    models: Vec<ModelEntry>,
+    /// Compiled patterns with the index of their entry, in catalog order
+    patterns: Vec<(Regex, usize)>,
+}
+
+impl ModelCatalog {
+    /// Parse a catalog from JSON
+    pub fn from_json(content: &str) -> anyhow::Result<Self> {
+        let file: CatalogFile = serde_json::from_str(content)?;
+        if file.models.is_empty() {
+            anyhow::bail!("Model catalog has no models");
+        }
+        let mut patterns = Vec::new();
+        for (index, entry) in file.models.iter().enumerate() {
+            for pattern in &entry.patterns {
+                let regex = Regex::new(&format!("(?i){}", pattern)).with_context(|| {
+                    format!("Invalid pattern {:?} for model {}", pattern, entry.id)
+                })?;
+                patterns.push((regex, index));
+            }
+        }
+        Ok(Self {
+            models: file.models,
+            patterns,
+        })
+    }
+
+    /// Load a catalog file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read model catalog {}", path.display()))?;
+        Self::from_json(&content)
+            .with_context(|| format!("Invalid model catalog {}", path.display()))
+    }
+
+    /// Built-in catalog
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_CATALOG).expect("built-in model catalog is valid")
+    }
+
+    /// All entries, in catalog order
+    pub fn models(&self) -> &[ModelEntry] {
+        &self.models
+    }
+
+    /// Find the entry for a requested model name
+    ///
+    /// The ID and aliases are matched exactly first (ignoring `-thinking`/`-agentic` suffixes),
+    /// then the patterns in catalog order.
+    pub fn resolve(&self, model: &str) -> Option<&ModelEntry> {
+        let mut name = model.to_lowercase();
+        while let Some(base) = VARIANT_SUFFIXES
+            .iter()
+            .find_map(|suffix| name.strip_suffix(suffix))
+        {
+            name = base.to_string();
+        }
+
+        self.models
+            .iter()
+            .find(|m| {
+                m.id.eq_ignore_ascii_case(&name)
+                    || m.aliases.iter().any(|a| a.eq_ignore_ascii_case(&name))
+            })
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(regex, _)| regex.is_match(model))
+                    .map(|(_, index)| &self.models[*index])
+            })
+    }
+
+    /// Context window of a requested model ([`DEFAULT_CONTEXT_WINDOW`] when unknown)
+    pub fn context_window(&self, model: &str) -> i32 {
+        self.resolve(model)
+            .map(|m| m.context_window)
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+    }
+}
+
+/// Catalog in use (the built-in one until `init` is called)
+static CATALOG: LazyLock<RwLock<Arc<ModelCatalog>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(ModelCatalog::builtin())));
+
+/// Install the catalog loaded from the `modelCatalog` file
+///
+/// Called at application startup, before any request is converted
+pub fn init(catalog: ModelCatalog) {
+    *CATALOG.write() = Arc::new(catalog);
+}
+
+/// Catalog in use
+pub fn current() -> Arc<ModelCatalog> {
+    CATALOG.read().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalog_resolves_ids_aliases_and_patterns() {
+        let catalog = ModelCatalog::builtin();
+        let kiro_id = |model: &str| catalog.resolve(model).map(|m| m.kiro_id.as_str());
+
+        assert_eq!(
+            kiro_id("claude-sonnet-4-5-20250929-thinking"),
+            Some("CLAUDE_SONNET_4_5_20250929_V1_0")
+        );
+        assert_eq!(kiro_id("Claude-Opus-4.5"), Some("claude-opus-4.5"));
+        assert_eq!(kiro_id("claude-3-5-sonnet-20241022"), Some("claude-sonnet-4.5"));
+        assert_eq!(kiro_id("gpt-4"), None);
+
+        let opus_1m = catalog.resolve("claude-opus-4-6-1m-agentic").unwrap();
+        assert_eq!(opus_1m.id, "claude-opus-4-6-1m");
+        assert_eq!(opus_1m.context_window, 1_000_000);
+        assert_eq!(opus_1m.tier, ModelTier::Paid);
+        assert!(opus_1m.adaptive_thinking);
+        assert!(catalog.resolve("claude-opus-4.6").unwrap().adaptive_thinking);
+        let dated = catalog.resolve("claude-opus-4-6-20260205").unwrap();
+        assert_eq!(dated.id, "claude-opus-4-6");
+        assert!(!catalog.resolve("claude-opus-4-5").unwrap().adaptive_thinking);
+        assert!(!catalog.resolve("claude-sonnet-4-5").unwrap().adaptive_thinking);
+        assert_eq!(catalog.context_window("claude-opus-4-6"), 200_000);
+        assert_eq!(catalog.context_window("unknown"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn test_custom_catalog() {
+        let catalog = ModelCatalog::from_json(
+            r#"{"models": [{
+                "id": "claude-sonnet-5",
+                "kiroId": "CLAUDE_SONNET_5_V1_0",
+                "displayName": "Claude Sonnet 5",
+                "patterns": ["sonnet-5"],
+                "contextWindow": 500000
+            }]}"#,
+        )
+        .unwrap();
+        let entry = catalog.resolve("claude-sonnet-5-20260101").unwrap();
+        assert_eq!(entry.kiro_id, "CLAUDE_SONNET_5_V1_0");
+        assert_eq!(entry.max_output, 64_000);
+        assert_eq!(entry.tier, ModelTier::Free);
+        assert!(!entry.adaptive_thinking);
+        assert!(entry.listed);
+        assert!(catalog.resolve("claude-sonnet-4-5").is_none());
+
+        let err = ModelCatalog::from_json(
+            r#"{"models": [{"id": "a", "kiroId": "A", "displayName": "A", "patterns": ["("]}]}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid pattern"));
+    }
+}
@@ -1,7 +1,12 @@
 use anyhow::Context;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::anthropic::tool_compression::TOOL_COMPRESSION_TARGET_SIZE;
+use crate::http_client::ProxyConfig;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
@@ -86,10 +91,42 @@ pub struct Config {
     #[serde(default)]
     pub admin_api_key: Option<String>,
 
-    /// Load balancing mode ("priority" or "balanced")
+    /// Whether the OAuth Web routes (`/v0/oauth`) are enabled (default: true, requires adminApiKey)
+    #[serde(default = "default_oauth_web_enabled")]
+    pub oauth_web_enabled: bool,
+
+    /// File containing the key for encrypting credentials and caches at rest
+    /// (optional, the `KIRO_ENCRYPTION_KEY` env var takes precedence)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption_key_file: Option<String>,
+
+    /// Directory with one credential JSON file per account (optional, in addition to the credentials file)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_dir: Option<String>,
+
+    /// Never write back to files in `credentialsDir` (e.g. mounted secrets)
+    #[serde(default)]
+    pub credentials_dir_read_only: bool,
+
+    /// Prefix of credential environment variables (default: "KIRO_", "" = disabled)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_env_prefix: Option<String>,
+
+    /// Load balancing mode ("priority", "balanced" or "quota")
     #[serde(default = "default_load_balancing_mode")]
     pub load_balancing_mode: String,
 
+    /// Keep each conversation on the credential it started with while that credential is healthy
+    #[serde(default)]
+    pub session_affinity: bool,
+
+    /// Idle time after which a conversation's credential mapping is dropped (default: 1800)
+    #[serde(default = "default_session_affinity_ttl_secs")]
+    pub session_affinity_ttl_secs: u64,
+
     /// Model name suffix to trigger thinking mode (default: "-thinking")
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -104,6 +141,40 @@ pub struct Config {
     #[serde(default = "default_max_request_body_bytes")]
     pub max_request_body_bytes: usize,
 
+    /// Tool definitions size above which tools are compressed (0 = disabled, default: 20480)
+    #[serde(default = "default_tool_compression_threshold_bytes")]
+    pub tool_compression_threshold_bytes: usize,
+
+    /// Time to wait for the first content of a streaming response before retrying (0 = no limit, default: 60)
+    #[serde(default = "default_first_content_timeout_secs")]
+    pub first_content_timeout_secs: u64,
+
+    /// Model catalog file (optional, replaces the built-in catalog, see `models.example.json`)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_catalog: Option<String>,
+
+    /// Directory to write request capture bundles to (optional, capture is off when unset)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture_dir: Option<String>,
+
+    /// Kiro API endpoint override (default: `https://q.{region}.amazonaws.com`)
+    /// Used for generateAssistantResponse, MCP and getUsageLimits, e.g. to point at a mock upstream
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_endpoint: Option<String>,
+
+    /// Social token refresh endpoint override (default: `https://prod.{region}.auth.desktop.kiro.dev`)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_endpoint: Option<String>,
+
+    /// IdC token refresh endpoint override (default: `https://oidc.{region}.amazonaws.com`)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oidc_endpoint: Option<String>,
+
     /// Config file path (runtime metadata, not written to JSON)
     #[serde(skip)]
     config_path: Option<PathBuf>,
@@ -142,14 +213,47 @@ fn default_tls_backend() -> TlsBackend {
     TlsBackend::Rustls
 }
 
+fn default_oauth_web_enabled() -> bool {
+    true
+}
+
 fn default_load_balancing_mode() -> String {
     "priority".to_string()
 }
 
+fn default_session_affinity_ttl_secs() -> u64 {
+    1800
+}
+
 fn default_max_request_body_bytes() -> usize {
     400_000
 }
 
+fn default_tool_compression_threshold_bytes() -> usize {
+    TOOL_COMPRESSION_TARGET_SIZE
+}
+
+fn default_first_content_timeout_secs() -> u64 {
+    60
+}
+
+/// Use a configured endpoint override, or the region-based default
+fn endpoint_or(endpoint: Option<&str>, default: impl FnOnce() -> String) -> String {
+    match endpoint.map(str::trim).filter(|e| !e.is_empty()) {
+        Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+        None => default(),
+    }
+}
+
+/// Host (with port) of a URL, used for explicit `Host` headers
+pub fn url_host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    without_scheme
+        .split(['/', '?'])
+        .next()
+        .unwrap_or(without_scheme)
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -171,10 +275,24 @@ impl Default for Config {
             proxy_username: None,
             proxy_password: None,
             admin_api_key: None,
+            oauth_web_enabled: default_oauth_web_enabled(),
+            encryption_key_file: None,
+            credentials_dir: None,
+            credentials_dir_read_only: false,
+            credentials_env_prefix: None,
             load_balancing_mode: default_load_balancing_mode(),
+            session_affinity: false,
+            session_affinity_ttl_secs: default_session_affinity_ttl_secs(),
             thinking_suffix: None,
             thinking_format: None,
             max_request_body_bytes: default_max_request_body_bytes(),
+            tool_compression_threshold_bytes: default_tool_compression_threshold_bytes(),
+            first_content_timeout_secs: default_first_content_timeout_secs(),
+            model_catalog: None,
+            capture_dir: None,
+            api_endpoint: None,
+            auth_endpoint: None,
+            oidc_endpoint: None,
             config_path: None,
         }
     }
@@ -208,6 +326,120 @@ impl Config {
         self.api_region.as_deref().unwrap_or(&self.region)
     }
 
+    /// Kiro API base URL for a region (no trailing slash)
+    pub fn api_base_url(&self, region: &str) -> String {
+        endpoint_or(self.api_endpoint.as_deref(), || {
+            format!("https://q.{}.amazonaws.com", region)
+        })
+    }
+
+    /// Social token refresh base URL for a region (no trailing slash)
+    pub fn auth_base_url(&self, region: &str) -> String {
+        endpoint_or(self.auth_endpoint.as_deref(), || {
+            format!("https://prod.{}.auth.desktop.kiro.dev", region)
+        })
+    }
+
+    /// AWS SSO OIDC base URL for a region (no trailing slash)
+    pub fn oidc_base_url(&self, region: &str) -> String {
+        endpoint_or(self.oidc_endpoint.as_deref(), || {
+            format!("https://oidc.{}.amazonaws.com", region)
+        })
+    }
+
+    /// Proxy configuration (None if proxyUrl is not set)
+    pub fn proxy_config(&self) -> Option<ProxyConfig> {
+        self.proxy_url.as_ref().map(|url| {
+            let mut proxy = ProxyConfig::new(url);
+            if let (Some(username), Some(password)) = (&self.proxy_username, &self.proxy_password) {
+                proxy = proxy.with_auth(username, password);
+            }
+            proxy
+        })
+    }
+
+    /// Merge a reloaded config into the running one
+    ///
+    /// Only fields that are safe to change at runtime are taken from `reloaded`; changes to any
+    /// other field are reported in `restart_required` and ignored until the next restart.
+    pub fn merge_reloaded(&self, reloaded: Config) -> ConfigReload {
+        let mut applied = Vec::new();
+        let mut restart_required = Vec::new();
+
+        macro_rules! check {
+            ($list:ident, $name:literal, $($field:ident),+) => {
+                if $(self.$field != reloaded.$field)||+ {
+                    $list.push($name);
+                }
+            };
+        }
+
+        check!(applied, "loadBalancingMode", load_balancing_mode);
+        check!(applied, "sessionAffinity", session_affinity, session_affinity_ttl_secs);
+        check!(applied, "thinkingSuffix", thinking_suffix);
+        check!(applied, "thinkingFormat", thinking_format);
+        check!(applied, "maxRequestBodyBytes", max_request_body_bytes);
+        check!(applied, "toolCompressionThresholdBytes", tool_compression_threshold_bytes);
+        check!(applied, "firstContentTimeoutSecs", first_content_timeout_secs);
+        check!(applied, "captureDir", capture_dir);
+        check!(applied, "proxy", proxy_url, proxy_username, proxy_password);
+        check!(
+            applied,
+            "credentialSources",
+            credentials_dir,
+            credentials_dir_read_only,
+            credentials_env_prefix
+        );
+        check!(
+            applied,
+            "countTokens",
+            count_tokens_api_url,
+            count_tokens_api_key,
+            count_tokens_auth_type
+        );
+
+        check!(restart_required, "host", host);
+        check!(restart_required, "port", port);
+        check!(restart_required, "region", region, auth_region, api_region);
+        check!(restart_required, "kiroVersion", kiro_version);
+        check!(restart_required, "machineId", machine_id);
+        check!(restart_required, "apiKey", api_key);
+        check!(restart_required, "adminApiKey", admin_api_key);
+        check!(restart_required, "oauthWebEnabled", oauth_web_enabled);
+        check!(restart_required, "encryptionKeyFile", encryption_key_file);
+        check!(restart_required, "modelCatalog", model_catalog);
+        check!(restart_required, "nodeVersion", node_version);
+        check!(restart_required, "tlsBackend", tls_backend);
+        check!(restart_required, "endpoints", api_endpoint, auth_endpoint, oidc_endpoint);
+        // systemVersion is not compared: when unset it is randomized on every load
+
+        let mut config = self.clone();
+        config.load_balancing_mode = reloaded.load_balancing_mode;
+        config.session_affinity = reloaded.session_affinity;
+        config.session_affinity_ttl_secs = reloaded.session_affinity_ttl_secs;
+        config.thinking_suffix = reloaded.thinking_suffix;
+        config.thinking_format = reloaded.thinking_format;
+        config.max_request_body_bytes = reloaded.max_request_body_bytes;
+        config.tool_compression_threshold_bytes = reloaded.tool_compression_threshold_bytes;
+        config.first_content_timeout_secs = reloaded.first_content_timeout_secs;
+        config.capture_dir = reloaded.capture_dir;
+        config.proxy_url = reloaded.proxy_url;
+        config.proxy_username = reloaded.proxy_username;
+        config.proxy_password = reloaded.proxy_password;
+        config.credentials_dir = reloaded.credentials_dir;
+        config.credentials_dir_read_only = reloaded.credentials_dir_read_only;
+        config.credentials_env_prefix = reloaded.credentials_env_prefix;
+        config.count_tokens_api_url = reloaded.count_tokens_api_url;
+        config.count_tokens_api_key = reloaded.count_tokens_api_key;
+        config.count_tokens_auth_type = reloaded.count_tokens_auth_type;
+
+        ConfigReload {
+            config,
+            applied,
+            restart_required,
+        }
+    }
+
     /// Load configuration from file
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
         let path = path.as_ref();
@@ -241,3 +473,89 @@ impl Config {
         Ok(())
     }
 }
+
+/// Result of merging a reloaded config file (see `Config::merge_reloaded`)
+#[derive(Debug)]
+pub struct ConfigReload {
+    /// Running config with the runtime-safe fields replaced
+    pub config: Config,
+    /// Changed fields that took effect
+    pub applied: Vec<&'static str>,
+    /// Changed fields that only take effect after a restart
+    pub restart_required: Vec<&'static str>,
+}
+
+/// Config shared by components that must observe hot reloads
+///
+/// Readers take a cheap snapshot with `load`; `store` swaps the whole config atomically.
+#[derive(Debug, Clone)]
+pub struct SharedConfig(Arc<RwLock<Arc<Config>>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    /// Current config snapshot
+    pub fn load(&self) -> Arc<Config> {
+        self.0.read().clone()
+    }
+
+    /// Replace the config
+    pub fn store(&self, config: Config) {
+        *self.0.write() = Arc::new(config);
+    }
+}
+
+impl From<Config> for SharedConfig {
+    fn from(config: Config) -> Self {
+        Self::new(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_reloaded_applies_only_runtime_fields() {
+        let running = Config {
+            config_path: Some(PathBuf::from("config.json")),
+            ..Default::default()
+        };
+
+        let mut reloaded = running.clone();
+        reloaded.load_balancing_mode = "balanced".to_string();
+        reloaded.thinking_format = Some("think".to_string());
+        reloaded.proxy_url = Some("http://127.0.0.1:3128".to_string());
+        reloaded.port = 9999;
+        reloaded.api_key = Some("new-key".to_string());
+        reloaded.system_version = "other".to_string();
+
+        let merged = running.merge_reloaded(reloaded);
+        assert_eq!(merged.applied, vec!["loadBalancingMode", "thinkingFormat", "proxy"]);
+        assert_eq!(merged.restart_required, vec!["port", "apiKey"]);
+        assert_eq!(merged.config.load_balancing_mode, "balanced");
+        assert_eq!(merged.config.thinking_format(), "think");
+        assert_eq!(merged.config.proxy_config().unwrap().url, "http://127.0.0.1:3128");
+        assert_eq!(merged.config.port, running.port);
+        assert_eq!(merged.config.api_key, None);
+        assert_eq!(merged.config.system_version, running.system_version);
+        assert_eq!(merged.config.config_path(), Some(Path::new("config.json")));
+    }
+
+    #[test]
+    fn test_shared_config_store_is_visible_to_clones() {
+        let shared = SharedConfig::new(Config::default());
+        let other = shared.clone();
+        let before = shared.load();
+
+        other.store(Config {
+            max_request_body_bytes: 1,
+            ..Default::default()
+        });
+
+        assert_eq!(shared.load().max_request_body_bytes, 1);
+        assert_eq!(before.max_request_body_bytes, default_max_request_body_bytes());
+    }
+}
@@ -1,4 +1,5 @@
 //! Application configuration models
 
 pub mod arg;
+pub mod catalog;
 pub mod config;
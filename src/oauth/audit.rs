@@ -0,0 +1,136 @@
+//! OAuth Web audit log
+//!
+//! Every credential added through the OAuth routes is appended as one JSON line to
+//! `oauth_audit.jsonl` in the credentials directory and logged under the `audit` target.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use super::auth::OAuthCaller;
+
+/// Audit log file name (stored next to credentials.json)
+pub const AUDIT_FILE_NAME: &str = "oauth_audit.jsonl";
+
+/// Audit entry for a credential added through the OAuth routes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Time of the event (RFC3339)
+    pub timestamp: String,
+    /// Event name
+    pub event: &'static str,
+    /// How the credential was obtained ("import", "builder-id" or "idc")
+    pub method: String,
+    /// ID assigned to the credential
+    pub credential_id: u64,
+    /// SHA-256 hash of the refreshToken (matches `refreshTokenHash` in the Admin API)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token_hash: Option<String>,
+    /// How the caller authenticated ("api_key" or "session")
+    pub authenticated_by: &'static str,
+    /// Caller address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_addr: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn credential_added(
+        method: &str,
+        credential_id: u64,
+        refresh_token_hash: Option<String>,
+        caller: &OAuthCaller,
+    ) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            event: "credential_added",
+            method: method.to_string(),
+            credential_id,
+            refresh_token_hash,
+            authenticated_by: caller.authenticated_by.as_str(),
+            remote_addr: caller.remote_addr.clone(),
+        }
+    }
+}
+
+/// Append-only audit log
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    /// Audit log writing to `path` (log only if None)
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Record an entry
+    pub fn record(&self, entry: &AuditEntry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to serialize audit entry: {}", e);
+                return;
+            }
+        };
+        tracing::info!(target: "audit", "{}", line);
+
+        let Some(path) = &self.path else {
+            return;
+        };
+        let _guard = self.lock.lock();
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            tracing::warn!("Failed to write audit log {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth::auth::AuthenticatedBy;
+
+    #[test]
+    fn test_audit_log_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("kiro-audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = AuditLog::new(Some(path.clone()));
+        let caller = OAuthCaller {
+            authenticated_by: AuthenticatedBy::Session,
+            remote_addr: Some("127.0.0.1:5000".to_string()),
+        };
+
+        log.record(&AuditEntry::credential_added(
+            "import",
+            3,
+            Some("abc".to_string()),
+            &caller,
+        ));
+        log.record(&AuditEntry::credential_added("idc", 4, None, &caller));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "credential_added");
+        assert_eq!(lines[0]["credentialId"], 3);
+        assert_eq!(lines[0]["authenticatedBy"], "session");
+        assert_eq!(lines[0]["remoteAddr"], "127.0.0.1:5000");
+        assert_eq!(lines[1]["method"], "idc");
+        assert!(lines[1].get("refreshTokenHash").is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+//! OAuth Web authentication
+//!
+//! The OAuth routes are protected by the admin API key. API clients send it as a header
+//! (`x-api-key` or `Authorization: Bearer`); browsers exchange it once on the login page for a
+//! short-lived session cookie signed with a per-process secret.
+
+use std::net::SocketAddr;
+
+use axum::{
+    Form,
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{Method, Request, StatusCode, header},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use super::router::OAuthState;
+use super::templates;
+use crate::common::auth;
+
+/// Session cookie name
+pub const SESSION_COOKIE: &str = "kiro_oauth_session";
+
+/// Session cookie lifetime
+const SESSION_TTL_SECS: i64 = 3600;
+
+/// How an OAuth request was authenticated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthenticatedBy {
+    /// Admin API key header
+    ApiKey,
+    /// Session cookie from the login page
+    Session,
+}
+
+impl AuthenticatedBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthenticatedBy::ApiKey => "api_key",
+            AuthenticatedBy::Session => "session",
+        }
+    }
+}
+
+/// Authenticated caller, inserted into request extensions by `oauth_auth_middleware`
+#[derive(Debug, Clone)]
+pub struct OAuthCaller {
+    pub authenticated_by: AuthenticatedBy,
+    /// Client socket address (when the server provides connection info)
+    pub remote_addr: Option<String>,
+}
+
+/// OAuth Web authenticator
+pub struct OAuthAuth {
+    admin_api_key: String,
+    /// Session signing key (random per process, so sessions end on restart)
+    secret: [u8; 32],
+}
+
+impl OAuthAuth {
+    pub fn new(admin_api_key: impl Into<String>) -> Self {
+        let mut secret = [0u8; 32];
+        secret[..16].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        secret[16..].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        Self {
+            admin_api_key: admin_api_key.into(),
+            secret,
+        }
+    }
+
+    /// Check an admin API key
+    pub fn check_key(&self, key: &str) -> bool {
+        auth::constant_time_eq(key, &self.admin_api_key)
+    }
+
+    /// Issue a session token valid until `now + SESSION_TTL_SECS` (`{expires}.{signature}`)
+    pub fn issue_session(&self, now: i64) -> String {
+        let expires = (now + SESSION_TTL_SECS).to_string();
+        let signature = hex::encode(hmac_sha256(&self.secret, expires.as_bytes()));
+        format!("{}.{}", expires, signature)
+    }
+
+    /// Verify a session token
+    pub fn verify_session(&self, token: &str, now: i64) -> bool {
+        let Some((expires, signature)) = token.split_once('.') else {
+            return false;
+        };
+        let Ok(expires_at) = expires.parse::<i64>() else {
+            return false;
+        };
+        let expected = hex::encode(hmac_sha256(&self.secret, expires.as_bytes()));
+        auth::constant_time_eq(signature, &expected) && now < expires_at
+    }
+
+    /// Authenticate a request by header or session cookie
+    fn authenticate(&self, request: &Request<Body>) -> Option<AuthenticatedBy> {
+        if let Some(key) = auth::extract_api_key(request) {
+            return self.check_key(&key).then_some(AuthenticatedBy::ApiKey);
+        }
+        let now = Utc::now().timestamp();
+        request
+            .headers()
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|c| c.trim().split_once('='))
+            .any(|(name, value)| name == SESSION_COOKIE && self.verify_session(value, now))
+            .then_some(AuthenticatedBy::Session)
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// OAuth Web authentication middleware
+///
+/// Unauthenticated page requests get the login page, everything else gets 401.
+pub async fn oauth_auth_middleware(
+    State(state): State<OAuthState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(authenticated_by) = state.auth.authenticate(&request) else {
+        let is_page = request.method() == Method::GET
+            && matches!(request.uri().path(), "/kiro" | "/kiro/start");
+        return if is_page {
+            login_page(StatusCode::UNAUTHORIZED, None)
+        } else {
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::CONTENT_TYPE, "application/json")],
+                r#"{"error": "Unauthorized"}"#,
+            )
+                .into_response()
+        };
+    };
+
+    let remote_addr = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.to_string());
+    request.extensions_mut().insert(OAuthCaller {
+        authenticated_by,
+        remote_addr,
+    });
+    next.run(request).await
+}
+
+/// Login form
+#[derive(Debug, Deserialize)]
+pub struct LoginForm {
+    key: String,
+}
+
+/// Handle login page (GET /v0/oauth/kiro/login)
+pub async fn handle_login_page() -> Response {
+    login_page(StatusCode::OK, None)
+}
+
+/// Handle login (POST /v0/oauth/kiro/login)
+///
+/// Sets the session cookie and redirects to the OAuth page.
+pub async fn handle_login(
+    State(state): State<OAuthState>,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    if !state.auth.check_key(form.key.trim()) {
+        tracing::warn!("OAuth Web: login with invalid admin API key");
+        return login_page(StatusCode::UNAUTHORIZED, Some("Invalid admin API key"));
+    }
+
+    let token = state.auth.issue_session(Utc::now().timestamp());
+    let cookie = format!(
+        "{}={}; Path=/v0/oauth; HttpOnly; SameSite=Strict; Max-Age={}",
+        SESSION_COOKIE, token, SESSION_TTL_SECS
+    );
+    Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header(header::LOCATION, "/v0/oauth/kiro")
+        .header(header::SET_COOKIE, cookie)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn login_page(status: StatusCode, error: Option<&str>) -> Response {
+    (status, Html(templates::render_login_page(error))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            hex::encode(mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_session_token_roundtrip_and_expiry() {
+        let auth = OAuthAuth::new("admin");
+        let token = auth.issue_session(1_000);
+
+        assert!(auth.verify_session(&token, 1_000));
+        assert!(!auth.verify_session(&token, 1_000 + SESSION_TTL_SECS));
+        assert!(!OAuthAuth::new("admin").verify_session(&token, 1_000));
+
+        let (expires, signature) = token.split_once('.').unwrap();
+        let extended = format!("{}.{}", expires.parse::<i64>().unwrap() + 3600, signature);
+        assert!(!auth.verify_session(&extended, 1_000));
+        assert!(!auth.verify_session("garbage", 1_000));
+    }
+}
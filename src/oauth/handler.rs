@@ -8,33 +8,32 @@ use std::sync::Arc;
 use chrono::{Duration, Utc};
 use parking_lot::Mutex;
 
-use crate::http_client::ProxyConfig;
 use crate::kiro::model::credentials::KiroCredentials;
-use crate::kiro::token_manager::MultiTokenManager;
+use crate::kiro::token_manager::{MultiTokenManager, sha256_hex};
 use crate::model::config::Config;
 
-use super::sso_oidc::{CreateTokenResult, SsoOidcClient};
+use super::audit::{AUDIT_FILE_NAME, AuditEntry, AuditLog};
+use super::auth::OAuthCaller;
+use super::sso_oidc::SsoOidcClient;
 use super::types::*;
 
 /// OAuth Web Handler
 pub struct OAuthWebHandler {
     config: Config,
-    proxy: Option<ProxyConfig>,
     sessions: Arc<Mutex<HashMap<String, WebAuthSession>>>,
     token_manager: Arc<MultiTokenManager>,
+    audit: Arc<AuditLog>,
 }
 
 impl OAuthWebHandler {
-    pub fn new(
-        config: Config,
-        proxy: Option<ProxyConfig>,
-        token_manager: Arc<MultiTokenManager>,
-    ) -> Self {
+    /// Proxy configuration is taken from the token manager, so it follows hot reloads
+    pub fn new(config: Config, token_manager: Arc<MultiTokenManager>) -> Self {
+        let audit_path = token_manager.cache_dir().map(|d| d.join(AUDIT_FILE_NAME));
         Self {
             config,
-            proxy,
             sessions: Arc::new(Mutex::new(HashMap::new())),
             token_manager,
+            audit: Arc::new(AuditLog::new(audit_path)),
         }
     }
 
@@ -49,15 +48,20 @@ impl OAuthWebHandler {
     }
 
     /// Start Builder ID authentication
-    pub async fn start_builder_id_auth(&self) -> Result<WebAuthSession, String> {
+    pub async fn start_builder_id_auth(&self, caller: OAuthCaller) -> Result<WebAuthSession, String> {
         let region = SsoOidcClient::default_region();
         let start_url = SsoOidcClient::builder_id_start_url();
-        self.start_device_auth("builder-id", start_url, region).await
+        self.start_device_auth("builder-id", start_url, region, caller).await
     }
 
     /// Start IDC authentication
-    pub async fn start_idc_auth(&self, start_url: &str, region: &str) -> Result<WebAuthSession, String> {
-        self.start_device_auth("idc", start_url, region).await
+    pub async fn start_idc_auth(
+        &self,
+        start_url: &str,
+        region: &str,
+        caller: OAuthCaller,
+    ) -> Result<WebAuthSession, String> {
+        self.start_device_auth("idc", start_url, region, caller).await
     }
 
     /// Start device code authentication flow
@@ -66,9 +70,10 @@ impl OAuthWebHandler {
         auth_method: &str,
         start_url: &str,
         region: &str,
+        caller: OAuthCaller,
     ) -> Result<WebAuthSession, String> {
         let state_id = Self::generate_state_id();
-        let sso_client = SsoOidcClient::new(self.proxy.clone(), self.config.tls_backend);
+        let sso_client = SsoOidcClient::new(self.token_manager.proxy(), self.config.tls_backend);
 
         // Register client
         let reg_resp = sso_client
@@ -109,6 +114,7 @@ impl OAuthWebHandler {
             region: region.to_string(),
             client_id: reg_resp.client_id,
             client_secret: reg_resp.client_secret,
+            caller,
         };
 
         // Store session
@@ -126,9 +132,10 @@ impl OAuthWebHandler {
     /// Start background polling for token
     fn start_polling(&self, state_id: String) {
         let sessions = self.sessions.clone();
-        let proxy = self.proxy.clone();
+        let proxy = self.token_manager.proxy();
         let tls_backend = self.config.tls_backend;
         let token_manager = self.token_manager.clone();
+        let audit = self.audit.clone();
 
         tokio::spawn(async move {
             let session_data = {
@@ -142,95 +149,65 @@ impl OAuthWebHandler {
             };
 
             let sso_client = SsoOidcClient::new(proxy, tls_backend);
-            let mut interval = std::time::Duration::from_secs(session.interval as u64);
-            let deadline = session.started_at + Duration::seconds(session.expires_in);
-
-            loop {
-                tokio::time::sleep(interval).await;
+            let elapsed = (Utc::now() - session.started_at).num_seconds();
+            let result = sso_client
+                .wait_for_token(
+                    &session.client_id,
+                    &session.client_secret,
+                    &session.device_code,
+                    &session.region,
+                    session.interval,
+                    session.expires_in - elapsed,
+                )
+                .await;
+
+            match result {
+                Ok(token_resp) => {
+                    let credentials = sso_client
+                        .device_credentials(
+                            token_resp,
+                            &session.auth_method,
+                            &session.region,
+                            &session.client_id,
+                            &session.client_secret,
+                        )
+                        .await;
+                    let expires_at = credentials
+                        .expires_at
+                        .as_deref()
+                        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                        .map(|t| t.with_timezone(&Utc));
+
+                    // Add to token manager
+                    let refresh_token_hash = credentials.refresh_token.as_deref().map(sha256_hex);
+                    match token_manager.add_credential(credentials).await {
+                        Ok(id) => audit.record(&AuditEntry::credential_added(
+                            &session.auth_method,
+                            id,
+                            refresh_token_hash,
+                            &session.caller,
+                        )),
+                        Err(e) => tracing::error!("Failed to add credential: {}", e),
+                    }
 
-                if Utc::now() >= deadline {
+                    // Update session
                     let mut sessions = sessions.lock();
                     if let Some(s) = sessions.get_mut(&state_id) {
-                        s.status = AuthSessionStatus::Failed;
-                        s.error = Some("Authentication timed out".to_string());
+                        s.status = AuthSessionStatus::Success;
                         s.completed_at = Some(Utc::now());
+                        s.expires_at = expires_at;
                     }
-                    break;
-                }
 
-                let result = sso_client
-                    .create_token(
-                        &session.client_id,
-                        &session.client_secret,
-                        &session.device_code,
-                        &session.region,
-                    )
-                    .await;
-
-                match result {
-                    Ok(CreateTokenResult::Success(token_resp)) => {
-                        let expires_in = token_resp.expires_in.unwrap_or(3600);
-                        let expires_at = Utc::now() + Duration::seconds(expires_in);
-
-                        // Fetch profile ARN
-                        let profile_arn = sso_client
-                            .fetch_profile_arn(&token_resp.access_token, &session.region)
-                            .await;
-
-                        // Create credentials
-                        let mut credentials = KiroCredentials::default();
-                        credentials.access_token = Some(token_resp.access_token);
-                        credentials.refresh_token = token_resp.refresh_token;
-                        credentials.profile_arn = profile_arn;
-                        credentials.expires_at = Some(expires_at.to_rfc3339());
-                        credentials.auth_method = Some(session.auth_method.clone());
-                        credentials.client_id = Some(session.client_id.clone());
-                        credentials.client_secret = Some(session.client_secret.clone());
-                        credentials.region = Some(session.region.clone());
-
-                        // Add to token manager
-                        if let Err(e) = token_manager.add_credential(credentials).await {
-                            tracing::error!("Failed to add credential: {}", e);
-                        }
-
-                        // Update session
-                        let mut sessions = sessions.lock();
-                        if let Some(s) = sessions.get_mut(&state_id) {
-                            s.status = AuthSessionStatus::Success;
-                            s.completed_at = Some(Utc::now());
-                            s.expires_at = Some(expires_at);
-                        }
-
-                        tracing::info!("OAuth Web: authentication successful");
-                        break;
-                    }
-                    Ok(CreateTokenResult::Pending) => {
-                        // Continue polling
-                        continue;
-                    }
-                    Ok(CreateTokenResult::SlowDown) => {
-                        interval += std::time::Duration::from_secs(5);
-                        continue;
-                    }
-                    Ok(CreateTokenResult::Expired) => {
-                        let mut sessions = sessions.lock();
-                        if let Some(s) = sessions.get_mut(&state_id) {
-                            s.status = AuthSessionStatus::Failed;
-                            s.error = Some("Device code expired".to_string());
-                            s.completed_at = Some(Utc::now());
-                        }
-                        break;
-                    }
-                    Err(e) => {
-                        let mut sessions = sessions.lock();
-                        if let Some(s) = sessions.get_mut(&state_id) {
-                            s.status = AuthSessionStatus::Failed;
-                            s.error = Some(format!("Token creation failed: {}", e));
-                            s.completed_at = Some(Utc::now());
-                        }
-                        tracing::error!("OAuth Web: token polling failed: {}", e);
-                        break;
+                    tracing::info!("OAuth Web: authentication successful");
+                }
+                Err(e) => {
+                    let mut sessions = sessions.lock();
+                    if let Some(s) = sessions.get_mut(&state_id) {
+                        s.status = AuthSessionStatus::Failed;
+                        s.error = Some(e.to_string());
+                        s.completed_at = Some(Utc::now());
                     }
+                    tracing::error!("OAuth Web: authentication failed: {}", e);
                 }
             }
         });
@@ -275,7 +252,11 @@ impl OAuthWebHandler {
     }
 
     /// Import token from refresh token
-    pub async fn import_token(&self, refresh_token: &str) -> Result<ImportTokenResponse, String> {
+    pub async fn import_token(
+        &self,
+        refresh_token: &str,
+        caller: &OAuthCaller,
+    ) -> Result<ImportTokenResponse, String> {
         let refresh_token = refresh_token.trim();
 
         if refresh_token.is_empty() {
@@ -304,12 +285,20 @@ impl OAuthWebHandler {
 
         // Add to token manager (will trigger refresh)
         match self.token_manager.add_credential(credentials).await {
-            Ok(_) => Ok(ImportTokenResponse {
-                success: true,
-                message: Some("Token imported successfully".to_string()),
-                error: None,
-                file_name: Some("credentials.json".to_string()),
-            }),
+            Ok(id) => {
+                self.audit.record(&AuditEntry::credential_added(
+                    "import",
+                    id,
+                    Some(sha256_hex(refresh_token)),
+                    caller,
+                ));
+                Ok(ImportTokenResponse {
+                    success: true,
+                    message: Some("Token imported successfully".to_string()),
+                    error: None,
+                    file_name: Some("credentials.json".to_string()),
+                })
+            }
             Err(e) => Ok(ImportTokenResponse {
                 success: false,
                 message: None,
@@ -6,7 +6,12 @@
 //! - AWS Identity Center (IDC) (device code flow)
 //! - Token import from Kiro IDE
 //! - Manual token refresh
+//!
+//! All routes require the admin API key (header or login session cookie), and credentials
+//! added through them are recorded in an audit log.
 
+mod audit;
+mod auth;
 mod handler;
 mod router;
 mod sso_oidc;
@@ -15,3 +20,4 @@ mod types;
 
 pub use handler::OAuthWebHandler;
 pub use router::create_oauth_router;
+pub use sso_oidc::SsoOidcClient;
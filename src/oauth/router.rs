@@ -5,15 +5,17 @@
 use std::sync::Arc;
 
 use axum::{
-    Router,
+    Extension, Router,
     extract::{Query, State},
     http::{StatusCode, header},
+    middleware,
     response::{Html, IntoResponse, Response},
     routing::{get, post},
     Json,
 };
 use serde::Deserialize;
 
+use super::auth::{self, OAuthAuth, OAuthCaller, oauth_auth_middleware};
 use super::handler::OAuthWebHandler;
 use super::templates::{self, SELECT_PAGE_HTML};
 use super::types::{ImportTokenRequest, ImportTokenResponse, RefreshResponse};
@@ -22,11 +24,17 @@ use super::types::{ImportTokenRequest, ImportTokenResponse, RefreshResponse};
 #[derive(Clone)]
 pub struct OAuthState {
     pub handler: Arc<OAuthWebHandler>,
+    pub auth: Arc<OAuthAuth>,
 }
 
 /// Create OAuth router
-pub fn create_oauth_router(handler: Arc<OAuthWebHandler>) -> Router {
-    let state = OAuthState { handler };
+///
+/// All routes except the login page require the admin API key or a session cookie.
+pub fn create_oauth_router(handler: Arc<OAuthWebHandler>, admin_api_key: &str) -> Router {
+    let state = OAuthState {
+        handler,
+        auth: Arc::new(OAuthAuth::new(admin_api_key)),
+    };
 
     Router::new()
         .route("/kiro", get(handle_select))
@@ -34,6 +42,14 @@ pub fn create_oauth_router(handler: Arc<OAuthWebHandler>) -> Router {
         .route("/kiro/status", get(handle_status))
         .route("/kiro/import", post(handle_import))
         .route("/kiro/refresh", post(handle_refresh))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            oauth_auth_middleware,
+        ))
+        .route(
+            "/kiro/login",
+            get(auth::handle_login_page).post(auth::handle_login),
+        )
         .with_state(state)
 }
 
@@ -60,10 +76,11 @@ async fn handle_select() -> impl IntoResponse {
 /// Handle start authentication (GET /v0/oauth/kiro/start)
 async fn handle_start(
     State(state): State<OAuthState>,
+    Extension(caller): Extension<OAuthCaller>,
     Query(params): Query<StartParams>,
 ) -> Response {
     let result = match params.method.as_str() {
-        "builder-id" => state.handler.start_builder_id_auth().await,
+        "builder-id" => state.handler.start_builder_id_auth(caller).await,
         "idc" => {
             let start_url = match params.start_url {
                 Some(url) if !url.is_empty() => url,
@@ -72,7 +89,7 @@ async fn handle_start(
                 }
             };
             let region = params.region.as_deref().unwrap_or("us-east-1");
-            state.handler.start_idc_auth(&start_url, region).await
+            state.handler.start_idc_auth(&start_url, region, caller).await
         }
         _ => {
             return render_error(&format!("Unknown authentication method: {}", params.method));
@@ -115,9 +132,10 @@ async fn handle_status(
 /// Handle token import (POST /v0/oauth/kiro/import)
 async fn handle_import(
     State(state): State<OAuthState>,
+    Extension(caller): Extension<OAuthCaller>,
     Json(req): Json<ImportTokenRequest>,
 ) -> Json<ImportTokenResponse> {
-    match state.handler.import_token(&req.refresh_token).await {
+    match state.handler.import_token(&req.refresh_token, &caller).await {
         Ok(resp) => Json(resp),
         Err(e) => Json(ImportTokenResponse {
             success: false,
@@ -2,10 +2,13 @@
 //!
 //! Handles AWS SSO OIDC authentication for Builder ID and Identity Center (IDC)
 
+use std::time::{Duration, Instant};
+
 use anyhow::{bail, Result};
 use serde_json::json;
 
 use crate::http_client::{ProxyConfig, build_client};
+use crate::kiro::model::credentials::KiroCredentials;
 use crate::model::config::TlsBackend;
 
 use super::types::{
@@ -159,6 +162,67 @@ impl SsoOidcClient {
         Ok(CreateTokenResult::Success(result))
     }
 
+    /// Poll for the token until the user authorizes the device or the device code expires
+    ///
+    /// `interval_secs` and `expires_in` come from the device authorization response.
+    pub async fn wait_for_token(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        device_code: &str,
+        region: &str,
+        interval_secs: i64,
+        expires_in: i64,
+    ) -> Result<CreateTokenResponse> {
+        let mut interval = Duration::from_secs(interval_secs.max(1) as u64);
+        let deadline = Instant::now() + Duration::from_secs(expires_in.max(0) as u64);
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            if Instant::now() >= deadline {
+                bail!("Authentication timed out");
+            }
+
+            let result = self
+                .create_token(client_id, client_secret, device_code, region)
+                .await
+                .map_err(|e| anyhow::anyhow!("Token creation failed: {}", e))?;
+            match result {
+                CreateTokenResult::Success(token) => return Ok(token),
+                CreateTokenResult::Pending => {}
+                CreateTokenResult::SlowDown => interval += Duration::from_secs(5),
+                CreateTokenResult::Expired => bail!("Device code expired"),
+            }
+        }
+    }
+
+    /// Build Kiro credentials from a device flow token (fetches the profile ARN)
+    pub async fn device_credentials(
+        &self,
+        token: CreateTokenResponse,
+        auth_method: &str,
+        region: &str,
+        client_id: &str,
+        client_secret: &str,
+    ) -> KiroCredentials {
+        let expires_in = token.expires_in.unwrap_or(3600);
+        let expires_at = chrono::Utc::now() + chrono::Duration::seconds(expires_in);
+        let profile_arn = self.fetch_profile_arn(&token.access_token, region).await;
+
+        KiroCredentials {
+            access_token: Some(token.access_token),
+            refresh_token: token.refresh_token,
+            profile_arn,
+            expires_at: Some(expires_at.to_rfc3339()),
+            auth_method: Some(auth_method.to_string()),
+            client_id: Some(client_id.to_string()),
+            client_secret: Some(client_secret.to_string()),
+            region: Some(region.to_string()),
+            ..Default::default()
+        }
+    }
+
     /// Fetch profile ARN from CodeWhisperer API
     pub async fn fetch_profile_arn(&self, access_token: &str, region: &str) -> Option<String> {
         let host = format!("codewhisperer.{}.amazonaws.com", region);
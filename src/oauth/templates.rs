@@ -715,6 +715,70 @@ pub const ERROR_PAGE_HTML: &str = r#"<!DOCTYPE html>
 </body>
 </html>"#;
 
+/// Login page (admin API key)
+pub const LOGIN_PAGE_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Kiro OAuth - Sign In</title>
+    <style>
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
+            max-width: 600px;
+            margin: 50px auto;
+            padding: 20px;
+            background: #f5f5f5;
+        }
+        .login {
+            background: #fff;
+            padding: 30px;
+            border-radius: 8px;
+            box-shadow: 0 2px 4px rgba(0,0,0,0.1);
+        }
+        h1 { margin-top: 0; color: #333; }
+        p { color: #666; line-height: 1.6; }
+        input[type="password"] {
+            width: 100%;
+            padding: 10px;
+            border: 1px solid #ddd;
+            border-radius: 4px;
+            box-sizing: border-box;
+            margin-bottom: 15px;
+        }
+        button {
+            padding: 10px 20px;
+            background: #007bff;
+            color: white;
+            border: none;
+            border-radius: 4px;
+            cursor: pointer;
+        }
+        button:hover { background: #0056b3; }
+        .error { color: #dc3545; }
+    </style>
+</head>
+<body>
+    <div class="login">
+        <h1>Sign In</h1>
+        <p>Enter the admin API key to manage Kiro credentials.</p>
+        {{ERROR}}
+        <form action="/v0/oauth/kiro/login" method="post">
+            <input type="password" name="key" placeholder="Admin API key" autofocus required>
+            <button type="submit">Sign In</button>
+        </form>
+    </div>
+</body>
+</html>"#;
+
+/// Render login page with an optional error message
+pub fn render_login_page(error: Option<&str>) -> String {
+    let error = error
+        .map(|e| format!(r#"<p class="error">{}</p>"#, html_escape(e)))
+        .unwrap_or_default();
+    LOGIN_PAGE_HTML.replace("{{ERROR}}", &error)
+}
+
 /// Render start page with values
 pub fn render_start_page(auth_url: &str, user_code: &str, expires_in: i64, state_id: &str) -> String {
     START_PAGE_HTML
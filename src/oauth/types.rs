@@ -31,6 +31,8 @@ pub struct WebAuthSession {
     pub region: String,
     pub client_id: String,
     pub client_secret: String,
+    /// Caller that started the flow (for the audit log)
+    pub caller: super::auth::OAuthCaller,
 }
 
 /// Status response for polling
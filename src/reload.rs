@@ -0,0 +1,478 @@
+//! Hot reload of config.json and credentials.json
+//!
+//! A reload is triggered by a change to either file or to the credentials directory (polled),
+//! by SIGHUP, or by the Admin API.
+//! Both files are read and validated before anything is applied, so a broken edit leaves the
+//! running state untouched.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::Mutex as TokioMutex;
+
+use crate::anthropic::tool_compression;
+use crate::kiro::credential_sources;
+use crate::kiro::token_manager::{CredentialsReload, LOAD_BALANCING_MODES, MultiTokenManager};
+use crate::model::config::Config;
+use crate::token;
+
+/// Interval between file modification checks
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Result of a reload
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadSummary {
+    /// Changed config fields that took effect
+    pub applied: Vec<&'static str>,
+    /// Changed config fields that only take effect after a restart
+    pub restart_required: Vec<&'static str>,
+    /// Credential changes
+    pub credentials: CredentialsReload,
+}
+
+/// Reloads config and credentials files into the running process
+pub struct Reloader {
+    config_path: PathBuf,
+    credentials_path: PathBuf,
+    token_manager: Arc<MultiTokenManager>,
+    /// Serializes reloads
+    reload_lock: TokioMutex<()>,
+    /// File versions seen by the watcher
+    seen: Mutex<Versions>,
+}
+
+/// File version as seen by the watcher
+pub(crate) type FileVersion = (SystemTime, u64);
+
+/// Version of every watched file: config, credentials file, then the credentials directory's files
+type Versions = Vec<(PathBuf, Option<FileVersion>)>;
+
+pub(crate) fn file_version(path: &Path) -> Option<FileVersion> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Versions of the files in a directory, sorted by path
+fn dir_versions(dir: Option<&str>) -> Versions {
+    let Some(entries) = dir.and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut versions: Versions = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let version = file_version(&path)?;
+            Some((path, Some(version)))
+        })
+        .collect();
+    versions.sort();
+    versions
+}
+
+impl Reloader {
+    pub fn new(
+        config_path: impl Into<PathBuf>,
+        credentials_path: impl Into<PathBuf>,
+        token_manager: Arc<MultiTokenManager>,
+    ) -> Self {
+        let reloader = Self {
+            config_path: config_path.into(),
+            credentials_path: credentials_path.into(),
+            token_manager,
+            reload_lock: TokioMutex::new(()),
+            seen: Mutex::new(Vec::new()),
+        };
+        *reloader.seen.lock() = reloader.versions();
+        reloader
+    }
+
+    /// Reload both files
+    pub async fn reload(&self) -> anyhow::Result<ReloadSummary> {
+        let _guard = self.reload_lock.lock().await;
+        // Record versions first so the watcher doesn't re-trigger for edits already applied
+        *self.seen.lock() = self.versions();
+
+        let reloaded_config = Config::load(&self.config_path)?;
+        if !LOAD_BALANCING_MODES.contains(&reloaded_config.load_balancing_mode.as_str()) {
+            anyhow::bail!(
+                "Invalid load balancing mode: {}",
+                reloaded_config.load_balancing_mode
+            );
+        }
+        let loaded = credential_sources::load_all(&reloaded_config, &self.credentials_path)?;
+
+        let merged = self.token_manager.config().merge_reloaded(reloaded_config);
+        if !merged.applied.is_empty() {
+            token::init_config(token::CountTokensConfig::from_config(&merged.config));
+            tool_compression::init_config(merged.config.tool_compression_threshold_bytes);
+            self.token_manager.apply_config(merged.config);
+            tracing::info!("Reloaded config: {}", merged.applied.join(", "));
+        }
+        if !merged.restart_required.is_empty() {
+            tracing::warn!(
+                "Config changes require a restart to take effect: {}",
+                merged.restart_required.join(", ")
+            );
+        }
+
+        let credentials = self
+            .token_manager
+            .reload_credentials(loaded.credentials, loaded.is_multiple_format)
+            .await?;
+
+        Ok(ReloadSummary {
+            applied: merged.applied,
+            restart_required: merged.restart_required,
+            credentials,
+        })
+    }
+
+    /// Current versions of the watched files
+    fn versions(&self) -> Versions {
+        let mut versions = vec![
+            (self.config_path.clone(), file_version(&self.config_path)),
+            (
+                self.credentials_path.clone(),
+                file_version(&self.credentials_path),
+            ),
+        ];
+        versions.extend(dir_versions(
+            self.token_manager.config().credentials_dir.as_deref(),
+        ));
+        versions
+    }
+
+    /// Whether a watched file changed since the last reload
+    ///
+    /// Write-backs of this process (e.g. a refreshed token) are not changes: their versions are
+    /// taken as seen without a reload.
+    fn files_changed(&self) -> bool {
+        let current = self.versions();
+        let mut seen = self.seen.lock();
+        if *seen == current {
+            return false;
+        }
+        let own_writes_only = seen.len() == current.len()
+            && seen
+                .iter()
+                .zip(&current)
+                .all(|((seen_path, seen_version), (path, version))| {
+                    seen_path == path
+                        && (seen_version == version
+                            || version.is_some_and(|v| self.token_manager.is_own_write(path, v)))
+                });
+        if own_writes_only {
+            *seen = current;
+        }
+        !own_writes_only
+    }
+
+    /// Reload and log the outcome (background triggers)
+    async fn reload_logged(&self, trigger: &str) {
+        tracing::info!("Reloading config and credentials ({})", trigger);
+        if let Err(e) = self.reload().await {
+            tracing::error!(
+                "Reload failed, keeping current config and credentials: {}",
+                e
+            );
+        }
+    }
+
+    /// Start polling both files for changes
+    pub fn spawn_file_watcher(self: &Arc<Self>) {
+        let reloader = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(WATCH_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if reloader.files_changed() {
+                    reloader.reload_logged("file change").await;
+                }
+            }
+        });
+    }
+
+    /// Reload on SIGHUP (Unix only)
+    pub fn spawn_signal_handler(self: &Arc<Self>) {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    tracing::warn!("Failed to install SIGHUP handler: {}", e);
+                    return;
+                }
+            };
+            let reloader = self.clone();
+            tokio::spawn(async move {
+                while hangup.recv().await.is_some() {
+                    reloader.reload_logged("SIGHUP").await;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::credentials::{CredentialsConfig, KiroCredentials};
+
+    struct Fixture {
+        dir: PathBuf,
+        manager: Arc<MultiTokenManager>,
+        reloader: Reloader,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn fixture(config: &str, credentials: &str) -> Fixture {
+        let dir = std::env::temp_dir().join(format!("kiro-reload-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.json");
+        let credentials_path = dir.join("credentials.json");
+        std::fs::write(&config_path, config).unwrap();
+        std::fs::write(&credentials_path, credentials).unwrap();
+
+        let config = Config::load(&config_path).unwrap();
+        let credentials_config = CredentialsConfig::load(&credentials_path).unwrap();
+        let is_multiple = credentials_config.is_multiple();
+        let manager = Arc::new(
+            MultiTokenManager::new(
+                config,
+                credentials_config.into_sorted_credentials(),
+                None,
+                Some(credentials_path.clone()),
+                is_multiple,
+            )
+            .unwrap(),
+        );
+        let reloader = Reloader::new(config_path, credentials_path, manager.clone());
+        Fixture {
+            dir,
+            manager,
+            reloader,
+        }
+    }
+
+    const CREDENTIALS: &str = r#"[
+        {"id": 1, "refreshToken": "a", "accessToken": "live-a", "priority": 0, "machineId": "m1"},
+        {"id": 2, "refreshToken": "b", "priority": 1, "machineId": "m2"}
+    ]"#;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_applies_runtime_config_fields() {
+        let f = fixture(
+            r#"{"loadBalancingMode": "priority", "port": 8080}"#,
+            CREDENTIALS,
+        );
+        let shared = f.manager.shared_config();
+
+        std::fs::write(
+            f.dir.join("config.json"),
+            r#"{"loadBalancingMode": "balanced", "port": 9000, "thinkingFormat": "think",
+                "proxyUrl": "http://127.0.0.1:3128"}"#,
+        )
+        .unwrap();
+        let summary = f.reloader.reload().await.unwrap();
+
+        assert_eq!(
+            summary.applied,
+            vec!["loadBalancingMode", "thinkingFormat", "proxy"]
+        );
+        assert_eq!(summary.restart_required, vec!["port"]);
+        assert!(summary.credentials.is_empty());
+        assert_eq!(f.manager.get_load_balancing_mode(), "balanced");
+        assert_eq!(shared.load().thinking_format(), "think");
+        assert_eq!(shared.load().port, 8080);
+        assert_eq!(f.manager.proxy().unwrap().url, "http://127.0.0.1:3128");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_rejects_invalid_files_without_applying() {
+        let f = fixture(r#"{"loadBalancingMode": "priority"}"#, CREDENTIALS);
+
+        std::fs::write(
+            f.dir.join("config.json"),
+            r#"{"loadBalancingMode": "random"}"#,
+        )
+        .unwrap();
+        assert!(f.reloader.reload().await.is_err());
+
+        std::fs::write(
+            f.dir.join("config.json"),
+            r#"{"loadBalancingMode": "balanced"}"#,
+        )
+        .unwrap();
+        std::fs::write(f.dir.join("credentials.json"), "[{").unwrap();
+        assert!(f.reloader.reload().await.is_err());
+
+        assert_eq!(f.manager.get_load_balancing_mode(), "priority");
+        assert_eq!(f.manager.total_count(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rejected_credentials_leave_config_unchanged() {
+        let f = fixture(r#"{"loadBalancingMode": "priority"}"#, CREDENTIALS);
+        let shared = f.manager.shared_config();
+
+        std::fs::write(
+            f.dir.join("config.json"),
+            r#"{"loadBalancingMode": "balanced", "thinkingFormat": "think"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            f.dir.join("credentials.json"),
+            r#"[{"id": 1, "refreshToken": "a"}, {"id": 1, "refreshToken": "b"}]"#,
+        )
+        .unwrap();
+        let err = f.reloader.reload().await.unwrap_err();
+        assert!(err.to_string().contains("Duplicate credential IDs"));
+
+        assert_eq!(f.manager.get_load_balancing_mode(), "priority");
+        assert_eq!(shared.load().thinking_format(), "thinking");
+        assert_eq!(f.manager.total_count(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_diffs_credentials_and_keeps_stats() {
+        let f = fixture("{}", CREDENTIALS);
+        f.manager.report_success(1);
+        f.manager.report_failure(2);
+
+        std::fs::write(
+            f.dir.join("credentials.json"),
+            r#"[
+                {"id": 1, "refreshToken": "a", "accessToken": "stale-a", "priority": 5, "machineId": "m1"},
+                {"refreshToken": "c", "priority": 0, "machineId": "m3"}
+            ]"#,
+        )
+        .unwrap();
+        let summary = f.reloader.reload().await.unwrap();
+
+        assert_eq!(summary.credentials.added, vec![3]);
+        assert_eq!(summary.credentials.updated, vec![1]);
+        assert_eq!(summary.credentials.removed, vec![2]);
+
+        let snapshot = f.manager.snapshot();
+        assert_eq!(snapshot.total, 2);
+        let first = snapshot.entries.iter().find(|e| e.id == 1).unwrap();
+        assert_eq!(first.priority, 5);
+        assert_eq!(first.success_count, 1);
+        // New highest priority credential becomes current
+        assert_eq!(snapshot.current_id, 3);
+
+        // Assigned ID is written back to the file (in priority order)
+        let written: Vec<KiroCredentials> =
+            serde_json::from_str(&std::fs::read_to_string(f.dir.join("credentials.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            written.iter().filter_map(|c| c.id).collect::<Vec<_>>(),
+            vec![3, 1]
+        );
+
+        // Reloading the written-back file is a no-op
+        assert!(f.reloader.reload().await.unwrap().credentials.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_new_refresh_token_resets_failures() {
+        let f = fixture("{}", CREDENTIALS);
+        for _ in 0..3 {
+            f.manager.report_failure(1);
+        }
+        assert!(
+            f.manager
+                .snapshot()
+                .entries
+                .iter()
+                .any(|e| e.id == 1 && e.disabled)
+        );
+
+        std::fs::write(
+            f.dir.join("credentials.json"),
+            r#"[
+                {"id": 1, "refreshToken": "a2", "priority": 0, "machineId": "m1"},
+                {"id": 2, "refreshToken": "b", "priority": 1, "machineId": "m2"}
+            ]"#,
+        )
+        .unwrap();
+        let summary = f.reloader.reload().await.unwrap();
+
+        assert_eq!(summary.credentials.updated, vec![1]);
+        let first = f
+            .manager
+            .snapshot()
+            .entries
+            .into_iter()
+            .find(|e| e.id == 1)
+            .unwrap();
+        assert!(!first.disabled);
+        assert_eq!(first.failure_count, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_files_changed_tracks_versions() {
+        let f = fixture("{}", CREDENTIALS);
+        assert!(!f.reloader.files_changed());
+
+        std::fs::write(f.dir.join("config.json"), r#"{"thinkingSuffix": "-t"}"#).unwrap();
+        assert!(f.reloader.files_changed());
+
+        f.reloader.reload().await.unwrap();
+        assert!(!f.reloader.files_changed());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_own_write_back_is_not_a_change() {
+        let f = fixture("{}", CREDENTIALS);
+
+        // Written back by the process itself
+        f.manager.set_priority(1, 7).unwrap();
+        assert!(!f.reloader.files_changed());
+
+        std::fs::write(f.dir.join("credentials.json"), CREDENTIALS).unwrap();
+        assert!(f.reloader.files_changed());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_directory_write_back_is_not_a_change() {
+        let secrets =
+            std::env::temp_dir().join(format!("kiro-reload-dir-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&secrets).unwrap();
+        std::fs::write(secrets.join("a.json"), r#"{"refreshToken": "dir-a"}"#).unwrap();
+        let config = serde_json::json!({
+            "credentialsDir": secrets,
+            "credentialsEnvPrefix": ""
+        });
+        let f = fixture(&config.to_string(), CREDENTIALS);
+
+        // The new credential's ID and machineId are written back to its file
+        let summary = f.reloader.reload().await.unwrap();
+        assert_eq!(summary.credentials.added, vec![3]);
+        let written: KiroCredentials =
+            serde_json::from_str(&std::fs::read_to_string(secrets.join("a.json")).unwrap())
+                .unwrap();
+        assert_eq!(written.id, Some(3));
+        assert!(!f.reloader.files_changed());
+
+        std::fs::write(
+            secrets.join("a.json"),
+            r#"{"id": 3, "refreshToken": "dir-a2"}"#,
+        )
+        .unwrap();
+        assert!(f.reloader.files_changed());
+
+        std::fs::remove_dir_all(&secrets).unwrap();
+    }
+}
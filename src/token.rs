@@ -11,8 +11,8 @@ use crate::anthropic::types::{
     CountTokensRequest, CountTokensResponse, Message, SystemMessage, Tool,
 };
 use crate::http_client::{ProxyConfig, build_client};
-use crate::model::config::TlsBackend;
-use std::sync::OnceLock;
+use crate::model::config::{Config, TlsBackend};
+use parking_lot::RwLock;
 
 /// Count Tokens API configuration
 #[derive(Clone, Default)]
@@ -29,19 +29,32 @@ pub struct CountTokensConfig {
     pub tls_backend: TlsBackend,
 }
 
+impl CountTokensConfig {
+    /// Build from application config
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            api_url: config.count_tokens_api_url.clone(),
+            api_key: config.count_tokens_api_key.clone(),
+            auth_type: config.count_tokens_auth_type.clone(),
+            proxy: config.proxy_config(),
+            tls_backend: config.tls_backend,
+        }
+    }
+}
+
 /// Global configuration storage
-static COUNT_TOKENS_CONFIG: OnceLock<CountTokensConfig> = OnceLock::new();
+static COUNT_TOKENS_CONFIG: RwLock<Option<CountTokensConfig>> = RwLock::new(None);
 
 /// Initialize count_tokens configuration
 ///
-/// Should be called once at application startup
+/// Called at application startup and again when the config is hot reloaded
 pub fn init_config(config: CountTokensConfig) {
-    let _ = COUNT_TOKENS_CONFIG.set(config);
+    *COUNT_TOKENS_CONFIG.write() = Some(config);
 }
 
 /// Get configuration
-fn get_config() -> Option<&'static CountTokensConfig> {
-    COUNT_TOKENS_CONFIG.get()
+fn get_config() -> Option<CountTokensConfig> {
+    COUNT_TOKENS_CONFIG.read().clone()
 }
 
 /// Check if character is non-Western
@@ -117,7 +130,7 @@ pub(crate) fn count_all_tokens(
             // Try calling remote API
             let result = tokio::task::block_in_place(|| {
                 tokio::runtime::Handle::current().block_on(call_remote_count_tokens(
-                    api_url, config, model, &system, &messages, &tools,
+                    api_url, &config, model, &system, &messages, &tools,
                 ))
             });
 
@@ -232,6 +245,9 @@ pub(crate) fn estimate_output_tokens(content: &[serde_json::Value]) -> i32 {
         if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
             total += count_tokens(text) as i32;
         }
+        if let Some(thinking) = block.get("thinking").and_then(|v| v.as_str()) {
+            total += count_tokens(thinking) as i32;
+        }
         if block.get("type").and_then(|v| v.as_str()) == Some("tool_use") {
             // Tool call overhead
             if let Some(input) = block.get("input") {